@@ -0,0 +1,268 @@
+//! Aggregates behind the admin dashboard charts.
+//!
+//! Time series and class distributions are computed on demand from the
+//! entries in the [`crate::traffic::TrafficLog`] window, so they cover the
+//! same span as `/admin/traffic`. Gate queue depth has no traffic entry to
+//! derive it from, so [`GateHistory`] keeps periodic samples of its own.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeZone as _, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::traffic::TrafficEntry;
+
+/// How often `main` samples the priority gates.
+pub const GATE_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Gate samples retained — one hour at [`GATE_SAMPLE_INTERVAL`].
+pub const GATE_SAMPLE_CAPACITY: usize = 720;
+
+/// Upper bound on buckets per series; wider spans get wider buckets.
+const MAX_BUCKETS: u64 = 1_440;
+
+/// Dimension to split a time series by.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Tier,
+    Profile,
+    Backend,
+}
+
+impl GroupBy {
+    fn key(self, e: &TrafficEntry) -> &str {
+        match self {
+            Self::Tier => &e.tier,
+            Self::Profile => e.profile.as_deref().unwrap_or("default"),
+            Self::Backend => &e.backend,
+        }
+    }
+}
+
+/// Request counts and latency percentiles per time bucket.
+#[derive(Debug, Serialize)]
+pub struct TimeSeries {
+    /// Bucket width actually used (raised when the window spans more than
+    /// the bucket limit).
+    pub bucket_secs: u64,
+    /// `"all"` plus one series per group when grouped. Every series has the
+    /// same contiguous buckets, oldest first.
+    pub series: BTreeMap<String, Vec<Bucket>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub requests: usize,
+    pub errors: usize,
+    pub escalations: usize,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+/// Bucket `entries` by timestamp, optionally split by `group_by`.
+pub fn timeseries(entries: &[TrafficEntry], bucket_secs: u64, group_by: Option<GroupBy>) -> TimeSeries {
+    let Some((first, last)) = entries
+        .iter()
+        .map(|e| e.timestamp.timestamp())
+        .fold(None, |acc: Option<(i64, i64)>, ts| match acc {
+            Some((lo, hi)) => Some((lo.min(ts), hi.max(ts))),
+            None => Some((ts, ts)),
+        })
+    else {
+        return TimeSeries { bucket_secs: bucket_secs.max(1), series: BTreeMap::new() };
+    };
+    let span = (last - first) as u64;
+    let bucket_secs = bucket_secs.max(1).max(span.div_ceil(MAX_BUCKETS));
+    let width = bucket_secs as i64;
+    let origin = first.div_euclid(width) * width;
+    let len = ((last - origin) / width + 1) as usize;
+
+    // Latencies per (series, bucket), then reduced to counts and percentiles.
+    let mut raw: BTreeMap<&str, Vec<RawBucket>> = BTreeMap::new();
+    for e in entries {
+        let idx = ((e.timestamp.timestamp() - origin) / width) as usize;
+        let keys = std::iter::once("all").chain(group_by.map(|g| g.key(e)));
+        for key in keys {
+            raw.entry(key).or_insert_with(|| vec![RawBucket::default(); len])[idx].add(e);
+        }
+    }
+    let series = raw
+        .into_iter()
+        .map(|(key, buckets)| {
+            let buckets = buckets
+                .into_iter()
+                .enumerate()
+                .map(|(i, b)| b.finish(origin + i as i64 * width))
+                .collect();
+            (key.to_string(), buckets)
+        })
+        .collect();
+    TimeSeries { bucket_secs, series }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RawBucket {
+    errors: usize,
+    escalations: usize,
+    latencies: Vec<u64>,
+}
+
+impl RawBucket {
+    fn add(&mut self, e: &TrafficEntry) {
+        self.errors += usize::from(!e.success);
+        self.escalations += usize::from(e.escalated);
+        self.latencies.push(e.latency_ms);
+    }
+
+    fn finish(mut self, start_secs: i64) -> Bucket {
+        self.latencies.sort_unstable();
+        Bucket {
+            start: Utc.timestamp_opt(start_secs, 0).single().unwrap_or_default(),
+            requests: self.latencies.len(),
+            errors: self.errors,
+            escalations: self.escalations,
+            p50_ms: percentile(&self.latencies, 50),
+            p95_ms: percentile(&self.latencies, 95),
+            p99_ms: percentile(&self.latencies, 99),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending slice.
+fn percentile(sorted: &[u64], pct: usize) -> Option<u64> {
+    let rank = (sorted.len() * pct).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// Classifier label counts per profile.
+///
+/// Entries are attributed to the last profile in their cascade chain — the
+/// one whose classifier produced the label.
+pub fn class_distribution(entries: &[TrafficEntry]) -> BTreeMap<String, BTreeMap<String, usize>> {
+    let mut out: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for e in entries {
+        let Some(label) = &e.class_label else {
+            continue;
+        };
+        let profile = e
+            .profile_chain
+            .as_ref()
+            .and_then(|chain| chain.last())
+            .or(e.profile.as_ref())
+            .map_or("default", String::as_str);
+        *out.entry(profile.to_string()).or_default().entry(label.clone()).or_default() += 1;
+    }
+    out
+}
+
+/// Occupancy of one tier's priority gate.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct GateDepth {
+    pub in_flight: usize,
+    pub queued: usize,
+}
+
+/// All gates at one instant.
+#[derive(Debug, Clone, Serialize)]
+pub struct GateSample {
+    pub timestamp: DateTime<Utc>,
+    pub tiers: BTreeMap<String, GateDepth>,
+}
+
+/// Fixed-size history of [`GateSample`]s, oldest first.
+pub struct GateHistory {
+    capacity: usize,
+    samples: Mutex<VecDeque<GateSample>>,
+}
+
+impl GateHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), samples: Mutex::new(VecDeque::new()) }
+    }
+
+    pub fn record(&self, sample: GateSample) {
+        let mut samples = self.samples.lock().expect("gate history lock poisoned");
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> Vec<GateSample> {
+        self.samples.lock().expect("gate history lock poisoned").iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(secs: i64, tier: &str, latency_ms: u64, success: bool) -> TrafficEntry {
+        let mut e = TrafficEntry::new(tier.into(), "b".into(), latency_ms, success);
+        e.timestamp = Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap();
+        e
+    }
+
+    #[test]
+    fn timeseries_fills_gaps_and_computes_percentiles() {
+        let mut entries: Vec<TrafficEntry> = (1..=100).map(|ms| entry(0, "fast", ms, true)).collect();
+        entries.push(entry(130, "deep", 500, false));
+
+        let ts = timeseries(&entries, 60, Some(GroupBy::Tier));
+        assert_eq!(ts.bucket_secs, 60);
+        let all = &ts.series["all"];
+        assert_eq!(all.len(), 3, "empty middle bucket is kept");
+        assert_eq!(all[0].requests, 100);
+        assert_eq!((all[0].p50_ms, all[0].p95_ms, all[0].p99_ms), (Some(50), Some(95), Some(99)));
+        assert_eq!(all[1].requests, 0);
+        assert_eq!(all[1].p50_ms, None);
+        assert_eq!(all[2].errors, 1);
+
+        assert_eq!(ts.series["fast"][2].requests, 0);
+        assert_eq!(ts.series["deep"][2].p99_ms, Some(500));
+        assert_eq!(ts.series["deep"][0].start, all[0].start);
+    }
+
+    #[test]
+    fn timeseries_widens_buckets_for_long_spans() {
+        let entries = vec![entry(0, "fast", 1, true), entry(86_400 * 2, "fast", 1, true)];
+        let ts = timeseries(&entries, 1, None);
+        assert!(ts.series["all"].len() as u64 <= MAX_BUCKETS + 1);
+        assert_eq!(ts.bucket_secs, 120);
+    }
+
+    #[test]
+    fn class_distribution_uses_final_profile() {
+        let mut cascaded = entry(0, "fast", 1, true).with_profile("auto");
+        cascaded = cascaded.with_routing_trace("lights".into(), vec!["auto".into(), "ha".into()]);
+        let direct = entry(0, "fast", 1, true)
+            .with_profile("auto")
+            .with_routing_trace("chat".into(), vec!["auto".into()]);
+        let unclassified = entry(0, "fast", 1, true).with_profile("auto");
+
+        let dist = class_distribution(&[cascaded, direct, unclassified]);
+        assert_eq!(dist["ha"]["lights"], 1);
+        assert_eq!(dist["auto"]["chat"], 1);
+        assert_eq!(dist["auto"].len(), 1);
+    }
+
+    #[test]
+    fn gate_history_keeps_newest_samples() {
+        let history = GateHistory::new(2);
+        for queued in 0..3 {
+            let tiers = BTreeMap::from([("fast".to_string(), GateDepth { in_flight: 1, queued })]);
+            history.record(GateSample { timestamp: Utc::now(), tiers });
+        }
+        let samples = history.samples();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].tiers["fast"].queued, 1);
+        assert_eq!(samples[1].tiers["fast"].queued, 2);
+    }
+}
@@ -0,0 +1,108 @@
+//! Analytics over the traffic window: time series, class mix and gate samples.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    analytics::{self, GroupBy},
+    router::RouterState,
+    traffic::TrafficFilter,
+};
+
+#[derive(Deserialize)]
+pub struct TimeSeriesQuery {
+    #[serde(default = "default_bucket_secs")]
+    bucket_secs: u64,
+    group_by: Option<GroupBy>,
+}
+fn default_bucket_secs() -> u64 {
+    60
+}
+
+/// GET /admin/analytics/timeseries — per-bucket request counts, errors,
+/// escalations and p50/p95/p99 latency over the traffic window.
+///
+/// Query parameters: `bucket_secs` (default 60), `group_by` (`tier`,
+/// `profile` or `backend`) and any `GET /admin/traffic` filter, e.g.
+/// `profile=` for a per-profile drilldown.
+pub async fn analytics_timeseries(
+    State(state): State<Arc<RouterState>>,
+    Query(q): Query<TimeSeriesQuery>,
+    Query(filter): Query<TrafficFilter>,
+) -> impl IntoResponse {
+    let entries = state.traffic.matching(&filter);
+    Json(analytics::timeseries(&entries, q.bucket_secs, q.group_by))
+}
+
+/// GET /admin/analytics/classes — classifier label counts per profile over
+/// the traffic window. Accepts the `GET /admin/traffic` filters.
+pub async fn analytics_classes(
+    State(state): State<Arc<RouterState>>,
+    Query(filter): Query<TrafficFilter>,
+) -> impl IntoResponse {
+    let entries = state.traffic.matching(&filter);
+    Json(json!({ "profiles": analytics::class_distribution(&entries) }))
+}
+
+/// GET /admin/analytics/gates — priority gate in-flight and queued counts,
+/// sampled every few seconds, oldest first.
+pub async fn analytics_gates(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
+    Json(json!({
+        "interval_secs": analytics::GATE_SAMPLE_INTERVAL.as_secs(),
+        "samples": state.gate_history.samples(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        api::admin::tests::{get_json, minimal_state},
+        traffic::TrafficEntry,
+    };
+
+    #[tokio::test]
+    async fn analytics_timeseries_groups_and_filters() {
+        let state = minimal_state();
+        for (profile, latency, ok) in [("chat", 10, true), ("chat", 30, false), ("voice", 20, true)] {
+            state.traffic.push(TrafficEntry::new("local:fast".into(), "mock".into(), latency, ok).with_profile(profile));
+        }
+
+        let ts = get_json(&state, "/admin/analytics/timeseries?group_by=profile").await;
+        let all = ts["series"]["all"].as_array().unwrap();
+        let requests: u64 = all.iter().map(|b| b["requests"].as_u64().unwrap()).sum();
+        assert_eq!(requests, 3);
+        assert!(ts["series"]["chat"].is_array() && ts["series"]["voice"].is_array());
+
+        let chat = get_json(&state, "/admin/analytics/timeseries?profile=chat&bucket_secs=3600").await;
+        let bucket = &chat["series"]["all"][0];
+        assert_eq!(bucket["requests"], 2);
+        assert_eq!(bucket["errors"], 1);
+        assert_eq!(bucket["p99_ms"], 30);
+        assert!(chat["series"].get("voice").is_none());
+    }
+
+    #[tokio::test]
+    async fn analytics_classes_and_gates() {
+        let state = minimal_state();
+        state.traffic.push(
+            TrafficEntry::new("local:fast".into(), "mock".into(), 5, true)
+                .with_profile("default")
+                .with_routing_trace("greeting".into(), vec!["default".into()]),
+        );
+        let classes = get_json(&state, "/admin/analytics/classes").await;
+        assert_eq!(classes["profiles"]["default"]["greeting"], 1);
+
+        state.sample_gates().await;
+        let gates = get_json(&state, "/admin/analytics/gates").await;
+        let sample = &gates["samples"][0];
+        assert_eq!(sample["tiers"]["local:fast"]["queued"], 0);
+        assert_eq!(sample["tiers"]["local:fast"]["in_flight"], 0);
+    }
+}
@@ -0,0 +1,414 @@
+//! Editing the running config: entry reads, upserts, deletes and rule
+//! replacement, persisted through the admin overlay.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    config::{
+        AdminOverlay, ClientConfig, Config, ConfigSource, ProfileConfig, RuleConfig, Section, SplitConfig,
+        TierConfig,
+    },
+    router::RouterState,
+};
+
+/// GET /admin/config/{section}/{id} — one tier, alias, profile, client or
+/// split in full.
+///
+/// `section` is `tiers`, `aliases`, `profiles`, `clients` or `splits`; clients are
+/// identified by their `key_env`. Interpolated values are shown as their
+/// `${...}` template, so the response can be edited and sent back with `PUT`.
+pub async fn config_entry(
+    State(state): State<Arc<RouterState>>,
+    Path((section, id)): Path<(Section, String)>,
+) -> Response {
+    let cfg = state.config();
+    let entry = match section {
+        Section::Tiers => cfg.tiers.iter().find(|t| t.name == id).map(|t| json!(t)),
+        Section::Aliases => cfg.aliases.get(&id).map(|tier| json!({ "tier": tier })),
+        // Inheriting profiles are returned as declared so a PUT round-trip
+        // keeps inheriting rather than freezing the parent's current values.
+        Section::Profiles => cfg.profiles.get(&id).map(|p| match &p.declared {
+            Some(declared) => declared_json(&cfg, &format!("profiles.{id}"), declared),
+            None => json!(p),
+        }),
+        Section::Clients => cfg.clients.iter().find(|c| c.key_env == id).map(|c| json!(c)),
+        Section::Splits => cfg.splits.get(&id).map(|s| json!(s)),
+    };
+    let Some(mut entry) = entry else {
+        return not_found(section, &id);
+    };
+    let path = format!("{}.{id}", section.key());
+    if section == Section::Aliases {
+        entry["tier"] = json!(cfg.display_value(&path, &cfg.aliases[&id]));
+    } else {
+        cfg.show_templates(&path, &mut entry);
+    }
+    Json(entry).into_response()
+}
+
+/// PUT /admin/config/{section}/{id} — create or fully replace an entry.
+///
+/// The body has the same shape as the corresponding TOML section; `name`
+/// (tiers) and `key_env` (clients) are taken from the path. Aliases take
+/// `{"tier": "<tier name>"}`. Values may contain `${...}` placeholders.
+///
+/// The edit is merged with the running layers and validated like a startup
+/// load; on success it is written to `conf.d/admin.toml` and applied live.
+/// Returns `400` for a malformed body and `422` when validation fails, in
+/// which case nothing is persisted.
+pub async fn upsert_config_entry(
+    State(state): State<Arc<RouterState>>,
+    Path((section, id)): Path<(Section, String)>,
+    Json(body): Json<Value>,
+) -> Response {
+    let entry = match entry_to_toml(section, &id, body) {
+        Ok(entry) => entry,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response();
+        }
+    };
+    apply_config_mutation(&state, section, |overlay| overlay.upsert(section, &id, entry)).await
+}
+
+/// DELETE /admin/config/{section}/{id} — remove an entry from the running
+/// config, wherever it was defined.
+///
+/// Fails with `422` if something still references it (e.g. deleting a tier a
+/// profile classifies with).
+pub async fn delete_config_entry(
+    State(state): State<Arc<RouterState>>,
+    Path((section, id)): Path<(Section, String)>,
+) -> Response {
+    let cfg = state.config();
+    let exists = match section {
+        Section::Tiers => cfg.tiers.iter().any(|t| t.name == id),
+        Section::Aliases => cfg.aliases.contains_key(&id),
+        Section::Profiles => cfg.profiles.contains_key(&id),
+        Section::Clients => cfg.clients.iter().any(|c| c.key_env == id),
+        Section::Splits => cfg.splits.contains_key(&id),
+    };
+    if !exists {
+        return not_found(section, &id);
+    }
+    apply_config_mutation(&state, section, |overlay| overlay.remove(section, &id)).await
+}
+
+/// PUT /admin/config/profiles/{name}/rules — replace a profile's rule list.
+///
+/// The body is a JSON array of rules (`when`, request conditions, `route_to`,
+/// `priority`). Other profile fields are left as they are. Send `[]` to clear
+/// all rules.
+pub async fn replace_profile_rules(
+    State(state): State<Arc<RouterState>>,
+    Path(name): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !state.config().profiles.contains_key(&name) {
+        return not_found(Section::Profiles, &name);
+    }
+    let rules = serde_json::from_value::<Vec<RuleConfig>>(body)
+        .map_err(|e| e.to_string())
+        .and_then(|rules| toml::Value::try_from(rules).map_err(|e| e.to_string()));
+    let rules = match rules {
+        Ok(rules) => rules,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response();
+        }
+    };
+    apply_config_mutation(&state, Section::Profiles, |overlay| overlay.set_rules(&name, rules))
+        .await
+}
+
+/// Validate a request body for `section` and convert it to its TOML form.
+fn entry_to_toml(section: Section, id: &str, mut body: Value) -> Result<toml::Value, String> {
+    fn to_toml<T: serde::Serialize>(value: &T) -> Result<toml::Value, String> {
+        toml::Value::try_from(value).map_err(|e| e.to_string())
+    }
+    let id_field = match section {
+        Section::Tiers => Some("name"),
+        Section::Clients => Some("key_env"),
+        _ => None,
+    };
+    if let (Some(field), Some(obj)) = (id_field, body.as_object_mut()) {
+        obj.insert(field.into(), json!(id));
+    }
+    match section {
+        Section::Tiers => to_toml(&serde_json::from_value::<TierConfig>(body).map_err(|e| e.to_string())?),
+        // Stored as sent (after validating its shape) so omitted fields stay
+        // unset and keep inheriting through `extends`.
+        Section::Profiles => {
+            serde_json::from_value::<ProfileConfig>(body.clone()).map_err(|e| e.to_string())?;
+            to_toml(&strip_nulls(body))
+        }
+        Section::Clients => {
+            to_toml(&serde_json::from_value::<ClientConfig>(body).map_err(|e| e.to_string())?)
+        }
+        Section::Splits => {
+            to_toml(&serde_json::from_value::<SplitConfig>(body).map_err(|e| e.to_string())?)
+        }
+        Section::Aliases => body
+            .get("tier")
+            .and_then(Value::as_str)
+            .map(|tier| toml::Value::String(tier.to_owned()))
+            .ok_or_else(|| "expected `{\"tier\": \"<tier name>\"}`".to_owned()),
+    }
+}
+
+/// Drop `null` members recursively — TOML has no null, and an absent key is
+/// how an optional field is left unset.
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(strip_nulls).collect()),
+        other => other,
+    }
+}
+
+/// A declared (pre-`extends`) profile table as JSON, templates restored.
+pub(super) fn declared_json(cfg: &Config, path: &str, declared: &toml::Value) -> Value {
+    let mut value = serde_json::to_value(declared).unwrap_or_default();
+    cfg.show_templates(path, &mut value);
+    value
+}
+
+/// Apply `mutate` to the admin overlay, validate the merged result, then
+/// persist the overlay and swap the live config.
+pub(super) async fn apply_config_mutation(
+    state: &RouterState,
+    section: Section,
+    mutate: impl FnOnce(&mut AdminOverlay),
+) -> Response {
+    let _guard = state.config_write.lock().await;
+
+    let mut overlay = match AdminOverlay::read(&state.config_path) {
+        Ok(overlay) => overlay,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{e:#}") })),
+            )
+                .into_response();
+        }
+    };
+    mutate(&mut overlay);
+
+    let new_cfg = match Config::load_with_overlay(&state.config_path, Some(&overlay)) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            state.record_reload_failure(ConfigSource::AdminApi);
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": format!("{e:#}") })),
+            )
+                .into_response();
+        }
+    };
+    if let Err(e) = overlay.write(&state.config_path) {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{e:#}") })),
+        )
+            .into_response();
+    }
+    let version = state.replace_config(Arc::new(new_cfg), ConfigSource::AdminApi);
+    tracing::info!(section = section.key(), version, "config updated via admin API");
+
+    Json(json!({ "status": "applied", "version": version })).into_response()
+}
+
+pub(super) fn not_found(section: Section, id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("no entry `{id}` in `{}`", section.key()) })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use serde_json::json;
+    use tower::ServiceExt; // oneshot
+
+    use crate::{
+        api::admin::{self, tests::{body_json, send, state_on_disk}},
+        config::Config,
+    };
+
+    #[tokio::test]
+    async fn put_tier_applies_live_and_persists_to_admin_overlay() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(
+            &state,
+            "PUT",
+            "/admin/config/tiers/local:deep",
+            json!({ "backend": "ollama", "model": "qwen3:8b" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(state.config().resolve_tier("local:deep").is_some());
+        assert!(state.index.load().gates.contains_key("local:deep"), "new tier is gated");
+
+        // Survives a restart: a fresh load from disk sees the new tier.
+        let reloaded = Config::load(&state.config_path).unwrap();
+        assert_eq!(reloaded.resolve_tier("local:deep").unwrap().model, "qwen3:8b");
+        assert!(crate::config::AdminOverlay::path(&state.config_path).exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn client_and_profile_limit_edits_apply_without_restart() {
+        let (state, dir) = state_on_disk();
+        // PATH is always set, so it stands in for a client key variable.
+        let key = std::env::var("PATH").unwrap();
+        let (status, json) =
+            send(&state, "PUT", "/admin/config/clients/PATH", json!({ "profile": "default" })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(json.get("restart_required_for").is_none());
+        let client = state.index.load().clients.get(&key).cloned().expect("client key mapped");
+        assert_eq!((client.profile.as_str(), client.name.as_str()), ("default", "PATH"));
+
+        let mut profile = send(&state, "GET", "/admin/config/profiles/default", json!(null)).await.1;
+        profile["rate_limit_rpm"] = json!(30);
+        let (status, _) = send(&state, "PUT", "/admin/config/profiles/default", profile).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state.index.load().profile_limiters["default"].rpm, 30);
+
+        let (status, _) = send(&state, "DELETE", "/admin/config/clients/PATH", json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(state.index.load().clients.is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn put_tier_with_unknown_backend_is_rejected_and_not_persisted() {
+        let (state, dir) = state_on_disk();
+        let (status, json) = send(
+            &state,
+            "PUT",
+            "/admin/config/tiers/cloud:x",
+            json!({ "backend": "nope", "model": "m" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(json["error"].as_str().unwrap().contains("unknown backend"), "{json}");
+        assert!(state.config().resolve_tier("cloud:x").is_none());
+        assert!(!dir.join("conf.d").exists(), "nothing should be written on failure");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn delete_alias_defined_in_config_toml_survives_reload() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(&state, "DELETE", "/admin/config/aliases/hint:fast", json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!state.config().aliases.contains_key("hint:fast"));
+
+        let reloaded = Config::load(&state.config_path).unwrap();
+        assert!(!reloaded.aliases.contains_key("hint:fast"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn delete_referenced_tier_is_rejected() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(&state, "DELETE", "/admin/config/tiers/local:fast", json!(null)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(state.config().resolve_tier("local:fast").is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn delete_unknown_entry_returns_404() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(&state, "DELETE", "/admin/config/profiles/ghost", json!(null)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn put_profile_rules_keeps_other_profile_fields() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(
+            &state,
+            "PUT",
+            "/admin/config/profiles/default/rules",
+            json!([{ "when": { "intent": "greeting" }, "route_to": "local:fast", "priority": 5 }]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let cfg = state.config();
+        let profile = &cfg.profiles["default"];
+        assert_eq!(profile.rules.len(), 1);
+        assert_eq!(profile.classifier, "local:fast");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn get_entry_returns_full_profile() {
+        let (state, dir) = state_on_disk();
+        let req = Request::builder()
+            .uri("/admin/config/profiles/default")
+            .body(Body::empty())
+            .unwrap();
+        let resp = admin::router(Arc::clone(&state)).oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["mode"], "dispatch");
+        assert_eq!(json["max_auto_tier"], "local:fast");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn inheriting_profile_shows_declared_and_effective_values() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(
+            &state,
+            "PUT",
+            "/admin/config/profiles/child",
+            json!({ "extends": "default", "system_prompt": "child prompt" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, cfg) = send(&state, "GET", "/admin/config", json!(null)).await;
+        let child = &cfg["profiles"]["child"];
+        assert_eq!(child["mode"], "dispatch", "effective mode inherited from default");
+        assert_eq!(child["extends"], "default");
+        assert_eq!(child["declared"]["system_prompt"], "child prompt");
+        assert!(child["declared"].get("mode").is_none());
+
+        // Entry GET returns the declared form, so it round-trips through PUT.
+        let (_, entry) = send(&state, "GET", "/admin/config/profiles/child", json!(null)).await;
+        assert!(entry.get("classifier").is_none(), "{entry}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
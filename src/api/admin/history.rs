@@ -0,0 +1,144 @@
+//! Config version history: listing, diffs and rollback.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::router::RouterState;
+
+/// GET /admin/config/history — retained config versions, newest first.
+///
+/// Each entry has `version`, `applied_at`, `source` (`startup`,
+/// `file_watcher`, `admin_reload`, `admin_api` or `rollback`) and, for
+/// rollbacks, `rollback_of`.
+pub async fn config_history(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
+    let history = state.history.lock().expect("history lock poisoned");
+    let current = history.current().version;
+    let versions: Vec<_> = history.versions().collect();
+    Json(json!({ "current": current, "versions": versions }))
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+/// GET /admin/config/diff?from=N&to=M — path-level changes between two versions.
+///
+/// `to` defaults to the current version and `from` to the version before
+/// `to`. Returns `404` if either version has been evicted from history.
+pub async fn config_diff(
+    State(state): State<Arc<RouterState>>,
+    Query(q): Query<DiffQuery>,
+) -> Response {
+    let (from, to, old, new) = {
+        let history = state.history.lock().expect("history lock poisoned");
+        let to = q.to.unwrap_or(history.current().version);
+        let from = q.from.unwrap_or(to.saturating_sub(1));
+        match (history.get(from), history.get(to)) {
+            (Some(a), Some(b)) => (from, to, Arc::clone(&a.config), Arc::clone(&b.config)),
+            _ => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(json!({ "error": format!("version {from} or {to} is not in history") })),
+                )
+                    .into_response();
+            }
+        }
+    };
+    Json(json!({
+        "from": from,
+        "to": to,
+        "changes": crate::config::diff(&old, &new),
+    }))
+    .into_response()
+}
+
+/// POST /admin/config/rollback/{version} — swap a retained config back in.
+///
+/// The restored version's admin overlay is written back to
+/// `conf.d/admin.toml`, so the rollback survives later admin edits, reloads
+/// and restarts; `config.toml` and other `conf.d/` files are untouched.
+pub async fn config_rollback(
+    State(state): State<Arc<RouterState>>,
+    Path(version): Path<u64>,
+) -> Response {
+    match state.rollback(version).await {
+        Ok(Some(new_version)) => {
+            tracing::warn!(restored = version, version = new_version, "config rolled back via admin API");
+            Json(json!({ "status": "rolled_back", "restored": version, "version": new_version }))
+                .into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("version {version} is not in history") })),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{e:#}") })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::{
+        api::admin::tests::{send, state_on_disk},
+        config::Config,
+    };
+
+    #[tokio::test]
+    async fn history_diff_and_rollback_round_trip() {
+        let (state, dir) = state_on_disk();
+        let (status, json) = send(
+            &state,
+            "PUT",
+            "/admin/config/aliases/hint:fast",
+            json!({ "tier": "local:fast" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["version"], 2);
+        let (_, _) = send(&state, "DELETE", "/admin/config/aliases/hint:fast", json!(null)).await;
+        assert!(!state.config().aliases.contains_key("hint:fast"));
+
+        let (status, history) = send(&state, "GET", "/admin/config/history", json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(history["current"], 3);
+        assert_eq!(history["versions"][0]["source"], "admin_api");
+        assert_eq!(history["versions"][2]["source"], "startup");
+
+        let (status, diff) = send(&state, "GET", "/admin/config/diff", json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(diff["from"], 2);
+        assert_eq!(diff["to"], 3);
+        assert_eq!(diff["changes"][0]["path"], "aliases.hint:fast");
+        assert_eq!(diff["changes"][0]["kind"], "removed");
+
+        let (status, json) = send(&state, "POST", "/admin/config/rollback/1", json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["version"], 4);
+        assert!(state.config().aliases.contains_key("hint:fast"));
+        // The restored overlay is on disk, so a reload keeps the rollback.
+        let reloaded = Config::load(&state.config_path).unwrap();
+        assert!(reloaded.aliases.contains_key("hint:fast"), "rollback survives a reload");
+
+        let (status, _) = send(&state, "POST", "/admin/config/rollback/99", json!(null)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -7,27 +7,55 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Query, State},
+    extract::State,
     http::{header, StatusCode},
     middleware,
-    response::IntoResponse,
-    routing::{get, post},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
     Json, Router,
 };
 use serde::Deserialize;
 use serde_json::{json, Value};
 
-use crate::{backends::BackendClient, router::RouterState};
+use crate::{backends::BackendClient, config::ConfigSource, router::RouterState};
+
+mod analytics;
+mod config_mutation;
+mod history;
+mod replay;
+mod splits;
+mod traffic;
+
+use config_mutation::declared_json;
 
 /// Build the admin-facing axum router (port 8081).
 pub fn router(state: Arc<RouterState>) -> Router {
     Router::new()
         .route("/", get(dashboard))
         .route("/admin/health", get(health))
-        .route("/admin/traffic", get(traffic))
+        .route("/admin/traffic", get(traffic::traffic))
+        .route("/admin/traffic/stream", get(traffic::traffic_stream))
+        .route("/admin/traffic/{id}/replay", post(replay::replay))
+        .route("/admin/route/explain", post(replay::route_explain))
+        .route("/admin/analytics/timeseries", get(analytics::analytics_timeseries))
+        .route("/admin/analytics/classes", get(analytics::analytics_classes))
+        .route("/admin/analytics/gates", get(analytics::analytics_gates))
         .route("/admin/config", get(config))
+        .route(
+            "/admin/config/{section}/{id}",
+            get(config_mutation::config_entry)
+                .put(config_mutation::upsert_config_entry)
+                .delete(config_mutation::delete_config_entry),
+        )
+        .route("/admin/config/profiles/{name}/rules", put(config_mutation::replace_profile_rules))
+        .route("/admin/config/history", get(history::config_history))
+        .route("/admin/config/diff", get(history::config_diff))
+        .route("/admin/config/rollback/{version}", post(history::config_rollback))
+        .route("/admin/splits", get(splits::splits))
+        .route("/admin/splits/{name}/weights", put(splits::set_split_weights))
         .route("/admin/backends/health", get(backends_health))
         .route("/admin/reload", post(reload))
+        .route("/admin/log-level", get(log_level).put(set_log_level))
         .route("/metrics", get(super::metrics::metrics))
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -38,7 +66,7 @@ pub fn router(state: Arc<RouterState>) -> Router {
 
 /// GET / — admin dashboard (single-page UI)
 pub async fn dashboard() -> impl IntoResponse {
-    const HTML: &str = include_str!("admin_ui.html");
+    const HTML: &str = include_str!("../admin_ui.html");
     (StatusCode::OK, [(header::CONTENT_TYPE, "text/html; charset=utf-8")], HTML)
 }
 
@@ -64,29 +92,11 @@ pub async fn health(State(state): State<Arc<RouterState>>) -> impl IntoResponse
     }))
 }
 
-#[derive(Deserialize)]
-pub struct TrafficQuery {
-    #[serde(default = "default_limit")]
-    limit: usize,
-}
-fn default_limit() -> usize {
-    100
-}
-
-/// GET /admin/traffic?limit=N — recent N traffic entries (default 100)
-pub async fn traffic(
-    State(state): State<Arc<RouterState>>,
-    Query(q): Query<TrafficQuery>,
-) -> impl IntoResponse {
-    let entries = state.traffic.recent(q.limit).await;
-    let stats = state.traffic.stats().await;
-    Json(json!({
-        "stats": stats,
-        "entries": entries,
-    }))
-}
-
 /// GET /admin/config — returns the current config with secrets redacted
+///
+/// Values that were interpolated from `${...}` placeholders at load time are
+/// shown as their template (e.g. `http://${OLLAMA_HOST}:11434`), never as the
+/// resolved value.
 pub async fn config(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
     let cfg = state.config();
 
@@ -95,14 +105,16 @@ pub async fn config(State(state): State<Arc<RouterState>>) -> impl IntoResponse
         .backends
         .iter()
         .map(|(name, b)| {
+            let path = format!("backends.{name}");
             json!({
                 "name": name,
                 "provider": b.provider.to_string(),
-                "base_url": b.base_url,
+                "base_url": cfg.display_value(&format!("{path}.base_url"), &b.base_url),
                 "has_api_key": b.has_api_key_configured(),
                 "api_key_source": b.api_key_source_type(),
                 // Expose the env var *name* (never the resolved value) for diagnostics.
-                "api_key_env": b.api_key_env,
+                "api_key_env": b.api_key_env.as_deref()
+                    .map(|v| cfg.display_value(&format!("{path}.api_key_env"), v)),
             })
         })
         .collect();
@@ -111,10 +123,11 @@ pub async fn config(State(state): State<Arc<RouterState>>) -> impl IntoResponse
         .tiers
         .iter()
         .map(|t| {
+            let path = format!("tiers.{}", t.name);
             let mut tier = json!({
                 "name": t.name,
-                "backend": t.backend,
-                "model": t.model,
+                "backend": cfg.display_value(&format!("{path}.backend"), &t.backend),
+                "model": cfg.display_value(&format!("{path}.model"), &t.model),
             });
             if let Some(think) = t.think {
                 tier["think"] = json!(think);
@@ -122,6 +135,9 @@ pub async fn config(State(state): State<Arc<RouterState>>) -> impl IntoResponse
             if let Some(max_ctx) = t.max_context_tokens {
                 tier["max_context_tokens"] = json!(max_ctx);
             }
+            if let Value::Object(capabilities) = json!(t.capabilities) {
+                tier.as_object_mut().expect("tier is an object").extend(capabilities);
+            }
             tier
         })
         .collect();
@@ -130,20 +146,28 @@ pub async fn config(State(state): State<Arc<RouterState>>) -> impl IntoResponse
         .profiles
         .iter()
         .map(|(name, p)| {
-            (
-                name.clone(),
-                json!({
-                    "mode": p.mode.to_string(),
-                    "classifier": p.classifier,
-                    "max_auto_tier": p.max_auto_tier,
-                    "expert_requires_flag": p.expert_requires_flag,
-                    "rate_limit_rpm": p.rate_limit_rpm,
-                }),
-            )
+            let path = format!("profiles.{name}");
+            let mut profile = json!({
+                "mode": p.mode.to_string(),
+                "classifier": cfg.display_value(&format!("{path}.classifier"), &p.classifier),
+                "max_auto_tier": cfg.display_value(&format!("{path}.max_auto_tier"), &p.max_auto_tier),
+                "expert_requires_flag": p.expert_requires_flag,
+                "rate_limit_rpm": p.rate_limit_rpm,
+            });
+            // Inheriting profiles: the fields above are effective values;
+            // `declared` is the profile as written, before `extends` merging.
+            if let (Some(parent), Some(declared)) = (&p.extends, &p.declared) {
+                profile["extends"] = json!(parent);
+                profile["declared"] = declared_json(&cfg, &path, declared);
+            }
+            (name.clone(), profile)
         })
         .collect::<serde_json::Map<_, _>>()
         .into();
 
+    let mut interpolated: Vec<&String> = cfg.templates.keys().collect();
+    interpolated.sort();
+
     Json(json!({
         "gateway": {
             "client_port": cfg.gateway.client_port,
@@ -154,6 +178,9 @@ pub async fn config(State(state): State<Arc<RouterState>>) -> impl IntoResponse
         "tiers": tiers,
         "aliases": cfg.aliases,
         "profiles": profiles,
+        "splits": cfg.splits,
+        // Paths of values resolved from `${...}` placeholders (templates shown above).
+        "interpolated": interpolated,
     }))
 }
 
@@ -164,7 +191,7 @@ pub async fn backends_health(State(state): State<Arc<RouterState>>) -> impl Into
     let health_threshold = cfg.gateway.health_error_threshold.unwrap_or(0.7);
     // Snapshot of traffic-based backend health (empty when no traffic yet or window=0).
     let traffic_health = if health_window > 0 {
-        state.traffic.backend_health(health_window, health_threshold).await
+        state.traffic.backend_health(health_window, health_threshold)
     } else {
         std::collections::HashMap::new()
     };
@@ -227,18 +254,75 @@ pub async fn backends_health(State(state): State<Arc<RouterState>>) -> impl Into
 pub async fn reload(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
     match crate::config::Config::load(&state.config_path) {
         Ok(new_cfg) => {
-            state.replace_config(Arc::new(new_cfg));
-            tracing::info!("config reloaded via POST /admin/reload");
-            Json(json!({ "status": "reloaded" })).into_response()
+            let version = state.replace_config(Arc::new(new_cfg), ConfigSource::AdminReload);
+            tracing::info!(version, "config reloaded via POST /admin/reload");
+            Json(json!({ "status": "reloaded", "version": version })).into_response()
+        }
+        Err(e) => {
+            state.record_reload_failure(ConfigSource::AdminReload);
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Log level
+// ---------------------------------------------------------------------------
+
+/// Body of `PUT /admin/log-level`.
+#[derive(Deserialize)]
+pub struct LogLevelBody {
+    /// Filter in `RUST_LOG` syntax, e.g. `lm_gateway=debug,tower_http=info`.
+    pub filter: String,
+}
+
+/// GET /admin/log-level — the active log filter.
+pub async fn log_level(State(state): State<Arc<RouterState>>) -> Response {
+    let Some(control) = &state.log_control else {
+        return log_control_unavailable();
+    };
+    Json(json!({ "filter": control.current(), "env_override": control.env_override() }))
+        .into_response()
+}
+
+/// PUT /admin/log-level — swap the log filter without a restart.
+///
+/// The change is in memory only: it lasts until the process restarts or a
+/// config reload changes `gateway.log_level`. An unparseable filter returns
+/// `422 Unprocessable Entity` and leaves the active one in place.
+pub async fn set_log_level(
+    State(state): State<Arc<RouterState>>,
+    Json(body): Json<LogLevelBody>,
+) -> Response {
+    let Some(control) = &state.log_control else {
+        return log_control_unavailable();
+    };
+    let previous = control.current();
+    match control.set(&body.filter) {
+        Ok(()) => {
+            tracing::warn!(filter = %body.filter, %previous, "log level changed via admin API");
+            Json(json!({ "filter": body.filter, "previous": previous })).into_response()
         }
         Err(e) => (
             StatusCode::UNPROCESSABLE_ENTITY,
-            Json(json!({ "error": e.to_string() })),
+            Json(json!({ "error": format!("{e:#}") })),
         )
             .into_response(),
     }
 }
 
+fn log_control_unavailable() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({ "error": "log level control is not available" })),
+    )
+        .into_response()
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -255,30 +339,20 @@ mod tests {
     use crate::{
         config::{BackendConfig, Config, GatewayConfig, ProfileConfig, RoutingMode, TierConfig},
         router::RouterState,
-        traffic::{TrafficEntry, TrafficLog},
+        traffic::TrafficLog,
     };
 
     // -----------------------------------------------------------------------
     // Test helpers
     // -----------------------------------------------------------------------
 
-    fn state_with_backend(base_url: &str) -> Arc<RouterState> {
+    pub(super) fn state_with_backend(base_url: &str) -> Arc<RouterState> {
         let config = Config {
             gateway: GatewayConfig {
                 client_port: 8080,
                 admin_port: 8081,
                 traffic_log_capacity: 100,
-                log_level: None,
-                rate_limit_rpm: None,
-                admin_token_env: None,
-                max_retries: None,
-                retry_delay_ms: None,
-                health_window: None,
-                health_error_threshold: None,
-                public_profile: None,
-                request_timeout_ms: None,
-                traffic_log_debug: false,
-                profile_dir: None,
+                ..Default::default()
             },
             backends: {
                 let mut m = std::collections::HashMap::new();
@@ -302,6 +376,7 @@ mod tests {
                     model: "fast-model".into(),
                     think: None,
                     max_context_tokens: None,
+                    capabilities: Default::default(),
                 },
             ],
             aliases: {
@@ -329,6 +404,9 @@ mod tests {
                 m
             },
             clients: vec![],
+            splits: Default::default(),
+            templates: Default::default(),
+            admin_overlay: Default::default(),
         };
         Arc::new(RouterState::new(
             Arc::new(config),
@@ -337,15 +415,69 @@ mod tests {
         ))
     }
 
-    fn minimal_state() -> Arc<RouterState> {
+    pub(super) fn minimal_state() -> Arc<RouterState> {
         state_with_backend("http://127.0.0.1:0")
     }
 
-    async fn body_json(body: Body) -> serde_json::Value {
+    pub(super) async fn body_json(body: Body) -> serde_json::Value {
         let bytes = to_bytes(body, usize::MAX).await.unwrap();
         serde_json::from_slice(&bytes).unwrap()
     }
 
+    pub(super) async fn get_json(state: &Arc<RouterState>, uri: &str) -> serde_json::Value {
+        let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = super::router(Arc::clone(state)).oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        body_json(resp.into_body()).await
+    }
+
+    pub(super) const ON_DISK_CONFIG: &str = r#"
+[gateway]
+
+[backends.ollama]
+base_url = "http://localhost:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "qwen2.5:1.5b"
+
+[aliases]
+"hint:fast" = "local:fast"
+
+[profiles.default]
+mode          = "dispatch"
+classifier    = "local:fast"
+max_auto_tier = "local:fast"
+"#;
+
+    /// State backed by a real config file in a fresh temp dir.
+    pub(super) fn state_on_disk() -> (Arc<RouterState>, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("lmg-admin-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, ON_DISK_CONFIG).unwrap();
+        let config = Config::load(&path).unwrap();
+        let state = Arc::new(RouterState::new(
+            Arc::new(config),
+            path,
+            Arc::new(TrafficLog::new(100)),
+        ));
+        (state, dir)
+    }
+
+    pub(super) async fn send(state: &Arc<RouterState>, method: &str, uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = super::router(Arc::clone(state)).oneshot(req).await.unwrap();
+        let status = resp.status();
+        (status, body_json(resp.into_body()).await)
+    }
+
     // -----------------------------------------------------------------------
     // GET /admin/health
     // -----------------------------------------------------------------------
@@ -368,49 +500,6 @@ mod tests {
         assert_eq!(json["backends"], 1);
     }
 
-    // -----------------------------------------------------------------------
-    // GET /admin/traffic
-    // -----------------------------------------------------------------------
-
-    #[tokio::test]
-    async fn traffic_returns_stats_and_empty_entries_list_on_fresh_log() {
-        let app = super::router(minimal_state());
-        let req = Request::builder()
-            .method("GET")
-            .uri("/admin/traffic?limit=10")
-            .body(Body::empty())
-            .unwrap();
-
-        let resp = app.oneshot(req).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::OK);
-
-        let json = body_json(resp.into_body()).await;
-        assert_eq!(json["stats"]["total_requests"], 0);
-        assert!(json["entries"].as_array().unwrap().is_empty());
-    }
-
-    #[tokio::test]
-    async fn traffic_returns_pushed_entries_newest_first() {
-        let state = minimal_state();
-        state.traffic.push(TrafficEntry::new("local:fast".into(), "mock".into(), 50, true));
-        state.traffic.push(TrafficEntry::new("cloud:economy".into(), "mock".into(), 150, true));
-
-        let app = super::router(Arc::clone(&state));
-        let req = Request::builder()
-            .method("GET")
-            .uri("/admin/traffic?limit=10")
-            .body(Body::empty())
-            .unwrap();
-
-        let resp = app.oneshot(req).await.unwrap();
-        let json = body_json(resp.into_body()).await;
-        let entries = json["entries"].as_array().unwrap();
-        assert_eq!(entries.len(), 2);
-        // Newest first — cloud:economy was pushed last
-        assert_eq!(entries[0]["tier"], "cloud:economy");
-        assert_eq!(entries[1]["tier"], "local:fast");
-    }
-
     // -----------------------------------------------------------------------
     // GET /admin/config
     // -----------------------------------------------------------------------
@@ -437,6 +526,28 @@ mod tests {
         assert!(b.get("api_key").is_none(), "raw api_key must not be in response");
     }
 
+    #[tokio::test]
+    async fn config_shows_interpolation_template_instead_of_resolved_value() {
+        let state = minimal_state();
+        let mut cfg = (*state.config()).clone();
+        cfg.templates.insert(
+            "backends.mock.base_url".into(),
+            "http://${OLLAMA_HOST}:11434".into(),
+        );
+        state.replace_config(Arc::new(cfg), crate::config::ConfigSource::AdminApi);
+
+        let app = super::router(state);
+        let req = Request::builder()
+            .method("GET")
+            .uri("/admin/config")
+            .body(Body::empty())
+            .unwrap();
+
+        let json = body_json(app.oneshot(req).await.unwrap().into_body()).await;
+        assert_eq!(json["backends"][0]["base_url"], "http://${OLLAMA_HOST}:11434");
+        assert_eq!(json["interpolated"][0], "backends.mock.base_url");
+    }
+
     #[tokio::test]
     async fn config_serializes_routing_mode_using_display_not_debug() {
         let app = super::router(minimal_state());
@@ -499,7 +610,68 @@ mod tests {
         let backends = json["backends"].as_array().unwrap();
         assert_eq!(backends[0]["status"], "unreachable");
     }
-}
 
+    // -----------------------------------------------------------------------
+    // Log level
+    // -----------------------------------------------------------------------
+
+    /// On-disk state with a live log filter; keep the subscriber alive.
+    fn state_with_log_control() -> (Arc<RouterState>, std::path::PathBuf, impl tracing::Subscriber) {
+        let (state, dir) = state_on_disk();
+        let (control, subscriber) = crate::logging::LogControl::detached("lm_gateway=info");
+        let state = RouterState::new(state.config(), state.config_path.clone(), Arc::new(TrafficLog::new(100)))
+            .with_log_control(control);
+        (Arc::new(state), dir, subscriber)
+    }
 
+    #[tokio::test]
+    async fn put_log_level_changes_filter() {
+        let (state, dir, _subscriber) = state_with_log_control();
+        let (status, json) = send(
+            &state,
+            "PUT",
+            "/admin/log-level",
+            json!({ "filter": "lm_gateway::router=trace,tower_http=info" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["previous"], "lm_gateway=info");
+
+        let (_, json) = send(&state, "GET", "/admin/log-level", json!(null)).await;
+        assert_eq!(json["filter"], "lm_gateway::router=trace,tower_http=info");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 
+    #[tokio::test]
+    async fn put_invalid_log_level_is_rejected() {
+        let (state, dir, _subscriber) = state_with_log_control();
+        let (status, _) = send(&state, "PUT", "/admin/log-level", json!({ "filter": "lm_gateway=loud" })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(state.log_control.as_ref().unwrap().current(), "lm_gateway=info");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn reload_applies_changed_log_level() {
+        let (state, dir, _subscriber) = state_with_log_control();
+        let edited = ON_DISK_CONFIG.replacen("[gateway]", "[gateway]\nlog_level = \"lm_gateway=debug\"", 1);
+        std::fs::write(&state.config_path, edited).unwrap();
+
+        let (status, _) = send(&state, "POST", "/admin/reload", json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state.log_control.as_ref().unwrap().current(), "lm_gateway=debug");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn log_level_without_control_is_unavailable() {
+        let (state, dir) = state_on_disk();
+        let (status, _) = send(&state, "GET", "/admin/log-level", json!(null)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,266 @@
+//! Re-running captured requests, and dry-run routing traces.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    router::{self, RequestMeta, RouterState},
+    traffic::TrafficFilter,
+};
+
+/// Body of `POST /admin/traffic/{id}/replay`. Set at most one target.
+#[derive(Deserialize)]
+pub struct ReplayRequest {
+    /// Dispatch straight to this tier (or alias), skipping profile routing.
+    tier: Option<String>,
+    /// Route through this profile; defaults to the original entry's profile.
+    profile: Option<String>,
+    /// Record the replay in the traffic log, tagged with `replay_of`.
+    #[serde(default)]
+    record: bool,
+}
+
+/// POST /admin/traffic/{id}/replay — re-run a captured request.
+///
+/// Needs the request body on the original entry, so the gateway must be built
+/// with the `debug-traffic` feature and run with `traffic_log_debug = true`.
+/// The captured body is the client's, so a profile replay applies the
+/// profile's prompts once, as the original did. A tier replay gets the
+/// original profile's `system_prompt` (class prompts are not re-applied).
+///
+/// Responds with the original entry, the replay's entry and the backend
+/// response side by side. Replays stay out of the traffic log unless
+/// `record` is set.
+pub async fn replay(
+    State(state): State<Arc<RouterState>>,
+    Path(id): Path<String>,
+    Json(req): Json<ReplayRequest>,
+) -> Response {
+    if req.tier.is_some() && req.profile.is_some() {
+        return unprocessable("set `tier` or `profile`, not both");
+    }
+    let filter = TrafficFilter { id: Some(id.clone()), ..Default::default() };
+    let Some(original) = state.traffic.matching(&filter).into_iter().next() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("no traffic entry `{id}` in the window") })),
+        )
+            .into_response();
+    };
+    let Some(mut body) = original.captured_request_body() else {
+        return unprocessable(
+            "no request body captured for this entry — requires the `debug-traffic` \
+             feature and `traffic_log_debug = true`",
+        );
+    };
+
+    let meta = RequestMeta {
+        client: original.client.clone(),
+        priority: original.priority,
+        replay_of: Some(id.clone()),
+        skip_traffic_log: !req.record,
+        ..Default::default()
+    };
+    let result = match &req.tier {
+        Some(tier) => {
+            let config = state.config();
+            let prompt = original.profile.as_deref().and_then(|p| config.profile(p)).and_then(|p| p.system_prompt.as_deref());
+            if let Some(prompt) = prompt {
+                router::inject_system_prompt(&mut body, prompt);
+            }
+            router::route_to_tier(&state, body, tier, &meta).await
+        }
+        None => {
+            let profile = req.profile.as_deref().or(original.profile.as_deref());
+            router::route(&state, body, profile, &meta, false, false).await
+        }
+    };
+    tracing::info!(replay_of = %id, tier = ?req.tier, profile = ?req.profile, ok = result.is_ok(), "traffic entry replayed");
+
+    let (replay, response) = match result {
+        Ok((response, entry)) => (json!(entry), response),
+        Err(e) => (json!({ "success": false, "error": format!("{e:#}") }), Value::Null),
+    };
+    let mut original = json!(original);
+    if let Some(obj) = original.as_object_mut() {
+        obj.remove("debug_request_body");
+    }
+    Json(json!({ "original": original, "replay": replay, "response": response })).into_response()
+}
+
+/// Query for `POST /admin/route/explain`.
+#[derive(Deserialize)]
+pub struct ExplainQuery {
+    /// Profile to route through (default: `default`).
+    profile: Option<String>,
+    /// Behave as if the client sent `X-LMG-Expert: true`.
+    #[serde(default)]
+    expert: bool,
+}
+
+/// POST /admin/route/explain — dry-run a chat request through the router.
+///
+/// The body is a `/v1/chat/completions` request. Returns the routing trace —
+/// classifier input and output, tags, every rule evaluated, cascade hops,
+/// context-window bumps and the final tier — without calling that tier.
+/// Works regardless of `gateway.allow_dry_run`, which only gates the client
+/// header.
+pub async fn route_explain(
+    State(state): State<Arc<RouterState>>,
+    Query(q): Query<ExplainQuery>,
+    Json(body): Json<Value>,
+) -> Response {
+    // Client routing falls back to `default` for unknown profiles; here a
+    // typo should be reported rather than explained away.
+    if let Some(profile) = q.profile.as_deref().filter(|p| !state.config().profiles.contains_key(*p)) {
+        return unprocessable(&format!("unknown profile `{profile}`"));
+    }
+    match router::explain(&state, body, q.profile.as_deref(), &RequestMeta::default(), q.expert).await {
+        Ok(explanation) => Json(explanation).into_response(),
+        Err(e) => unprocessable(&format!("{e:#}")),
+    }
+}
+
+fn unprocessable(error: &str) -> Response {
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "error": error }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::{
+        api::admin::tests::{minimal_state, send},
+        traffic::TrafficEntry,
+    };
+
+    #[tokio::test]
+    async fn replay_rejects_unknown_entry_and_missing_body() {
+        let state = minimal_state();
+        let (status, _) = send(&state, "POST", "/admin/traffic/nope/replay", json!({})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let entry = TrafficEntry::new("local:fast".into(), "mock".into(), 5, true).with_id("req-1");
+        state.traffic.push(entry);
+        let (status, json) = send(&state, "POST", "/admin/traffic/req-1/replay", json!({})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(json["error"].as_str().unwrap().contains("debug-traffic"), "{json}");
+
+        let both = json!({ "tier": "local:fast", "profile": "default" });
+        let (status, _) = send(&state, "POST", "/admin/traffic/req-1/replay", both).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[cfg(feature = "debug-traffic")]
+    #[tokio::test]
+    async fn replay_runs_captured_body_against_tier() {
+        use wiremock::{matchers::{method, path}, Mock, MockServer, ResponseTemplate};
+
+        use crate::api::admin::tests::state_with_backend;
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "Replayed answer that is long enough." } }]
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let state = state_with_backend(&server.uri());
+        let captured = json!({ "model": "fast-model", "messages": [{ "role": "user", "content": "hi" }] });
+        state.traffic.push(
+            TrafficEntry::new("local:fast".into(), "mock".into(), 5, false)
+                .with_id("req-1")
+                .with_error("boom")
+                .with_debug_request_body(captured),
+        );
+
+        let (status, json) =
+            send(&state, "POST", "/admin/traffic/req-1/replay", json!({ "tier": "local:fast" })).await;
+        assert_eq!(status, StatusCode::OK, "{json}");
+        assert_eq!(json["original"]["success"], false);
+        assert!(json["original"].get("debug_request_body").is_none());
+        assert_eq!(json["replay"]["success"], true);
+        assert_eq!(json["replay"]["replay_of"], "req-1");
+        assert_eq!(json["response"]["choices"][0]["message"]["content"], "Replayed answer that is long enough.");
+        assert_eq!(state.traffic.recent(10).len(), 1, "unrecorded replay stays out of the log");
+
+        let (_, json) = send(
+            &state,
+            "POST",
+            "/admin/traffic/req-1/replay",
+            json!({ "profile": "default", "record": true }),
+        )
+        .await;
+        assert_eq!(json["replay"]["profile"], "default");
+        assert_eq!(state.traffic.recent(1)[0].replay_of.as_deref(), Some("req-1"));
+    }
+
+    #[cfg(feature = "debug-traffic")]
+    #[tokio::test]
+    async fn replay_applies_profile_system_prompt_once() {
+        use std::sync::Arc;
+
+        use wiremock::{matchers::{method, path}, Mock, MockServer, ResponseTemplate};
+
+        use crate::{api::admin::tests::state_with_backend, router::RouterState, traffic::TrafficLog};
+
+        let server = MockServer::start().await;
+        let sent = json!({ "messages": [
+            { "role": "system", "content": "Be brief." },
+            { "role": "user", "content": "hi" },
+        ] });
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(wiremock::matchers::body_partial_json(sent))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "Brief, but long enough to pass." } }]
+            })))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let mut config = (*state_with_backend(&server.uri()).config()).clone();
+        config.gateway.traffic_log_debug = true;
+        config.profiles.get_mut("default").unwrap().system_prompt = Some("Be brief.".into());
+        let state = Arc::new(RouterState::new(
+            Arc::new(config),
+            std::path::PathBuf::default(),
+            Arc::new(TrafficLog::new(100)),
+        ));
+
+        let body = json!({ "model": "local:fast", "messages": [{ "role": "user", "content": "hi" }] });
+        let (_, entry) =
+            crate::router::route(&state, body, None, &crate::router::RequestMeta::default(), false, false)
+                .await
+                .unwrap();
+        let uri = format!("/admin/traffic/{}/replay", entry.id);
+        for target in [json!({ "profile": "default" }), json!({ "tier": "local:fast" })] {
+            let (status, json) = send(&state, "POST", &uri, target).await;
+            assert_eq!(status, StatusCode::OK, "{json}");
+            assert_eq!(json["replay"]["success"], true, "{json}");
+        }
+    }
+
+    #[tokio::test]
+    async fn route_explain_returns_trace_and_rejects_unknown_profile() {
+        let state = minimal_state();
+        let body = json!({ "model": "local:fast", "messages": [{ "role": "user", "content": "hi" }] });
+        let (status, json) = send(&state, "POST", "/admin/route/explain", body.clone()).await;
+        assert_eq!(status, StatusCode::OK, "{json}");
+        assert_eq!(json["requested_tier"], "local:fast");
+        assert!(json["tier"].is_string());
+
+        let (status, _) = send(&state, "POST", "/admin/route/explain?profile=nope", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -0,0 +1,143 @@
+//! Traffic split status and live weight changes.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+
+use super::config_mutation::{apply_config_mutation, not_found};
+use crate::{
+    config::{Section, SplitVariant},
+    router::RouterState,
+    traffic::TrafficFilter,
+};
+
+/// GET /admin/splits — every traffic split with its variants' weights,
+/// current share, and the requests each variant served in the traffic log
+/// (`requests`, `errors`, `avg_latency_ms`).
+///
+/// For trends, `GET /admin/analytics/timeseries?split=<name>&group_by=tier`
+/// charts the variants side by side.
+pub async fn splits(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
+    let cfg = state.config();
+    let mut names: Vec<&String> = cfg.splits.keys().collect();
+    names.sort();
+    let splits: Vec<Value> = names
+        .into_iter()
+        .map(|name| {
+            let split = &cfg.splits[name];
+            let entries = state.traffic.matching(&TrafficFilter { split: Some(name.clone()), ..Default::default() });
+            let total = split.total_weight().max(1) as f64;
+            let variants: Vec<Value> = split
+                .variants
+                .iter()
+                .map(|v| {
+                    let tier = cfg.resolve_tier(&v.tier).map_or(v.tier.as_str(), |t| t.name.as_str());
+                    let served: Vec<_> = entries.iter().filter(|e| e.tier == tier).collect();
+                    let errors = served.iter().filter(|e| !e.success).count();
+                    let avg_latency_ms = (!served.is_empty())
+                        .then(|| served.iter().map(|e| e.latency_ms).sum::<u64>() / served.len() as u64);
+                    json!({
+                        "tier": v.tier,
+                        "weight": v.weight,
+                        "share": f64::from(v.weight) / total,
+                        "requests": served.len(),
+                        "errors": errors,
+                        "avg_latency_ms": avg_latency_ms,
+                    })
+                })
+                .collect();
+            json!({
+                "name": name,
+                "tier": cfg.resolve_tier(name).map(|t| t.name.as_str()),
+                "bucket_by": split.bucket_by,
+                "variants": variants,
+            })
+        })
+        .collect();
+    Json(json!({ "splits": splits }))
+}
+
+/// PUT /admin/splits/{name}/weights — shift a split's traffic between its
+/// variants.
+///
+/// The body maps variant tiers to their new weights, e.g.
+/// `{"local:fast": 50, "local:fast-next": 50}`; variants left out keep their
+/// weight. Add or remove variants with `PUT /admin/config/splits/{name}`.
+/// Applied and persisted like any other config mutation.
+pub async fn set_split_weights(
+    State(state): State<Arc<RouterState>>,
+    Path(name): Path<String>,
+    Json(weights): Json<HashMap<String, u32>>,
+) -> Response {
+    let Some(split) = state.config().splits.get(&name).cloned() else {
+        return not_found(Section::Splits, &name);
+    };
+    if let Some(unknown) = weights.keys().find(|tier| !split.variants.iter().any(|v| &v.tier == *tier)) {
+        let error = format!("`{unknown}` is not a variant of split `{name}`");
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response();
+    }
+    let variants: Vec<SplitVariant> = split
+        .variants
+        .into_iter()
+        .map(|v| SplitVariant { weight: weights.get(&v.tier).copied().unwrap_or(v.weight), ..v })
+        .collect();
+    let variants = match toml::Value::try_from(variants) {
+        Ok(variants) => variants,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))).into_response();
+        }
+    };
+    apply_config_mutation(&state, Section::Splits, |overlay| overlay.set_variants(&name, variants)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::{
+        api::admin::tests::{get_json, send, state_on_disk},
+        config::Config,
+    };
+
+    #[tokio::test]
+    async fn split_weights_shift_live_and_persist() {
+        let (state, dir) = state_on_disk();
+        let tier = json!({ "backend": "ollama", "model": "qwen3:1.7b" });
+        assert_eq!(send(&state, "PUT", "/admin/config/tiers/local:fast-next", tier).await.0, StatusCode::OK);
+        let split = json!({ "variants": [
+            { "tier": "local:fast", "weight": 90 },
+            { "tier": "local:fast-next", "weight": 10 },
+        ] });
+        assert_eq!(send(&state, "PUT", "/admin/config/splits/hint:fast", split).await.0, StatusCode::OK);
+
+        let (status, _) =
+            send(&state, "PUT", "/admin/splits/hint:fast/weights", json!({ "local:fast-next": 30 })).await;
+        assert_eq!(status, StatusCode::OK);
+        let listed = get_json(&state, "/admin/splits").await;
+        let split = &listed["splits"][0];
+        assert_eq!(split["tier"], "local:fast");
+        assert_eq!(split["bucket_by"], "client");
+        assert_eq!(split["variants"][0]["weight"], 90, "unlisted variants keep their weight");
+        assert_eq!(split["variants"][1]["share"], 0.25);
+
+        let reloaded = Config::load(&state.config_path).unwrap();
+        assert_eq!(reloaded.splits["hint:fast"].variants[1].weight, 30);
+
+        let (status, _) = send(&state, "PUT", "/admin/splits/hint:fast/weights", json!({ "cloud:x": 5 })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let all_off = json!({ "local:fast": 0, "local:fast-next": 0 });
+        let (status, _) = send(&state, "PUT", "/admin/splits/hint:fast/weights", all_off).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = send(&state, "PUT", "/admin/splits/ghost/weights", json!({})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,175 @@
+//! Traffic log endpoints: filtered pages and the live tail.
+
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
+};
+use futures_util::Stream;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{router::RouterState, traffic::TrafficFilter};
+
+#[derive(Deserialize)]
+pub struct TrafficQuery {
+    #[serde(default = "default_limit")]
+    limit: usize,
+    /// `next_cursor` from the previous page.
+    before: Option<u64>,
+}
+fn default_limit() -> usize {
+    100
+}
+
+/// GET /admin/traffic — recent traffic entries, newest first.
+///
+/// Query parameters: `limit` (default 100), `before` (the previous page's
+/// `next_cursor`) and any [`TrafficFilter`] field — `profile`, `tier`,
+/// `backend`, `class_label`, `success`, `min_priority`, `max_priority`,
+/// `since`, `until` (RFC 3339), `id`, `client`. `stats` aggregate every
+/// buffered entry that matches the filter.
+pub async fn traffic(
+    State(state): State<Arc<RouterState>>,
+    Query(q): Query<TrafficQuery>,
+    Query(filter): Query<TrafficFilter>,
+) -> impl IntoResponse {
+    Json(state.traffic.query(&filter, q.before, q.limit))
+}
+
+/// GET /admin/traffic/stream — live tail as Server-Sent Events.
+///
+/// Emits one `entry` event (the entry as JSON) per recorded request matching
+/// the same filter parameters as `GET /admin/traffic`. A `lagged` event with
+/// the number of skipped entries is sent when the client falls behind.
+pub async fn traffic_stream(
+    State(state): State<Arc<RouterState>>,
+    Query(filter): Query<TrafficFilter>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = state.traffic.subscribe();
+    let events = futures_util::stream::unfold((rx, filter), |(mut rx, filter)| async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(entry) if filter.matches(&entry) => Event::default()
+                    .event("entry")
+                    .json_data(&entry)
+                    .unwrap_or_else(|_| Event::default().event("error")),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => Event::default().event("lagged").data(skipped.to_string()),
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok(event), (rx, filter)));
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use tower::ServiceExt; // oneshot
+
+    use crate::{
+        api::admin::{self, tests::{body_json, minimal_state}},
+        traffic::TrafficEntry,
+    };
+
+    #[tokio::test]
+    async fn traffic_returns_stats_and_empty_entries_list_on_fresh_log() {
+        let app = admin::router(minimal_state());
+        let req = Request::builder()
+            .method("GET")
+            .uri("/admin/traffic?limit=10")
+            .body(Body::empty())
+            .unwrap();
+
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["stats"]["total_requests"], 0);
+        assert!(json["entries"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn traffic_returns_pushed_entries_newest_first() {
+        let state = minimal_state();
+        state.traffic.push(TrafficEntry::new("local:fast".into(), "mock".into(), 50, true));
+        state.traffic.push(TrafficEntry::new("cloud:economy".into(), "mock".into(), 150, true));
+
+        let app = admin::router(Arc::clone(&state));
+        let req = Request::builder()
+            .method("GET")
+            .uri("/admin/traffic?limit=10")
+            .body(Body::empty())
+            .unwrap();
+
+        let resp = app.oneshot(req).await.unwrap();
+        let json = body_json(resp.into_body()).await;
+        let entries = json["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        // Newest first — cloud:economy was pushed last
+        assert_eq!(entries[0]["tier"], "cloud:economy");
+        assert_eq!(entries[1]["tier"], "local:fast");
+    }
+
+    #[tokio::test]
+    async fn traffic_applies_filters_and_cursor() {
+        let state = minimal_state();
+        for latency in [10, 20, 30] {
+            state.traffic.push(TrafficEntry::new("local:fast".into(), "mock".into(), latency, false));
+        }
+        state.traffic.push(TrafficEntry::new("local:fast".into(), "mock".into(), 40, true));
+        let app = admin::router(Arc::clone(&state));
+
+        let get = |uri: String| {
+            let app = app.clone();
+            async move {
+                let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+                body_json(app.oneshot(req).await.unwrap().into_body()).await
+            }
+        };
+        let page = get("/admin/traffic?success=false&limit=2".into()).await;
+        assert_eq!(page["stats"]["total_requests"], 3);
+        assert_eq!(page["entries"][0]["latency_ms"], 30);
+        assert_eq!(page["entries"][1]["latency_ms"], 20);
+
+        let cursor = page["next_cursor"].as_u64().unwrap();
+        let next = get(format!("/admin/traffic?success=false&limit=2&before={cursor}")).await;
+        assert_eq!(next["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(next["entries"][0]["latency_ms"], 10);
+        assert!(next["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn traffic_stream_emits_matching_entries() {
+        use futures_util::StreamExt as _;
+
+        let state = minimal_state();
+        let app = admin::router(Arc::clone(&state));
+        let req = Request::builder()
+            .uri("/admin/traffic/stream?tier=cloud:economy")
+            .body(Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.headers()["content-type"], "text/event-stream");
+
+        state.traffic.push(TrafficEntry::new("local:fast".into(), "mock".into(), 1, true));
+        state.traffic.push(TrafficEntry::new("cloud:economy".into(), "mock".into(), 2, true));
+        let mut body = resp.into_body().into_data_stream();
+        let frame = body.next().await.unwrap().unwrap();
+        let text = String::from_utf8_lossy(&frame);
+        assert!(text.starts_with("event: entry"), "{text}");
+        assert!(text.contains("\"tier\":\"cloud:economy\""), "{text}");
+    }
+}
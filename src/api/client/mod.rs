@@ -7,13 +7,19 @@
 use std::sync::Arc;
 
 use axum::{
-    http::HeaderMap,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Router,
+    Json, Router,
 };
 use serde_json::{json, Value};
 
-use crate::{config::Config, router::RouterState, traffic::TrafficEntry};
+use crate::{
+    cache::CacheDirective,
+    config::Config,
+    router::{RequestMeta, RouterState},
+    traffic::TrafficEntry,
+};
 
 mod ollama;
 mod openai;
@@ -39,6 +45,7 @@ pub fn router(state: Arc<RouterState>) -> Router {
 /// - `X-LMG-Model`   — model used by that tier (e.g. `qwen3:1.7b`)
 /// - `X-LMG-Profile` — profile chain (e.g. `auto → code-auto` or just `ha-auto`)
 /// - `X-LMG-Class`   — class label from classification (e.g. `greeting`)
+/// - `X-LMG-Cache`   — `HIT`, `SEMANTIC`, `MISS` or `BYPASS` when the profile caches responses
 pub(super) fn inject_routing_headers(headers: &mut HeaderMap, entry: &TrafficEntry, config: &Config) {
     // X-LMG-Tier
     if let Ok(val) = entry.tier.parse() {
@@ -69,6 +76,57 @@ pub(super) fn inject_routing_headers(headers: &mut HeaderMap, entry: &TrafficEnt
             }
         }
     }
+    // X-LMG-Cache — only for profiles with a response cache
+    if let Some(outcome) = entry.cache {
+        headers.insert("x-lmg-cache", HeaderValue::from_static(outcome.header_value()));
+    }
+}
+
+/// Response cache preference from the `Cache-Control` request header.
+fn cache_directive(headers: &HeaderMap) -> CacheDirective {
+    headers
+        .get(axum::http::header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .map_or(CacheDirective::Default, CacheDirective::parse)
+}
+
+/// Non-empty `X-LMG-Conversation-Id`, used to pin classify-mode routing.
+fn conversation_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-lmg-conversation-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_owned)
+}
+
+/// Whether the client sent `X-LMG-Dry-Run: true`.
+fn dry_run_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get("x-lmg-dry-run")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+/// Answer a dry-run request with its routing trace instead of a completion.
+///
+/// Refused with 403 unless `gateway.allow_dry_run` is set; routing errors
+/// (unknown profile, expert gate) are returned as 422.
+async fn dry_run_response(
+    state: &RouterState,
+    body: Value,
+    profile: Option<&str>,
+    meta: &RequestMeta,
+    expert_gate: bool,
+) -> Response {
+    if !state.config().gateway.allow_dry_run {
+        let error = json!({ "error": "dry runs are disabled (set gateway.allow_dry_run)" });
+        return (StatusCode::FORBIDDEN, Json(error)).into_response();
+    }
+    match crate::router::explain(state, body, profile, meta, expert_gate).await {
+        Ok(explanation) => Json(explanation).into_response(),
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "error": format!("{e:#}") }))).into_response(),
+    }
 }
 
 /// Classify a backend error into a short, user-readable message.
@@ -169,17 +227,7 @@ mod tests {
                 client_port: 8080,
                 admin_port: 8081,
                 traffic_log_capacity: 100,
-                log_level: None,
-                rate_limit_rpm: None,
-                admin_token_env: None,
-                max_retries: None,
-                retry_delay_ms: None,
-                health_window: None,
-                health_error_threshold: None,
-                public_profile: None,
-                request_timeout_ms: None,
-                traffic_log_debug: false,
-                profile_dir: None,
+                ..Default::default()
             },
             backends: {
                 let mut m = std::collections::HashMap::new();
@@ -203,6 +251,7 @@ mod tests {
                     model: "fast-model".into(),
                     think: None,
                     max_context_tokens: None,
+                    capabilities: Default::default(),
                 },
                 TierConfig {
                     name: "cloud:economy".into(),
@@ -210,6 +259,7 @@ mod tests {
                     model: "economy-model".into(),
                     think: None,
                     max_context_tokens: None,
+                    capabilities: Default::default(),
                 },
             ],
             aliases: {
@@ -237,6 +287,9 @@ mod tests {
                 m
             },
             clients: vec![],
+            splits: Default::default(),
+            templates: Default::default(),
+            admin_overlay: Default::default(),
         };
         Arc::new(RouterState::new(
             Arc::new(config),
@@ -348,6 +401,38 @@ mod tests {
         assert!(json.pointer("/choices/0/message/content").is_some());
     }
 
+    #[tokio::test]
+    async fn dry_run_header_returns_trace_only_when_allowed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(500)).expect(0).mount(&server).await;
+        let state = state_with_backend(&server.uri());
+        let dry_run = || {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("Content-Type", "application/json")
+                .header("X-LMG-Dry-Run", "true")
+                .body(Body::from(
+                    serde_json::to_vec(&json!({ "model": "local:fast", "messages": [{"role": "user", "content": "hi"}] }))
+                        .unwrap(),
+                ))
+                .unwrap()
+        };
+
+        let resp = super::router(Arc::clone(&state)).oneshot(dry_run()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let mut config = (*state.config()).clone();
+        config.gateway.allow_dry_run = true;
+        state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+        let resp = super::router(Arc::clone(&state)).oneshot(dry_run()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = body_json(resp.into_body()).await;
+        assert_eq!(json["tier"], "local:fast");
+        assert_eq!(json["profile"], "default");
+        assert!(state.traffic.recent(10).is_empty());
+    }
+
     #[tokio::test]
     async fn chat_completions_returns_user_friendly_message_when_backend_is_unreachable() {
         // Port 1 is reserved and never responds — guaranteed connection refusal.
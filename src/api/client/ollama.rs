@@ -13,9 +13,9 @@ use axum::{
 use serde_json::{json, Value};
 
 use crate::{
-    api::{client_auth::ClientProfile, request_id::RequestId},
+    api::{client_auth::{ClientName, ClientProfile}, request_id::RequestId},
     error::AppError,
-    router::{priority::parse_priority, RouterState},
+    router::{priority::parse_priority, RequestMeta, RouterState},
 };
 
 /// `GET /api/tags` — Ollama-compatible model discovery.
@@ -87,6 +87,7 @@ pub async fn chat_completions_ollama(
     State(state): State<Arc<RouterState>>,
     request_id_ext: Option<Extension<RequestId>>,
     client_profile: Option<Extension<ClientProfile>>,
+    client_name: Option<Extension<ClientName>>,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<Response, AppError> {
@@ -95,8 +96,15 @@ pub async fn chat_completions_ollama(
         .and_then(|v| v.to_str().ok())
         .map(|v| v.eq_ignore_ascii_case("true"))
         .unwrap_or(false);
-    let priority = parse_priority(&headers);
-    let req_id = request_id_ext.map(|Extension(id)| id.0);
+    let meta = RequestMeta {
+        id: request_id_ext.map(|Extension(id)| id.0),
+        client: client_name.map(|Extension(c)| c.0),
+        priority: parse_priority(&headers),
+        cache_control: super::cache_directive(&headers),
+        conversation_id: super::conversation_id(&headers),
+        headers: headers.clone(),
+        ..Default::default()
+    };
     let profile = client_profile.map(|Extension(p)| p.0);
     let streaming = body.get("stream").and_then(Value::as_bool).unwrap_or(false);
 
@@ -125,6 +133,10 @@ pub async fn chat_completions_ollama(
 
     let effective_profile = profile_override.as_deref().or(profile.as_deref());
 
+    if super::dry_run_requested(&headers) {
+        return Ok(super::dry_run_response(&state, openai_body, effective_profile, &meta, expert_gate).await);
+    }
+
     let model_name = body
         .get("model")
         .and_then(Value::as_str)
@@ -136,8 +148,7 @@ pub async fn chat_completions_ollama(
             &state,
             openai_body,
             effective_profile,
-            req_id.as_deref(),
-            priority,
+            &meta,
             expert_gate,
             true, // use native /api/chat for Ollama — honours think:false
         )
@@ -175,8 +186,7 @@ pub async fn chat_completions_ollama(
         &state,
         openai_body,
         effective_profile,
-        req_id.as_deref(),
-        priority,
+        &meta,
         false,
         expert_gate,
     )
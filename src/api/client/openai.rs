@@ -13,10 +13,10 @@ use axum::{
 use serde_json::{json, Value};
 
 use crate::{
-    api::{client_auth::ClientProfile, request_id::RequestId},
+    api::{client_auth::{ClientName, ClientProfile}, request_id::RequestId},
     backends::SseStream,
     error::AppError,
-    router::{priority::parse_priority, RouterState},
+    router::{priority::parse_priority, RequestMeta, RouterState},
 };
 
 /// `POST /v1/chat/completions` — route a chat request through the tier ladder.
@@ -29,6 +29,7 @@ pub async fn chat_completions(
     State(state): State<Arc<RouterState>>,
     request_id_ext: Option<Extension<RequestId>>,
     client_profile: Option<Extension<ClientProfile>>,
+    client_name: Option<Extension<ClientName>>,
     headers: axum::http::HeaderMap,
     Json(body): Json<Value>,
 ) -> Result<Response, AppError> {
@@ -37,16 +38,24 @@ pub async fn chat_completions(
         .and_then(|v| v.to_str().ok())
         .map(|v| v.eq_ignore_ascii_case("true"))
         .unwrap_or(false);
-    let priority = parse_priority(&headers);
-    let req_id = request_id_ext.map(|Extension(id)| id.0);
+    let meta = RequestMeta {
+        id: request_id_ext.map(|Extension(id)| id.0),
+        client: client_name.map(|Extension(c)| c.0),
+        priority: parse_priority(&headers),
+        cache_control: super::cache_directive(&headers),
+        conversation_id: super::conversation_id(&headers),
+        headers: headers.clone(),
+        ..Default::default()
+    };
     let profile = client_profile.map(|Extension(p)| p.0);
     let streaming = body.get("stream").and_then(Value::as_bool).unwrap_or(false);
 
     // Per-profile rate limit: shared quota across all clients on the same profile.
     let profile_name = profile.as_deref().unwrap_or("default");
-    if let Some(limiter) = state.profile_limiters.get(profile_name) {
+    if let Some(limiter) = state.index.load().profile_limiters.get(profile_name) {
         if let Err(retry_after) = limiter.check_global() {
             use axum::http::StatusCode;
+            state.metrics.rate_limited.inc(&["profile"]);
             return Ok((
                 StatusCode::TOO_MANY_REQUESTS,
                 [
@@ -62,6 +71,10 @@ pub async fn chat_completions(
         }
     }
 
+    if super::dry_run_requested(&headers) {
+        return Ok(super::dry_run_response(&state, body, profile.as_deref(), &meta, expert_gate).await);
+    }
+
     let model_name = body
         .get("model")
         .and_then(Value::as_str)
@@ -69,7 +82,7 @@ pub async fn chat_completions(
         .to_owned();
 
     if streaming {
-        match crate::router::route_stream(&state, body, profile.as_deref(), req_id.as_deref(), priority, expert_gate, false).await {
+        match crate::router::route_stream(&state, body, profile.as_deref(), &meta, expert_gate, false).await {
             Ok((stream, entry, _is_native)) => {
                 let mut response = proxy_sse(stream);
                 super::inject_routing_headers(response.headers_mut(), &entry, &state.config());
@@ -79,7 +92,7 @@ pub async fn chat_completions(
         }
     }
 
-    match crate::router::route(&state, body, profile.as_deref(), req_id.as_deref(), priority, false, expert_gate).await {
+    match crate::router::route(&state, body, profile.as_deref(), &meta, false, expert_gate).await {
         Ok((resp, entry)) => {
             let mut response = Json(resp).into_response();
             super::inject_routing_headers(response.headers_mut(), &entry, &state.config());
@@ -33,6 +33,11 @@ use crate::router::RouterState;
 #[derive(Clone, Debug)]
 pub struct ClientProfile(pub String);
 
+/// Request extension set by [`client_auth_middleware`] when a client key
+/// matched: the client's `key_env`, recorded in the traffic log.
+#[derive(Clone, Debug)]
+pub struct ClientName(pub String);
+
 /// Axum middleware: enforces per-client Bearer token auth when `[[clients]]` is
 /// configured, and injects a [`ClientProfile`] extension for the handler.
 pub async fn client_auth_middleware(
@@ -41,7 +46,8 @@ pub async fn client_auth_middleware(
     next: Next,
 ) -> Response {
     // Feature disabled — pass through with no extension set.
-    if state.client_map.is_empty() {
+    let index = state.index.load_full();
+    if index.clients.is_empty() {
         return next.run(req).await;
     }
 
@@ -51,10 +57,11 @@ pub async fn client_auth_middleware(
         .and_then(|v| v.to_str().ok())
         .and_then(|v| v.strip_prefix("Bearer "));
 
-    match provided.and_then(|key| state.client_map.get(key)) {
-        Some(profile) => {
+    match provided.and_then(|key| index.clients.get(key)) {
+        Some(client) => {
             req.extensions_mut()
-                .insert(ClientProfile(profile.clone()));
+                .insert(ClientProfile(client.profile.clone()));
+            req.extensions_mut().insert(ClientName(client.name.clone()));
             next.run(req).await
         }
         None => {
@@ -92,42 +99,44 @@ mod tests {
 
     use crate::{
         config::GatewayConfig,
-        router::RouterState,
+        router::{index::ClientIdentity, ConfigIndex, RouterState},
         traffic::TrafficLog,
     };
 
     use super::ClientProfile;
 
     fn state_with_clients(map: HashMap<String, String>) -> Arc<RouterState> {
-        // Build a minimal RouterState then overwrite client_map via the public field.
-        let mut state = RouterState::new(
+        // Build a minimal RouterState then swap in the client keys.
+        let state = RouterState::new(
             Arc::new(crate::config::Config {
                 gateway: GatewayConfig {
                     client_port: 8080,
                     admin_port: 8081,
                     traffic_log_capacity: 10,
-                    log_level: None,
-                    rate_limit_rpm: None,
-                    admin_token_env: None,
-                    max_retries: None,
-                    retry_delay_ms: None,
-                    health_window: None,
-                    health_error_threshold: None,
-                    public_profile: None,
-                    request_timeout_ms: None,
-                    traffic_log_debug: false,
-                    profile_dir: None,
+                    ..Default::default()
                 },
                 backends: HashMap::new(),
                 tiers: vec![],
                 aliases: HashMap::new(),
                 profiles: HashMap::new(),
                 clients: vec![],
+                splits: Default::default(),
+                templates: Default::default(),
+                admin_overlay: Default::default(),
             }),
             std::path::PathBuf::default(),
             Arc::new(TrafficLog::new(10)),
         );
-        state.client_map = map;
+        let clients = map
+            .into_iter()
+            .map(|(key, profile)| (key, ClientIdentity { profile, name: "TEST_CLIENT_KEY".into() }))
+            .collect();
+        let index = state.index.load();
+        state.index.store(Arc::new(ConfigIndex {
+            clients,
+            profile_limiters: index.profile_limiters.clone(),
+            gates: index.gates.clone(),
+        }));
         Arc::new(state)
     }
 
@@ -1,9 +1,10 @@
 //! Prometheus-compatible `/metrics` endpoint.
 //!
-//! All metrics are derived from the in-memory ring-buffer window. Because the
-//! buffer has a fixed capacity, values represent a **sliding window** of recent
-//! requests rather than lifetime counters. Use `TYPE gauge` throughout for
-//! semantic accuracy — values may decrease as old entries rotate out.
+//! The window metrics are read from the traffic log's incremental aggregates
+//! over its ring buffer. Because the buffer has a fixed capacity, values
+//! represent a **sliding window** of recent requests rather than lifetime
+//! counters. Use `TYPE gauge` throughout for semantic accuracy — values may
+//! decrease as old entries rotate out.
 //!
 //! Metric families:
 //! - `lmg_window_size`             — entries currently in the ring buffer
@@ -12,11 +13,25 @@
 //! - `lmg_latency_ms_count`        — denominator matching the sum above
 //! - `lmg_escalations_total`       — requests that were escalated
 //! - `lmg_errors_total`            — requests that returned an error
+//!
+//! When the traffic webhook is enabled, its lifetime delivery counters are
+//! also exported (`TYPE counter`, reset on restart):
+//! - `lmg_webhook_entries_sent_total`    — entries acknowledged by the collector
+//! - `lmg_webhook_entries_dropped_total` — entries dropped, by `reason`
+//! - `lmg_webhook_retries_total`         — failed deliveries that were retried
+//!
+//! `lmg_traffic_entries_dropped_total{sink}` counts entries lost by the
+//! in-memory log (`memory`, expected to stay `0`) and the archive writer.
+//!
+//! Per-tier priority gate depth is read at scrape time:
+//! - `lmg_gate_in_flight` — requests currently holding a gate slot
+//! - `lmg_gate_queued`    — requests waiting for a slot
+//!
+//! Lifetime counters and histograms maintained on the request path
+//! (`lmg_requests_total`, `lmg_request_duration_seconds`, …) follow; see
+//! [`crate::metrics`].
 
-use std::{
-    collections::HashMap,
-    sync::Arc,
-};
+use std::sync::{atomic::Ordering, Arc};
 
 use axum::{
     extract::State,
@@ -28,31 +43,11 @@ use crate::router::RouterState;
 
 /// `GET /metrics` — renders Prometheus text format.
 pub async fn metrics(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
-    // Grab the full ring-buffer window in one lock acquisition.
-    let entries = state.traffic.recent(usize::MAX).await;
-
-    // --- aggregate ---
-    let window_size = entries.len();
-    let mut escalations: u64 = 0;
-    let mut errors: u64 = 0;
-
-    // (tier, backend, success) → count
-    let mut request_counts: HashMap<(String, String, bool), u64> = HashMap::new();
-    // (tier, backend) → (latency_sum_ms, count)
-    let mut latency: HashMap<(String, String), (u64, u64)> = HashMap::new();
-
-    for e in &entries {
-        if e.escalated { escalations += 1; }
-        if !e.success { errors += 1; }
-
-        *request_counts
-            .entry((e.tier.clone(), e.backend.clone(), e.success))
-            .or_default() += 1;
-
-        let lat = latency.entry((e.tier.clone(), e.backend.clone())).or_default();
-        lat.0 += e.latency_ms;
-        lat.1 += 1;
-    }
+    // Window aggregates are maintained incrementally by the traffic log.
+    let window = state.traffic.window_breakdown();
+    let window_size = window.total;
+    let escalations = window.escalations;
+    let errors = window.errors;
 
     // --- render ---
     let mut out = String::with_capacity(1024);
@@ -65,7 +60,7 @@ pub async fn metrics(State(state): State<Arc<RouterState>>) -> impl IntoResponse
     // request counts
     out.push_str("# HELP lmg_requests Request count in the current window, labelled by tier, backend, and outcome.\n");
     out.push_str("# TYPE lmg_requests gauge\n");
-    let mut req_rows: Vec<_> = request_counts.iter().collect();
+    let mut req_rows: Vec<_> = window.requests.iter().collect();
     req_rows.sort_by(|a, b| a.0.cmp(b.0));
     for ((tier, backend, success), count) in req_rows {
         let success_str = if *success { "true" } else { "false" };
@@ -80,7 +75,7 @@ pub async fn metrics(State(state): State<Arc<RouterState>>) -> impl IntoResponse
     out.push_str("# TYPE lmg_latency_ms_sum gauge\n");
     out.push_str("# HELP lmg_latency_ms_count Number of observations for the latency sum above.\n");
     out.push_str("# TYPE lmg_latency_ms_count gauge\n");
-    let mut lat_rows: Vec<_> = latency.iter().collect();
+    let mut lat_rows: Vec<_> = window.latency_ms.iter().collect();
     lat_rows.sort_by(|a, b| a.0.cmp(b.0));
     for ((tier, backend), (sum, count)) in lat_rows {
         out.push_str(&format!(
@@ -102,6 +97,67 @@ pub async fn metrics(State(state): State<Arc<RouterState>>) -> impl IntoResponse
     out.push_str("# TYPE lmg_errors_total gauge\n");
     out.push_str(&format!("lmg_errors_total {errors}\n"));
 
+    if let Some(webhook) = state.traffic.webhook() {
+        let stats = webhook.stats();
+        out.push_str("\n# HELP lmg_webhook_entries_sent_total Traffic entries delivered to the webhook collector.\n");
+        out.push_str("# TYPE lmg_webhook_entries_sent_total counter\n");
+        out.push_str(&format!(
+            "lmg_webhook_entries_sent_total {}\n\n",
+            stats.sent.load(Ordering::Relaxed)
+        ));
+        out.push_str("# HELP lmg_webhook_entries_dropped_total Traffic entries dropped by the webhook exporter.\n");
+        out.push_str("# TYPE lmg_webhook_entries_dropped_total counter\n");
+        out.push_str(&format!(
+            "lmg_webhook_entries_dropped_total{{reason=\"buffer_full\"}} {}\n",
+            stats.dropped_buffer_full.load(Ordering::Relaxed)
+        ));
+        out.push_str(&format!(
+            "lmg_webhook_entries_dropped_total{{reason=\"delivery_failed\"}} {}\n\n",
+            stats.dropped_delivery_failed.load(Ordering::Relaxed)
+        ));
+        out.push_str("# HELP lmg_webhook_retries_total Webhook delivery attempts that failed and were retried.\n");
+        out.push_str("# TYPE lmg_webhook_retries_total counter\n");
+        out.push_str(&format!(
+            "lmg_webhook_retries_total {}\n",
+            stats.retries.load(Ordering::Relaxed)
+        ));
+    }
+
+    // traffic log losses
+    out.push_str("# HELP lmg_traffic_entries_dropped_total Traffic entries lost by the in-memory log or the archive writer.\n");
+    out.push_str("# TYPE lmg_traffic_entries_dropped_total counter\n");
+    out.push_str(&format!(
+        "lmg_traffic_entries_dropped_total{{sink=\"memory\"}} {}\n",
+        state.traffic.dropped()
+    ));
+    if let Some(archive) = state.traffic.archive() {
+        out.push_str(&format!(
+            "lmg_traffic_entries_dropped_total{{sink=\"archive\"}} {}\n",
+            archive.dropped()
+        ));
+    }
+
+    // gate depth
+    let index = state.index.load_full();
+    let mut gates: Vec<_> = index.gates.iter().collect();
+    gates.sort_by(|a, b| a.0.cmp(b.0));
+    let mut in_flight_rows = String::new();
+    let mut queued_rows = String::new();
+    for (tier, gate) in gates {
+        let (in_flight, queued) = gate.depth().await;
+        in_flight_rows.push_str(&format!("lmg_gate_in_flight{{tier=\"{tier}\"}} {in_flight}\n"));
+        queued_rows.push_str(&format!("lmg_gate_queued{{tier=\"{tier}\"}} {queued}\n"));
+    }
+    out.push_str("\n# HELP lmg_gate_in_flight Requests currently holding a tier's priority gate slot.\n");
+    out.push_str("# TYPE lmg_gate_in_flight gauge\n");
+    out.push_str(&in_flight_rows);
+    out.push_str("\n# HELP lmg_gate_queued Requests waiting for a tier's priority gate slot.\n");
+    out.push_str("# TYPE lmg_gate_queued gauge\n");
+    out.push_str(&queued_rows);
+    out.push('\n');
+
+    state.metrics.render(&mut out);
+
     (
         StatusCode::OK,
         [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
@@ -138,14 +194,14 @@ mod tests {
     #[tokio::test]
     async fn window_size_equals_entry_count() {
         let log = mock_log();
-        let entries = log.recent(usize::MAX).await;
+        let entries = log.recent(usize::MAX);
         assert_eq!(entries.len(), 4);
     }
 
     #[tokio::test]
     async fn error_count_is_accurate() {
         let log = mock_log();
-        let entries = log.recent(usize::MAX).await;
+        let entries = log.recent(usize::MAX);
         let errors = entries.iter().filter(|e| !e.success).count();
         assert_eq!(errors, 1);
     }
@@ -153,7 +209,7 @@ mod tests {
     #[tokio::test]
     async fn latency_sum_is_accurate() {
         let log = mock_log();
-        let entries = log.recent(usize::MAX).await;
+        let entries = log.recent(usize::MAX);
         let sum: u64 = entries
             .iter()
             .filter(|e| e.tier == "fast" && e.backend == "openai-prod")
@@ -161,5 +217,8 @@ mod tests {
             .sum();
         // 120 + 95 + 80 = 295
         assert_eq!(sum, 295);
+        let window = log.window_breakdown();
+        assert_eq!(window.latency_ms[&("fast".to_string(), "openai-prod".to_string())], (295, 3));
+        assert_eq!(window.requests[&("fast".to_string(), "openai-prod".to_string(), false)], 1);
     }
 }
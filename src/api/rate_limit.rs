@@ -118,6 +118,7 @@ pub async fn rate_limit_middleware(
             .unwrap_or(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
 
         if let Err(retry_after) = limiter.check(ip) {
+            state.metrics.rate_limited.inc(&["ip"]);
             return (
                 StatusCode::TOO_MANY_REQUESTS,
                 [
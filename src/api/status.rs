@@ -44,7 +44,7 @@ use crate::router::RouterState;
 /// included pointing to the setup documentation.
 pub async fn status(State(state): State<Arc<RouterState>>) -> impl IntoResponse {
     let uptime_secs = state.started_at.elapsed().as_secs();
-    let stats = state.traffic.public_stats().await;
+    let stats = state.traffic.public_stats();
     let error_rate = if stats.total_requests == 0 {
         0.0_f64
     } else {
@@ -110,17 +110,7 @@ mod tests {
                 client_port: 8080,
                 admin_port: 8081,
                 traffic_log_capacity: 100,
-                log_level: None,
-                rate_limit_rpm: None,
-                admin_token_env: None,
-                max_retries: None,
-                retry_delay_ms: None,
-                health_window: None,
-                health_error_threshold: None,
-                public_profile: None,
-                request_timeout_ms: None,
-                traffic_log_debug: false,
-                profile_dir: None,
+                ..Default::default()
             },
             backends: std::collections::HashMap::new(),
             tiers: vec![TierConfig {
@@ -129,6 +119,7 @@ mod tests {
                 model: "fast-model".into(),
                 think: None,
                 max_context_tokens: None,
+                capabilities: Default::default(),
             }],
             aliases: std::collections::HashMap::new(),
             profiles: {
@@ -151,6 +142,9 @@ mod tests {
                 m
             },
             clients: vec![],
+            splits: Default::default(),
+            templates: Default::default(),
+            admin_overlay: Default::default(),
         };
         Arc::new(RouterState::new(Arc::new(config), std::path::PathBuf::default(), Arc::new(TrafficLog::new(100))))
     }
@@ -248,23 +242,16 @@ mod tests {
                 client_port: 8080,
                 admin_port: 8081,
                 traffic_log_capacity: 100,
-                log_level: None,
-                rate_limit_rpm: None,
-                admin_token_env: None,
-                max_retries: None,
-                retry_delay_ms: None,
-                health_window: None,
-                health_error_threshold: None,
-                public_profile: None,
-                request_timeout_ms: None,
-                traffic_log_debug: false,
-                profile_dir: None,
+                ..Default::default()
             },
             backends,
             tiers: vec![],
             aliases: std::collections::HashMap::new(),
             profiles: std::collections::HashMap::new(),
             clients: vec![],
+            splits: Default::default(),
+            templates: Default::default(),
+            admin_overlay: Default::default(),
         };
         let state = Arc::new(RouterState::new(
             Arc::new(config),
@@ -79,6 +79,7 @@ impl AnthropicAdapter {
         let response = self
             .client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&anthropic_req)
             .send()
             .await
@@ -112,6 +113,7 @@ impl AnthropicAdapter {
         let response = self
             .client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&probe)
             .send()
             .await
@@ -143,6 +145,7 @@ impl AnthropicAdapter {
         let response = self
             .stream_client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&anthropic_req)
             .send()
             .await
@@ -161,7 +164,7 @@ impl AnthropicAdapter {
             let mut byte_stream = response.bytes_stream();
             let mut buf = String::new();
             let mut event_type = String::new();
-            let mut model = String::from("unknown");
+            let mut state = StreamState { model: "unknown".into(), ..Default::default() };
 
             while let Some(chunk) = byte_stream.next().await {
                 match chunk {
@@ -184,7 +187,7 @@ impl AnthropicAdapter {
                                         event_type = val.to_string();
                                     } else if let Some(data) = line.strip_prefix("data: ") {
                                         if let Some(out) = translate_sse_event(
-                                            &event_type, data, &msg_id, &mut model,
+                                            &event_type, data, &msg_id, &mut state,
                                         ) {
                                             if tx.send(Ok(Bytes::from(out))).await.is_err() {
                                                 return; // client disconnected
@@ -304,32 +307,42 @@ pub(crate) fn from_anthropic(resp: Value) -> anyhow::Result<Value> {
 // SSE stream translation — Anthropic → OpenAI format
 // ──────────────────────────────────────────────────────────────────────────────
 
+/// Per-stream values carried between Anthropic SSE events.
+#[derive(Debug, Default)]
+pub(crate) struct StreamState {
+    /// Model name from `message_start`, reused for all subsequent chunks.
+    pub model: String,
+    /// Prompt tokens from `message_start`, reported with the final usage.
+    pub input_tokens: Option<u64>,
+}
+
 /// Translate a single Anthropic SSE event into an OpenAI-compatible SSE chunk.
 ///
 /// Returns `Some(bytes_to_emit)` for events that map to OpenAI chunks, `None`
 /// for Anthropic-specific events that have no OpenAI equivalent (ping,
 /// `content_block_start`, `content_block_stop`, `message_stop`).
 ///
-/// `model` is populated from the first `message_start` event and reused for
-/// all subsequent chunks.
+/// The `message_delta` chunk carries an OpenAI-style `usage` object combining
+/// the input tokens from `message_start` with the final output token count.
 pub(crate) fn translate_sse_event(
     event_type: &str,
     data: &str,
     msg_id: &str,
-    model: &mut String,
+    state: &mut StreamState,
 ) -> Option<String> {
     match event_type {
         "message_start" => {
             // Extract the model name from the first event for use in all chunks.
             if let Ok(v) = serde_json::from_str::<Value>(data) {
                 if let Some(m) = v.pointer("/message/model").and_then(Value::as_str) {
-                    *model = m.to_string();
+                    state.model = m.to_string();
                 }
+                state.input_tokens = v.pointer("/message/usage/input_tokens").and_then(Value::as_u64);
             }
             let chunk = json!({
                 "id": msg_id,
                 "object": "chat.completion.chunk",
-                "model": &*model,
+                "model": state.model,
                 "choices": [{"index": 0, "delta": {"role": "assistant", "content": ""}, "finish_reason": null}],
             });
             Some(format!("data: {chunk}\n\n"))
@@ -340,7 +353,7 @@ pub(crate) fn translate_sse_event(
             let chunk = json!({
                 "id": msg_id,
                 "object": "chat.completion.chunk",
-                "model": &*model,
+                "model": state.model,
                 "choices": [{"index": 0, "delta": {"content": text}, "finish_reason": null}],
             });
             Some(format!("data: {chunk}\n\n"))
@@ -356,12 +369,20 @@ pub(crate) fn translate_sse_event(
                     "max_tokens" => "length",
                     other => other,
                 });
-            let chunk = json!({
+            let mut chunk = json!({
                 "id": msg_id,
                 "object": "chat.completion.chunk",
-                "model": &*model,
+                "model": state.model,
                 "choices": [{"index": 0, "delta": {}, "finish_reason": finish_reason}],
             });
+            if let Some(output) = v.pointer("/usage/output_tokens").and_then(Value::as_u64) {
+                let input = state.input_tokens.unwrap_or(0);
+                chunk["usage"] = json!({
+                    "prompt_tokens": input,
+                    "completion_tokens": output,
+                    "total_tokens": input + output,
+                });
+            }
             Some(format!("data: {chunk}\n\n"))
         }
         // ping, content_block_start, content_block_stop, message_stop → skip
@@ -523,14 +544,14 @@ mod tests {
 
     #[test]
     fn translate_message_start_sets_role_and_captures_model() {
-        let mut model = String::from("unknown");
+        let mut state = StreamState { model: "unknown".into(), ..Default::default() };
         let data = json!({
             "type": "message_start",
             "message": { "model": "claude-3-5-sonnet-20241022" }
         })
         .to_string();
-        let out = translate_sse_event("message_start", &data, "id-1", &mut model).unwrap();
-        assert_eq!(model, "claude-3-5-sonnet-20241022");
+        let out = translate_sse_event("message_start", &data, "id-1", &mut state).unwrap();
+        assert_eq!(state.model, "claude-3-5-sonnet-20241022");
         let chunk: Value = serde_json::from_str(out.trim_start_matches("data: ").trim_end()).unwrap();
         assert_eq!(chunk["choices"][0]["delta"]["role"], "assistant");
         assert_eq!(chunk["choices"][0]["delta"]["content"], "");
@@ -538,14 +559,14 @@ mod tests {
 
     #[test]
     fn translate_content_block_delta_emits_text() {
-        let mut model = String::from("claude-3-5-haiku");
+        let mut state = StreamState { model: "claude-3-5-haiku".into(), ..Default::default() };
         let data = json!({
             "type": "content_block_delta",
             "index": 0,
             "delta": { "type": "text_delta", "text": "Hello!" }
         })
         .to_string();
-        let out = translate_sse_event("content_block_delta", &data, "id-2", &mut model).unwrap();
+        let out = translate_sse_event("content_block_delta", &data, "id-2", &mut state).unwrap();
         let chunk: Value = serde_json::from_str(out.trim_start_matches("data: ").trim_end()).unwrap();
         assert_eq!(chunk["choices"][0]["delta"]["content"], "Hello!");
     }
@@ -553,25 +574,47 @@ mod tests {
     #[test]
     fn translate_message_delta_maps_stop_reasons() {
         for (anthropic, openai) in [("end_turn", "stop"), ("max_tokens", "length")] {
-            let mut model = String::from("m");
+            let mut state = StreamState { model: "m".into(), ..Default::default() };
             let data = json!({
                 "type": "message_delta",
                 "delta": { "stop_reason": anthropic },
             })
             .to_string();
-            let out = translate_sse_event("message_delta", &data, "id-3", &mut model).unwrap();
+            let out = translate_sse_event("message_delta", &data, "id-3", &mut state).unwrap();
             let chunk: Value =
                 serde_json::from_str(out.trim_start_matches("data: ").trim_end()).unwrap();
             assert_eq!(chunk["choices"][0]["finish_reason"], openai);
         }
     }
 
+    #[test]
+    fn translate_message_delta_reports_usage_from_both_events() {
+        let mut state = StreamState::default();
+        let start = json!({
+            "type": "message_start",
+            "message": { "model": "m", "usage": { "input_tokens": 25, "output_tokens": 1 } }
+        })
+        .to_string();
+        translate_sse_event("message_start", &start, "id", &mut state).unwrap();
+        let delta = json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": "end_turn" },
+            "usage": { "output_tokens": 15 }
+        })
+        .to_string();
+        let out = translate_sse_event("message_delta", &delta, "id", &mut state).unwrap();
+        let chunk: Value = serde_json::from_str(out.trim_start_matches("data: ").trim_end()).unwrap();
+        assert_eq!(chunk["usage"]["prompt_tokens"], 25);
+        assert_eq!(chunk["usage"]["completion_tokens"], 15);
+        assert_eq!(chunk["usage"]["total_tokens"], 40);
+    }
+
     #[test]
     fn translate_skips_ping_and_housekeeping_events() {
-        let mut model = String::new();
+        let mut state = StreamState::default();
         for event in ["ping", "content_block_start", "content_block_stop", "message_stop"] {
             assert!(
-                translate_sse_event(event, "{}", "id", &mut model).is_none(),
+                translate_sse_event(event, "{}", "id", &mut state).is_none(),
                 "{event} should be skipped"
             );
         }
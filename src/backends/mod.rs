@@ -16,6 +16,7 @@ pub use openai::OpenAIAdapter;
 
 use std::pin::Pin;
 
+use anyhow::Context as _;
 use bytes::Bytes;
 use futures_util::Stream;
 use serde_json::Value;
@@ -163,6 +164,19 @@ impl BackendClient {
         }
     }
 
+    /// Embed `input` with `model`, returning the vector from `/v1/embeddings`.
+    ///
+    /// # Errors
+    /// Anthropic has no embeddings API; embedding tiers must use an
+    /// OpenAI-compatible or Ollama backend.
+    pub async fn embeddings(&self, model: &str, input: &str) -> anyhow::Result<Vec<f32>> {
+        match self {
+            Self::OpenAI(a) => a.embeddings(model, input).await,
+            Self::Ollama(a) => a.embeddings(model, input).await,
+            Self::Anthropic(_) => anyhow::bail!("Anthropic backends do not support embeddings"),
+        }
+    }
+
     /// Probe this backend for liveness. Implementation varies by provider.
     pub async fn health_check(&self) -> anyhow::Result<()> {
         match self {
@@ -173,6 +187,18 @@ impl BackendClient {
     }
 }
 
+/// `data[0].embedding` from an OpenAI-format embeddings response.
+fn first_embedding(response: &Value) -> anyhow::Result<Vec<f32>> {
+    let values = response
+        .pointer("/data/0/embedding")
+        .and_then(Value::as_array)
+        .context("embeddings response has no data[0].embedding array")?;
+    values
+        .iter()
+        .map(|v| v.as_f64().map(|f| f as f32).context("non-numeric embedding value"))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,4 +404,25 @@ mod tests {
             "expected HTTP 503 in error, got: {err}"
         );
     }
+
+    #[tokio::test]
+    async fn embeddings_returns_first_vector() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [{ "object": "embedding", "index": 0, "embedding": [0.5, -0.25, 1.0] }],
+            })))
+            .mount(&server)
+            .await;
+
+        let vector = BackendClient::new(&cfg_for(&server))
+            .unwrap()
+            .embeddings("embed-model", "kitchen lights on")
+            .await
+            .unwrap();
+
+        assert_eq!(vector, vec![0.5, -0.25, 1.0]);
+    }
 }
@@ -76,6 +76,7 @@ impl OllamaAdapter {
         let response = self
             .client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -92,6 +93,31 @@ impl OllamaAdapter {
             .with_context(|| format!("parsing Ollama response as JSON: {text}"))
     }
 
+    /// Embed `input` via Ollama's OpenAI-compat `POST /v1/embeddings`.
+    pub async fn embeddings(&self, model: &str, input: &str) -> anyhow::Result<Vec<f32>> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let body = serde_json::json!({ "model": model, "input": input, "keep_alive": -1 });
+        let response = self
+            .client
+            .post(&url)
+            .headers(crate::telemetry::trace_headers())
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("POST {url}"))?;
+
+        let status = response.status();
+        let text = response.text().await.context("reading Ollama embeddings body")?;
+
+        if !status.is_success() {
+            anyhow::bail!("Ollama returned HTTP {status}: {text}");
+        }
+
+        let body: Value = serde_json::from_str(&text)
+            .with_context(|| format!("parsing Ollama embeddings response as JSON: {text}"))?;
+        super::first_embedding(&body)
+    }
+
     /// Send `POST /v1/chat/completions` and return an [`SseStream`] for proxying.
     ///
     /// The backend response bytes are forwarded verbatim.
@@ -105,6 +131,7 @@ impl OllamaAdapter {
         let response = self
             .stream_client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -132,6 +159,7 @@ impl OllamaAdapter {
         let response = self
             .stream_client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -280,6 +308,7 @@ impl OllamaAdapter {
         let response = self
             .stream_client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -391,6 +420,7 @@ fn parse_plain_text_tool_calls(content: &str) -> Option<Vec<Value>> {
         let response = self
             .client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -61,6 +61,7 @@ impl OpenAIAdapter {
         let response = self
             .client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -77,6 +78,30 @@ impl OpenAIAdapter {
             .with_context(|| format!("parsing backend response as JSON: {text}"))
     }
 
+    /// Embed `input` via `POST /v1/embeddings`.
+    pub async fn embeddings(&self, model: &str, input: &str) -> anyhow::Result<Vec<f32>> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .headers(crate::telemetry::trace_headers())
+            .json(&serde_json::json!({ "model": model, "input": input }))
+            .send()
+            .await
+            .with_context(|| format!("POST {url}"))?;
+
+        let status = response.status();
+        let text = response.text().await.context("reading embeddings response body")?;
+
+        if !status.is_success() {
+            anyhow::bail!("backend returned HTTP {status}: {text}");
+        }
+
+        let body: Value = serde_json::from_str(&text)
+            .with_context(|| format!("parsing embeddings response as JSON: {text}"))?;
+        super::first_embedding(&body)
+    }
+
     /// Send `POST /v1/chat/completions` and return an [`SseStream`] for proxying.
     ///
     /// The backend response bytes are forwarded verbatim — no buffering, no schema
@@ -86,6 +111,7 @@ impl OpenAIAdapter {
         let response = self
             .stream_client
             .post(&url)
+            .headers(crate::telemetry::trace_headers())
             .json(&body)
             .send()
             .await
@@ -0,0 +1,102 @@
+//! The exact-match layer: one LRU of responses per profile, keyed by
+//! [`CacheKey`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+use super::{CacheKey, ResponseCache};
+use crate::config::CacheConfig;
+
+impl ResponseCache {
+    pub(super) fn get(&self, profile: &str, config: &CacheConfig, key: &CacheKey) -> Option<Arc<Value>> {
+        let mut profiles = self.profiles.lock().expect("response cache lock poisoned");
+        profiles.get_mut(profile)?.get(key, Duration::from_secs(config.ttl_secs))
+    }
+
+    pub(super) fn put(&self, profile: &str, config: &CacheConfig, key: CacheKey, response: Value) {
+        let mut profiles = self.profiles.lock().expect("response cache lock poisoned");
+        profiles
+            .entry(profile.to_owned())
+            .or_default()
+            .put(key, Arc::new(response), config.max_entries);
+    }
+
+    /// Number of live entries per profile.
+    #[allow(dead_code)] // used in tests
+    pub fn len(&self, profile: &str) -> usize {
+        let profiles = self.profiles.lock().expect("response cache lock poisoned");
+        profiles.get(profile).map_or(0, |lru| lru.slots.len())
+    }
+}
+
+/// One profile's entries, least recently used first in `order`.
+#[derive(Default)]
+pub(super) struct Lru {
+    slots: HashMap<CacheKey, Slot>,
+    /// Last-use tick → key; the first entry is the eviction candidate.
+    order: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+struct Slot {
+    response: Arc<Value>,
+    stored: Instant,
+    last_used: u64,
+}
+
+impl Lru {
+    pub(super) fn get(&mut self, key: &CacheKey, ttl: Duration) -> Option<Arc<Value>> {
+        let slot = self.slots.get(key)?;
+        if slot.stored.elapsed() >= ttl {
+            let last_used = slot.last_used;
+            self.slots.remove(key);
+            self.order.remove(&last_used);
+            return None;
+        }
+        self.tick += 1;
+        let slot = self.slots.get_mut(key)?;
+        self.order.remove(&slot.last_used);
+        slot.last_used = self.tick;
+        self.order.insert(self.tick, *key);
+        Some(Arc::clone(&slot.response))
+    }
+
+    pub(super) fn put(&mut self, key: CacheKey, response: Arc<Value>, max_entries: usize) {
+        self.tick += 1;
+        if let Some(old) = self.slots.insert(key, Slot { response, stored: Instant::now(), last_used: self.tick }) {
+            self.order.remove(&old.last_used);
+        }
+        self.order.insert(self.tick, key);
+        // A reload may have lowered max_entries; shrink all the way down.
+        while self.slots.len() > max_entries.max(1) {
+            let Some((_, oldest)) = self.order.pop_first() else { break };
+            self.slots.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn completion(text: &str) -> Value {
+        json!({ "choices": [{ "message": { "role": "assistant", "content": text }, "finish_reason": "stop" }] })
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let mut lru = Lru::default();
+        let key = CacheKey([0; 32]);
+        lru.put(key, Arc::new(completion("x")), 10);
+        assert!(lru.get(&key, Duration::from_secs(60)).is_some());
+        assert!(lru.get(&key, Duration::ZERO).is_none());
+        assert!(lru.slots.is_empty() && lru.order.is_empty());
+    }
+}
@@ -0,0 +1,420 @@
+//! Response cache: an exact-match layer (`exact`) and an optional semantic
+//! layer (`semantic`).
+//!
+//! Opt-in per profile via `[profiles.<name>.cache]`. A response is stored
+//! under a [`CacheKey`] — a SHA-256 over the resolved tier and a normalised
+//! form of the request fields that affect generation (see [`KEY_FIELDS`]) —
+//! so identical requests after routing skip the backend call. Each profile
+//! has its own LRU with the TTL and size limit from its config, read on every
+//! access so hot-reloaded limits apply immediately.
+//!
+//! Profiles can add a semantic layer (`[profiles.<name>.cache.semantic]`):
+//! the last user message is embedded and matched by cosine similarity against
+//! earlier requests with the same tier, class and surrounding request (see
+//! [`CacheKey::semantic_partition`]).
+//!
+//! Streaming requests replay a cached completion as synthetic SSE (see
+//! [`completion_sse`]), and streamed completions are captured into the cache
+//! by the router's stream wrapper.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest as _, Sha256};
+
+mod exact;
+mod semantic;
+
+use exact::Lru;
+pub(crate) use semantic::normalize;
+pub use semantic::SemanticQuery;
+use semantic::VectorIndex;
+
+use crate::{backends::SseStream, config::CacheConfig};
+
+/// Request fields hashed into the key besides the tier. `stream` is left out
+/// so streaming and non-streaming requests share entries.
+pub const KEY_FIELDS: &[&str] = &[
+    "model",
+    "messages",
+    "tools",
+    "tool_choice",
+    "temperature",
+    "top_p",
+    "top_k",
+    "max_tokens",
+    "max_completion_tokens",
+    "stop",
+    "seed",
+    "n",
+    "presence_penalty",
+    "frequency_penalty",
+    "repeat_penalty",
+    "logit_bias",
+    "response_format",
+    "think",
+    "options",
+];
+
+/// How a request was served with respect to the cache.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheOutcome {
+    /// Served from the cache without calling the backend.
+    Hit,
+    /// Served from the semantic layer: a similar, not identical, request.
+    Semantic,
+    /// Not cached; the backend response was stored.
+    Miss,
+    /// The client opted out with `Cache-Control: no-cache` / `no-store`.
+    Bypass,
+}
+
+impl CacheOutcome {
+    /// Metric label value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Semantic => "semantic",
+            Self::Miss => "miss",
+            Self::Bypass => "bypass",
+        }
+    }
+
+    /// Value of the `X-LMG-Cache` response header.
+    pub fn header_value(self) -> &'static str {
+        match self {
+            Self::Hit => "HIT",
+            Self::Semantic => "SEMANTIC",
+            Self::Miss => "MISS",
+            Self::Bypass => "BYPASS",
+        }
+    }
+}
+
+/// Client cache preference from the `Cache-Control` request header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheDirective {
+    #[default]
+    Default,
+    /// `no-cache`: skip the lookup but store the fresh response.
+    NoCache,
+    /// `no-store`: neither look up nor store.
+    NoStore,
+}
+
+impl CacheDirective {
+    /// Parse a `Cache-Control` header value; `no-store` wins over `no-cache`.
+    pub fn parse(header: &str) -> Self {
+        let mut directive = Self::Default;
+        for token in header.split(',').map(|t| t.trim().to_ascii_lowercase()) {
+            match token.as_str() {
+                "no-store" => return Self::NoStore,
+                "no-cache" => directive = Self::NoCache,
+                _ => {}
+            }
+        }
+        directive
+    }
+}
+
+/// SHA-256 of a normalised request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey([u8; 32]);
+
+impl CacheKey {
+    /// Key for `body` as it will be sent to `tier`.
+    ///
+    /// Object keys are sorted, `null` fields dropped and strings trimmed, so
+    /// key order and stray whitespace don't split entries.
+    pub fn new(tier: &str, body: &Value) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(tier.as_bytes());
+        for field in KEY_FIELDS {
+            if let Some(value) = body.get(*field).filter(|v| !v.is_null()) {
+                hasher.update([0]);
+                hasher.update(field.as_bytes());
+                hash_canonical(&mut hasher, value);
+            }
+        }
+        Self(hasher.finalize().into())
+    }
+
+    /// Key for `body` minus its last user message, which the semantic layer
+    /// matches by embedding instead. Requests only match within a partition,
+    /// so the system prompt, earlier turns, tools and sampling must agree.
+    pub fn semantic_partition(tier: &str, class: &str, body: &Value) -> Self {
+        let mut body = body.clone();
+        if let Some(last_user) = body
+            .get_mut("messages")
+            .and_then(Value::as_array_mut)
+            .and_then(|messages| messages.iter_mut().rev().find(|m| m["role"] == "user"))
+        {
+            last_user["content"] = Value::Null;
+        }
+        Self::new(&format!("{tier}\0{}", class.to_ascii_lowercase()), &body)
+    }
+}
+
+fn hash_canonical(hasher: &mut Sha256, value: &Value) {
+    match value {
+        Value::Null => hasher.update(b"n"),
+        Value::Bool(b) => hasher.update(if *b { b"t" } else { b"f" }),
+        Value::Number(n) => {
+            hasher.update(b"#");
+            hasher.update(n.to_string().as_bytes());
+        }
+        Value::String(s) => {
+            let s = s.trim();
+            hasher.update(b"s");
+            hasher.update((s.len() as u64).to_le_bytes());
+            hasher.update(s.as_bytes());
+        }
+        Value::Array(items) => {
+            hasher.update(b"[");
+            hasher.update((items.len() as u64).to_le_bytes());
+            for item in items {
+                hash_canonical(hasher, item);
+            }
+        }
+        Value::Object(map) => {
+            let fields: BTreeMap<&String, &Value> = map.iter().filter(|(_, v)| !v.is_null()).collect();
+            hasher.update(b"{");
+            hasher.update((fields.len() as u64).to_le_bytes());
+            for (k, v) in fields {
+                hasher.update((k.len() as u64).to_le_bytes());
+                hasher.update(k.as_bytes());
+                hash_canonical(hasher, v);
+            }
+        }
+    }
+}
+
+/// Cached responses for every profile with caching enabled.
+#[derive(Default)]
+pub struct ResponseCache {
+    profiles: Mutex<HashMap<String, Lru>>,
+    semantic: Mutex<HashMap<String, VectorIndex>>,
+}
+
+/// Result of looking a request up in its profile's cache.
+pub enum CacheLookup {
+    Hit(CacheHit),
+    /// Not served from the cache; store the backend response through the fill.
+    Miss(CacheFill),
+}
+
+pub struct CacheHit {
+    pub response: Value,
+    /// Cosine similarity to the stored request, for semantic hits.
+    pub similarity: Option<f32>,
+}
+
+impl CacheHit {
+    pub fn outcome(&self) -> CacheOutcome {
+        if self.similarity.is_some() { CacheOutcome::Semantic } else { CacheOutcome::Hit }
+    }
+}
+
+/// Where a missed request's response goes once the backend returns it.
+#[derive(Clone)]
+pub struct CacheFill {
+    scope: CacheScope,
+    key: CacheKey,
+    semantic: Option<(CacheKey, Vec<f32>)>,
+}
+
+impl CacheFill {
+    pub fn new(scope: CacheScope, key: CacheKey) -> Self {
+        Self { scope, key, semantic: None }
+    }
+
+    /// Also index the response under `query`'s embedding.
+    pub fn with_embedding(mut self, query: SemanticQuery, vector: Vec<f32>) -> Self {
+        self.semantic = normalize(vector).map(|v| (query.partition, v));
+        self
+    }
+
+    pub fn store(&self, response: &Value) {
+        self.scope.store(self.key, response);
+        if let Some((partition, vector)) = &self.semantic {
+            if self.scope.storable(response) {
+                let scope = &self.scope;
+                scope.cache.insert(&scope.profile, &scope.config, *partition, vector.clone(), response.clone());
+            }
+        }
+    }
+
+    pub fn outcome(&self) -> CacheOutcome {
+        self.scope.miss()
+    }
+}
+
+/// One request's handle on its profile's cache.
+#[derive(Clone)]
+pub struct CacheScope {
+    cache: Arc<ResponseCache>,
+    profile: String,
+    config: CacheConfig,
+    directive: CacheDirective,
+    /// Classifier label of the request, once classification has run.
+    class: Option<String>,
+}
+
+impl CacheScope {
+    pub fn new(cache: Arc<ResponseCache>, profile: &str, config: CacheConfig, directive: CacheDirective) -> Self {
+        Self { cache, profile: profile.to_owned(), config, directive, class: None }
+    }
+
+    /// Record the request's class, which decides semantic-layer eligibility.
+    pub fn with_class(mut self, class: &str) -> Self {
+        self.class = Some(class.to_owned());
+        self
+    }
+
+    /// Cached response for `key`, unless the client asked to skip the lookup.
+    pub fn lookup(&self, key: &CacheKey) -> Option<Value> {
+        if self.directive != CacheDirective::Default {
+            return None;
+        }
+        self.cache.get(&self.profile, &self.config, key).map(|v| (*v).clone())
+    }
+
+    /// Store a backend response. Only completions with a message are kept.
+    pub fn store(&self, key: CacheKey, response: &Value) {
+        if self.storable(response) {
+            self.cache.put(&self.profile, &self.config, key, response.clone());
+        }
+    }
+
+    fn storable(&self, response: &Value) -> bool {
+        self.directive != CacheDirective::NoStore && response.pointer("/choices/0/message").is_some()
+    }
+
+    /// Outcome to record for a request that did not hit.
+    pub fn miss(&self) -> CacheOutcome {
+        match self.directive {
+            CacheDirective::Default => CacheOutcome::Miss,
+            CacheDirective::NoCache | CacheDirective::NoStore => CacheOutcome::Bypass,
+        }
+    }
+}
+
+/// Replay a cached `chat.completion` as an OpenAI SSE stream: the message in
+/// one delta chunk, a final chunk with `finish_reason` and usage, then `[DONE]`.
+pub fn completion_sse(response: &Value) -> SseStream {
+    let id = response.get("id").cloned().unwrap_or_else(|| json!("chatcmpl-cached"));
+    let created = response.get("created").cloned().unwrap_or_else(|| json!(chrono::Utc::now().timestamp()));
+    let model = response.get("model").cloned().unwrap_or(Value::Null);
+    let message = response.pointer("/choices/0/message").cloned().unwrap_or_else(|| json!({}));
+    let finish_reason = response
+        .pointer("/choices/0/finish_reason")
+        .cloned()
+        .filter(|v| !v.is_null())
+        .unwrap_or_else(|| json!("stop"));
+
+    let mut delta = json!({ "role": "assistant" });
+    if let Some(content) = message.get("content").filter(|c| !c.is_null()) {
+        delta["content"] = content.clone();
+    }
+    if let Some(Value::Array(calls)) = message.get("tool_calls") {
+        let indexed: Vec<Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| {
+                let mut call = call.clone();
+                call["index"] = json!(i);
+                call
+            })
+            .collect();
+        delta["tool_calls"] = Value::Array(indexed);
+    }
+    let chunk = |choice: Value| {
+        json!({ "id": id, "object": "chat.completion.chunk", "created": created, "model": model, "choices": [choice] })
+    };
+    let first = chunk(json!({ "index": 0, "delta": delta, "finish_reason": null }));
+    let mut last = chunk(json!({ "index": 0, "delta": {}, "finish_reason": finish_reason }));
+    if let Some(usage) = response.get("usage") {
+        last["usage"] = usage.clone();
+    }
+    let frames = [format!("data: {first}\n\n"), format!("data: {last}\n\n"), "data: [DONE]\n\n".to_owned()];
+    Box::pin(futures_util::stream::iter(frames.map(|f| Ok(Bytes::from(f)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt as _;
+
+    use super::*;
+
+    fn config(max_entries: usize) -> CacheConfig {
+        CacheConfig { ttl_secs: 60, max_entries, semantic: None }
+    }
+
+    fn completion(text: &str) -> Value {
+        json!({ "choices": [{ "message": { "role": "assistant", "content": text }, "finish_reason": "stop" }] })
+    }
+
+    #[test]
+    fn key_ignores_key_order_whitespace_and_stream() {
+        let a = json!({ "model": "m", "stream": false, "messages": [{ "role": "user", "content": "hi " }], "temperature": 0 });
+        let b = json!({ "temperature": 0, "messages": [{ "content": "hi", "role": "user" }], "model": "m", "stream": true });
+        assert_eq!(CacheKey::new("fast", &a), CacheKey::new("fast", &b));
+        assert_ne!(CacheKey::new("fast", &a), CacheKey::new("deep", &a));
+        let warmer = json!({ "model": "m", "messages": [{ "role": "user", "content": "hi" }], "temperature": 0.7 });
+        assert_ne!(CacheKey::new("fast", &a), CacheKey::new("fast", &warmer));
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let cache = Arc::new(ResponseCache::default());
+        let scope = CacheScope::new(Arc::clone(&cache), "p", config(2), CacheDirective::Default);
+        let key = |n: u8| CacheKey([n; 32]);
+        scope.store(key(1), &completion("one"));
+        scope.store(key(2), &completion("two"));
+        assert!(scope.lookup(&key(1)).is_some()); // 1 is now newer than 2
+        scope.store(key(3), &completion("three"));
+
+        assert!(scope.lookup(&key(2)).is_none());
+        assert!(scope.lookup(&key(1)).is_some());
+        assert!(scope.lookup(&key(3)).is_some());
+        assert_eq!(cache.len("p"), 2);
+    }
+
+    #[test]
+    fn directives_control_lookup_and_store() {
+        assert_eq!(CacheDirective::parse("max-age=0, No-Cache"), CacheDirective::NoCache);
+        assert_eq!(CacheDirective::parse("no-cache, no-store"), CacheDirective::NoStore);
+
+        let cache = Arc::new(ResponseCache::default());
+        let key = CacheKey([7; 32]);
+        let no_store = CacheScope::new(Arc::clone(&cache), "p", config(5), CacheDirective::NoStore);
+        no_store.store(key, &completion("x"));
+        assert_eq!(cache.len("p"), 0);
+
+        let no_cache = CacheScope::new(Arc::clone(&cache), "p", config(5), CacheDirective::NoCache);
+        no_cache.store(key, &completion("x"));
+        assert!(no_cache.lookup(&key).is_none());
+        assert_eq!(no_cache.miss(), CacheOutcome::Bypass);
+        assert_eq!(cache.len("p"), 1);
+    }
+
+    #[tokio::test]
+    async fn completion_replays_as_sse() {
+        let mut response = completion("cached answer");
+        response["usage"] = json!({ "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5 });
+        let frames: Vec<String> = completion_sse(&response)
+            .map(|f| String::from_utf8(f.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].contains("\"content\":\"cached answer\""));
+        assert!(frames[1].contains("\"finish_reason\":\"stop\"") && frames[1].contains("\"total_tokens\":5"));
+        assert_eq!(frames[2], "data: [DONE]\n\n");
+    }
+}
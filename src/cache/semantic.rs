@@ -0,0 +1,240 @@
+//! The semantic layer: per-profile indexes of embedded requests, matched by
+//! cosine similarity within a partition (see
+//! [`CacheKey::semantic_partition`]). Each index is a brute-force scan over
+//! unit vectors, bounded by the profile's `max_entries`.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+use super::{CacheDirective, CacheHit, CacheKey, CacheScope, ResponseCache};
+use crate::config::{CacheConfig, SemanticCacheConfig};
+
+impl ResponseCache {
+    pub(super) fn nearest(
+        &self,
+        profile: &str,
+        config: &CacheConfig,
+        partition: &CacheKey,
+        vector: &[f32],
+        threshold: f32,
+    ) -> Option<(Arc<Value>, f32)> {
+        let mut indexes = self.semantic.lock().expect("semantic cache lock poisoned");
+        indexes
+            .get_mut(profile)?
+            .nearest(partition, vector, threshold, Duration::from_secs(config.ttl_secs))
+    }
+
+    pub(super) fn insert(&self, profile: &str, config: &CacheConfig, partition: CacheKey, vector: Vec<f32>, response: Value) {
+        let mut indexes = self.semantic.lock().expect("semantic cache lock poisoned");
+        indexes
+            .entry(profile.to_owned())
+            .or_default()
+            .insert(partition, vector, Arc::new(response), config.max_entries);
+    }
+
+    /// Number of semantic entries per profile.
+    #[allow(dead_code)] // used in tests
+    pub fn semantic_len(&self, profile: &str) -> usize {
+        let indexes = self.semantic.lock().expect("semantic cache lock poisoned");
+        indexes.get(profile).map_or(0, |index| index.entries.len())
+    }
+}
+
+/// One profile's semantic entries, each a unit vector so that cosine
+/// similarity is a dot product.
+#[derive(Default)]
+pub(super) struct VectorIndex {
+    entries: Vec<Embedded>,
+    tick: u64,
+}
+
+struct Embedded {
+    partition: CacheKey,
+    vector: Vec<f32>,
+    response: Arc<Value>,
+    stored: Instant,
+    last_used: u64,
+}
+
+/// Similarity above which a new entry replaces an existing one instead of
+/// sitting next to it.
+const DUPLICATE_SIMILARITY: f32 = 0.9999;
+
+impl VectorIndex {
+    fn nearest(&mut self, partition: &CacheKey, vector: &[f32], threshold: f32, ttl: Duration) -> Option<(Arc<Value>, f32)> {
+        self.entries.retain(|e| e.stored.elapsed() < ttl);
+        let (idx, similarity) = self.best_match(partition, vector)?;
+        if similarity < threshold {
+            return None;
+        }
+        self.tick += 1;
+        let entry = &mut self.entries[idx];
+        entry.last_used = self.tick;
+        Some((Arc::clone(&entry.response), similarity))
+    }
+
+    fn insert(&mut self, partition: CacheKey, vector: Vec<f32>, response: Arc<Value>, max_entries: usize) {
+        self.tick += 1;
+        let entry = Embedded { partition, vector, response, stored: Instant::now(), last_used: self.tick };
+        match self.best_match(&entry.partition, &entry.vector) {
+            Some((idx, similarity)) if similarity >= DUPLICATE_SIMILARITY => self.entries[idx] = entry,
+            _ => self.entries.push(entry),
+        }
+        while self.entries.len() > max_entries.max(1) {
+            let Some(oldest) = self.entries.iter().enumerate().min_by_key(|(_, e)| e.last_used).map(|(i, _)| i) else {
+                break;
+            };
+            self.entries.swap_remove(oldest);
+        }
+    }
+
+    fn best_match(&self, partition: &CacheKey, vector: &[f32]) -> Option<(usize, f32)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.partition == *partition && e.vector.len() == vector.len())
+            .map(|(i, e)| (i, e.vector.iter().zip(vector).map(|(a, b)| a * b).sum::<f32>()))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+/// Scale `vector` to unit length; `None` for a zero or non-finite vector.
+pub(crate) fn normalize(mut vector: Vec<f32>) -> Option<Vec<f32>> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if !norm.is_finite() || norm == 0.0 {
+        return None;
+    }
+    vector.iter_mut().for_each(|v| *v /= norm);
+    Some(vector)
+}
+
+/// A request eligible for the semantic layer: the text to embed and the
+/// partition it may match within.
+pub struct SemanticQuery {
+    pub embedding_tier: String,
+    pub text: String,
+    pub(super) partition: CacheKey,
+}
+
+impl CacheScope {
+    /// Semantic-layer query for `body` bound for `tier`, when the profile has
+    /// a semantic cache, the request's class is listed and it has user text.
+    pub fn semantic_query(&self, tier: &str, body: &Value) -> Option<SemanticQuery> {
+        if self.directive == CacheDirective::NoStore {
+            return None;
+        }
+        let semantic: &SemanticCacheConfig = self.config.semantic.as_ref()?;
+        let class = self.class.as_deref()?;
+        if !semantic.classes.iter().any(|c| c.eq_ignore_ascii_case(class)) {
+            return None;
+        }
+        let text = body
+            .get("messages")?
+            .as_array()?
+            .iter()
+            .rev()
+            .find(|m| m["role"] == "user")
+            .and_then(crate::router::extract_message_text)
+            .filter(|t| !t.trim().is_empty())?;
+        Some(SemanticQuery {
+            embedding_tier: semantic.embedding_tier.clone(),
+            text,
+            partition: CacheKey::semantic_partition(tier, class, body),
+        })
+    }
+
+    /// Closest cached response to `vector` within `query`'s partition, with
+    /// its similarity, unless the client asked to skip the lookup.
+    pub fn lookup_similar(&self, query: &SemanticQuery, vector: &[f32]) -> Option<CacheHit> {
+        let semantic = self.config.semantic.as_ref()?;
+        if self.directive != CacheDirective::Default {
+            return None;
+        }
+        let vector = normalize(vector.to_vec())?;
+        let (response, similarity) =
+            self.cache.nearest(&self.profile, &self.config, &query.partition, &vector, semantic.threshold)?;
+        Some(CacheHit { response: (*response).clone(), similarity: Some(similarity) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::cache::{CacheFill, CacheOutcome};
+
+    fn config(max_entries: usize) -> CacheConfig {
+        CacheConfig { ttl_secs: 60, max_entries, semantic: None }
+    }
+
+    fn completion(text: &str) -> Value {
+        json!({ "choices": [{ "message": { "role": "assistant", "content": text }, "finish_reason": "stop" }] })
+    }
+
+    fn semantic_scope(cache: &Arc<ResponseCache>, class: &str) -> CacheScope {
+        let mut config = config(10);
+        config.semantic = Some(SemanticCacheConfig {
+            embedding_tier: "local:embed".into(),
+            threshold: 0.9,
+            classes: vec!["inquiry".into()],
+        });
+        CacheScope::new(Arc::clone(cache), "p", config, CacheDirective::Default).with_class(class)
+    }
+
+    fn ask(system: &str, text: &str) -> Value {
+        json!({ "messages": [{ "role": "system", "content": system }, { "role": "user", "content": text }] })
+    }
+
+    #[test]
+    fn semantic_queries_only_for_listed_classes() {
+        let cache = Arc::new(ResponseCache::default());
+        let body = ask("be brief", "is the light on");
+        let query = semantic_scope(&cache, "Inquiry").semantic_query("fast", &body).unwrap();
+        assert_eq!(query.text, "is the light on");
+        assert!(semantic_scope(&cache, "command").semantic_query("fast", &body).is_none());
+        let unclassified = CacheScope::new(Arc::clone(&cache), "p", config(10), CacheDirective::Default);
+        assert!(unclassified.semantic_query("fast", &body).is_none());
+    }
+
+    #[test]
+    fn semantic_lookup_respects_threshold_and_partition() {
+        let cache = Arc::new(ResponseCache::default());
+        let scope = semantic_scope(&cache, "inquiry");
+        let stored = scope.semantic_query("fast", &ask("be brief", "is the light on")).unwrap();
+        let key = CacheKey::new("fast", &ask("be brief", "is the light on"));
+        CacheFill::new(scope.clone(), key).with_embedding(stored, vec![3.0, 4.0]).store(&completion("yes"));
+
+        let paraphrase = scope.semantic_query("fast", &ask("be brief", "light on?")).unwrap();
+        let hit = scope.lookup_similar(&paraphrase, &[0.62, 0.78]).unwrap();
+        assert_eq!(hit.outcome(), CacheOutcome::Semantic);
+        assert!(hit.similarity.unwrap() > 0.99);
+        assert!(scope.lookup_similar(&paraphrase, &[1.0, 0.0]).is_none(), "0.6 is below the threshold");
+
+        let other_prompt = scope.semantic_query("fast", &ask("be verbose", "light on?")).unwrap();
+        assert!(scope.lookup_similar(&other_prompt, &[0.6, 0.8]).is_none());
+        let other_tier = scope.semantic_query("deep", &ask("be brief", "light on?")).unwrap();
+        assert!(scope.lookup_similar(&other_tier, &[0.6, 0.8]).is_none());
+    }
+
+    #[test]
+    fn vector_index_replaces_duplicates_and_evicts_lru() {
+        let mut index = VectorIndex::default();
+        let partition = CacheKey([1; 32]);
+        let unit = |v: Vec<f32>| normalize(v).unwrap();
+        index.insert(partition, unit(vec![1.0, 0.0]), Arc::new(completion("a")), 2);
+        index.insert(partition, unit(vec![2.0, 0.0]), Arc::new(completion("a2")), 2);
+        assert_eq!(index.entries.len(), 1);
+        index.insert(partition, unit(vec![0.0, 1.0]), Arc::new(completion("b")), 2);
+        let ttl = Duration::from_secs(60);
+        assert!(index.nearest(&partition, &unit(vec![1.0, 0.0]), 0.9, ttl).is_some()); // a2 is now newer than b
+        index.insert(partition, unit(vec![-1.0, 0.0]), Arc::new(completion("c")), 2);
+        assert_eq!(index.entries.len(), 2);
+        assert!(index.nearest(&partition, &unit(vec![0.0, 1.0]), 0.9, ttl).is_none());
+        assert!(normalize(vec![0.0, 0.0]).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+//! Request-feature rule conditions.
+//!
+//! A [`RuleConfig`](super::RuleConfig) may test the request itself rather than
+//! (or as well as) the classifier's tags:
+//!
+//! ```toml
+//! [[profiles.auto.rules]]
+//! has_images = true
+//! route_to   = "cloud:vision"
+//!
+//! [[profiles.auto.rules]]
+//! message_matches = "(?i)\\b(traceback|panicked at)\\b"
+//! min_tokens      = 2000
+//! route_to        = "local:code"
+//! ```
+//!
+//! Every condition that is set must hold. Rules made only of request
+//! conditions are decided before the classifier is called; the router
+//! evaluates them (see `router::conditions`).
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Conditions over the request's content and metadata.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RequestConditions {
+    /// Regex searched for in the last user message's text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_matches: Option<Pattern>,
+
+    /// Keywords looked for in the last user message's text, case-insensitively.
+    /// Any one suffices.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub message_contains: Vec<String>,
+
+    /// Whether the request carries a non-empty `tools` (or `functions`) array.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub has_tools: Option<bool>,
+
+    /// Whether any message carries an image (`image_url` / `image` content
+    /// parts or Ollama `images`).
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub has_images: Option<bool>,
+
+    /// Inclusive bounds on the request's estimated token count.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub min_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub max_tokens: Option<u64>,
+
+    /// Request headers that must be present with exactly these values.
+    /// Header names are case-insensitive.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// Authenticated clients (their `key_env`) the rule applies to. Any one
+    /// suffices; requests without a client never match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<String>,
+
+    /// UTC time-of-day window, `"HH:MM-HH:MM"`. The end is exclusive; a
+    /// window may wrap past midnight (`"22:00-06:00"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours_utc: Option<HourRange>,
+}
+
+impl RequestConditions {
+    /// True when no condition is set.
+    pub fn is_empty(&self) -> bool {
+        self.message_matches.is_none()
+            && self.message_contains.is_empty()
+            && self.has_tools.is_none()
+            && self.has_images.is_none()
+            && self.min_tokens.is_none()
+            && self.max_tokens.is_none()
+            && self.headers.is_empty()
+            && self.clients.is_empty()
+            && self.hours_utc.is_none()
+    }
+}
+
+/// A regex compiled when the config loads, so a bad pattern is a load error.
+#[derive(Debug, Clone)]
+pub struct Pattern(regex::Regex);
+
+impl Pattern {
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        regex::Regex::new(&source).map(Self).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A time-of-day window; `start == end` covers the whole day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HourRange {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl HourRange {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl FromStr for HourRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("expected \"HH:MM-HH:MM\", got `{s}`"))?;
+        let parse = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|e| format!("invalid time `{}` in `{s}`: {e}", t.trim()))
+        };
+        Ok(Self { start: parse(start)?, end: parse(end)? })
+    }
+}
+
+impl fmt::Display for HourRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+impl Serialize for HourRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for HourRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn hour_ranges_parse_and_wrap_midnight() {
+        let office: HourRange = "09:00-17:30".parse().unwrap();
+        assert!(office.contains(at(9, 0)) && office.contains(at(17, 29)));
+        assert!(!office.contains(at(17, 30)) && !office.contains(at(8, 59)));
+
+        let night: HourRange = "22:00-06:00".parse().unwrap();
+        assert!(night.contains(at(23, 0)) && night.contains(at(5, 59)));
+        assert!(!night.contains(at(12, 0)));
+        assert_eq!(night.to_string(), "22:00-06:00");
+
+        assert!("9am-5pm".parse::<HourRange>().is_err());
+        assert!("09:00".parse::<HourRange>().is_err());
+    }
+
+    #[test]
+    fn bad_patterns_fail_to_load() {
+        let parsed: Result<RequestConditions, _> = toml::from_str(r#"message_matches = "(unclosed""#);
+        assert!(parsed.is_err());
+        let parsed: RequestConditions = toml::from_str(r#"message_matches = "(?i)traceback""#).unwrap();
+        assert!(parsed.message_matches.unwrap().is_match("Here is the TRACEBACK"));
+    }
+}
@@ -1,35 +1,56 @@
 //! Gateway and backend configuration types.
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 use super::Provider;
 
+/// Log output format for the gateway's own logs.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines (the `tracing_subscriber` default formatter).
+    #[default]
+    Text,
+    /// Newline-delimited JSON, for log shippers.
+    Json,
+}
+
 /// Core gateway settings.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GatewayConfig {
     /// Port for the agent-facing client API (default: 8080).
-    #[serde(default = "defaults::client_port")]
+    #[serde(default = "defaults::client_port", deserialize_with = "super::lenient::scalar")]
     pub client_port: u16,
 
     /// Port for the admin API + web UI (default: 8081).
-    #[serde(default = "defaults::admin_port")]
+    #[serde(default = "defaults::admin_port", deserialize_with = "super::lenient::scalar")]
     pub admin_port: u16,
 
     /// Number of recent requests to keep in the in-memory traffic log (default: 500).
-    #[serde(default = "defaults::traffic_log_capacity")]
+    #[serde(default = "defaults::traffic_log_capacity", deserialize_with = "super::lenient::scalar")]
     pub traffic_log_capacity: usize,
 
-    /// Log level override (also controlled by `RUST_LOG` env var).
+    /// Log filter applied at startup and on hot-reload, in `RUST_LOG` syntax:
+    /// a bare level (`debug`) or per-module directives
+    /// (`lm_gateway=debug,tower_http=info`). Ignored when `RUST_LOG` is set.
+    /// Default: `lm_gateway=info,tower_http=warn`.
     #[serde(default)]
     pub log_level: Option<String>,
 
+    /// Log output format: `text` (human-readable, default) or `json` (one
+    /// object per line). Read at startup only.
+    #[serde(default)]
+    pub log_format: LogFormat,
+
     /// Maximum requests per minute per client IP on the client port.
     ///
     /// Leave unset (or set to 0) to disable rate limiting.
     /// The burst allowance equals half of this value, rounded up,
     /// so `rate_limit_rpm = 60` allows 60 req/min sustained and up to
     /// 30 back-to-back requests before the bucket empties.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub rate_limit_rpm: Option<u32>,
 
     /// Environment variable whose value is the Bearer token required for all
@@ -59,14 +80,14 @@ pub struct GatewayConfig {
     /// On each retry the gateway waits `retry_delay_ms` (doubled per attempt,
     /// capped at 2 s) before calling the backend again. Only transient errors
     /// (network failures, 5xx) benefit from retries; 4xx errors are not retried.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub max_retries: Option<u32>,
 
     /// Initial delay between retry attempts in milliseconds (default: 200).
     ///
     /// Doubles on each subsequent attempt, capped at 2000 ms.
     /// Ignored when `max_retries` is 0 or unset.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub retry_delay_ms: Option<u64>,
 
     /// Sliding-window size for backend health tracking (default: 10).
@@ -75,7 +96,7 @@ pub struct GatewayConfig {
     /// escalate mode, if a backend's error rate over this window exceeds
     /// `health_error_threshold`, that backend is skipped and the next tier is
     /// tried instead. Set to 0 to disable health-based routing entirely.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub health_window: Option<usize>,
 
     /// Hard timeout in milliseconds applied at the gateway level to the entire
@@ -90,7 +111,7 @@ pub struct GatewayConfig {
     /// permit is released promptly and the backend TCP connection is torn down.
     ///
     /// Set to `0` to disable the gateway-level timeout entirely (not recommended).
-    #[serde(default = "defaults::request_timeout_ms")]
+    #[serde(default = "defaults::request_timeout_ms", deserialize_with = "super::lenient::optional")]
     pub request_timeout_ms: Option<u64>,
 
     /// Error-rate threshold above which a backend is considered unhealthy
@@ -99,7 +120,7 @@ pub struct GatewayConfig {
     /// Value in `(0.0, 1.0]`. A backend must have at least 3 samples in the
     /// window before it can be flagged as unhealthy. Set to `1.0` to
     /// effectively disable health-based skipping.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub health_error_threshold: Option<f64>,
 
     /// When `true` and the `debug-traffic` Cargo feature is enabled, the
@@ -112,9 +133,17 @@ pub struct GatewayConfig {
     /// The field is always present in config (so it can be set without the
     /// feature flag causing a parse error); capture behaviour is only active
     /// when the binary is compiled with `--features debug-traffic`.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::scalar")]
     pub traffic_log_debug: bool,
 
+    /// Honour the `X-LMG-Dry-Run: true` client header (default: false).
+    ///
+    /// A dry run classifies and resolves the request like a real one but
+    /// returns the routing trace instead of calling the target tier — the same
+    /// trace as `POST /admin/route/explain`. The classifier call still runs.
+    #[serde(default, deserialize_with = "super::lenient::scalar")]
+    pub allow_dry_run: bool,
+
     /// Directory containing per-profile TOML files.
     ///
     /// Each `*.toml` file in this directory is loaded as a `ProfileConfig`
@@ -131,6 +160,130 @@ pub struct GatewayConfig {
     /// ```
     #[serde(default)]
     pub profile_dir: Option<String>,
+
+    /// Number of applied configs kept in memory for
+    /// `GET /admin/config/history`, diffing and rollback (default: 20).
+    #[serde(default = "defaults::config_history_size", deserialize_with = "super::lenient::scalar")]
+    pub config_history_size: usize,
+
+    /// Directory that receives a JSON snapshot of every applied config.
+    ///
+    /// Snapshots show `${...}` templates rather than resolved values and are
+    /// never pruned — this is an audit trail, not a cache. Relative paths are
+    /// resolved against the parent of the main config file. Unset = memory only.
+    #[serde(default)]
+    pub config_history_dir: Option<String>,
+
+    /// Seconds between background refreshes of `exec` and `http` API key
+    /// secrets (default: 300). Set to 0 to fetch them only at startup and
+    /// when first referenced after a reload.
+    #[serde(default = "defaults::secret_refresh_secs", deserialize_with = "super::lenient::scalar")]
+    pub secret_refresh_secs: u64,
+
+    /// Append every traffic entry to this JSONL file (default: disabled).
+    ///
+    /// Relative paths resolve against the config file's directory. On startup
+    /// the in-memory traffic log is re-hydrated from the newest segments.
+    #[serde(default)]
+    pub traffic_log_path: Option<String>,
+
+    /// Rotate the active traffic archive segment at this size in MiB
+    /// (default: 64, 0 = no size limit).
+    #[serde(default = "defaults::traffic_log_rotate_mb", deserialize_with = "super::lenient::scalar")]
+    pub traffic_log_rotate_mb: u64,
+
+    /// Rotate the active traffic archive segment at this age in hours
+    /// (default: 24, 0 = no age limit).
+    #[serde(default = "defaults::traffic_log_rotate_hours", deserialize_with = "super::lenient::scalar")]
+    pub traffic_log_rotate_hours: u64,
+
+    /// Gzipped segments kept after rotation (default: 14, 0 = unlimited).
+    #[serde(default = "defaults::traffic_log_retain_files", deserialize_with = "super::lenient::scalar")]
+    pub traffic_log_retain_files: usize,
+
+    /// Delete rotated segments older than this many days (default: 30,
+    /// 0 = unlimited).
+    #[serde(default = "defaults::traffic_log_retain_days", deserialize_with = "super::lenient::scalar")]
+    pub traffic_log_retain_days: u64,
+
+    /// POST batches of traffic entries to this URL (default: disabled).
+    #[serde(default)]
+    pub traffic_webhook_url: Option<String>,
+
+    /// Extra headers sent with every webhook batch (e.g. collector auth).
+    /// Values support the usual interpolation — `${VAR}`, `${VAR:-default}`
+    /// and `${file:/path}` — so credentials can stay out of the config file.
+    #[serde(default)]
+    pub traffic_webhook_headers: HashMap<String, String>,
+
+    /// Env var holding the HMAC-SHA256 key used to sign webhook batches
+    /// (`X-LMG-Signature`). Omit to send unsigned batches.
+    #[serde(default)]
+    pub traffic_webhook_secret_env: Option<String>,
+
+    /// Maximum entries per webhook batch (default: 100).
+    #[serde(default = "defaults::traffic_webhook_batch_size", deserialize_with = "super::lenient::scalar")]
+    pub traffic_webhook_batch_size: usize,
+
+    /// Longest an entry waits before its batch is sent, in ms (default: 1000).
+    #[serde(default = "defaults::traffic_webhook_flush_ms", deserialize_with = "super::lenient::scalar")]
+    pub traffic_webhook_flush_ms: u64,
+
+    /// OTLP/HTTP collector base URL for trace and metric export, e.g.
+    /// `http://otel-collector:4318` (default: disabled). `/v1/traces` and
+    /// `/v1/metrics` are appended. Read at startup only.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// `service.name` resource attribute on exported telemetry
+    /// (default: `"lm-gateway"`).
+    #[serde(default = "defaults::otel_service_name")]
+    pub otel_service_name: String,
+
+    /// Fraction of new traces to sample, `0.0`–`1.0` (default: 1.0). Requests
+    /// that arrive with a sampled `traceparent` are always traced.
+    #[serde(default = "defaults::otel_sample_ratio", deserialize_with = "super::lenient::scalar")]
+    pub otel_sample_ratio: f64,
+}
+
+/// The settings an empty `[gateway]` table deserializes to.
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            client_port: defaults::client_port(),
+            admin_port: defaults::admin_port(),
+            traffic_log_capacity: defaults::traffic_log_capacity(),
+            log_level: None,
+            log_format: LogFormat::default(),
+            rate_limit_rpm: None,
+            admin_token_env: None,
+            public_profile: None,
+            max_retries: None,
+            retry_delay_ms: None,
+            health_window: None,
+            request_timeout_ms: defaults::request_timeout_ms(),
+            health_error_threshold: None,
+            traffic_log_debug: false,
+            allow_dry_run: false,
+            profile_dir: None,
+            config_history_size: defaults::config_history_size(),
+            config_history_dir: None,
+            secret_refresh_secs: defaults::secret_refresh_secs(),
+            traffic_log_path: None,
+            traffic_log_rotate_mb: defaults::traffic_log_rotate_mb(),
+            traffic_log_rotate_hours: defaults::traffic_log_rotate_hours(),
+            traffic_log_retain_files: defaults::traffic_log_retain_files(),
+            traffic_log_retain_days: defaults::traffic_log_retain_days(),
+            traffic_webhook_url: None,
+            traffic_webhook_headers: HashMap::new(),
+            traffic_webhook_secret_env: None,
+            traffic_webhook_batch_size: defaults::traffic_webhook_batch_size(),
+            traffic_webhook_flush_ms: defaults::traffic_webhook_flush_ms(),
+            otlp_endpoint: None,
+            otel_service_name: defaults::otel_service_name(),
+            otel_sample_ratio: defaults::otel_sample_ratio(),
+        }
+    }
 }
 
 /// A reference to a secret value from one of the supported secret stores.
@@ -148,7 +301,19 @@ pub struct GatewayConfig {
 ///
 /// # Docker / Kubernetes file secret:
 /// api_key_secret = { source = "file", path = "/run/secrets/anthropic_key" }
+///
+/// # Helper command (password manager, cloud CLI) — stdout is the secret:
+/// api_key_secret = { source = "exec", command = ["pass", "show", "openai"] }
+///
+/// # systemd credential (LoadCredential=openai_key:/etc/creds/openai):
+/// api_key_secret = { source = "systemd", name = "openai_key" }
+///
+/// # Vault-compatible KV store (token read from VAULT_TOKEN):
+/// api_key_secret = { source = "http", url = "https://vault:8200/v1/secret/data/openai", field = "api_key" }
 /// ```
+///
+/// `exec` and `http` secrets are cached and refreshed in the background (see
+/// [`super::secrets`]); the others are re-read on every request.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "source", rename_all = "snake_case")]
 pub enum SecretSource {
@@ -166,21 +331,91 @@ pub enum SecretSource {
         /// Absolute path to the file containing the secret.
         path: String,
     },
+    /// Run a command and use its standard output as the secret.
+    ///
+    /// The command is executed directly (no shell). Trailing newlines are
+    /// stripped; a non-zero exit status is a failed fetch.
+    Exec {
+        /// Program followed by its arguments.
+        command: Vec<String>,
+    },
+    /// Read a systemd credential from `$CREDENTIALS_DIRECTORY/<name>`.
+    ///
+    /// Pair with `LoadCredential=` or `LoadCredentialEncrypted=` in the unit file.
+    Systemd {
+        /// Credential name as given to `LoadCredential=`.
+        name: String,
+    },
+    /// Fetch a field from a Vault-compatible KV endpoint.
+    ///
+    /// Sends `GET <url>` with an `X-Vault-Token` header and reads `field` from
+    /// `data.data` (KV v2) or `data` (KV v1) in the JSON response.
+    Http {
+        /// Full secret URL, e.g. `https://vault:8200/v1/secret/data/openai`.
+        url: String,
+        /// Key within the secret's data map.
+        field: String,
+        /// Environment variable holding the store token (default: `VAULT_TOKEN`).
+        #[serde(default = "defaults::vault_token_env")]
+        token_env: String,
+    },
 }
 
 impl SecretSource {
     /// Resolve and return the secret, or `None` if unavailable.
+    ///
+    /// `exec` and `http` sources return the last value fetched by
+    /// [`super::secrets::refresh`] and never block.
     pub fn resolve(&self) -> Option<String> {
         match self {
             Self::Env { var } => std::env::var(var).ok().filter(|v| !v.is_empty()),
-            Self::File { path } => std::fs::read_to_string(path)
-                .ok()
-                .map(|s| s.trim_end_matches(['\n', '\r']).to_owned())
-                .filter(|v| !v.is_empty()),
+            Self::File { path } => read_secret_file(std::path::Path::new(path)),
+            Self::Systemd { name } => {
+                read_systemd_in(std::path::Path::new(&std::env::var_os("CREDENTIALS_DIRECTORY")?), name)
+            }
+            Self::Exec { .. } | Self::Http { .. } => super::secrets::cached(&self.cache_key()?),
+        }
+    }
+
+    /// Short source name, as shown in the admin API (`"env"`, `"exec"`, ...).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Env { .. } => "env",
+            Self::File { .. } => "file",
+            Self::Exec { .. } => "exec",
+            Self::Systemd { .. } => "systemd",
+            Self::Http { .. } => "http",
+        }
+    }
+
+    /// Key into the secret cache for sources fetched in the background, or
+    /// `None` for sources resolved on every call.
+    pub(super) fn cache_key(&self) -> Option<String> {
+        match self {
+            Self::Exec { command } => Some(format!("exec:{}", command.join("\0"))),
+            Self::Http { url, field, token_env } => Some(format!("http:{url}#{field}@{token_env}")),
+            Self::Env { .. } | Self::File { .. } | Self::Systemd { .. } => None,
         }
     }
 }
 
+/// Read the systemd credential `name` from the credentials directory `dir`.
+/// Names with path separators are rejected.
+fn read_systemd_in(dir: &std::path::Path, name: &str) -> Option<String> {
+    if name.contains(['/', '\\']) {
+        return None;
+    }
+    read_secret_file(&dir.join(name))
+}
+
+/// Read a secret file, stripping trailing newlines. Empty files count as unset.
+fn read_secret_file(path: &std::path::Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim_end_matches(['\n', '\r']).to_owned())
+        .filter(|v| !v.is_empty())
+}
+
 /// A named backend (Ollama instance, OpenRouter, Anthropic direct, etc.).
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BackendConfig {
@@ -196,13 +431,14 @@ pub struct BackendConfig {
 
     /// Typed secret reference for the API key.
     ///
-    /// Supports `"env"` (same as `api_key_env`) and `"file"` (Docker / k8s
-    /// secrets and any file-based store). Takes precedence over `api_key_env`.
+    /// Supports `"env"` (same as `api_key_env`), `"file"` (Docker / k8s
+    /// secrets), `"exec"`, `"systemd"` and `"http"` — see [`SecretSource`].
+    /// Takes precedence over `api_key_env`.
     #[serde(default)]
     pub api_key_secret: Option<SecretSource>,
 
     /// Request timeout in milliseconds (default: 30 000).
-    #[serde(default = "defaults::timeout_ms")]
+    #[serde(default = "defaults::timeout_ms", deserialize_with = "super::lenient::scalar")]
     pub timeout_ms: u64,
 
     /// Protocol adapter to use when talking to this backend.
@@ -246,12 +482,12 @@ impl BackendConfig {
         self.api_key_secret.is_some() || self.api_key_env.is_some()
     }
 
-    /// Returns the source type string (`"env"` or `"file"`) when a key is
-    /// configured, or `None` for keyless backends.
+    /// Returns the source type string (`"env"`, `"file"`, `"exec"`,
+    /// `"systemd"` or `"http"`) when a key is configured, or `None` for
+    /// keyless backends.
     pub fn api_key_source_type(&self) -> Option<&'static str> {
         match &self.api_key_secret {
-            Some(SecretSource::Env { .. }) => Some("env"),
-            Some(SecretSource::File { .. }) => Some("file"),
+            Some(source) => Some(source.kind()),
             None if self.api_key_env.is_some() => Some("env"),
             None => None,
         }
@@ -266,12 +502,36 @@ pub(super) mod defaults {
     pub fn timeout_ms() -> u64 { 30_000 }
     pub fn request_timeout_ms() -> Option<u64> { Some(120_000) }
     pub fn classifier_timeout_ms() -> u64 { 10_000 }
+    pub fn routing_pin_secs() -> u64 { 600 }
+    pub fn cache_ttl_secs() -> u64 { 300 }
+    pub fn cache_max_entries() -> usize { 1_000 }
+    pub fn semantic_cache_threshold() -> f32 { 0.92 }
+    pub fn knn_k() -> usize { 5 }
+    pub fn knn_min_confidence() -> f32 { 0.6 }
+    pub fn config_history_size() -> usize { 20 }
+    pub fn secret_refresh_secs() -> u64 { 300 }
+    pub fn traffic_log_rotate_mb() -> u64 { 64 }
+    pub fn traffic_log_rotate_hours() -> u64 { 24 }
+    pub fn traffic_log_retain_files() -> usize { 14 }
+    pub fn traffic_log_retain_days() -> u64 { 30 }
+    pub fn traffic_webhook_batch_size() -> usize { 100 }
+    pub fn traffic_webhook_flush_ms() -> u64 { 1000 }
+    pub fn otel_service_name() -> String { "lm-gateway".into() }
+    pub fn otel_sample_ratio() -> f64 { 1.0 }
+    pub fn vault_token_env() -> String { "VAULT_TOKEN".into() }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::GatewayConfig;
     use crate::config::Config;
 
+    #[test]
+    fn default_matches_an_empty_gateway_table() {
+        let parsed: GatewayConfig = toml::from_str("").unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), serde_json::to_value(GatewayConfig::default()).unwrap());
+    }
+
     #[test]
     fn default_options_parses_from_toml() {
         let toml = r#"
@@ -292,4 +552,38 @@ default_options = { num_ctx = 16384 }
         let num_ctx = map.get("num_ctx").expect("num_ctx missing");
         assert_eq!(num_ctx.as_i64(), Some(16384), "num_ctx should be 16384, got: {num_ctx:?}");
     }
+
+    #[test]
+    fn systemd_secret_reads_from_credentials_directory() {
+        let dir = std::env::temp_dir().join(format!("lmg-creds-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("openai_key"), "sk-systemd\n").unwrap();
+
+        assert_eq!(super::read_systemd_in(&dir, "openai_key").as_deref(), Some("sk-systemd"));
+        assert!(super::read_systemd_in(&dir, "../openai_key").is_none(), "path separators are rejected");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn new_secret_sources_parse_and_report_kind() {
+        let toml = r#"
+[gateway]
+
+[backends.a]
+base_url = "http://a"
+api_key_secret = { source = "exec", command = ["pass", "show", "openai"] }
+
+[backends.b]
+base_url = "http://b"
+api_key_secret = { source = "http", url = "https://vault/v1/secret/data/x", field = "key" }
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.backends["a"].api_key_source_type(), Some("exec"));
+        assert_eq!(config.backends["b"].api_key_source_type(), Some("http"));
+        match config.backends["b"].api_key_secret.as_ref().unwrap() {
+            super::SecretSource::Http { token_env, .. } => assert_eq!(token_env, "VAULT_TOKEN"),
+            other => panic!("unexpected source {other:?}"),
+        }
+    }
 }
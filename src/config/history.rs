@@ -0,0 +1,372 @@
+//! Applied-config history — what changed, when, and how to go back.
+//!
+//! Every config swapped in through [`crate::router::RouterState::replace_config`]
+//! is recorded as a numbered [`ConfigVersion`]. The last
+//! `gateway.config_history_size` versions are kept in memory for
+//! `GET /admin/config/history`, [`diff`] and rollback. When
+//! `gateway.config_history_dir` is set, each version is also written there as
+//! JSON (with `${...}` templates in place of resolved values and webhook header
+//! values redacted) as an audit trail.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Config;
+
+/// What caused a config to be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// Loaded at process start.
+    Startup,
+    /// Picked up by the mtime-polling hot-reload task.
+    FileWatcher,
+    /// `POST /admin/reload`.
+    AdminReload,
+    /// An admin API config mutation (`PUT`/`DELETE /admin/config/...`).
+    AdminApi,
+    /// `POST /admin/config/rollback/{version}`.
+    Rollback,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Startup => "startup",
+            Self::FileWatcher => "file_watcher",
+            Self::AdminReload => "admin_reload",
+            Self::AdminApi => "admin_api",
+            Self::Rollback => "rollback",
+        };
+        f.write_str(s)
+    }
+}
+
+/// One applied config.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigVersion {
+    /// Monotonic version number; the startup config is version 1.
+    pub version: u64,
+    pub applied_at: DateTime<Utc>,
+    pub source: ConfigSource,
+    /// For rollbacks, the version that was restored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollback_of: Option<u64>,
+    #[serde(skip)]
+    pub config: Arc<Config>,
+}
+
+/// Bounded, newest-last list of applied configs.
+#[derive(Debug)]
+pub struct ConfigHistory {
+    versions: VecDeque<ConfigVersion>,
+    capacity: usize,
+    next_version: u64,
+    dir: Option<PathBuf>,
+}
+
+impl ConfigHistory {
+    /// Start a history whose version 1 is `initial`.
+    ///
+    /// `capacity` is clamped to at least 1 so the current config is always
+    /// present. `dir`, when set, receives a JSON snapshot of every version.
+    pub fn new(initial: Arc<Config>, capacity: usize, dir: Option<PathBuf>) -> Self {
+        let mut history = Self {
+            versions: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_version: 1,
+            dir,
+        };
+        if let (_, Some(snapshot)) = history.record(initial, ConfigSource::Startup, None) {
+            snapshot.write();
+        }
+        history
+    }
+
+    /// Record a newly applied config and return its version number.
+    ///
+    /// When `config_history_dir` is set, also returns the version's snapshot
+    /// for the caller to [write](HistorySnapshot::write) once it has released the
+    /// history lock.
+    pub fn record(
+        &mut self,
+        config: Arc<Config>,
+        source: ConfigSource,
+        rollback_of: Option<u64>,
+    ) -> (u64, Option<HistorySnapshot>) {
+        let version = ConfigVersion {
+            version: self.next_version,
+            applied_at: Utc::now(),
+            source,
+            rollback_of,
+            config,
+        };
+        self.next_version += 1;
+        let snapshot = self.dir.clone().map(|dir| HistorySnapshot { dir, version: version.clone() });
+        if self.versions.len() == self.capacity {
+            self.versions.pop_front();
+        }
+        let number = version.version;
+        self.versions.push_back(version);
+        (number, snapshot)
+    }
+
+    /// The version currently live.
+    pub fn current(&self) -> &ConfigVersion {
+        self.versions.back().expect("history always holds the current config")
+    }
+
+    /// Look up a retained version.
+    pub fn get(&self, version: u64) -> Option<&ConfigVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    /// Retained versions, newest first.
+    pub fn versions(&self) -> impl Iterator<Item = &ConfigVersion> {
+        self.versions.iter().rev()
+    }
+}
+
+/// A recorded version not yet written to `config_history_dir`.
+#[derive(Debug)]
+pub struct HistorySnapshot {
+    dir: PathBuf,
+    version: ConfigVersion,
+}
+
+impl HistorySnapshot {
+    /// Write the snapshot, logging (not returning) failures — the audit trail
+    /// never blocks a config change. Does blocking I/O.
+    pub fn write(self) {
+        if let Err(e) = persist(&self.dir, &self.version) {
+            tracing::warn!(dir = %self.dir.display(), error = %e, "failed to write config history snapshot");
+        }
+    }
+}
+
+/// Write `version` to `dir` as `v<version>-<timestamp>.json`.
+fn persist(dir: &std::path::Path, version: &ConfigVersion) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let file = dir.join(format!(
+        "v{:06}-{}.json",
+        version.version,
+        version.applied_at.format("%Y%m%dT%H%M%SZ")
+    ));
+    let body = serde_json::json!({
+        "version": version,
+        "config": version.config.display_json(),
+    });
+    std::fs::write(file, serde_json::to_vec_pretty(&body)?)?;
+    Ok(())
+}
+
+/// How a single config value differs between two versions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One entry of a [`diff`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    /// Dotted config path, e.g. `profiles.default.classifier_prompt`.
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Structured, path-level diff between two configs, sorted by path.
+///
+/// Tiers and clients are keyed by name / `key_env` rather than position, so
+/// inserting a tier reports one addition instead of a cascade of changes.
+/// Other arrays (e.g. a profile's `rules`) are compared as a whole. Values are
+/// shown as their `${...}` templates where interpolated.
+pub fn diff(old: &Config, new: &Config) -> Vec<ConfigChange> {
+    let mut old_leaves = BTreeMap::new();
+    let mut new_leaves = BTreeMap::new();
+    flatten("", old.display_json(), &mut old_leaves);
+    flatten("", new.display_json(), &mut new_leaves);
+
+    let mut changes = Vec::new();
+    for (path, old_value) in &old_leaves {
+        match new_leaves.get(path) {
+            None => changes.push(ConfigChange {
+                path: path.clone(),
+                kind: ChangeKind::Removed,
+                old: Some(old_value.clone()),
+                new: None,
+            }),
+            Some(new_value) if new_value != old_value => changes.push(ConfigChange {
+                path: path.clone(),
+                kind: ChangeKind::Changed,
+                old: Some(old_value.clone()),
+                new: Some(new_value.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (path, new_value) in new_leaves {
+        if !old_leaves.contains_key(&path) {
+            changes.push(ConfigChange {
+                path,
+                kind: ChangeKind::Added,
+                old: None,
+                new: Some(new_value),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Flatten `value` into `path → leaf` pairs. Objects recurse; arrays of named
+/// objects recurse by id; every other value is a leaf.
+fn flatten(path: &str, value: Value, out: &mut BTreeMap<String, Value>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                flatten(&join(&key), child, out);
+            }
+        }
+        Value::Array(items) if !items.is_empty() && items.iter().all(|i| json_entry_id(i).is_some()) => {
+            for item in items {
+                let id = json_entry_id(&item).expect("checked above").to_owned();
+                flatten(&join(&id), item, out);
+            }
+        }
+        Value::Null => {}
+        leaf => {
+            out.insert(path.to_owned(), leaf);
+        }
+    }
+}
+
+/// JSON counterpart of [`super::overlay::entry_id`].
+pub(super) fn json_entry_id(entry: &Value) -> Option<&str> {
+    entry
+        .get("name")
+        .or_else(|| entry.get("key_env"))
+        .and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Arc<Config> {
+        Arc::new(toml::from_str(toml).unwrap())
+    }
+
+    const BASE: &str = r#"
+[gateway]
+
+[backends.ollama]
+base_url = "http://localhost:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "qwen2.5:1.5b"
+
+[profiles.default]
+mode          = "classify"
+classifier    = "local:fast"
+max_auto_tier = "local:fast"
+classifier_prompt = "old prompt"
+"#;
+
+    #[test]
+    fn history_evicts_oldest_and_keeps_numbering() {
+        let mut history = ConfigHistory::new(config(BASE), 2, None);
+        history.record(config(BASE), ConfigSource::FileWatcher, None);
+        let (v3, _) = history.record(config(BASE), ConfigSource::AdminApi, None);
+
+        assert_eq!(v3, 3);
+        assert!(history.get(1).is_none(), "version 1 should be evicted");
+        assert_eq!(history.current().version, 3);
+        let listed: Vec<u64> = history.versions().map(|v| v.version).collect();
+        assert_eq!(listed, vec![3, 2]);
+    }
+
+    #[test]
+    fn diff_reports_changed_added_and_removed_paths() {
+        let old = config(BASE);
+        let new = config(
+            &BASE
+                .replace("old prompt", "new prompt")
+                .replace("base_url = \"http://localhost:11434\"", "base_url = \"http://localhost:11434\"\n\n[aliases]\n\"hint:fast\" = \"local:fast\""),
+        );
+        let changes = diff(&old, &new);
+
+        let prompt = changes
+            .iter()
+            .find(|c| c.path == "profiles.default.classifier_prompt")
+            .expect("prompt change");
+        assert_eq!(prompt.kind, ChangeKind::Changed);
+        assert_eq!(prompt.old, Some(Value::from("old prompt")));
+        assert_eq!(prompt.new, Some(Value::from("new prompt")));
+
+        let alias = changes.iter().find(|c| c.path == "aliases.hint:fast").expect("alias added");
+        assert_eq!(alias.kind, ChangeKind::Added);
+
+        let back = diff(&new, &old);
+        assert!(back.iter().any(|c| c.path == "aliases.hint:fast" && c.kind == ChangeKind::Removed));
+    }
+
+    #[test]
+    fn diff_redacts_literal_webhook_headers() {
+        let with_header = |value: &str| {
+            config(&BASE.replace("[gateway]", &format!("[gateway]\ntraffic_webhook_headers = {{ Authorization = \"{value}\" }}")))
+        };
+        let changes = diff(&config(BASE), &with_header("Bearer s3cret"));
+        let header = changes
+            .iter()
+            .find(|c| c.path == "gateway.traffic_webhook_headers.Authorization")
+            .expect("header added");
+        assert_eq!(header.new, Some(Value::from("<redacted>")));
+        assert!(!serde_json::to_string(&changes).unwrap().contains("s3cret"));
+    }
+
+    #[test]
+    fn diff_keys_tiers_by_name() {
+        let old = config(BASE);
+        let new = config(&BASE.replace("qwen2.5:1.5b", "qwen3:1.7b"));
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "tiers.local:fast.model");
+    }
+
+    #[test]
+    fn snapshots_are_written_to_history_dir() {
+        let dir = std::env::temp_dir().join(format!("lmg-history-{}", uuid::Uuid::new_v4()));
+        let mut history = ConfigHistory::new(config(BASE), 5, Some(dir.clone()));
+        let (_, snapshot) = history.record(config(BASE), ConfigSource::AdminReload, None);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "written by the caller, not under the lock");
+        snapshot.expect("history dir is set").write();
+
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 2);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
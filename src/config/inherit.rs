@@ -0,0 +1,277 @@
+//! Profile inheritance — `extends = "<parent>"`.
+//!
+//! Resolved on the merged TOML document in [`super::Config::load`], after every
+//! config layer has been applied, so a child inherits exactly the fields it
+//! does not set itself:
+//!
+//! | Field kind | Behaviour |
+//! |---|---|
+//! | Scalars (`mode`, `classifier_prompt`, ...) | Child wins when set |
+//! | Maps (`class_prompts`, `thinking_messages`) | Key-level merge — child wins per key |
+//! | `rules` | Parent rules, then child rules; a child rule with the same conditions (`when` and request conditions) replaces the parent's |
+//!
+//! Chains (`a` extends `b` extends `c`) resolve from the root down. Cycles and
+//! unknown parents are load errors.
+
+use std::collections::HashMap;
+
+/// Resolve `extends` for every profile in `profiles` in place.
+///
+/// Returns the declared (pre-merge) table of each profile that uses
+/// `extends`, keyed by profile name. Interpolation templates recorded for a
+/// parent field are copied to children that inherit that field.
+pub(super) fn resolve_extends(
+    profiles: &mut toml::Table,
+    templates: &mut HashMap<String, String>,
+) -> anyhow::Result<HashMap<String, toml::Value>> {
+    let declared: HashMap<String, toml::Value> = profiles
+        .iter()
+        .filter(|(_, p)| p.get("extends").is_some())
+        .map(|(name, p)| (name.clone(), p.clone()))
+        .collect();
+
+    let mut resolved: HashMap<String, toml::Value> = HashMap::new();
+    let mut names: Vec<&String> = declared.keys().collect();
+    names.sort();
+    for name in names {
+        let mut chain = vec![name.clone()];
+        resolve_one(name, profiles, &mut resolved, &mut chain, templates)?;
+    }
+    for (name, effective) in resolved {
+        profiles.insert(name, effective);
+    }
+    Ok(declared)
+}
+
+/// Effective table for `name`, resolving its ancestors first.
+fn resolve_one(
+    name: &str,
+    profiles: &toml::Table,
+    resolved: &mut HashMap<String, toml::Value>,
+    chain: &mut Vec<String>,
+    templates: &mut HashMap<String, String>,
+) -> anyhow::Result<toml::Value> {
+    if let Some(done) = resolved.get(name) {
+        return Ok(done.clone());
+    }
+    let declared = &profiles[name];
+    let Some(parent) = declared.get("extends") else {
+        return Ok(declared.clone());
+    };
+    let parent = parent
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("profile `{name}`: `extends` must be a profile name"))?;
+    anyhow::ensure!(
+        profiles.contains_key(parent),
+        "profile `{name}` extends unknown profile `{parent}`"
+    );
+    if let Some(start) = chain.iter().position(|p| p == parent) {
+        let mut cycle = chain[start..].to_vec();
+        cycle.push(parent.to_owned());
+        anyhow::bail!("circular profile inheritance: {}", cycle.join(" → "));
+    }
+
+    chain.push(parent.to_owned());
+    let parent_effective = resolve_one(parent, profiles, resolved, chain, templates)?;
+    chain.pop();
+
+    inherit_templates(name, parent, declared, templates);
+    let effective = merge_profile(parent_effective, declared);
+    resolved.insert(name.to_owned(), effective.clone());
+    Ok(effective)
+}
+
+/// Overlay a child profile table on its parent's effective table.
+fn merge_profile(parent: toml::Value, child: &toml::Value) -> toml::Value {
+    let (toml::Value::Table(mut merged), Some(child)) = (parent, child.as_table()) else {
+        return child.clone();
+    };
+    for (key, value) in child {
+        let inherited = merged.remove(key);
+        let value = match (key.as_str(), inherited, value) {
+            ("rules", Some(toml::Value::Array(mut rules)), toml::Value::Array(own)) => {
+                for rule in own {
+                    match rules.iter_mut().find(|r| rule_conditions(r) == rule_conditions(rule)) {
+                        Some(existing) => *existing = rule.clone(),
+                        None => rules.push(rule.clone()),
+                    }
+                }
+                toml::Value::Array(rules)
+            }
+            (_, Some(toml::Value::Table(mut map)), toml::Value::Table(own)) => {
+                map.extend(own.iter().map(|(k, v)| (k.clone(), v.clone())));
+                toml::Value::Table(map)
+            }
+            _ => value.clone(),
+        };
+        merged.insert(key.clone(), value);
+    }
+    toml::Value::Table(merged)
+}
+
+/// A rule table without its outcome (`route_to`, `priority`) — what decides
+/// whether a child rule overrides a parent rule.
+fn rule_conditions(rule: &toml::Value) -> Option<toml::map::Map<String, toml::Value>> {
+    let mut conditions = rule.as_table()?.clone();
+    conditions.remove("route_to");
+    conditions.remove("priority");
+    Some(conditions)
+}
+
+/// Copy `profiles.<parent>.*` templates to `profiles.<child>.*` for every
+/// field the child does not declare itself.
+fn inherit_templates(
+    child: &str,
+    parent: &str,
+    declared: &toml::Value,
+    templates: &mut HashMap<String, String>,
+) {
+    let parent_prefix = format!("profiles.{parent}.");
+    let inherited: Vec<(String, String)> = templates
+        .iter()
+        .filter_map(|(path, template)| {
+            let suffix = path.strip_prefix(&parent_prefix)?;
+            (!declares(declared, suffix))
+                .then(|| (format!("profiles.{child}.{suffix}"), template.clone()))
+        })
+        .collect();
+    templates.extend(inherited);
+}
+
+/// `true` when `table` sets the dotted `path` (or an ancestor of it that is
+/// not a table, such as a `rules` array).
+fn declares(table: &toml::Value, path: &str) -> bool {
+    let mut current = table;
+    for segment in path.split('.') {
+        match current.get(segment) {
+            Some(next @ toml::Value::Table(_)) => current = next,
+            Some(_) => return true,
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles(doc: &str) -> toml::Table {
+        toml::from_str(doc).unwrap()
+    }
+
+    #[test]
+    fn child_inherits_unset_fields_and_overrides_set_ones() {
+        let mut p = profiles(
+            r#"
+            [base]
+            mode = "classify"
+            classifier = "local:instant"
+            classifier_prompt = "shared prompt"
+
+            [child]
+            extends = "base"
+            classifier = "local:fast"
+            "#,
+        );
+        let declared = resolve_extends(&mut p, &mut HashMap::new()).unwrap();
+        assert_eq!(p["child"]["classifier_prompt"].as_str(), Some("shared prompt"));
+        assert_eq!(p["child"]["classifier"].as_str(), Some("local:fast"));
+        assert_eq!(p["child"]["mode"].as_str(), Some("classify"));
+        assert!(declared["child"].get("classifier_prompt").is_none());
+    }
+
+    #[test]
+    fn maps_merge_per_key_and_rules_replace_by_when() {
+        let mut p = profiles(
+            r#"
+            [base.class_prompts]
+            greeting = "warm"
+            command  = "brief"
+
+            [[base.rules]]
+            when = { class = "greeting" }
+            route_to = "local:instant"
+
+            [[base.rules]]
+            when = { class = "command" }
+            route_to = "local:moderate"
+
+            [child]
+            extends = "base"
+
+            [child.class_prompts]
+            command = "confirm"
+
+            [[child.rules]]
+            when = { class = "command" }
+            route_to = "local:fast"
+
+            [[child.rules]]
+            when = { class = "inquiry" }
+            route_to = "local:deep"
+            "#,
+        );
+        resolve_extends(&mut p, &mut HashMap::new()).unwrap();
+        let prompts = p["child"]["class_prompts"].as_table().unwrap();
+        assert_eq!(prompts["greeting"].as_str(), Some("warm"));
+        assert_eq!(prompts["command"].as_str(), Some("confirm"));
+
+        let rules = p["child"]["rules"].as_array().unwrap();
+        let targets: Vec<&str> = rules.iter().map(|r| r["route_to"].as_str().unwrap()).collect();
+        assert_eq!(targets, vec!["local:instant", "local:fast", "local:deep"]);
+    }
+
+    #[test]
+    fn chains_resolve_from_the_root() {
+        let mut p = profiles(
+            r#"
+            [a]
+            extends = "b"
+            [b]
+            extends = "c"
+            [c]
+            system_prompt = "root"
+            "#,
+        );
+        resolve_extends(&mut p, &mut HashMap::new()).unwrap();
+        assert_eq!(p["a"]["system_prompt"].as_str(), Some("root"));
+    }
+
+    #[test]
+    fn cycles_are_rejected_with_path() {
+        let mut p = profiles(
+            r#"
+            [a]
+            extends = "b"
+            [b]
+            extends = "a"
+            "#,
+        );
+        let err = resolve_extends(&mut p, &mut HashMap::new()).unwrap_err().to_string();
+        assert!(err.contains("circular profile inheritance: a → b → a"), "{err}");
+    }
+
+    #[test]
+    fn unknown_parent_is_rejected() {
+        let mut p = profiles("[a]\nextends = \"ghost\"\n");
+        let err = resolve_extends(&mut p, &mut HashMap::new()).unwrap_err().to_string();
+        assert!(err.contains("unknown profile `ghost`"), "{err}");
+    }
+
+    #[test]
+    fn inherited_fields_carry_parent_templates() {
+        let mut p = profiles(
+            r#"
+            [base]
+            system_prompt = "resolved"
+            [child]
+            extends = "base"
+            "#,
+        );
+        let mut templates =
+            HashMap::from([("profiles.base.system_prompt".to_owned(), "${file:/p}".to_owned())]);
+        resolve_extends(&mut p, &mut templates).unwrap();
+        assert_eq!(templates["profiles.child.system_prompt"], "${file:/p}");
+    }
+}
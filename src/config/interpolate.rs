@@ -0,0 +1,205 @@
+//! `${...}` placeholder interpolation for config string values.
+//!
+//! Applied to every parsed TOML document in [`super::Config::load`] — the main
+//! config file, each `conf.d/` overlay and each profile-directory file — before
+//! the documents are merged. Supported forms:
+//!
+//! | Placeholder | Resolves to |
+//! |---|---|
+//! | `${VAR}` | Value of environment variable `VAR` (error if unset) |
+//! | `${VAR:-default}` | Value of `VAR`, or `default` when unset or empty |
+//! | `${file:/path}` | Contents of `/path`, trailing newlines stripped |
+//! | `$${` | A literal `${` (escape) |
+//!
+//! Resolved values are always strings; numeric and boolean fields parse them
+//! on deserialization (see [`super::lenient`]), so
+//! `client_port = "${LMG_PORT:-8080}"` works while `model = "${MODEL}"` keeps
+//! a numeric-looking model name as text.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+/// Resolve all placeholders in `value` in place.
+///
+/// `path` is the dotted location of `value` within the config (used in error
+/// messages and as the key into `templates`). Every string that contained at
+/// least one placeholder is recorded in `templates` under its path, mapped to
+/// the original template text, so the admin API can show the template instead
+/// of the resolved (possibly secret) value.
+pub(super) fn interpolate_value(
+    value: &mut toml::Value,
+    path: &str,
+    templates: &mut HashMap<String, String>,
+) -> anyhow::Result<()> {
+    match value {
+        toml::Value::String(s) => {
+            if !s.contains('$') {
+                return Ok(());
+            }
+            let (resolved, placeholders) = interpolate_str(s)
+                .with_context(|| format!("interpolating `{path}`"))?;
+            if placeholders == 0 {
+                *s = resolved;
+                return Ok(());
+            }
+            templates.insert(path.to_owned(), std::mem::replace(s, resolved));
+        }
+        toml::Value::Table(table) => {
+            for (key, child) in table.iter_mut() {
+                interpolate_value(child, &join_path(path, key), templates)?;
+            }
+        }
+        toml::Value::Array(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                // Named tables (tiers, clients) are addressed by id so the path
+                // stays stable when `conf.d/` overlays replace entries in-place.
+                let segment = super::overlay::entry_id(child)
+                    .map(str::to_owned)
+                    .unwrap_or_else(|| i.to_string());
+                interpolate_value(child, &join_path(path, &segment), templates)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Join a dotted config path and a child key.
+pub(super) fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+/// Expand every placeholder in `input`.
+///
+/// Returns the expanded string and the number of placeholders resolved
+/// (escapes are not counted).
+fn interpolate_str(input: &str) -> anyhow::Result<(String, usize)> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    let mut count = 0;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            out.push_str("${");
+            rest = escaped;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body
+                .find('}')
+                .with_context(|| format!("unterminated placeholder `${{{body}`"))?;
+            out.push_str(&resolve_placeholder(&body[..end])?);
+            count += 1;
+            rest = &body[end + 1..];
+        } else {
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok((out, count))
+}
+
+/// Resolve the text between `${` and `}`.
+fn resolve_placeholder(expr: &str) -> anyhow::Result<String> {
+    if let Some(path) = expr.strip_prefix("file:") {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("unresolved file placeholder `${{file:{path}}}`"))?;
+        return Ok(content.trim_end_matches(['\n', '\r']).to_owned());
+    }
+
+    let (var, default) = match expr.split_once(":-") {
+        Some((var, default)) => (var, Some(default)),
+        None => (expr, None),
+    };
+    anyhow::ensure!(!var.is_empty(), "empty placeholder `${{{expr}}}`");
+
+    match (std::env::var(var).ok().filter(|v| !v.is_empty()), default) {
+        (Some(v), _) => Ok(v),
+        (None, Some(d)) => Ok(d.to_owned()),
+        (None, None) => anyhow::bail!("unresolved variable `{var}` (set it or use `${{{var}:-default}}`)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpolate(doc: &str) -> anyhow::Result<(toml::Value, HashMap<String, String>)> {
+        let mut value: toml::Value = toml::from_str(doc).unwrap();
+        let mut templates = HashMap::new();
+        interpolate_value(&mut value, "", &mut templates)?;
+        Ok((value, templates))
+    }
+
+    #[test]
+    fn env_var_is_substituted_and_template_recorded() {
+        // Read an inherited variable rather than setting one: `set_var` is
+        // unsound while other test threads may read the environment.
+        let path = std::env::var("PATH").expect("PATH is set");
+        let (v, templates) = interpolate(r#"search = "${PATH}:/opt/bin""#).unwrap();
+        assert_eq!(v["search"].as_str(), Some(format!("{path}:/opt/bin").as_str()));
+        assert_eq!(templates["search"], "${PATH}:/opt/bin");
+    }
+
+    #[test]
+    fn default_is_used_when_var_is_unset() {
+        let (v, _) = interpolate(r#"model = "${LMG_INTERP_TEST_UNSET_1:-qwen3:1.7b}""#).unwrap();
+        assert_eq!(v["model"].as_str(), Some("qwen3:1.7b"));
+    }
+
+    #[test]
+    fn unresolved_variable_error_names_variable_and_path() {
+        let err = interpolate(
+            r#"
+            [backends.ollama]
+            base_url = "${LMG_INTERP_TEST_UNSET_2}"
+            "#,
+        )
+        .unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(msg.contains("LMG_INTERP_TEST_UNSET_2"), "{msg}");
+        assert!(msg.contains("backends.ollama.base_url"), "{msg}");
+    }
+
+    #[test]
+    fn file_placeholder_reads_and_trims_file() {
+        let path = std::env::temp_dir().join(format!("lmg-interp-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "You are helpful.\n").unwrap();
+        let doc = format!("system_prompt = \"${{file:{}}}\"", path.display());
+        let (v, _) = interpolate(&doc).unwrap();
+        assert_eq!(v["system_prompt"].as_str(), Some("You are helpful."));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn numeric_placeholder_stays_a_string() {
+        let (v, _) = interpolate(r#"model = "${LMG_INTERP_TEST_UNSET_3:-7}""#).unwrap();
+        assert_eq!(v["model"].as_str(), Some("7"));
+    }
+
+    #[test]
+    fn escape_produces_literal_placeholder_text() {
+        let (v, templates) = interpolate(r#"prompt = "cost: $5, keep $${HOME}""#).unwrap();
+        assert_eq!(v["prompt"].as_str(), Some("cost: $5, keep ${HOME}"));
+        assert!(templates.is_empty(), "escapes are not templates");
+    }
+
+    #[test]
+    fn named_array_entries_use_name_in_path() {
+        let (_, templates) = interpolate(
+            r#"
+            [[tiers]]
+            name  = "local:fast"
+            model = "${LMG_INTERP_TEST_UNSET_4:-qwen3:1.7b}"
+            "#,
+        )
+        .unwrap();
+        assert!(templates.contains_key("tiers.local:fast.model"), "{templates:?}");
+    }
+}
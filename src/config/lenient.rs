@@ -0,0 +1,71 @@
+//! Deserializers for numeric and boolean fields that also accept strings.
+//!
+//! [Interpolation](super::interpolate) always produces strings, so
+//! `client_port = "${LMG_PORT:-8080}"` reaches deserialization as `"8080"`.
+//! Fields that take a number or boolean parse such a string into their own
+//! type; string fields keep it verbatim, so `model = "${MODEL}"` with
+//! `MODEL=7` stays the string `"7"`.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer};
+
+/// A `T`, written natively or as a string that parses to one.
+pub(super) fn scalar<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + FromStr,
+    T::Err: Display,
+{
+    parse(toml::Value::deserialize(deserializer)?)
+}
+
+/// Like [`scalar`], for optional fields.
+pub(super) fn optional<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + FromStr,
+    T::Err: Display,
+{
+    Option::<toml::Value>::deserialize(deserializer)?.map(parse).transpose()
+}
+
+fn parse<T, E>(value: toml::Value) -> Result<T, E>
+where
+    T: DeserializeOwned + FromStr,
+    T::Err: Display,
+    E: de::Error,
+{
+    match value {
+        toml::Value::String(s) => s.parse().map_err(|e| E::custom(format!("invalid value `{s}`: {e}"))),
+        other => T::deserialize(other).map_err(E::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Fields {
+        #[serde(deserialize_with = "scalar")]
+        port: u16,
+        #[serde(default, deserialize_with = "optional")]
+        enabled: Option<bool>,
+    }
+
+    #[test]
+    fn accepts_native_and_string_values() {
+        let native: Fields = toml::from_str("port = 8080\nenabled = true").unwrap();
+        assert_eq!((native.port, native.enabled), (8080, Some(true)));
+
+        let strings: Fields = toml::from_str("port = \"8080\"\nenabled = \"false\"").unwrap();
+        assert_eq!((strings.port, strings.enabled), (8080, Some(false)));
+
+        let unset: Fields = toml::from_str("port = 1").unwrap();
+        assert_eq!(unset.enabled, None);
+
+        let err = toml::from_str::<Fields>("port = \"eighty\"").unwrap_err().to_string();
+        assert!(err.contains("invalid value `eighty`"), "{err}");
+    }
+}
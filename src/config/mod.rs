@@ -31,14 +31,29 @@ use std::{collections::HashMap, path::Path};
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
+mod conditions;
 mod gateway;
+mod history;
+mod inherit;
+mod interpolate;
+mod lenient;
+mod overlay;
 mod profile;
+pub mod secrets;
+mod split;
 
 // Re-exported for downstream code that matches on BackendConfig::api_key_secret.
 #[allow(unused_imports)]
-pub use gateway::{BackendConfig, GatewayConfig, SecretSource};
+pub use conditions::{HourRange, Pattern, RequestConditions};
+pub use gateway::{BackendConfig, GatewayConfig, LogFormat, SecretSource};
+pub use history::{diff, ConfigHistory, ConfigSource};
+pub use overlay::{AdminOverlay, Section};
 #[allow(unused_imports)]
-pub use profile::{DEFAULT_CLASSIFIER_PROMPT, ProfileConfig, RuleConfig, RoutingMode, TierConfig};
+pub use profile::{
+    CacheConfig, Capability, ClassifierKind, DEFAULT_CLASSIFIER_PROMPT, KnnConfig, ProfileConfig, RuleConfig, RoutingMode,
+    SemanticCacheConfig, TierCapabilities, TierConfig,
+};
+pub use split::{BucketBy, SplitConfig, SplitVariant};
 
 /// Which API protocol a backend speaks.
 ///
@@ -108,9 +123,6 @@ struct ProfileFile {
     /// Explicit profile name. When absent, the file stem is used.
     #[serde(default)]
     name: Option<String>,
-    /// All remaining fields are deserialized as `ProfileConfig`.
-    #[serde(flatten)]
-    config: ProfileConfig,
 }
 
 /// Top-level gateway configuration.
@@ -144,13 +156,35 @@ pub struct Config {
     /// not match any entry, the `default` profile is used (if configured).
     #[serde(default)]
     pub clients: Vec<ClientConfig>,
+
+    /// Weighted traffic splits, keyed by the tier (or alias) whose requests
+    /// are shared out among variant tiers.
+    #[serde(default)]
+    pub splits: HashMap<String, SplitConfig>,
+
+    /// Original text of every string value that contained a `${...}`
+    /// placeholder, keyed by dotted config path (e.g. `backends.ollama.base_url`).
+    ///
+    /// Populated by [`Config::load`]; the admin API shows these templates in
+    /// place of resolved values so secrets pulled from the environment or files
+    /// are never echoed back.
+    #[serde(skip)]
+    pub templates: HashMap<String, String>,
+
+    /// The `conf.d/admin.toml` overlay this config was loaded with.
+    ///
+    /// A rollback writes it back to disk so the restored admin edits survive
+    /// later edits, reloads and restarts.
+    #[serde(skip)]
+    pub admin_overlay: AdminOverlay,
 }
 
 /// Deep-merge `overlay` into `base` in-place.
 ///
 /// - **Tables**: keys in `overlay` recursively override/extend `base`.
-/// - **Arrays of tables that have a `name` field**: `overlay` entries replace
-///   same-named `base` entries in-place (order preserved); new names append.
+/// - **Arrays of tables that have a `name` field** (or `key_env`, for
+///   `[[clients]]`): `overlay` entries replace same-named `base` entries
+///   in-place (order preserved); new names append.
 /// - **All other arrays and scalars**: `overlay` replaces `base` wholesale.
 fn deep_merge(base: &mut toml::Value, overlay: toml::Value) {
     use toml::Value;
@@ -170,18 +204,12 @@ fn deep_merge(base: &mut toml::Value, overlay: toml::Value) {
                 // Named-table deduplication: if the overlay item is a table
                 // with a `name` key, replace the existing entry with the
                 // same name rather than appending a duplicate.
-                let maybe_name = if let Value::Table(ref t) = ov_item {
-                    t.get("name").and_then(|v| v.as_str()).map(str::to_owned)
-                } else {
-                    None
-                };
+                let maybe_name = overlay::entry_id(&ov_item).map(str::to_owned);
                 if let Some(name) = maybe_name {
-                    if let Some(existing) = base_arr.iter_mut().find(|v| {
-                        v.as_table()
-                            .and_then(|t| t.get("name"))
-                            .and_then(|n| n.as_str())
-                            == Some(&name)
-                    }) {
+                    if let Some(existing) = base_arr
+                        .iter_mut()
+                        .find(|v| overlay::entry_id(v) == Some(name.as_str()))
+                    {
                         *existing = ov_item;
                         continue;
                     }
@@ -194,6 +222,59 @@ fn deep_merge(base: &mut toml::Value, overlay: toml::Value) {
     }
 }
 
+/// Interpolate a `conf.d/` overlay, apply its `remove` list to `base`, then
+/// [`deep_merge`] it, keeping `templates` in step with the merged values.
+fn apply_overlay(
+    base: &mut toml::Value,
+    mut overlay: toml::Value,
+    templates: &mut HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let mut overlay_templates = HashMap::new();
+    interpolate::interpolate_value(&mut overlay, "", &mut overlay_templates)?;
+    for removed in overlay::take_removals(&mut overlay) {
+        overlay::remove_entry(base, &removed);
+        forget_templates(templates, &removed);
+    }
+    // Values the overlay replaces no longer come from a base template.
+    for replaced in overridden_paths(&overlay, "") {
+        forget_templates(templates, &replaced);
+    }
+    templates.extend(overlay_templates);
+    deep_merge(base, overlay);
+    Ok(())
+}
+
+/// Dotted paths whose base values `overlay` replaces when passed to [`deep_merge`].
+///
+/// Scalars and plain arrays are replaced at their own path; named array
+/// entries (tiers, clients) are replaced wholesale at `<array>.<name>`.
+fn overridden_paths(overlay: &toml::Value, path: &str) -> Vec<String> {
+    match overlay {
+        toml::Value::Table(t) => t
+            .iter()
+            .flat_map(|(k, v)| overridden_paths(v, &interpolate::join_path(path, k)))
+            .collect(),
+        toml::Value::Array(items) => {
+            let names: Vec<String> = items
+                .iter()
+                .filter_map(|v| overlay::entry_id(v).map(str::to_owned))
+                .collect();
+            if names.is_empty() {
+                vec![path.to_owned()]
+            } else {
+                names.iter().map(|n| interpolate::join_path(path, n)).collect()
+            }
+        }
+        _ => vec![path.to_owned()],
+    }
+}
+
+/// Remove template records at `path` and everything nested below it.
+fn forget_templates(templates: &mut HashMap<String, String>, path: &str) {
+    let nested = format!("{path}.");
+    templates.retain(|k, _| k != path && !k.starts_with(&nested));
+}
+
 /// Validate that no profile cascade `route_to` chain forms a cycle.
 ///
 /// DFS from every profile's rule `route_to` targets that are themselves
@@ -243,7 +324,11 @@ impl Config {
     /// | Section | Behaviour |
     /// |---|---|
     /// | `[gateway]`, `[backends.*]`, `[aliases]`, `[profiles.*]` | Key-level merge — overlay wins per key |
-    /// | `[[tiers]]`, `[[clients]]` | Deduplicated by `name` field — overlay replaces same-named entry; new names append |
+    /// | `[[tiers]]`, `[[clients]]` | Deduplicated by `name` (`key_env` for clients) — overlay replaces same-named entry; new names append |
+    /// | `remove = ["<section>.<id>", ...]` | Entries deleted from earlier layers before the overlay is merged |
+    ///
+    /// `conf.d/admin.toml` (written by the admin API) is held back and applied
+    /// after the `profiles/` directory; see [`Config::load_with_overlay`].
     ///
     /// A minimal `conf.d/local.toml` only needs to contain the sections it overrides:
     ///
@@ -258,13 +343,30 @@ impl Config {
     /// model   = "qwen3:1.7b"
     /// ```
     pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::load_with_overlay(path, None)
+    }
+
+    /// Like [`Config::load`], but apply `admin` in place of the on-disk
+    /// `conf.d/admin.toml`.
+    ///
+    /// Used by the admin API to validate a mutation against the fully merged
+    /// config before persisting it.
+    ///
+    /// Layers are applied in this order, later layers winning:
+    /// `config.toml` → `conf.d/*.toml` → `profiles/` → `conf.d/admin.toml`.
+    pub fn load_with_overlay(path: &Path, admin: Option<&AdminOverlay>) -> anyhow::Result<Self> {
         let content =
             std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
         let mut base: toml::Value = toml::from_str(&content)
             .with_context(|| format!("parsing {}", path.display()))?;
+        let mut templates = HashMap::new();
+        interpolate::interpolate_value(&mut base, "", &mut templates)
+            .with_context(|| format!("resolving placeholders in {}", path.display()))?;
 
-        // Layer conf.d/*.toml files alphabetically.
+        // Layer conf.d/*.toml files alphabetically; the admin overlay is held
+        // back until after the profile directory.
         let conf_d = path.parent().unwrap_or(Path::new(".")).join("conf.d");
+        let mut admin_file = None;
         if conf_d.is_dir() {
             let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(&conf_d)
                 .with_context(|| format!("reading conf.d directory {}", conf_d.display()))?
@@ -275,22 +377,24 @@ impl Config {
             entries.sort();
 
             for entry in entries {
+                if entry.file_name().is_some_and(|n| n == overlay::ADMIN_OVERLAY_FILE) {
+                    admin_file = Some(entry);
+                    continue;
+                }
                 let overlay_content = std::fs::read_to_string(&entry)
                     .with_context(|| format!("reading {}", entry.display()))?;
                 let overlay: toml::Value = toml::from_str(&overlay_content)
                     .with_context(|| format!("parsing {}", entry.display()))?;
-                deep_merge(&mut base, overlay);
+                apply_overlay(&mut base, overlay, &mut templates)
+                    .with_context(|| format!("resolving placeholders in {}", entry.display()))?;
             }
         }
 
-        // Serialize back to string and use toml::from_str exclusively (see gotchas.md).
-        let merged = toml::to_string(&base).context("re-serializing merged config")?;
-        let mut config: Self = toml::from_str(&merged).context("deserializing merged config")?;
-
         // Layer per-profile files from the profile directory.
         let config_parent = path.parent().unwrap_or(Path::new("."));
-        let profile_dir = match &config.gateway.profile_dir {
+        let profile_dir = match base.get("gateway").and_then(|g| g.get("profile_dir")) {
             Some(dir) => {
+                let dir = dir.as_str().context("`gateway.profile_dir` must be a string")?;
                 let p = Path::new(dir);
                 if p.is_absolute() {
                     p.to_path_buf()
@@ -321,6 +425,13 @@ impl Config {
                 .collect();
             entries.sort();
 
+            let profiles = base
+                .as_table_mut()
+                .context("config root must be a table")?
+                .entry("profiles")
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .context("`profiles` must be a table")?;
             for entry in &entries {
                 let fallback_name = entry
                     .file_stem()
@@ -329,17 +440,39 @@ impl Config {
                     .with_context(|| format!("invalid profile filename {}", entry.display()))?;
                 let content = std::fs::read_to_string(entry)
                     .with_context(|| format!("reading profile {}", entry.display()))?;
-                let file: ProfileFile = toml::from_str(&content)
+                let mut raw: toml::Value = toml::from_str(&content)
+                    .with_context(|| format!("parsing profile {}", entry.display()))?;
+                let mut file_templates = HashMap::new();
+                interpolate::interpolate_value(&mut raw, "", &mut file_templates)
+                    .with_context(|| format!("resolving placeholders in {}", entry.display()))?;
+                let file: ProfileFile = raw
+                    .clone()
+                    .try_into()
                     .with_context(|| format!("parsing profile {}", entry.display()))?;
                 let name = file.name.unwrap_or(fallback_name);
-                if config.profiles.contains_key(&name) {
+                // Keep the file as written (minus `name`) so `extends` only
+                // fills in fields the file leaves unset; parsing it here only
+                // reports errors against the file that has them.
+                if let Some(t) = raw.as_table_mut() {
+                    t.remove("name");
+                }
+                ProfileConfig::deserialize(raw.clone())
+                    .with_context(|| format!("parsing profile {}", entry.display()))?;
+                let prefix = interpolate::join_path("profiles", &name);
+                forget_templates(&mut templates, &prefix);
+                templates.extend(
+                    file_templates
+                        .into_iter()
+                        .map(|(k, v)| (interpolate::join_path(&prefix, &k), v)),
+                );
+                if profiles.contains_key(&name) {
                     tracing::warn!(
                         name = %name,
                         file = %entry.display(),
                         "profile directory entry overwrites existing profile"
                     );
                 }
-                config.profiles.insert(name, file.config);
+                profiles.insert(name, raw);
             }
             if !entries.is_empty() {
                 tracing::info!(
@@ -350,11 +483,52 @@ impl Config {
             }
         }
 
+        // Admin-managed overlay last, so runtime edits win over every file.
+        let admin = match (admin, admin_file) {
+            (Some(overlay), _) => overlay.clone(),
+            (None, Some(_)) => AdminOverlay::read(path)?,
+            (None, None) => AdminOverlay::default(),
+        };
+        apply_overlay(&mut base, admin.to_value(), &mut templates)
+            .context("resolving placeholders in admin overlay")?;
+
+        // Resolve profile inheritance once every layer is in place.
+        let declared = match base.get_mut("profiles").and_then(toml::Value::as_table_mut) {
+            Some(profiles) => inherit::resolve_extends(profiles, &mut templates)?,
+            None => HashMap::new(),
+        };
+
+        // Serialize back to string and use toml::from_str exclusively (see gotchas.md).
+        let merged = toml::to_string(&base).context("re-serializing merged config")?;
+        let mut config: Self = toml::from_str(&merged).context("deserializing merged config")?;
+        config.templates = templates;
+        config.admin_overlay = admin;
+        for (name, declared) in declared {
+            if let Some(profile) = config.profiles.get_mut(&name) {
+                profile.declared = Some(declared);
+            }
+        }
+
+        config.load_knn_examples(config_parent)?;
         config.normalize();
         config.validate()?;
         Ok(config)
     }
 
+    /// Read each kNN classifier's `examples_file`, relative to `config_dir`.
+    fn load_knn_examples(&mut self, config_dir: &Path) -> anyhow::Result<()> {
+        for (name, profile) in &mut self.profiles {
+            let Some(knn) = profile.knn.as_mut() else { continue };
+            let Some(file) = knn.examples_file.as_deref() else { continue };
+            let path = config_dir.join(file);
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading kNN examples {} for profile `{name}`", path.display()))?;
+            knn.file_examples = toml::from_str(&content)
+                .with_context(|| format!("parsing kNN examples {} for profile `{name}`", path.display()))?;
+        }
+        Ok(())
+    }
+
     /// Sort rules within each profile by priority descending so that rule
     /// evaluation in the hot path can iterate without re-sorting on every request.
     fn normalize(&mut self) {
@@ -398,6 +572,152 @@ impl Config {
             );
         }
 
+        // Dispatch-mode request rules override the model hint with a tier
+        for (name, profile) in &self.profiles {
+            if profile.mode != RoutingMode::Dispatch {
+                continue;
+            }
+            for rule in profile.rules.iter().filter(|rule| rule.is_request_rule()) {
+                anyhow::ensure!(
+                    self.resolve_tier(&rule.route_to).is_some(),
+                    "profile `{name}` is in dispatch mode, so its request rules must route to a tier, got `{}`",
+                    rule.route_to
+                );
+            }
+        }
+
+        // Classifier fallbacks must resolve; default labels must name a tier
+        for (name, profile) in &self.profiles {
+            if let Some(fallback) = profile.classifier_fallback.as_deref() {
+                anyhow::ensure!(
+                    profile.mode == RoutingMode::Classify,
+                    "profile `{name}` sets classifier_fallback but is not in classify mode"
+                );
+                anyhow::ensure!(
+                    self.resolve_tier(fallback).is_some(),
+                    "profile `{name}` classifier_fallback references unknown tier `{fallback}`"
+                );
+            }
+            if let Some(label) = profile.classifier_default_label.as_deref() {
+                anyhow::ensure!(
+                    self.tiers.iter().any(|t| t.name == label || t.name.rsplit(':').next() == Some(label)),
+                    "profile `{name}` classifier_default_label `{label}` matches no tier name or suffix"
+                );
+            }
+        }
+
+        // Speculation needs a classifier to race and a tier to predict
+        for (name, profile) in &self.profiles {
+            if let Some(predict) = profile.speculate.as_deref() {
+                anyhow::ensure!(
+                    profile.mode == RoutingMode::Classify,
+                    "profile `{name}` sets speculate but is not in classify mode"
+                );
+                anyhow::ensure!(
+                    matches!(predict, "recent" | "classifier") || self.resolve_tier(predict).is_some(),
+                    "profile `{name}` speculate must be \"recent\", \"classifier\" or a known tier, got `{predict}`"
+                );
+            }
+        }
+
+        // kNN classifiers need examples and an embedding tier; the LLM
+        // classifier stays as the fallback for uncertain votes
+        for (name, profile) in &self.profiles {
+            if profile.classifier_kind.is_llm() {
+                continue;
+            }
+            anyhow::ensure!(
+                profile.mode == RoutingMode::Classify,
+                "profile `{name}` sets classifier_kind = \"knn\" but is not in classify mode"
+            );
+            let knn = profile
+                .knn
+                .as_ref()
+                .with_context(|| format!("profile `{name}` sets classifier_kind = \"knn\" without a [knn] section"))?;
+            anyhow::ensure!(knn.k > 0, "profile `{name}` knn.k must be at least 1");
+            anyhow::ensure!(
+                knn.min_confidence > 0.0 && knn.min_confidence <= 1.0,
+                "profile `{name}` knn.min_confidence must be in (0, 1]"
+            );
+            anyhow::ensure!(
+                knn.labelled_examples().next().is_some(),
+                "profile `{name}` kNN classifier needs at least one example"
+            );
+            let tier = self.resolve_tier(&knn.embedding_tier).with_context(|| {
+                format!("profile `{name}` knn references unknown embedding tier `{}`", knn.embedding_tier)
+            })?;
+            anyhow::ensure!(
+                self.backends.get(&tier.backend).is_none_or(|b| b.provider != Provider::Anthropic),
+                "profile `{name}` embedding tier `{}` uses an Anthropic backend, which has no embeddings API",
+                tier.name
+            );
+        }
+
+        // Cache limits must leave room for at least one response
+        for (name, profile) in &self.profiles {
+            if let Some(cache) = &profile.cache {
+                anyhow::ensure!(
+                    cache.ttl_secs > 0 && cache.max_entries > 0,
+                    "profile `{name}` cache needs ttl_secs and max_entries above 0"
+                );
+                if let Some(semantic) = &cache.semantic {
+                    anyhow::ensure!(
+                        profile.mode == RoutingMode::Classify,
+                        "profile `{name}` semantic cache needs mode = \"classify\" to know each request's class"
+                    );
+                    anyhow::ensure!(
+                        semantic.threshold > 0.0 && semantic.threshold <= 1.0,
+                        "profile `{name}` semantic cache threshold must be in (0, 1]"
+                    );
+                    anyhow::ensure!(
+                        !semantic.classes.is_empty(),
+                        "profile `{name}` semantic cache needs at least one class"
+                    );
+                    let tier = self.resolve_tier(&semantic.embedding_tier).with_context(|| {
+                        format!(
+                            "profile `{name}` semantic cache references unknown embedding tier `{}`",
+                            semantic.embedding_tier
+                        )
+                    })?;
+                    anyhow::ensure!(
+                        self.backends.get(&tier.backend).is_none_or(|b| b.provider != Provider::Anthropic),
+                        "profile `{name}` embedding tier `{}` uses an Anthropic backend, which has no embeddings API",
+                        tier.name
+                    );
+                }
+            }
+        }
+
+        // Splits share one tier among known tiers, and don't chain
+        let mut split_tiers: HashMap<&str, &str> = HashMap::new();
+        for (name, split) in &self.splits {
+            let tier = self
+                .resolve_tier(name)
+                .with_context(|| format!("split `{name}` applies to unknown tier or alias"))?;
+            if let Some(other) = split_tiers.insert(&tier.name, name) {
+                anyhow::bail!("splits `{other}` and `{name}` both apply to tier `{}`", tier.name);
+            }
+            anyhow::ensure!(split.total_weight() > 0, "split `{name}` needs a variant with weight above 0");
+        }
+        for (name, split) in &self.splits {
+            let mut seen = std::collections::HashSet::new();
+            for variant in &split.variants {
+                let tier = self
+                    .resolve_tier(&variant.tier)
+                    .with_context(|| format!("split `{name}` variant references unknown tier `{}`", variant.tier))?;
+                anyhow::ensure!(
+                    seen.insert(tier.name.as_str()),
+                    "split `{name}` lists tier `{}` more than once",
+                    tier.name
+                );
+                anyhow::ensure!(
+                    split_tiers.get(tier.name.as_str()).is_none_or(|owner| owner == name),
+                    "split `{name}` variant `{}` has a split of its own; splits don't chain",
+                    tier.name
+                );
+            }
+        }
+
         // Every client entry must reference a known profile
         let profile_names: std::collections::HashSet<&str> =
             self.profiles.keys().map(|k| k.as_str()).collect();
@@ -413,6 +733,12 @@ impl Config {
         // Profile cascade routes must not form cycles
         validate_profile_route_cycles(&self.profiles)?;
 
+        // The log filter must parse, so a bad hot-reload can't silence logging
+        if let Some(level) = &self.gateway.log_level {
+            tracing_subscriber::EnvFilter::try_new(level)
+                .map_err(|e| anyhow::anyhow!("invalid gateway.log_level `{level}`: {e}"))?;
+        }
+
         Ok(())
     }
 
@@ -427,12 +753,76 @@ impl Config {
         self.tiers.iter().find(|t| t.name == tier_name)
     }
 
+    /// The split applying to `tier_name`, with its key, if any.
+    pub fn split_for(&self, tier_name: &str) -> Option<(&str, &SplitConfig)> {
+        self.splits
+            .iter()
+            .find(|(key, _)| self.resolve_tier(key).is_some_and(|t| t.name == tier_name))
+            .map(|(key, split)| (key.as_str(), split))
+    }
+
     /// Return the named profile, falling back to `"default"`.
     ///
     /// Returns `None` only if neither the named profile nor a `"default"` profile exists.
     pub fn profile(&self, name: &str) -> Option<&ProfileConfig> {
         self.profiles.get(name).or_else(|| self.profiles.get("default"))
     }
+
+    /// Return the `${...}` template for the value at `path` when it was
+    /// interpolated, otherwise the resolved `value` itself.
+    ///
+    /// Used by the admin API so interpolated values are displayed as written
+    /// in the config file rather than as resolved at load time.
+    pub fn display_value<'a>(&'a self, path: &str, value: &'a str) -> &'a str {
+        self.templates.get(path).map(String::as_str).unwrap_or(value)
+    }
+
+    /// Replace every string in `value` that was interpolated at load time with
+    /// its original `${...}` template. `path` is the dotted config path of
+    /// `value`; tiers and clients are addressed by name / `key_env`.
+    pub fn show_templates(&self, path: &str, value: &mut serde_json::Value) {
+        use serde_json::Value;
+        match value {
+            Value::String(s) => {
+                if let Some(template) = self.templates.get(path) {
+                    *s = template.clone();
+                }
+            }
+            Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    self.show_templates(&interpolate::join_path(path, key), child);
+                }
+            }
+            Value::Array(items) => {
+                for (i, child) in items.iter_mut().enumerate() {
+                    let segment = history::json_entry_id(child)
+                        .map(str::to_owned)
+                        .unwrap_or_else(|| i.to_string());
+                    self.show_templates(&interpolate::join_path(path, &segment), child);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The whole config as JSON, with interpolated values shown as templates
+    /// and literal `traffic_webhook_headers` values (collector credentials,
+    /// typically) redacted.
+    pub fn display_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).expect("config always serializes to JSON");
+        if let Some(headers) = value
+            .pointer_mut("/gateway/traffic_webhook_headers")
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            for (name, header) in headers.iter_mut() {
+                if !self.templates.contains_key(&format!("gateway.traffic_webhook_headers.{name}")) {
+                    *header = "<redacted>".into();
+                }
+            }
+        }
+        self.show_templates("", &mut value);
+        value
+    }
 }
 
 #[cfg(test)]
@@ -497,10 +887,182 @@ mod tests {
             model: "x".into(),
             think: None,
             max_context_tokens: None,
+            capabilities: Default::default(),
         });
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn validation_rejects_unparseable_log_level() {
+        let mut config = minimal_config();
+        config.gateway.log_level = Some("lm_gateway=debug,tower_http=loud".into());
+        assert!(config.validate().is_err());
+        config.gateway.log_level = Some("lm_gateway::router=trace,warn".into());
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn validation_rejects_zero_cache_limits() {
+        let mut config = minimal_config();
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.cache = Some(CacheConfig { ttl_secs: 0, max_entries: 10, semantic: None });
+        assert!(config.validate().is_err());
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.cache = Some(CacheConfig { ttl_secs: 60, max_entries: 10, semantic: None });
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn validation_checks_semantic_cache() {
+        let mut config = minimal_config();
+        let semantic = SemanticCacheConfig {
+            embedding_tier: "hint:fast".into(),
+            threshold: 0.9,
+            classes: vec!["inquiry".into()],
+        };
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.cache = Some(CacheConfig { ttl_secs: 60, max_entries: 10, semantic: Some(semantic.clone()) });
+        assert!(config.validate().is_err(), "dispatch profiles have no class to match");
+
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.mode = RoutingMode::Classify;
+        config.validate().unwrap();
+
+        for broken in [
+            SemanticCacheConfig { embedding_tier: "no-such-tier".into(), ..semantic.clone() },
+            SemanticCacheConfig { threshold: 1.5, ..semantic.clone() },
+            SemanticCacheConfig { classes: vec![], ..semantic.clone() },
+        ] {
+            let cache = config.profiles.get_mut("default").unwrap().cache.as_mut().unwrap();
+            cache.semantic = Some(broken);
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn validation_checks_knn_classifier() {
+        let mut config = minimal_config();
+        let knn = KnnConfig {
+            embedding_tier: "hint:fast".into(),
+            k: 3,
+            min_confidence: 0.6,
+            examples: HashMap::from([("instant".into(), vec!["lights off".into()])]),
+            examples_file: None,
+            file_examples: HashMap::new(),
+        };
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.classifier_kind = ClassifierKind::Knn;
+        profile.knn = Some(knn.clone());
+        assert!(config.validate().is_err(), "kNN labels only matter in classify mode");
+
+        config.profiles.get_mut("default").unwrap().mode = RoutingMode::Classify;
+        config.validate().unwrap();
+
+        for broken in [
+            KnnConfig { embedding_tier: "no-such-tier".into(), ..knn.clone() },
+            KnnConfig { k: 0, ..knn.clone() },
+            KnnConfig { min_confidence: 0.0, ..knn.clone() },
+            KnnConfig { examples: HashMap::new(), ..knn.clone() },
+        ] {
+            config.profiles.get_mut("default").unwrap().knn = Some(broken);
+            assert!(config.validate().is_err());
+        }
+        config.profiles.get_mut("default").unwrap().knn = None;
+        assert!(config.validate().is_err(), "knn kind needs a [knn] section");
+    }
+
+    #[test]
+    fn validation_checks_splits() {
+        let mut config = minimal_config();
+        let variant = |tier: &str, weight| SplitVariant { tier: tier.into(), weight };
+        let canary = SplitConfig {
+            bucket_by: BucketBy::Client,
+            variants: vec![variant("local:fast", 90), variant("hint:cloud", 10)],
+        };
+        config.splits.insert("hint:fast".into(), canary.clone());
+        config.validate().unwrap();
+        assert_eq!(config.split_for("local:fast").map(|(key, _)| key), Some("hint:fast"), "keyed through the alias");
+        assert!(config.split_for("cloud:economy").is_none());
+
+        for broken in [
+            SplitConfig { variants: vec![variant("local:fast", 0)], ..canary.clone() },
+            SplitConfig { variants: vec![variant("no-such-tier", 1)], ..canary.clone() },
+            SplitConfig { variants: vec![variant("local:fast", 1), variant("hint:fast", 1)], ..canary.clone() },
+        ] {
+            config.splits.insert("hint:fast".into(), broken);
+            assert!(config.validate().is_err());
+        }
+
+        config.splits = HashMap::from([("local:fast".into(), canary.clone()), ("hint:fast".into(), canary.clone())]);
+        assert!(config.validate().is_err(), "two splits on one tier");
+        config.splits = HashMap::from([("local:fast".into(), canary.clone()), ("cloud:economy".into(), canary)]);
+        assert!(config.validate().is_err(), "a variant with its own split");
+        config.splits = HashMap::from([("no-such-tier".into(), SplitConfig { bucket_by: BucketBy::Client, variants: vec![] })]);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn request_rules_parse_flat_and_dispatch_rules_need_a_tier() {
+        let mut config = minimal_config();
+        let rules: toml::Value = toml::from_str(
+            r#"
+            [[rules]]
+            has_images = true
+            route_to   = "hint:fast"
+
+            [[rules]]
+            when     = { class = "code" }
+            min_tokens = 4000
+            route_to = "local:fast"
+            "#,
+        )
+        .unwrap();
+        let rules: Vec<RuleConfig> = rules["rules"].clone().try_into().unwrap();
+        assert!(rules[0].is_request_rule());
+        assert!(!rules[1].is_request_rule(), "tag rules stay tag rules");
+        assert_eq!(rules[1].request.min_tokens, Some(4000));
+
+        config.profiles.get_mut("default").unwrap().rules = rules;
+        config.validate().unwrap();
+        config.profiles.get_mut("default").unwrap().rules[0].route_to = "default".into();
+        assert!(config.validate().is_err(), "dispatch mode can't cascade");
+    }
+
+    #[test]
+    fn validation_checks_classifier_fallback() {
+        let mut config = minimal_config();
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.classifier_fallback = Some("hint:fast".into());
+        assert!(config.validate().is_err(), "only classify mode calls a classifier");
+
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.mode = RoutingMode::Classify;
+        profile.classifier_default_label = Some("fast".into());
+        config.validate().unwrap();
+
+        config.profiles.get_mut("default").unwrap().classifier_fallback = Some("no-such-tier".into());
+        assert!(config.validate().is_err());
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.classifier_fallback = None;
+        profile.classifier_default_label = Some("genius".into());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn validation_checks_speculate() {
+        let mut config = minimal_config();
+        config.profiles.get_mut("default").unwrap().speculate = Some("recent".into());
+        assert!(config.validate().is_err(), "dispatch profiles have no classifier to race");
+
+        config.profiles.get_mut("default").unwrap().mode = RoutingMode::Classify;
+        for predict in ["recent", "classifier", "hint:fast"] {
+            config.profiles.get_mut("default").unwrap().speculate = Some(predict.into());
+            config.validate().unwrap();
+        }
+        config.profiles.get_mut("default").unwrap().speculate = Some("no-such-tier".into());
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn validation_rejects_alias_pointing_to_unknown_tier() {
         let mut config = minimal_config();
@@ -848,6 +1410,122 @@ max_auto_tier = "local:fast"
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[test]
+    fn load_reads_knn_examples_file_relative_to_config() {
+        let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let dir = std::env::temp_dir().join(format!("lmg-test-{uid}"));
+        std::fs::create_dir_all(dir.join("knn")).unwrap();
+
+        let base_toml = r#"
+[gateway]
+
+[backends.ollama]
+base_url = "http://localhost:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "qwen2.5:1.5b"
+
+[profiles.default]
+mode          = "classify"
+classifier    = "local:fast"
+max_auto_tier = "local:fast"
+classifier_kind = "knn"
+
+[profiles.default.knn]
+embedding_tier = "local:fast"
+examples_file  = "knn/default.toml"
+"#;
+        let examples_toml = r#"
+"instant class=command" = ["turn off the kitchen lights", "open the blinds"]
+"#;
+        let cfg_path = dir.join("config.toml");
+        std::fs::write(&cfg_path, base_toml).unwrap();
+        std::fs::write(dir.join("knn/default.toml"), examples_toml).unwrap();
+
+        let config = Config::load(&cfg_path).expect("examples file should load");
+        let knn = config.profiles["default"].knn.as_ref().unwrap();
+        assert_eq!(knn.k, 5);
+        assert_eq!(knn.labelled_examples().count(), 2);
+        assert!(knn.labelled_examples().all(|(label, _)| label == "instant class=command"));
+
+        std::fs::remove_file(dir.join("knn/default.toml")).unwrap();
+        assert!(Config::load(&cfg_path).is_err(), "a missing examples file is a load error");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn load_interpolates_placeholders_and_conf_d_literal_clears_template() {
+        let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let dir = std::env::temp_dir().join(format!("lmg-test-{uid}"));
+        let conf_d = dir.join("conf.d");
+        std::fs::create_dir_all(&conf_d).unwrap();
+
+        let base_toml = r#"
+[gateway]
+client_port = "${LMG_LOAD_TEST_UNSET_PORT:-9090}"
+
+[backends.ollama]
+base_url = "http://${LMG_LOAD_TEST_UNSET_HOST:-localhost}:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "${LMG_LOAD_TEST_UNSET_MODEL:-qwen2.5:1.5b}"
+
+[[tiers]]
+name    = "local:numbered"
+backend = "ollama"
+model   = "${LMG_LOAD_TEST_UNSET_NUMBERED:-7}"
+
+[profiles.default]
+mode          = "dispatch"
+classifier    = "local:fast"
+max_auto_tier = "local:fast"
+"#;
+        let override_toml = r#"
+[backends.ollama]
+base_url = "http://192.168.1.50:11434"
+"#;
+        let cfg_path = dir.join("config.toml");
+        std::fs::write(&cfg_path, base_toml).unwrap();
+        std::fs::write(conf_d.join("10-local.toml"), override_toml).unwrap();
+
+        let config = Config::load(&cfg_path).expect("should load with placeholders");
+        assert_eq!(config.gateway.client_port, 9090);
+        assert_eq!(config.tiers[0].model, "qwen2.5:1.5b");
+        assert_eq!(config.tiers[1].model, "7", "string fields keep numeric-looking values");
+        assert_eq!(
+            config.display_value("tiers.local:fast.model", &config.tiers[0].model),
+            "${LMG_LOAD_TEST_UNSET_MODEL:-qwen2.5:1.5b}"
+        );
+        // The overlay replaced the templated base_url with a literal.
+        assert!(!config.templates.contains_key("backends.ollama.base_url"));
+        assert_eq!(config.backends["ollama"].base_url, "http://192.168.1.50:11434");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn load_fails_with_unresolved_variable_name() {
+        let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let dir = std::env::temp_dir().join(format!("lmg-test-{uid}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg_path = dir.join("config.toml");
+        std::fs::write(
+            &cfg_path,
+            "[gateway]\n[backends.ollama]\nbase_url = \"${LMG_LOAD_TEST_UNSET_URL}\"\n",
+        )
+        .unwrap();
+
+        let err = Config::load(&cfg_path).unwrap_err();
+        assert!(format!("{err:#}").contains("LMG_LOAD_TEST_UNSET_URL"), "{err:#}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     // -----------------------------------------------------------------------
     // Profile cascade cycle detection
     // -----------------------------------------------------------------------
@@ -973,6 +1651,33 @@ max_auto_tier = "local:fast"
         std::fs::remove_dir_all(&dir).ok();
     }
 
+    #[test]
+    fn profile_dir_error_names_the_file() {
+        let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let dir = std::env::temp_dir().join(format!("lmg-test-{uid}"));
+        let profiles_dir = dir.join("profiles");
+        std::fs::create_dir_all(&profiles_dir).unwrap();
+
+        let base_toml = r#"
+[gateway]
+
+[backends.ollama]
+base_url = "http://localhost:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "qwen2.5:1.5b"
+"#;
+        std::fs::write(dir.join("config.toml"), base_toml).unwrap();
+        std::fs::write(profiles_dir.join("default.toml"), "mode = \"sideways\"\n").unwrap();
+
+        let err = format!("{:#}", Config::load(&dir.join("config.toml")).unwrap_err());
+        assert!(err.contains("parsing profile") && err.contains("default.toml"), "{err}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn profile_dir_name_override_takes_precedence() {
         let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
@@ -1113,4 +1818,102 @@ max_auto_tier = "local:fast"
 
         std::fs::remove_dir_all(&dir).ok();
     }
+
+    #[test]
+    fn admin_overlay_applies_after_profile_dir_and_honours_remove() {
+        let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let dir = std::env::temp_dir().join(format!("lmg-test-{uid}"));
+        std::fs::create_dir_all(dir.join("profiles")).unwrap();
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+
+        let base_toml = r#"
+[gateway]
+
+[backends.ollama]
+base_url = "http://localhost:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "qwen2.5:1.5b"
+
+[aliases]
+"hint:fast" = "local:fast"
+"#;
+        let profile_toml = r#"
+mode          = "dispatch"
+classifier    = "local:fast"
+max_auto_tier = "local:fast"
+system_prompt = "directory version"
+"#;
+        let admin_toml = r#"
+remove = ["aliases.hint:fast"]
+
+[profiles.default]
+system_prompt = "admin version"
+"#;
+        std::fs::write(dir.join("config.toml"), base_toml).unwrap();
+        std::fs::write(dir.join("profiles").join("default.toml"), profile_toml).unwrap();
+        // "admin.toml" sorts before "zz.toml" but must still be applied last.
+        std::fs::write(dir.join("conf.d").join("admin.toml"), admin_toml).unwrap();
+        std::fs::write(dir.join("conf.d").join("zz.toml"), "[aliases]\n\"hint:zz\" = \"local:fast\"\n").unwrap();
+
+        let config = Config::load(&dir.join("config.toml")).expect("should load with admin overlay");
+        let profile = &config.profiles["default"];
+        assert_eq!(profile.system_prompt.as_deref(), Some("admin version"));
+        assert_eq!(profile.classifier, "local:fast", "key-level merge keeps other fields");
+        assert!(!config.aliases.contains_key("hint:fast"));
+        assert!(config.aliases.contains_key("hint:zz"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn profile_dir_file_can_extend_inline_profile() {
+        let uid = uuid::Uuid::new_v4().to_string().replace('-', "");
+        let dir = std::env::temp_dir().join(format!("lmg-test-{uid}"));
+        std::fs::create_dir_all(dir.join("profiles")).unwrap();
+
+        let base_toml = r#"
+[gateway]
+
+[backends.ollama]
+base_url = "http://localhost:11434"
+
+[[tiers]]
+name    = "local:fast"
+backend = "ollama"
+model   = "qwen2.5:1.5b"
+
+[[tiers]]
+name    = "local:deep"
+backend = "ollama"
+model   = "qwen3:8b"
+
+[profiles.ha-auto]
+mode          = "classify"
+classifier    = "local:fast"
+max_auto_tier = "local:deep"
+system_prompt = "shared"
+"#;
+        let child_toml = r#"
+name          = "ha-auto:fast"
+extends       = "ha-auto"
+max_auto_tier = "local:fast"
+"#;
+        std::fs::write(dir.join("config.toml"), base_toml).unwrap();
+        std::fs::write(dir.join("profiles").join("ha-auto_fast.toml"), child_toml).unwrap();
+
+        let config = Config::load(&dir.join("config.toml")).expect("should load with extends");
+        let child = &config.profiles["ha-auto:fast"];
+        assert_eq!(child.mode, RoutingMode::Classify);
+        assert_eq!(child.system_prompt.as_deref(), Some("shared"));
+        assert_eq!(child.max_auto_tier, "local:fast");
+        assert_eq!(child.extends.as_deref(), Some("ha-auto"));
+        let declared = child.declared.as_ref().expect("declared profile kept");
+        assert!(declared.get("system_prompt").is_none());
+        assert!(config.profiles["ha-auto"].declared.is_none());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,359 @@
+//! Admin-managed `conf.d/` overlay — runtime config edits that survive restarts.
+//!
+//! The admin API never rewrites `config.toml`. Every create/update/delete is
+//! recorded in a dedicated overlay file, `conf.d/admin.toml`, which
+//! [`super::Config::load`] applies after all other `conf.d/` files and after
+//! the `profiles/` directory, so admin edits always win.
+//!
+//! The file is ordinary config TOML plus one extra top-level key, `remove`: a
+//! list of `<section>.<id>` entries deleted from the layers beneath it before
+//! the overlay is merged:
+//!
+//! ```toml
+//! remove = ["tiers.cloud:expert", "profiles.general"]
+//!
+//! # `general` was removed then re-added — a full replacement rather than a
+//! # key-level merge with the inline definition.
+//! [profiles.general]
+//! mode          = "dispatch"
+//! classifier    = "local:fast"
+//! max_auto_tier = "local:fast"
+//! ```
+//!
+//! `remove` is honoured in any `conf.d/` file, not just the admin overlay.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// File name of the admin-managed overlay inside `conf.d/`.
+pub const ADMIN_OVERLAY_FILE: &str = "admin.toml";
+
+/// Top-level overlay key listing `<section>.<id>` entries to delete.
+pub(super) const REMOVE_KEY: &str = "remove";
+
+/// Config sections the admin API can mutate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    Tiers,
+    Aliases,
+    Profiles,
+    Clients,
+    Splits,
+}
+
+impl Section {
+    /// TOML key of this section.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Tiers => "tiers",
+            Self::Aliases => "aliases",
+            Self::Profiles => "profiles",
+            Self::Clients => "clients",
+            Self::Splits => "splits",
+        }
+    }
+
+    /// `true` for `[[array]]` sections, whose entries are identified by
+    /// [`entry_id`] rather than by table key.
+    fn is_array(self) -> bool {
+        matches!(self, Self::Tiers | Self::Clients)
+    }
+}
+
+/// Identity of an `[[array]]` entry: its `name`, or `key_env` for `[[clients]]`.
+pub(super) fn entry_id(entry: &toml::Value) -> Option<&str> {
+    let table = entry.as_table()?;
+    table
+        .get("name")
+        .or_else(|| table.get("key_env"))
+        .and_then(toml::Value::as_str)
+}
+
+/// Delete the entry at `path` (`<section>.<id>`) from a merged config document.
+///
+/// Unknown sections and missing entries are ignored so a stale `remove` list
+/// never prevents the gateway from starting.
+pub(super) fn remove_entry(doc: &mut toml::Value, path: &str) {
+    let Some((section, id)) = path.split_once('.') else {
+        return;
+    };
+    match doc.get_mut(section) {
+        Some(toml::Value::Table(t)) => {
+            t.remove(id);
+        }
+        Some(toml::Value::Array(items)) => items.retain(|e| entry_id(e) != Some(id)),
+        _ => {}
+    }
+}
+
+/// Split the `remove` list off an overlay document.
+pub(super) fn take_removals(overlay: &mut toml::Value) -> Vec<String> {
+    let Some(table) = overlay.as_table_mut() else {
+        return Vec::new();
+    };
+    match table.remove(REMOVE_KEY) {
+        Some(toml::Value::Array(items)) => items
+            .into_iter()
+            .filter_map(|v| v.as_str().map(str::to_owned))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// In-memory copy of `conf.d/admin.toml`.
+///
+/// Mutations only touch this document; callers validate the result with
+/// [`super::Config::load_with_overlay`] before calling [`AdminOverlay::write`].
+#[derive(Debug, Clone, Default)]
+pub struct AdminOverlay {
+    doc: toml::Table,
+}
+
+impl AdminOverlay {
+    /// Location of the overlay file for the config at `config_path`.
+    pub fn path(config_path: &Path) -> PathBuf {
+        config_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("conf.d")
+            .join(ADMIN_OVERLAY_FILE)
+    }
+
+    /// Read the overlay from disk. A missing file is an empty overlay.
+    pub fn read(config_path: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(config_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        let doc = toml::from_str(&content).with_context(|| format!("parsing {}", path.display()))?;
+        Ok(Self { doc })
+    }
+
+    /// Persist the overlay, creating `conf.d/` if needed.
+    ///
+    /// Written to a temporary file and renamed into place so a crash never
+    /// leaves a half-written overlay behind.
+    pub fn write(&self, config_path: &Path) -> anyhow::Result<()> {
+        let path = Self::path(config_path);
+        let dir = path.parent().expect("overlay path always has a parent");
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        let content = format!(
+            "# Managed by the lm-gateway admin API — edits made here by hand may be overwritten.\n{}",
+            toml::to_string_pretty(&self.doc).context("serializing admin overlay")?
+        );
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, content).with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("replacing {}", path.display()))?;
+        Ok(())
+    }
+
+    /// The overlay as a TOML document, ready for merging.
+    pub(super) fn to_value(&self) -> toml::Value {
+        toml::Value::Table(self.doc.clone())
+    }
+
+    /// Create or fully replace the entry `id` in `section`.
+    ///
+    /// `[[tiers]]` and `[[clients]]` entries replace same-id entries in place,
+    /// preserving ladder order. Profiles and splits are tombstoned first so
+    /// the new definition replaces, rather than merges with, the one beneath
+    /// it.
+    pub fn upsert(&mut self, section: Section, id: &str, entry: toml::Value) {
+        let path = format!("{}.{id}", section.key());
+        if section.is_array() {
+            self.forget_removal(&path);
+            let items = self
+                .doc
+                .entry(section.key())
+                .or_insert_with(|| toml::Value::Array(Vec::new()));
+            if let toml::Value::Array(items) = items {
+                match items.iter_mut().find(|e| entry_id(e) == Some(id)) {
+                    Some(existing) => *existing = entry,
+                    None => items.push(entry),
+                }
+            }
+        } else {
+            if matches!(section, Section::Profiles | Section::Splits) {
+                self.record_removal(&path);
+            }
+            self.section_table(section).insert(id.to_owned(), entry);
+        }
+    }
+
+    /// Delete the entry `id` from `section`, including any definition of it in
+    /// `config.toml`, other `conf.d/` files or the `profiles/` directory.
+    pub fn remove(&mut self, section: Section, id: &str) {
+        match self.doc.get_mut(section.key()) {
+            Some(toml::Value::Array(items)) => items.retain(|e| entry_id(e) != Some(id)),
+            Some(toml::Value::Table(t)) => {
+                t.remove(id);
+            }
+            _ => {}
+        }
+        self.record_removal(&format!("{}.{id}", section.key()));
+    }
+
+    /// Replace the rule list of `profile`, leaving its other fields untouched.
+    pub fn set_rules(&mut self, profile: &str, rules: toml::Value) {
+        let profiles = self.section_table(Section::Profiles);
+        let entry = profiles
+            .entry(profile.to_owned())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(t) = entry {
+            t.insert("rules".into(), rules);
+        }
+    }
+
+    /// Replace the variant list of `split`, leaving `bucket_by` untouched.
+    pub fn set_variants(&mut self, split: &str, variants: toml::Value) {
+        let splits = self.section_table(Section::Splits);
+        let entry = splits
+            .entry(split.to_owned())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(t) = entry {
+            t.insert("variants".into(), variants);
+        }
+    }
+
+    fn section_table(&mut self, section: Section) -> &mut toml::Table {
+        let value = self
+            .doc
+            .entry(section.key())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if !value.is_table() {
+            *value = toml::Value::Table(toml::Table::new());
+        }
+        value.as_table_mut().expect("just ensured table")
+    }
+
+    fn removals_mut(&mut self) -> &mut Vec<toml::Value> {
+        let value = self
+            .doc
+            .entry(REMOVE_KEY)
+            .or_insert_with(|| toml::Value::Array(Vec::new()));
+        if !value.is_array() {
+            *value = toml::Value::Array(Vec::new());
+        }
+        value.as_array_mut().expect("just ensured array")
+    }
+
+    fn record_removal(&mut self, path: &str) {
+        let removals = self.removals_mut();
+        if !removals.iter().any(|v| v.as_str() == Some(path)) {
+            removals.push(toml::Value::String(path.to_owned()));
+        }
+    }
+
+    fn forget_removal(&mut self, path: &str) {
+        if let Some(toml::Value::Array(items)) = self.doc.get_mut(REMOVE_KEY) {
+            items.retain(|v| v.as_str() != Some(path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(name: &str, model: &str) -> toml::Value {
+        toml::from_str(&format!(
+            "name = \"{name}\"\nbackend = \"ollama\"\nmodel = \"{model}\""
+        ))
+        .unwrap()
+    }
+
+    fn removals(overlay: &AdminOverlay) -> Vec<String> {
+        let mut value = overlay.to_value();
+        take_removals(&mut value)
+    }
+
+    #[test]
+    fn upsert_tier_replaces_same_name_in_place() {
+        let mut overlay = AdminOverlay::default();
+        overlay.upsert(Section::Tiers, "local:fast", tier("local:fast", "a"));
+        overlay.upsert(Section::Tiers, "local:deep", tier("local:deep", "b"));
+        overlay.upsert(Section::Tiers, "local:fast", tier("local:fast", "c"));
+
+        let doc = overlay.to_value();
+        let tiers = doc["tiers"].as_array().unwrap();
+        assert_eq!(tiers.len(), 2);
+        assert_eq!(tiers[0]["model"].as_str(), Some("c"));
+    }
+
+    #[test]
+    fn remove_then_upsert_tier_clears_tombstone() {
+        let mut overlay = AdminOverlay::default();
+        overlay.remove(Section::Tiers, "local:fast");
+        assert_eq!(removals(&overlay), vec!["tiers.local:fast"]);
+
+        overlay.upsert(Section::Tiers, "local:fast", tier("local:fast", "a"));
+        assert!(removals(&overlay).is_empty());
+    }
+
+    #[test]
+    fn upsert_profile_records_tombstone_for_full_replacement() {
+        let mut overlay = AdminOverlay::default();
+        overlay.upsert(Section::Profiles, "general", toml::Value::Table(toml::Table::new()));
+        assert_eq!(removals(&overlay), vec!["profiles.general"]);
+    }
+
+    #[test]
+    fn set_variants_leaves_other_split_fields() {
+        let mut overlay = AdminOverlay::default();
+        let split: toml::Value = toml::from_str("bucket_by = \"conversation\"\nvariants = []").unwrap();
+        overlay.upsert(Section::Splits, "local:fast", split);
+        let variants: toml::Value = toml::from_str("v = [{ tier = \"local:fast\", weight = 1 }]").unwrap();
+        overlay.set_variants("local:fast", variants["v"].clone());
+
+        let doc = overlay.to_value();
+        assert_eq!(doc["splits"]["local:fast"]["bucket_by"].as_str(), Some("conversation"));
+        assert_eq!(doc["splits"]["local:fast"]["variants"][0]["weight"].as_integer(), Some(1));
+        assert_eq!(removals(&overlay), vec!["splits.local:fast"], "upsert replaces wholesale");
+    }
+
+    #[test]
+    fn remove_entry_handles_tables_and_named_arrays() {
+        let mut doc: toml::Value = toml::from_str(
+            r#"
+            [aliases]
+            "hint:fast" = "local:fast"
+
+            [[tiers]]
+            name = "local:fast"
+
+            [[clients]]
+            key_env = "ACME_KEY"
+            profile = "default"
+            "#,
+        )
+        .unwrap();
+        remove_entry(&mut doc, "aliases.hint:fast");
+        remove_entry(&mut doc, "tiers.local:fast");
+        remove_entry(&mut doc, "clients.ACME_KEY");
+        remove_entry(&mut doc, "profiles.missing");
+
+        assert!(doc["aliases"].as_table().unwrap().is_empty());
+        assert!(doc["tiers"].as_array().unwrap().is_empty());
+        assert!(doc["clients"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let dir = std::env::temp_dir().join(format!("lmg-overlay-{}", uuid::Uuid::new_v4()));
+        let config_path = dir.join("config.toml");
+        let mut overlay = AdminOverlay::default();
+        overlay.upsert(Section::Aliases, "hint:fast", "local:fast".into());
+        overlay.remove(Section::Profiles, "old");
+        overlay.write(&config_path).unwrap();
+
+        let read = AdminOverlay::read(&config_path).unwrap();
+        assert_eq!(read.to_value(), overlay.to_value());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
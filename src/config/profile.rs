@@ -4,6 +4,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use super::RequestConditions;
+
 /// A routing tier — a named combination of backend + model.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TierConfig {
@@ -19,7 +21,7 @@ pub struct TierConfig {
     /// If set, inject `"think": <value>` into every request forwarded to this tier.
     /// Primarily for Ollama backends: `false` disables chain-of-thought (faster),
     /// `true` enables it (slower but deeper reasoning). Absent = no injection.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
     pub think: Option<bool>,
 
     /// Context window size (in tokens) for this tier's model.
@@ -30,8 +32,83 @@ pub struct TierConfig {
     /// silent truncation at the model level.
     ///
     /// Leave unset to disable context-window gating for this tier.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
     pub max_context_tokens: Option<u32>,
+
+    /// Features the tier's model supports (`vision`, `tools`, `json_mode`,
+    /// `reasoning`), declared as flat keys on the tier.
+    #[serde(flatten)]
+    pub capabilities: TierCapabilities,
+}
+
+/// Optional model features a request may depend on.
+///
+/// Unset means unknown and is treated as supported, like an unset
+/// `max_context_tokens`; mark a tier `false` to keep requests needing the
+/// feature off it. Requests that need a feature are bumped up the ladder to
+/// the lowest tier that doesn't lack it.
+///
+/// ```toml
+/// [[tiers]]
+/// name      = "local:fast"
+/// backend   = "ollama"
+/// model     = "qwen3:1.7b"
+/// vision    = false
+/// json_mode = false
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TierCapabilities {
+    /// Image input (`image_url` / `image` content parts, Ollama `images`).
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub vision: Option<bool>,
+
+    /// Function calling (a non-empty `tools` or `functions` array).
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub tools: Option<bool>,
+
+    /// Structured output (`response_format` of `json_object` / `json_schema`,
+    /// Ollama `format`).
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub json_mode: Option<bool>,
+
+    /// Requested reasoning (`reasoning_effort`, `reasoning`, Anthropic
+    /// `thinking`, Ollama `think = true`).
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "super::lenient::optional")]
+    pub reasoning: Option<bool>,
+}
+
+impl TierCapabilities {
+    /// Whether the tier may serve requests needing `capability`.
+    pub fn supports(&self, capability: Capability) -> bool {
+        let declared = match capability {
+            Capability::Vision => self.vision,
+            Capability::Tools => self.tools,
+            Capability::JsonMode => self.json_mode,
+            Capability::Reasoning => self.reasoning,
+        };
+        declared != Some(false)
+    }
+}
+
+/// A model feature a request can need; see [`TierCapabilities`].
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Vision,
+    Tools,
+    JsonMode,
+    Reasoning,
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Vision => "vision",
+            Self::Tools => "tools",
+            Self::JsonMode => "json_mode",
+            Self::Reasoning => "reasoning",
+        })
+    }
 }
 
 /// A routing rule evaluated against semantic classification tags.
@@ -51,23 +128,176 @@ pub struct TierConfig {
 /// route_to = "local:fast"
 /// priority = 20
 /// ```
+///
+/// A rule may also test the request itself (see [`RequestConditions`]). Rules
+/// with request conditions and no `when` are decided before the classifier is
+/// called — a match skips classification — and also apply in `dispatch` mode.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RuleConfig {
     /// All key=value pairs in this map must match the classifier's tags for the
     /// rule to fire. Matching is case-insensitive.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub when: HashMap<String, String>,
 
+    /// Conditions on the request's content and metadata; all must hold.
+    #[serde(flatten)]
+    pub request: RequestConditions,
+
     /// Tier name (or alias) to route to when this rule matches.
     pub route_to: String,
 
-    /// Evaluation order: higher priority rules are checked first. Default: 0.
+    /// Evaluation order within a pass: higher priority rules are checked
+    /// first. Default: 0.
+    ///
+    /// Rules are checked in two passes. Request rules (see
+    /// [`Self::is_request_rule`]) all run before the classifier is called;
+    /// rules with `when` tags run once it has answered. A matching request
+    /// rule therefore wins over any tag rule, whatever their priorities.
     #[serde(default)]
     pub priority: i32,
 }
 
+impl RuleConfig {
+    /// Decided from the request alone, without classifier tags.
+    pub fn is_request_rule(&self) -> bool {
+        self.when.is_empty() && !self.request.is_empty()
+    }
+}
+
+/// Response cache settings for a profile.
+///
+/// ```toml
+/// [profiles.ha-auto.cache]
+/// ttl_secs    = 300
+/// max_entries = 1000
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct CacheConfig {
+    /// Seconds a cached response stays valid (default: 300).
+    #[serde(default = "super::gateway::defaults::cache_ttl_secs", deserialize_with = "super::lenient::scalar")]
+    pub ttl_secs: u64,
+
+    /// Responses kept for this profile; the least recently used is evicted
+    /// first (default: 1000).
+    #[serde(default = "super::gateway::defaults::cache_max_entries", deserialize_with = "super::lenient::scalar")]
+    pub max_entries: usize,
+
+    /// Also serve paraphrases of cached requests (off when absent).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic: Option<SemanticCacheConfig>,
+}
+
+/// Semantic layer of a profile's response cache.
+///
+/// The last user message is embedded with `embedding_tier` and compared
+/// against earlier requests that resolved to the same tier and class with the
+/// same surrounding conversation, tools and sampling parameters. The closest
+/// one at or above `threshold` cosine similarity is served. Only requests
+/// whose classifier label is listed in `classes` take part, so
+/// state-changing intents never replay a stale answer.
+///
+/// ```toml
+/// [profiles.ha-auto.cache.semantic]
+/// embedding_tier = "local:embed"
+/// threshold      = 0.92
+/// classes        = ["inquiry", "greeting"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SemanticCacheConfig {
+    /// Tier whose backend and model produce the embeddings. The backend must
+    /// serve an OpenAI-compatible `/v1/embeddings` endpoint.
+    pub embedding_tier: String,
+
+    /// Minimum cosine similarity for a hit (default: 0.92).
+    #[serde(default = "super::gateway::defaults::semantic_cache_threshold", deserialize_with = "super::lenient::scalar")]
+    pub threshold: f32,
+
+    /// Classifier labels (`class_label`) eligible for semantic hits.
+    pub classes: Vec<String>,
+}
+
+/// Embedding nearest-neighbour classifier for `classifier_kind = "knn"`.
+///
+/// Each key of `examples` is a classification written the way an LLM
+/// classifier would reply (`"fast"`, `"tier=fast class=inquiry"`), and its
+/// utterances are embedded with `embedding_tier` when the config loads. A
+/// request's last user message is labelled by a similarity-weighted vote of
+/// its `k` nearest examples; when the winning label's share of the vote is
+/// below `min_confidence`, the profile's LLM `classifier` decides instead.
+///
+/// ```toml
+/// [profiles.ha-auto.knn]
+/// embedding_tier = "local:embed"
+/// k              = 5
+/// min_confidence = 0.6
+/// examples_file  = "knn/ha-auto.toml"
+///
+/// [profiles.ha-auto.knn.examples]
+/// "instant class=command" = ["turn off the kitchen lights", "set the thermostat to 20"]
+/// "fast class=inquiry"    = ["is the garage door open?", "what's the weather tomorrow?"]
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct KnnConfig {
+    /// Tier whose backend and model produce the embeddings. The backend must
+    /// serve an OpenAI-compatible `/v1/embeddings` endpoint.
+    pub embedding_tier: String,
+
+    /// Neighbours that vote (default: 5).
+    #[serde(default = "super::gateway::defaults::knn_k", deserialize_with = "super::lenient::scalar")]
+    pub k: usize,
+
+    /// Minimum share of the neighbours' similarity-weighted vote the winning
+    /// label needs, in (0, 1] (default: 0.6).
+    #[serde(default = "super::gateway::defaults::knn_min_confidence", deserialize_with = "super::lenient::scalar")]
+    pub min_confidence: f32,
+
+    /// Example utterances keyed by the classification they stand for.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub examples: HashMap<String, Vec<String>>,
+
+    /// TOML file of further examples in the same `classification = [..]`
+    /// shape, relative to the main config file's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub examples_file: Option<String>,
+
+    /// Examples read from `examples_file` when the config loaded.
+    #[serde(skip)]
+    pub file_examples: HashMap<String, Vec<String>>,
+}
+
+impl KnnConfig {
+    /// Every `(classification, utterance)` pair, inline examples first.
+    pub fn labelled_examples(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.examples
+            .iter()
+            .chain(&self.file_examples)
+            .flat_map(|(label, texts)| texts.iter().map(move |text| (label.as_str(), text.as_str())))
+    }
+}
+
 /// Routing profile — controls routing behaviour for a client.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ProfileConfig {
+    /// Name of a profile to inherit unset fields from.
+    ///
+    /// Scalars set here override the parent; `class_prompts` and
+    /// `thinking_messages` merge per key; `rules` are appended to the parent's,
+    /// replacing any parent rule with the same `when`. Resolved at load time —
+    /// the rest of this struct always holds the effective values.
+    ///
+    /// ```toml
+    /// [profiles."ha-auto:fast"]
+    /// extends       = "ha-auto"
+    /// max_auto_tier = "local:moderate"
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+
+    /// The profile exactly as written in config, before `extends` was
+    /// resolved. Set only for profiles that use `extends`.
+    #[serde(skip)]
+    pub declared: Option<toml::Value>,
+
     /// Routing mode.
     #[serde(default)]
     pub mode: RoutingMode,
@@ -83,7 +313,7 @@ pub struct ProfileConfig {
 
     /// If true, the `cloud:expert` tier (or highest tier) requires an explicit
     /// `"tier": "expert"` field in the request body or a custom header.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::scalar")]
     pub expert_requires_flag: bool,
 
     /// Maximum requests per minute shared across **all** clients that resolve
@@ -92,7 +322,7 @@ pub struct ProfileConfig {
     /// This is a profile-wide quota — not per-client-key. A value of 0 or
     /// absent means no per-profile limit; the global `gateway.rate_limit_rpm`
     /// (per-IP) still applies independently.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub rate_limit_rpm: Option<u32>,
 
     /// System prompt used by `classify` mode to ask the classifier tier for a
@@ -103,23 +333,81 @@ pub struct ProfileConfig {
     #[serde(default)]
     pub classifier_prompt: Option<String>,
 
+    /// Tier (or alias) asked once more when the classifier call fails, times
+    /// out or replies with a label the profile can't map — neither a tier
+    /// name or suffix within `max_auto_tier` nor tags matching a rule.
+    /// Not consulted when the primary classifier succeeds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier_fallback: Option<String>,
+
+    /// Label used when no classifier produces a usable one (default:
+    /// `instant` for failures; unknown labels otherwise resolve to the middle
+    /// tier). When set, it also replaces unknown labels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier_default_label: Option<String>,
+
+    /// How classify mode labels requests: `llm` (default) asks the
+    /// `classifier` tier; `knn` votes among the embedded examples in `knn`
+    /// and asks the `classifier` tier only when the vote is uncertain.
+    #[serde(default, skip_serializing_if = "ClassifierKind::is_llm")]
+    pub classifier_kind: ClassifierKind,
+
+    /// Examples and thresholds for `classifier_kind = "knn"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub knn: Option<KnnConfig>,
+
     /// Override the `think` flag sent to the classifier model.
     ///
     /// When `true`, chain-of-thought reasoning is enabled for the pre-flight
     /// classification call (more accurate but ~2–4 s slower). When `false`
     /// (default), thinking is disabled for the fastest possible pre-flight.
     /// When absent, defaults to `false`.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub classifier_think: Option<bool>,
 
     /// Timeout in milliseconds for the classifier pre-flight call. Default: 10 000 (10 s).
     ///
     /// Classification should be fast (typically 200–600 ms). This timeout prevents
     /// a stuck backend from blocking the entire request for minutes. When the
-    /// timeout fires, the classifier defaults to the first tier (cheapest).
-    #[serde(default = "super::gateway::defaults::classifier_timeout_ms")]
+    /// timeout fires, `classifier_fallback` is tried; failing that, the request
+    /// routes by `classifier_default_label`. Applies to each call separately.
+    #[serde(default = "super::gateway::defaults::classifier_timeout_ms", deserialize_with = "super::lenient::scalar")]
     pub classifier_timeout_ms: u64,
 
+    /// Seconds to reuse the classifier's reply for an identical classifier
+    /// input (same prompt, context window and think flag). Off when absent.
+    ///
+    /// Failed or timed-out classifications are never cached.
+    #[serde(default, deserialize_with = "super::lenient::optional")]
+    pub classifier_cache_secs: Option<u64>,
+
+    /// Seconds a classify-mode decision stays pinned (default: 600; 0 disables).
+    ///
+    /// A request whose latest message is a `role: tool` result reuses the tier,
+    /// class and profile chain chosen for the request that made the tool call,
+    /// so an agent's tool loop neither re-runs the classifier on every hop nor
+    /// flips tiers mid-task. Requests carrying `X-LMG-Conversation-Id` reuse
+    /// the decision made for the first request with that ID. Context-window
+    /// gating still applies to the pinned tier.
+    #[serde(default = "super::gateway::defaults::routing_pin_secs", deserialize_with = "super::lenient::scalar")]
+    pub routing_pin_secs: u64,
+
+    /// Start the main request on a predicted tier while the classifier runs
+    /// (off when absent). Non-streaming requests only.
+    ///
+    /// - `"recent"` — the tier this profile resolved to most often over its
+    ///   last 100 decisions (the classifier tier until there are any)
+    /// - `"classifier"` — the classifier tier
+    /// - any tier name or alias
+    ///
+    /// If classification lands on the predicted tier without changing the
+    /// request (no `class_prompts` entry or think override for the label), the
+    /// speculative response is used; otherwise it is cancelled and the request
+    /// is dispatched to the classified tier. Outcomes are counted in
+    /// `lmg_speculative_dispatch_total`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speculate: Option<String>,
+
     /// Optional system prompt prepended to every request forwarded through this profile.
     ///
     /// When set, this text is injected as a `role = "system"` message at the front of
@@ -179,7 +467,7 @@ pub struct ProfileConfig {
     /// Useful for profiles where classification speed matters more than deep
     /// context awareness (e.g. Home Assistant voice, where each utterance is
     /// mostly self-contained).
-    #[serde(default)]
+    #[serde(default, deserialize_with = "super::lenient::optional")]
     pub classifier_context: Option<u32>,
 
     /// Static response text returned by `reply` mode without calling any backend.
@@ -208,6 +496,23 @@ pub struct ProfileConfig {
     /// ```
     #[serde(default)]
     pub thinking_messages: HashMap<String, Vec<String>>,
+
+    /// Opt-in response cache (off when absent).
+    ///
+    /// Responses are keyed on the resolved tier and model plus a normalised
+    /// hash of the messages, tools and sampling parameters, so only
+    /// byte-for-byte repeats (modulo key order and surrounding whitespace)
+    /// hit. Classification still runs on every request; the cache replaces the
+    /// call to the resolved tier. Clients can skip the lookup with
+    /// `Cache-Control: no-cache`, or skip the cache entirely with `no-store`.
+    /// Classify profiles can add a [`SemanticCacheConfig`] to match paraphrases.
+    ///
+    /// ```toml
+    /// [profiles.ha-auto.cache]
+    /// ttl_secs = 120
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheConfig>,
 }
 
 /// Default classification prompt injected as the system message for `classify` mode.
@@ -283,6 +588,25 @@ pub enum RoutingMode {
     Reply,
 }
 
+/// What labels requests in classify mode.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassifierKind {
+    /// A pre-flight chat call to the profile's `classifier` tier.
+    #[default]
+    Llm,
+
+    /// Nearest-neighbour vote over embedded example utterances
+    /// ([`KnnConfig`]), with the LLM classifier as the fallback.
+    Knn,
+}
+
+impl ClassifierKind {
+    pub fn is_llm(&self) -> bool {
+        *self == Self::Llm
+    }
+}
+
 impl std::fmt::Display for RoutingMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
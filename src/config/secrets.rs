@@ -0,0 +1,265 @@
+//! Cache for secret sources that are too slow to resolve per request.
+//!
+//! `env`, `file` and `systemd` secrets are cheap reads and are resolved on
+//! every call to [`super::SecretSource::resolve`], so rotations take effect on
+//! the next request. `exec` and `http` secrets spawn a process or make a
+//! network call, so they are fetched by [`refresh`] into a process-wide cache
+//! and `resolve` only reads the cache:
+//!
+//! - at startup, before the listeners open
+//! - every few seconds for sources not cached yet (e.g. added by a reload)
+//! - for all sources every `gateway.secret_refresh_secs`
+//!
+//! A failed refresh keeps the previously cached value, so a flapping secret
+//! store degrades to a stale key rather than no key. A source that keeps
+//! failing is retried with exponential backoff rather than every few seconds.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+
+use super::{Config, SecretSource};
+
+/// Upper bound for a single `exec` or `http` fetch.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wait before the first retry of a failed fetch; doubles per failure.
+const RETRY_BASE: Duration = Duration::from_secs(5);
+
+/// Longest wait between retries when `gateway.secret_refresh_secs` is 0.
+const RETRY_MAX: Duration = Duration::from_secs(300);
+
+fn cache() -> &'static RwLock<HashMap<String, String>> {
+    static CACHE: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Consecutive failures of one source and when it may be fetched again.
+struct Backoff {
+    failures: u32,
+    retry_at: Instant,
+}
+
+fn backoffs() -> &'static Mutex<HashMap<String, Backoff>> {
+    static BACKOFFS: OnceLock<Mutex<HashMap<String, Backoff>>> = OnceLock::new();
+    BACKOFFS.get_or_init(Default::default)
+}
+
+/// Cached value for `key`, if any.
+pub(super) fn cached(key: &str) -> Option<String> {
+    cache().read().expect("secret cache poisoned").get(key).cloned()
+}
+
+/// Fetch every cached-kind secret referenced by `config` into the cache.
+///
+/// With `only_missing`, sources that already have a cached value — or that
+/// failed recently and are still backing off — are skipped, so this is cheap
+/// enough to call every few seconds. The backoff starts at 5 s and doubles per
+/// consecutive failure, up to `gateway.secret_refresh_secs`. Failures are
+/// logged per backend and never abort the refresh.
+pub async fn refresh(config: &Config, only_missing: bool) {
+    let max_backoff = match config.gateway.secret_refresh_secs {
+        0 => RETRY_MAX,
+        secs => Duration::from_secs(secs),
+    };
+    for (name, backend) in &config.backends {
+        let Some(source) = &backend.api_key_secret else {
+            continue;
+        };
+        let Some(key) = source.cache_key() else {
+            continue;
+        };
+        if only_missing && (cached(&key).is_some() || backing_off(&key)) {
+            continue;
+        }
+        match fetch(source).await {
+            Ok(value) => {
+                backoffs().lock().expect("secret backoff poisoned").remove(&key);
+                cache().write().expect("secret cache poisoned").insert(key, value);
+                tracing::debug!(backend = %name, source = source.kind(), "secret refreshed");
+            }
+            Err(e) => {
+                let retry_in = record_failure(key, max_backoff);
+                tracing::warn!(
+                    backend = %name,
+                    source = source.kind(),
+                    error = %format!("{e:#}"),
+                    retry_in_secs = retry_in.as_secs(),
+                    "secret refresh failed — keeping previous value"
+                );
+            }
+        }
+    }
+}
+
+/// `true` while `key` is waiting out the backoff from a failed fetch.
+fn backing_off(key: &str) -> bool {
+    backoffs()
+        .lock()
+        .expect("secret backoff poisoned")
+        .get(key)
+        .is_some_and(|b| Instant::now() < b.retry_at)
+}
+
+/// Note a failed fetch of `key` and return how long until it is retried.
+fn record_failure(key: String, max: Duration) -> Duration {
+    let mut backoffs = backoffs().lock().expect("secret backoff poisoned");
+    let backoff = backoffs.entry(key).or_insert(Backoff { failures: 0, retry_at: Instant::now() });
+    let delay = RETRY_BASE.saturating_mul(1 << backoff.failures.min(16)).min(max);
+    backoff.failures += 1;
+    backoff.retry_at = Instant::now() + delay;
+    delay
+}
+
+/// Resolve an `exec` or `http` source from its store.
+async fn fetch(source: &SecretSource) -> anyhow::Result<String> {
+    let value = match source {
+        SecretSource::Exec { command } => {
+            let (program, args) = command.split_first().context("`command` is empty")?;
+            let output = tokio::time::timeout(
+                FETCH_TIMEOUT,
+                tokio::process::Command::new(program)
+                    .args(args)
+                    .kill_on_drop(true)
+                    .output(),
+            )
+            .await
+            .with_context(|| format!("`{program}` timed out"))?
+            .with_context(|| format!("running `{program}`"))?;
+            anyhow::ensure!(
+                output.status.success(),
+                "`{program}` exited with {}",
+                output.status
+            );
+            String::from_utf8(output.stdout)
+                .with_context(|| format!("`{program}` printed non-UTF-8 output"))?
+                .trim_end_matches(['\n', '\r'])
+                .to_owned()
+        }
+        SecretSource::Http { url, field, token_env } => {
+            let token = std::env::var(token_env)
+                .ok()
+                .filter(|t| !t.is_empty())
+                .with_context(|| format!("token variable `{token_env}` is not set"))?;
+            fetch_http(url, field, &token).await?
+        }
+        SecretSource::Env { .. } | SecretSource::File { .. } | SecretSource::Systemd { .. } => {
+            anyhow::bail!("`{}` secrets are not cached", source.kind())
+        }
+    };
+    anyhow::ensure!(!value.is_empty(), "secret is empty");
+    Ok(value)
+}
+
+/// Read `field` from the Vault-compatible KV store at `url`.
+async fn fetch_http(url: &str, field: &str, token: &str) -> anyhow::Result<String> {
+    let body: serde_json::Value = reqwest::Client::new()
+        .get(url)
+        .header("X-Vault-Token", token)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("requesting {url}"))?
+        .error_for_status()?
+        .json()
+        .await
+        .context("parsing secret store response")?;
+    // KV v2 nests the secret under `data.data`; KV v1 under `data`.
+    Ok(body
+        .pointer(&format!("/data/data/{field}"))
+        .or_else(|| body.pointer(&format!("/data/{field}")))
+        .and_then(serde_json::Value::as_str)
+        .with_context(|| format!("field `{field}` not found in response"))?
+        .to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::config::BackendConfig;
+
+    fn config_with(source: SecretSource) -> Config {
+        let mut config: Config = toml::from_str("[gateway]").unwrap();
+        config.backends.insert(
+            "cloud".into(),
+            BackendConfig {
+                base_url: "http://x".into(),
+                api_key_env: None,
+                api_key_secret: Some(source),
+                timeout_ms: 5_000,
+                provider: crate::config::Provider::OpenAI,
+                default_options: None,
+            },
+        );
+        config
+    }
+
+    #[tokio::test]
+    async fn exec_secret_is_cached_after_refresh() {
+        let source = SecretSource::Exec {
+            command: vec!["echo".into(), "sk-exec-test-1".into()],
+        };
+        assert!(source.resolve().is_none(), "not cached before refresh");
+
+        refresh(&config_with(source.clone()), false).await;
+        assert_eq!(source.resolve().as_deref(), Some("sk-exec-test-1"));
+    }
+
+    #[tokio::test]
+    async fn failed_exec_keeps_previous_value() {
+        let ok = SecretSource::Exec {
+            command: vec!["echo".into(), "sk-exec-test-2".into()],
+        };
+        refresh(&config_with(ok.clone()), false).await;
+
+        let failing = SecretSource::Exec {
+            command: vec!["false".into()],
+        };
+        refresh(&config_with(failing.clone()), false).await;
+        assert!(failing.resolve().is_none());
+        assert_eq!(ok.resolve().as_deref(), Some("sk-exec-test-2"));
+    }
+
+    #[tokio::test]
+    async fn failing_secret_backs_off_between_missing_refreshes() {
+        let log = std::env::temp_dir().join(format!("lmg-secret-attempts-{}", uuid::Uuid::new_v4()));
+        let failing = SecretSource::Exec {
+            command: vec!["sh".into(), "-c".into(), format!("echo attempt >> {}; exit 1", log.display())],
+        };
+        let config = config_with(failing);
+        let attempts = || std::fs::read_to_string(&log).unwrap_or_default().lines().count();
+
+        refresh(&config, true).await;
+        refresh(&config, true).await;
+        assert_eq!(attempts(), 1, "a missing-only refresh waits out the backoff");
+
+        refresh(&config, false).await;
+        assert_eq!(attempts(), 2, "a full refresh always retries");
+        std::fs::remove_file(&log).ok();
+    }
+
+    #[tokio::test]
+    async fn http_secret_reads_kv_v2_field_with_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/openai"))
+            .and(header("X-Vault-Token", "vault-test-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "data": { "api_key": "sk-from-vault" }, "metadata": {} }
+            })))
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/v1/secret/data/openai", server.uri());
+        let value = fetch_http(&url, "api_key", "vault-test-token").await.unwrap();
+        assert_eq!(value, "sk-from-vault");
+        assert!(fetch_http(&url, "api_key", "wrong-token").await.is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Weighted traffic splits.
+//!
+//! A split shares the requests routed to one tier out among variant tiers —
+//! a canary model on a slice of `local:fast` traffic, say — so a model
+//! upgrade can be rolled out gradually and compared before switching:
+//!
+//! ```toml
+//! [splits."local:fast"]
+//! bucket_by = "client"
+//! variants  = [
+//!     { tier = "local:fast",      weight = 90 },
+//!     { tier = "local:fast-next", weight = 10 },
+//! ]
+//! ```
+//!
+//! The key is a tier name or an alias of one; the split applies wherever a
+//! request ends up on that tier, in every routing mode. The router assigns
+//! variants (see `router::split`).
+
+use serde::{Deserialize, Serialize};
+
+/// Variant tiers sharing one tier's traffic.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SplitConfig {
+    /// What keeps a caller on the same variant (default: `client`).
+    #[serde(default)]
+    pub bucket_by: BucketBy,
+
+    /// Variants in bucket order. The split's own tier may be one of them,
+    /// as the control.
+    pub variants: Vec<SplitVariant>,
+}
+
+/// One variant tier and its relative share of the split's traffic.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct SplitVariant {
+    /// Tier name (or alias) serving this variant.
+    pub tier: String,
+    /// Relative weight; `0` takes the variant out of rotation.
+    #[serde(deserialize_with = "super::lenient::scalar")]
+    pub weight: u32,
+}
+
+/// Request identity hashed into a split's buckets.
+///
+/// Requests without it fall back to the other identity, then to the first
+/// user message, so one conversation stays on one variant either way.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketBy {
+    /// The authenticated client's `key_env`.
+    #[default]
+    Client,
+    /// The `X-LMG-Conversation-Id` header.
+    Conversation,
+}
+
+impl SplitConfig {
+    /// Sum of all variant weights.
+    pub fn total_weight(&self) -> u64 {
+        self.variants.iter().map(|v| u64::from(v.weight)).sum()
+    }
+
+    /// The variant at `point` (in `[0, 1)`) along the weighted line of the
+    /// variants `eligible` accepts. Each variant owns a slice proportional to
+    /// its weight, in declaration order, so shifting weight between adjacent
+    /// variants only moves the callers near the boundary. `None` when no
+    /// eligible variant has weight.
+    pub fn pick(&self, point: f64, eligible: impl Fn(&SplitVariant) -> bool) -> Option<&SplitVariant> {
+        let variants: Vec<&SplitVariant> = self.variants.iter().filter(|v| v.weight > 0 && eligible(v)).collect();
+        let total: u64 = variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let target = (point.clamp(0.0, 1.0) * total as f64) as u64;
+        let mut upper = 0;
+        for variant in &variants {
+            upper += u64::from(variant.weight);
+            if target < upper {
+                return Some(variant);
+            }
+        }
+        variants.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(weights: &[(&str, u32)]) -> SplitConfig {
+        SplitConfig {
+            bucket_by: BucketBy::Client,
+            variants: weights.iter().map(|&(tier, weight)| SplitVariant { tier: tier.into(), weight }).collect(),
+        }
+    }
+
+    fn tier_at(split: &SplitConfig, point: f64) -> Option<&str> {
+        split.pick(point, |_| true).map(|v| v.tier.as_str())
+    }
+
+    #[test]
+    fn picks_variants_by_weight() {
+        let canary = split(&[("stable", 90), ("next", 10)]);
+        assert_eq!(tier_at(&canary, 0.0), Some("stable"));
+        assert_eq!(tier_at(&canary, 0.89), Some("stable"));
+        assert_eq!(tier_at(&canary, 0.9), Some("next"));
+        assert_eq!(tier_at(&canary, 0.999), Some("next"));
+        assert_eq!(tier_at(&canary, 1.0), Some("next"), "clamped to the last variant");
+
+        // Growing the canary only moves callers into it.
+        let wider = split(&[("stable", 80), ("next", 20)]);
+        assert_eq!(tier_at(&wider, 0.85), Some("next"));
+        assert_eq!(tier_at(&wider, 0.95), Some("next"));
+
+        assert_eq!(tier_at(&split(&[("stable", 0), ("next", 5)]), 0.0), Some("next"), "zero weight is out");
+        assert_eq!(canary.pick(0.95, |v| v.tier != "next").map(|v| v.tier.as_str()), Some("stable"));
+        assert!(canary.pick(0.5, |_| false).is_none());
+    }
+
+    #[test]
+    fn bucket_by_defaults_to_client() {
+        let parsed: SplitConfig = toml::from_str(r#"variants = [{ tier = "a", weight = 1 }]"#).unwrap();
+        assert_eq!(parsed.bucket_by, BucketBy::Client);
+        let parsed: SplitConfig =
+            toml::from_str("bucket_by = \"conversation\"\nvariants = []").unwrap();
+        assert_eq!(parsed.bucket_by, BucketBy::Conversation);
+    }
+}
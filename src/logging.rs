@@ -0,0 +1,150 @@
+//! Gateway log output and runtime log filter control.
+//!
+//! The global subscriber's [`EnvFilter`] sits behind a
+//! [`tracing_subscriber::reload`] layer so it can be swapped without a
+//! restart. [`LogControl`] owns the reload handle and is shared through
+//! [`crate::router::RouterState`]; it is driven by:
+//!
+//! - `gateway.log_level` at startup and whenever a config reload changes it,
+//! - `PUT /admin/log-level` for ad-hoc changes (kept until the next restart
+//!   or a reload that changes `log_level`).
+//!
+//! When `RUST_LOG` is set it wins at startup and `log_level` changes from
+//! config are ignored; the admin endpoint still works.
+
+use std::sync::Mutex;
+
+use anyhow::Context as _;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::GatewayConfig;
+
+/// Filter used when neither `RUST_LOG` nor `gateway.log_level` is set.
+pub const DEFAULT_FILTER: &str = "lm_gateway=info,tower_http=warn";
+
+/// The reloadable filter layer; add it to the registry before any other layer.
+pub type FilterLayer = reload::Layer<EnvFilter, Registry>;
+
+/// Handle for changing the active log filter at runtime.
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directive string of the active filter.
+    current: Mutex<String>,
+    /// `RUST_LOG` was set at startup, so config changes leave the filter alone.
+    env_override: bool,
+}
+
+impl LogControl {
+    /// Build the startup filter layer and its control handle.
+    ///
+    /// Precedence: `RUST_LOG`, then `gateway.log_level`, then
+    /// [`DEFAULT_FILTER`]. An unparseable `RUST_LOG` falls through to config.
+    pub fn init(gateway: &GatewayConfig) -> (FilterLayer, Self) {
+        let env = std::env::var(EnvFilter::DEFAULT_ENV)
+            .ok()
+            .filter(|directives| EnvFilter::try_new(directives).is_ok());
+        let env_override = env.is_some();
+        let directives = env
+            .or_else(|| gateway.log_level.clone())
+            .unwrap_or_else(|| DEFAULT_FILTER.to_string());
+        let filter = EnvFilter::try_new(&directives).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+        let (layer, handle) = reload::Layer::new(filter);
+        let control = Self {
+            handle,
+            current: Mutex::new(directives),
+            env_override,
+        };
+        (layer, control)
+    }
+
+    /// The active filter directives.
+    pub fn current(&self) -> String {
+        self.current.lock().expect("log filter lock poisoned").clone()
+    }
+
+    /// Whether `RUST_LOG` pinned the filter at startup.
+    pub fn env_override(&self) -> bool {
+        self.env_override
+    }
+
+    /// Replace the active filter. Fails without changing anything when
+    /// `directives` doesn't parse.
+    pub fn set(&self, directives: &str) -> anyhow::Result<()> {
+        let filter = EnvFilter::try_new(directives)
+            .with_context(|| format!("invalid log filter `{directives}`"))?;
+        let mut current = self.current.lock().expect("log filter lock poisoned");
+        self.handle.reload(filter).context("log subscriber is gone")?;
+        *current = directives.to_string();
+        Ok(())
+    }
+
+    /// Apply a hot-reloaded `log_level` when it differs from the previous one.
+    ///
+    /// Leaves runtime overrides in place while the config value is unchanged,
+    /// and does nothing under `RUST_LOG`.
+    pub fn apply_config(&self, old: &GatewayConfig, new: &GatewayConfig) {
+        if self.env_override || old.log_level == new.log_level {
+            return;
+        }
+        let directives = new.log_level.as_deref().unwrap_or(DEFAULT_FILTER);
+        match self.set(directives) {
+            Ok(()) => tracing::info!(filter = directives, "log level changed by config reload"),
+            Err(e) => tracing::warn!(error = %e, "could not apply log_level from reloaded config"),
+        }
+    }
+
+    /// A control whose reload target is a live but non-global subscriber;
+    /// keep the subscriber alive for as long as the control is used.
+    #[cfg(test)]
+    pub(crate) fn detached(directives: &str) -> (Self, impl tracing::Subscriber + Send + Sync) {
+        use tracing_subscriber::layer::SubscriberExt as _;
+
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(directives));
+        let control = Self {
+            handle,
+            current: Mutex::new(directives.to_string()),
+            env_override: false,
+        };
+        (control, tracing_subscriber::registry().with(layer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gateway(log_level: Option<&str>) -> GatewayConfig {
+        GatewayConfig { log_level: log_level.map(String::from), ..Default::default() }
+    }
+
+    #[test]
+    fn set_accepts_per_module_directives() {
+        let (control, _subscriber) = LogControl::detached(DEFAULT_FILTER);
+        control.set("lm_gateway::router=trace,tower_http=info").unwrap();
+        assert_eq!(control.current(), "lm_gateway::router=trace,tower_http=info");
+    }
+
+    #[test]
+    fn set_rejects_invalid_filter_and_keeps_current() {
+        let (control, _subscriber) = LogControl::detached(DEFAULT_FILTER);
+        assert!(control.set("lm_gateway=loud").is_err());
+        assert_eq!(control.current(), DEFAULT_FILTER);
+    }
+
+    #[test]
+    fn config_reload_applies_only_changed_log_level() {
+        let (control, _subscriber) = LogControl::detached(DEFAULT_FILTER);
+        control.set("lm_gateway=trace").unwrap();
+
+        // Unchanged log_level keeps the runtime override.
+        control.apply_config(&gateway(None), &gateway(None));
+        assert_eq!(control.current(), "lm_gateway=trace");
+
+        control.apply_config(&gateway(None), &gateway(Some("debug")));
+        assert_eq!(control.current(), "debug");
+
+        // Removing log_level falls back to the default.
+        control.apply_config(&gateway(Some("debug")), &gateway(None));
+        assert_eq!(control.current(), DEFAULT_FILTER);
+    }
+}
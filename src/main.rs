@@ -3,13 +3,21 @@ use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use anyhow::Context;
 use tokio::signal;
 use tracing::{info, warn};
+use tracing_subscriber::{layer::SubscriberExt as _, util::SubscriberInitExt as _};
 
+mod analytics;
 mod api;
 mod backends;
+mod cache;
 mod config;
 mod error;
+mod logging;
+mod metrics;
 mod router;
+mod telemetry;
 mod traffic;
+mod traffic_archive;
+mod traffic_webhook;
 
 pub use config::Config;
 pub use error::AppError;
@@ -23,21 +31,47 @@ async fn main() -> anyhow::Result<()> {
         return healthcheck().await;
     }
 
-    // Initialise tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "lm_gateway=info,tower_http=warn".into()),
-        )
-        .init();
-
     // Load config
     let config_path = std::env::var("LMG_CONFIG")
         .map(PathBuf::from)
         .unwrap_or_else(|_| PathBuf::from("/etc/lm-gateway/config.toml"));
 
-    let config = Config::load(&config_path)
-        .with_context(|| format!("Failed to load config from {}", config_path.display()))?;
+    // The global subscriber depends on config (OTLP export), so load it under a
+    // scoped fmt subscriber to keep any load-time warnings visible.
+    let config = tracing::subscriber::with_default(
+        tracing_subscriber::fmt()
+            .with_env_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| logging::DEFAULT_FILTER.into()),
+            )
+            .finish(),
+        || Config::load(&config_path),
+    )
+    .with_context(|| format!("Failed to load config from {}", config_path.display()))?;
+
+    // Initialise tracing behind a reloadable filter, exporting spans over
+    // OTLP when configured
+    let (filter_layer, log_control) = logging::LogControl::init(&config.gateway);
+    let (otel_layer, telemetry) = match telemetry::init(&config.gateway)? {
+        Some((layer, handle)) => (Some(layer), Some(handle)),
+        None => (None, None),
+    };
+    let json = config.gateway.log_format == config::LogFormat::Json;
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .with(json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with(otel_layer)
+        .init();
+    info!(
+        filter = %log_control.current(),
+        format = ?config.gateway.log_format,
+        from_env = log_control.env_override(),
+        "logging initialised"
+    );
+    if let Some(endpoint) = &config.gateway.otlp_endpoint {
+        info!(%endpoint, "OpenTelemetry export enabled");
+    }
 
     info!(
         client_port = config.gateway.client_port,
@@ -45,19 +79,45 @@ async fn main() -> anyhow::Result<()> {
         "lm-gateway starting"
     );
 
-    let traffic_log = Arc::new(TrafficLog::new(config.gateway.traffic_log_capacity));
+    // Fetch exec/http API key secrets before the first request can need them.
+    config::secrets::refresh(&config, false).await;
+
+    let mut traffic_log = TrafficLog::new(config.gateway.traffic_log_capacity);
+    let base_dir = config_path.parent().unwrap_or(std::path::Path::new("."));
+    if let Some(settings) = traffic_archive::ArchiveSettings::from_gateway(&config.gateway, base_dir) {
+        let recovered = traffic_archive::rehydrate(&settings.path, config.gateway.traffic_log_capacity);
+        info!(path = %settings.path.display(), recovered = recovered.len(), "traffic archive enabled");
+        traffic_log.rehydrate(recovered);
+        traffic_log = traffic_log.with_archive(traffic_archive::TrafficArchive::start(settings)?);
+    }
+    if let Some(settings) = traffic_webhook::WebhookSettings::from_gateway(&config.gateway) {
+        info!(url = %settings.url, signed = settings.secret.is_some(), "traffic webhook enabled");
+        traffic_log = traffic_log.with_webhook(traffic_webhook::TrafficWebhook::start(settings));
+    }
+    let traffic_log = Arc::new(traffic_log);
     let config = Arc::new(config);
 
     // Build router state
-    let state = Arc::new(router::RouterState::new(
-        Arc::clone(&config),
-        config_path.clone(),
-        Arc::clone(&traffic_log),
-    ));
+    let state = Arc::new(
+        router::RouterState::new(Arc::clone(&config), config_path.clone(), Arc::clone(&traffic_log))
+            .with_log_control(log_control),
+    );
+
+    // Embed kNN classifier examples in the background
+    tokio::spawn({
+        let state = Arc::clone(&state);
+        async move { state.warm_knn_classifiers().await }
+    });
 
     // Spawn hot-reload watcher — polls the config file every 5 seconds
     tokio::spawn(config_watcher(Arc::clone(&state)));
 
+    // Spawn secret refresher — keeps exec/http API key secrets current
+    tokio::spawn(secret_refresher(Arc::clone(&state)));
+
+    // Spawn gate sampler — records queue depth for the dashboard charts
+    tokio::spawn(gate_sampler(Arc::clone(&state)));
+
     // Bind client API (agent-facing)
     let client_addr: SocketAddr = format!("0.0.0.0:{}", config.gateway.client_port).parse()?;
 
@@ -73,7 +133,7 @@ async fn main() -> anyhow::Result<()> {
     // Attach request tracing middleware to both servers
     let trace_layer = || {
         tower_http::trace::TraceLayer::new_for_http()
-            .make_span_with(tower_http::trace::DefaultMakeSpan::new().level(tracing::Level::INFO))
+            .make_span_with(telemetry::make_request_span)
             .on_response(tower_http::trace::DefaultOnResponse::new().level(tracing::Level::INFO))
     };
 
@@ -104,6 +164,11 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    // Flush buffered spans and metrics; the exporter blocks on HTTP.
+    if let Some(telemetry) = telemetry {
+        tokio::task::spawn_blocking(move || telemetry.shutdown()).await.ok();
+    }
+
     Ok(())
 }
 
@@ -170,15 +235,52 @@ async fn config_watcher(state: Arc<router::RouterState>) {
             continue;
         }
 
+        // Remember the mtime either way: a broken file is reported once, not
+        // on every tick until it is fixed.
+        last_mtime = mtime;
+
         match Config::load(path) {
             Ok(new_cfg) => {
-                state.replace_config(Arc::new(new_cfg));
+                state.replace_config(Arc::new(new_cfg), config::ConfigSource::FileWatcher);
                 info!(path = %path.display(), "config hot-reloaded");
-                last_mtime = mtime;
+                state.warm_knn_classifiers().await;
             }
             Err(e) => {
+                state.record_reload_failure(config::ConfigSource::FileWatcher);
                 warn!(path = %path.display(), error = %e, "config reload failed — keeping previous config");
             }
         }
     }
 }
+
+/// Background task: samples priority gate depth for `/admin/analytics/gates`.
+async fn gate_sampler(state: Arc<router::RouterState>) {
+    let mut interval = tokio::time::interval(analytics::GATE_SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        state.sample_gates().await;
+    }
+}
+
+/// Background task: keeps `exec` and `http` API key secrets fresh.
+///
+/// Every 5 seconds, fetches secrets not cached yet (e.g. backends added by a
+/// reload). Every `gateway.secret_refresh_secs` (read from the live config),
+/// re-fetches all of them so rotated keys take effect without a restart.
+async fn secret_refresher(state: Arc<router::RouterState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    interval.tick().await;
+    let mut last_full = tokio::time::Instant::now();
+
+    loop {
+        interval.tick().await;
+
+        let config = state.config();
+        let every = config.gateway.secret_refresh_secs;
+        let full = every > 0 && last_full.elapsed() >= Duration::from_secs(every);
+        config::secrets::refresh(&config, !full).await;
+        if full {
+            last_full = tokio::time::Instant::now();
+        }
+    }
+}
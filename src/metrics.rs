@@ -0,0 +1,345 @@
+//! Lifetime Prometheus counters and histograms.
+//!
+//! Unlike the ring-buffer window gauges in [`crate::api::metrics`], these are
+//! updated on the request path and only ever increase (until the process
+//! restarts), so `rate()`, `increase()` and `histogram_quantile()` work as
+//! expected. One [`Metrics`] registry lives in
+//! [`crate::router::RouterState`]; `GET /metrics` renders it after the window
+//! gauges.
+//!
+//! | Family | Type | Labels |
+//! |---|---|---|
+//! | `lmg_requests_total` | counter | `profile`, `tier`, `backend`, `outcome` |
+//! | `lmg_request_duration_seconds` | histogram | `profile`, `tier` |
+//! | `lmg_time_to_first_token_seconds` | histogram | `tier` (streaming only) |
+//! | `lmg_queue_wait_seconds` | histogram | `tier` |
+//! | `lmg_tokens_total` | counter | `tier`, `direction` (`input` / `output`) |
+//! | `lmg_classifier_duration_seconds` | histogram | `profile` |
+//! | `lmg_classifier_labels_total` | counter | `profile`, `label` |
+//! | `lmg_rate_limited_total` | counter | `scope` (`ip` / `profile`) |
+//! | `lmg_response_cache_total` | counter | `profile`, `outcome` (`hit` / `semantic` / `miss` / `bypass`) |
+//! | `lmg_speculative_dispatch_total` | counter | `profile`, `outcome` (`kept` / `discarded` / `cancelled`) |
+//! | `lmg_knn_classifications_total` | counter | `profile`, `outcome` (`confident` / `uncertain` / `error`) |
+//! | `lmg_split_requests_total` | counter | `split`, `variant` |
+//! | `lmg_config_reloads_total` | counter | `source`, `outcome` |
+//!
+//! Gate depth (`lmg_gate_in_flight`, `lmg_gate_queued`) is a point-in-time
+//! gauge read from the priority gates at scrape time.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::Mutex,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+/// Latency buckets in seconds — from a cached classifier hit to a slow cloud
+/// completion.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+/// A counter family keyed by label values.
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl CounterVec {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self { name, help, labels, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Add `n` to the series with `label_values` (in declaration order).
+    pub fn inc_by(&self, label_values: &[&str], n: u64) {
+        debug_assert_eq!(label_values.len(), self.labels.len(), "{}: label arity", self.name);
+        let key = label_values.iter().map(|v| (*v).to_owned()).collect();
+        *self.values.lock().expect("metrics lock poisoned").entry(key).or_default() += n;
+    }
+
+    pub fn inc(&self, label_values: &[&str]) {
+        self.inc_by(label_values, 1);
+    }
+
+    /// Current value of one series (0 when never incremented).
+    #[allow(dead_code)] // used in tests
+    pub fn get(&self, label_values: &[&str]) -> u64 {
+        let key: Vec<String> = label_values.iter().map(|v| (*v).to_owned()).collect();
+        self.values.lock().expect("metrics lock poisoned").get(&key).copied().unwrap_or(0)
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} counter", self.name);
+        for (values, count) in self.values.lock().expect("metrics lock poisoned").iter() {
+            let _ = writeln!(out, "{}{} {count}", self.name, label_set(self.labels, values, None));
+        }
+        out.push('\n');
+    }
+}
+
+#[derive(Default, Clone)]
+struct HistogramData {
+    /// Per-bucket (non-cumulative) observation counts.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// A histogram family keyed by label values.
+pub struct HistogramVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    bounds: &'static [f64],
+    values: Mutex<BTreeMap<Vec<String>, HistogramData>>,
+}
+
+impl HistogramVec {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self { name, help, labels, bounds: LATENCY_BUCKETS, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn observe(&self, label_values: &[&str], value: Duration) {
+        debug_assert_eq!(label_values.len(), self.labels.len(), "{}: label arity", self.name);
+        let secs = value.as_secs_f64();
+        let key = label_values.iter().map(|v| (*v).to_owned()).collect();
+        let mut values = self.values.lock().expect("metrics lock poisoned");
+        let data = values.entry(key).or_insert_with(|| HistogramData {
+            buckets: vec![0; self.bounds.len()],
+            ..Default::default()
+        });
+        if let Some(i) = self.bounds.iter().position(|&b| secs <= b) {
+            data.buckets[i] += 1;
+        }
+        data.sum += secs;
+        data.count += 1;
+    }
+
+    /// Observation count of one series (0 when never observed).
+    #[allow(dead_code)] // used in tests
+    pub fn count(&self, label_values: &[&str]) -> u64 {
+        let key: Vec<String> = label_values.iter().map(|v| (*v).to_owned()).collect();
+        self.values.lock().expect("metrics lock poisoned").get(&key).map_or(0, |d| d.count)
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} histogram", self.name);
+        for (values, data) in self.values.lock().expect("metrics lock poisoned").iter() {
+            let mut cumulative = 0;
+            for (bound, n) in self.bounds.iter().zip(&data.buckets) {
+                cumulative += n;
+                let le = bound.to_string();
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {cumulative}",
+                    self.name,
+                    label_set(self.labels, values, Some(&le))
+                );
+            }
+            let labels = label_set(self.labels, values, None);
+            let _ = writeln!(out, "{}_bucket{} {}", self.name, label_set(self.labels, values, Some("+Inf")), data.count);
+            let _ = writeln!(out, "{}_sum{labels} {}", self.name, data.sum);
+            let _ = writeln!(out, "{}_count{labels} {}", self.name, data.count);
+        }
+        out.push('\n');
+    }
+}
+
+/// `{a="x",b="y"}` (plus `le` for histogram buckets), or `""` with no labels.
+fn label_set(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values)
+        .map(|(n, v)| format!("{n}=\"{}\"", escape(v)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{le}\""));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+/// Escape a label value per the Prometheus text format.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Process-lifetime metric registry.
+pub struct Metrics {
+    pub requests: CounterVec,
+    pub request_duration: HistogramVec,
+    pub time_to_first_token: HistogramVec,
+    pub queue_wait: HistogramVec,
+    pub tokens: CounterVec,
+    pub classifier_duration: HistogramVec,
+    pub classifier_labels: CounterVec,
+    pub rate_limited: CounterVec,
+    pub response_cache: CounterVec,
+    pub speculative_dispatch: CounterVec,
+    pub knn_classifications: CounterVec,
+    pub split_requests: CounterVec,
+    pub config_reloads: CounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            requests: CounterVec::new(
+                "lmg_requests_total",
+                "Routed chat requests by profile, final tier, backend and outcome.",
+                &["profile", "tier", "backend", "outcome"],
+            ),
+            request_duration: HistogramVec::new(
+                "lmg_request_duration_seconds",
+                "End-to-end routing duration, including classification and queueing; for streams, until the last chunk.",
+                &["profile", "tier"],
+            ),
+            time_to_first_token: HistogramVec::new(
+                "lmg_time_to_first_token_seconds",
+                "Time from request start to the first streamed chunk from the backend.",
+                &["tier"],
+            ),
+            queue_wait: HistogramVec::new(
+                "lmg_queue_wait_seconds",
+                "Time spent waiting for a tier's priority gate.",
+                &["tier"],
+            ),
+            tokens: CounterVec::new(
+                "lmg_tokens_total",
+                "Tokens reported by backends, including classifier calls.",
+                &["tier", "direction"],
+            ),
+            classifier_duration: HistogramVec::new(
+                "lmg_classifier_duration_seconds",
+                "Classifier pre-flight call duration.",
+                &["profile"],
+            ),
+            classifier_labels: CounterVec::new(
+                "lmg_classifier_labels_total",
+                "Classification results by profile and class label.",
+                &["profile", "label"],
+            ),
+            rate_limited: CounterVec::new(
+                "lmg_rate_limited_total",
+                "Requests rejected with 429 by a rate limiter.",
+                &["scope"],
+            ),
+            response_cache: CounterVec::new(
+                "lmg_response_cache_total",
+                "Requests on cache-enabled profiles by cache outcome.",
+                &["profile", "outcome"],
+            ),
+            speculative_dispatch: CounterVec::new(
+                "lmg_speculative_dispatch_total",
+                "Speculative classify-mode requests by whether their result was used.",
+                &["profile", "outcome"],
+            ),
+            knn_classifications: CounterVec::new(
+                "lmg_knn_classifications_total",
+                "kNN classifier votes by whether they were used or deferred to the LLM classifier.",
+                &["profile", "outcome"],
+            ),
+            split_requests: CounterVec::new(
+                "lmg_split_requests_total",
+                "Requests assigned to each variant tier of a traffic split.",
+                &["split", "variant"],
+            ),
+            config_reloads: CounterVec::new(
+                "lmg_config_reloads_total",
+                "Config reload attempts by source and outcome.",
+                &["source", "outcome"],
+            ),
+        }
+    }
+}
+
+impl Metrics {
+    /// Count input/output tokens from an OpenAI- or Ollama-format response.
+    pub fn record_tokens(&self, tier: &str, response: &Value) {
+        let (input, output) = crate::telemetry::usage_tokens(response);
+        self.add_tokens(tier, input, output);
+    }
+
+    /// Count input/output tokens reported for `tier`.
+    pub fn add_tokens(&self, tier: &str, input: Option<u64>, output: Option<u64>) {
+        if let Some(n) = input {
+            self.tokens.inc_by(&[tier, "input"], n);
+        }
+        if let Some(n) = output {
+            self.tokens.inc_by(&[tier, "output"], n);
+        }
+    }
+
+    /// Render every family in Prometheus text format.
+    pub fn render(&self, out: &mut String) {
+        self.requests.render(out);
+        self.request_duration.render(out);
+        self.time_to_first_token.render(out);
+        self.queue_wait.render(out);
+        self.tokens.render(out);
+        self.classifier_duration.render(out);
+        self.classifier_labels.render(out);
+        self.rate_limited.render(out);
+        self.response_cache.render(out);
+        self.speculative_dispatch.render(out);
+        self.knn_classifications.render(out);
+        self.split_requests.render(out);
+        self.config_reloads.render(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_render_sorted_series_with_escaped_labels() {
+        let metrics = Metrics::default();
+        metrics.config_reloads.inc(&["file_watcher", "success"]);
+        metrics.config_reloads.inc(&["admin_reload", "failure"]);
+        metrics.classifier_labels.inc(&["default", "say \"hi\""]);
+
+        let mut out = String::new();
+        metrics.render(&mut out);
+        let reload_admin = out.find("lmg_config_reloads_total{source=\"admin_reload\",outcome=\"failure\"} 1").unwrap();
+        let reload_file = out.find("lmg_config_reloads_total{source=\"file_watcher\",outcome=\"success\"} 1").unwrap();
+        assert!(reload_admin < reload_file);
+        assert!(out.contains(r#"label="say \"hi\"""#));
+        assert!(out.contains("# TYPE lmg_requests_total counter"));
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        metrics.queue_wait.observe(&["local:fast"], Duration::from_millis(3));
+        metrics.queue_wait.observe(&["local:fast"], Duration::from_millis(200));
+        metrics.queue_wait.observe(&["local:fast"], Duration::from_secs(500));
+
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(out.contains("lmg_queue_wait_seconds_bucket{tier=\"local:fast\",le=\"0.005\"} 1"));
+        assert!(out.contains("lmg_queue_wait_seconds_bucket{tier=\"local:fast\",le=\"0.25\"} 2"));
+        assert!(out.contains("lmg_queue_wait_seconds_bucket{tier=\"local:fast\",le=\"120\"} 2"));
+        assert!(out.contains("lmg_queue_wait_seconds_bucket{tier=\"local:fast\",le=\"+Inf\"} 3"));
+        assert!(out.contains("lmg_queue_wait_seconds_count{tier=\"local:fast\"} 3"));
+    }
+
+    #[test]
+    fn tokens_are_counted_from_usage() {
+        let metrics = Metrics::default();
+        let response = serde_json::json!({ "usage": { "prompt_tokens": 10, "completion_tokens": 4 } });
+        metrics.record_tokens("cloud:fast", &response);
+        metrics.record_tokens("cloud:fast", &response);
+        assert_eq!(metrics.tokens.get(&["cloud:fast", "input"]), 20);
+        assert_eq!(metrics.tokens.get(&["cloud:fast", "output"]), 8);
+    }
+}
@@ -0,0 +1,76 @@
+//! Response-cache lookups for a routed request, and the embedding calls
+//! behind the semantic layer.
+
+use anyhow::Context;
+use serde_json::Value;
+use tracing::{debug, warn, Instrument as _};
+
+use crate::{
+    backends::BackendClient,
+    cache::{CacheFill, CacheHit, CacheKey, CacheLookup, CacheScope, SemanticQuery},
+    config::TierConfig,
+    telemetry::{self, Operation},
+    traffic::TrafficEntry,
+};
+
+use super::RouterState;
+
+/// Look `body`, as it will be sent to `tier`, up in the profile cache: an
+/// exact match first, then — for classes with a semantic cache — the closest
+/// earlier request by embedding. An embedding failure counts as a miss.
+pub(super) async fn cache_lookup(
+    state: &RouterState,
+    scope: &CacheScope,
+    tier: &TierConfig,
+    body: &Value,
+) -> CacheLookup {
+    let key = CacheKey::new(&tier.name, body);
+    if let Some(response) = scope.lookup(&key) {
+        return CacheLookup::Hit(CacheHit { response, similarity: None });
+    }
+    let fill = CacheFill::new(scope.clone(), key);
+    let Some(query) = scope.semantic_query(&tier.name, body) else {
+        return CacheLookup::Miss(fill);
+    };
+    match embed(state, &query).await {
+        Ok(vector) => match scope.lookup_similar(&query, &vector) {
+            Some(hit) => CacheLookup::Hit(hit),
+            None => CacheLookup::Miss(fill.with_embedding(query, vector)),
+        },
+        Err(e) => {
+            warn!(tier = %query.embedding_tier, error = %e, "semantic cache embedding failed — treating as a miss");
+            CacheLookup::Miss(fill)
+        }
+    }
+}
+
+/// Embed a semantic-cache query with its embedding tier.
+async fn embed(state: &RouterState, query: &SemanticQuery) -> anyhow::Result<Vec<f32>> {
+    embed_text(state, &query.embedding_tier, &query.text).await
+}
+
+/// Embed `text` with the tier (or alias) `embedding_tier`, recording the call.
+pub(super) async fn embed_text(state: &RouterState, embedding_tier: &str, text: &str) -> anyhow::Result<Vec<f32>> {
+    let config = state.config();
+    let tier = config
+        .resolve_tier(embedding_tier)
+        .with_context(|| format!("embedding tier `{embedding_tier}` not in config"))?;
+    let backend_cfg = config
+        .backends
+        .get(&tier.backend)
+        .with_context(|| format!("backend `{}` not in config", tier.backend))?;
+    let client = BackendClient::new(backend_cfg)?;
+    let span = telemetry::backend_span(Operation::Embeddings, tier, backend_cfg);
+    let t0 = std::time::Instant::now();
+    let result = client.embeddings(&tier.model, text).instrument(span.clone()).await;
+    let response = result.as_ref().map(|_| None);
+    state.record_backend_call(&span, Operation::Embeddings, tier, backend_cfg, t0.elapsed(), response);
+    result
+}
+
+/// A cache hit with a zero-latency entry for `tier`.
+pub(super) fn cached_entry(hit: CacheHit, tier: &TierConfig) -> (Value, TrafficEntry) {
+    debug!(tier = %tier.name, similarity = ?hit.similarity, "response cache hit");
+    let entry = TrafficEntry::new(tier.name.clone(), tier.backend.clone(), 0, true).with_cache_hit(&hit);
+    (hit.response, entry)
+}
@@ -0,0 +1,198 @@
+//! What a request needs from the tier serving it — room for its estimated
+//! prompt and the model features ([`Capability`]) its body asks for — and the
+//! floor every routing mode applies: a tier that falls short is bumped up to
+//! the lowest tier that doesn't.
+
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    config::{Capability, TierConfig},
+    traffic::RoutingDetail,
+};
+
+/// A request's estimated size and required capabilities.
+pub(crate) struct TierNeeds {
+    pub estimated_tokens: u32,
+    pub capabilities: Vec<Capability>,
+}
+
+impl TierNeeds {
+    pub fn of(body: &Value) -> Self {
+        Self { estimated_tokens: super::estimate_request_tokens(body), capabilities: required_capabilities(body) }
+    }
+
+    /// Required capabilities `tier` is declared to lack.
+    pub fn missing(&self, tier: &TierConfig) -> Vec<Capability> {
+        self.capabilities.iter().copied().filter(|&c| !tier.capabilities.supports(c)).collect()
+    }
+
+    /// Whether `tier` fits the request and has every required capability.
+    pub fn met_by(&self, tier: &TierConfig) -> bool {
+        tier.max_context_tokens.is_none_or(|max| self.estimated_tokens <= max) && self.missing(tier).is_empty()
+    }
+
+    /// Whether any of `candidates` has every required capability.
+    pub fn capable_tier_exists(&self, candidates: &[TierConfig]) -> bool {
+        candidates.iter().any(|tier| self.missing(tier).is_empty())
+    }
+
+    /// Index of the lowest tier from `start_idx` up that fits the request and
+    /// has every required capability. When no tier has them all, only the
+    /// context window counts — better to try than to reject.
+    pub fn floor(&self, candidates: &[TierConfig], start_idx: usize) -> usize {
+        if let Some(idx) = candidates.iter().skip(start_idx).position(|tier| self.met_by(tier)) {
+            return start_idx + idx;
+        }
+        if !self.capabilities.is_empty() {
+            debug!(
+                capabilities = ?self.capabilities,
+                "no tier in range has every capability the request needs — gating on context window only"
+            );
+        }
+        super::find_min_tier_for_tokens(candidates, self.estimated_tokens, start_idx)
+    }
+
+    /// `candidates[tier_idx]`, or the [floor](Self::floor) above it when it
+    /// falls short — recording the bump on `detail`.
+    pub fn gate<'c>(&self, candidates: &'c [TierConfig], tier_idx: usize, detail: &mut RoutingDetail) -> &'c TierConfig {
+        let min_idx = self.floor(candidates, tier_idx);
+        let (from, to) = (&candidates[tier_idx], &candidates[min_idx]);
+        if min_idx > tier_idx {
+            let missing = self.missing(from);
+            debug!(
+                estimated_tokens = self.estimated_tokens,
+                missing = ?missing,
+                from = %from.name,
+                to = %to.name,
+                "tier floor — bumping tier"
+            );
+            detail.bump(&from.name, &to.name, self.estimated_tokens, missing);
+            return to;
+        }
+        from
+    }
+}
+
+/// Capabilities the request body asks for, in [`Capability`] order.
+pub(crate) fn required_capabilities(body: &Value) -> Vec<Capability> {
+    [
+        (Capability::Vision, has_images(body)),
+        (Capability::Tools, has_tools(body)),
+        (Capability::JsonMode, wants_json(body)),
+        (Capability::Reasoning, wants_reasoning(body)),
+    ]
+    .into_iter()
+    .filter_map(|(capability, needed)| needed.then_some(capability))
+    .collect()
+}
+
+/// OpenAI `image_url` / `input_image` and Anthropic `image` content parts —
+/// the non-text parts [`super::extract_message_text`] skips — or Ollama's
+/// per-message `images` array.
+pub(crate) fn has_images(body: &Value) -> bool {
+    let messages = body.get("messages").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+    messages.iter().any(|message| {
+        let parts = message.get("content").and_then(Value::as_array).into_iter().flatten();
+        let ollama = message.get("images").and_then(Value::as_array).is_some_and(|images| !images.is_empty());
+        ollama
+            || parts.into_iter().any(|part| {
+                matches!(part.get("type").and_then(Value::as_str), Some("image_url" | "input_image" | "image"))
+            })
+    })
+}
+
+/// A non-empty `tools` array, or legacy `functions`.
+pub(crate) fn has_tools(body: &Value) -> bool {
+    ["tools", "functions"]
+        .iter()
+        .any(|key| body.get(key).and_then(Value::as_array).is_some_and(|defs| !defs.is_empty()))
+}
+
+/// OpenAI `response_format` of `json_object` / `json_schema`, or any Ollama
+/// `format` (`"json"` or a schema).
+fn wants_json(body: &Value) -> bool {
+    let response_format = body.pointer("/response_format/type").and_then(Value::as_str);
+    matches!(response_format, Some("json_object" | "json_schema"))
+        || body.get("format").is_some_and(|format| !format.is_null() && format != "")
+}
+
+/// OpenAI `reasoning_effort` / `reasoning`, Anthropic `thinking`, or Ollama
+/// `think = true`.
+fn wants_reasoning(body: &Value) -> bool {
+    let set = |key: &str| body.get(key).is_some_and(|v| !v.is_null());
+    set("reasoning_effort")
+        || set("reasoning")
+        || body.pointer("/thinking/type").and_then(Value::as_str) == Some("enabled")
+        || body.get("think").and_then(Value::as_bool) == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::TierCapabilities;
+
+    fn tier(name: &str, capabilities: TierCapabilities) -> TierConfig {
+        TierConfig {
+            name: name.into(),
+            backend: "b".into(),
+            model: "m".into(),
+            think: None,
+            max_context_tokens: None,
+            capabilities,
+        }
+    }
+
+    #[test]
+    fn detects_required_capabilities() {
+        assert!(required_capabilities(&json!({ "messages": [{ "role": "user", "content": "hi" }] })).is_empty());
+        let everything = json!({
+            "messages": [{ "role": "user", "content": [
+                { "type": "text", "text": "what is this?" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,AAAA" } }
+            ] }],
+            "tools": [{ "type": "function", "function": { "name": "search" } }],
+            "response_format": { "type": "json_object" },
+            "reasoning_effort": "high"
+        });
+        assert_eq!(
+            required_capabilities(&everything),
+            [Capability::Vision, Capability::Tools, Capability::JsonMode, Capability::Reasoning]
+        );
+        let ollama = json!({
+            "messages": [{ "role": "user", "content": "describe", "images": ["AAAA"] }],
+            "format": "json",
+            "think": true
+        });
+        assert_eq!(required_capabilities(&ollama), [Capability::Vision, Capability::JsonMode, Capability::Reasoning]);
+        let plain_text_format = json!({ "messages": [], "response_format": { "type": "text" }, "think": false });
+        assert!(required_capabilities(&plain_text_format).is_empty());
+    }
+
+    #[test]
+    fn floor_skips_tiers_lacking_a_capability() {
+        let text_only = TierCapabilities { vision: Some(false), ..Default::default() };
+        let tiers = [
+            tier("small", text_only.clone()),
+            tier("vision", TierCapabilities { vision: Some(true), tools: Some(false), ..Default::default() }),
+            tier("big", TierCapabilities::default()),
+        ];
+        let needs = |capabilities: Vec<Capability>| TierNeeds { estimated_tokens: 10, capabilities };
+
+        assert_eq!(needs(vec![]).floor(&tiers, 0), 0);
+        assert_eq!(needs(vec![Capability::Vision]).floor(&tiers, 0), 1);
+        assert_eq!(needs(vec![Capability::Vision, Capability::Tools]).floor(&tiers, 0), 2, "unset counts as capable");
+        assert_eq!(needs(vec![Capability::JsonMode]).floor(&tiers, 1), 1);
+
+        let mut detail = RoutingDetail::default();
+        let to = needs(vec![Capability::Vision]).gate(&tiers, 0, &mut detail);
+        assert_eq!(to.name, "vision");
+        assert_eq!(detail.context_bumps[0].missing_capabilities, [Capability::Vision]);
+
+        // Nobody can: gate on the context window alone rather than reject.
+        let all_text = [tier("a", text_only.clone()), tier("b", text_only)];
+        assert_eq!(needs(vec![Capability::Vision]).floor(&all_text, 0), 0);
+    }
+}
@@ -9,7 +9,7 @@ use std::collections::HashMap;
 use serde_json::Value;
 use tracing::debug;
 
-use crate::config::TierConfig;
+use crate::config::{RuleConfig, TierConfig};
 
 /// Full result of parsing a structured or plain-text classifier response.
 ///
@@ -74,6 +74,12 @@ pub(crate) fn parse_classification(response: &Value) -> ParsedClassification {
     ParsedClassification { tier_label, think_override, tags }
 }
 
+/// [`parse_classification`] for a bare reply, such as a cached classifier
+/// reply or a kNN example's classification.
+pub(crate) fn parse_classification_reply(reply: &str) -> ParsedClassification {
+    parse_classification(&serde_json::json!({ "choices": [{ "message": { "content": reply } }] }))
+}
+
 /// Parse a classification label from the classifier's response.
 ///
 /// Returns the base label token (lowercased, punctuation-stripped) and an
@@ -88,6 +94,28 @@ pub(crate) fn parse_classification_label(response: &Value) -> (String, Option<bo
     (p.tier_label, p.think_override)
 }
 
+/// Whether every `when` pair of `rule` is among the classifier's `tags`
+/// (values compared case-insensitively).
+pub(crate) fn rule_matches(rule: &RuleConfig, tags: &HashMap<String, String>) -> bool {
+    rule.when
+        .iter()
+        .all(|(k, v)| tags.get(k.as_str()).is_some_and(|tv| tv.eq_ignore_ascii_case(v)))
+}
+
+/// Whether a classification is one the profile can route: its label names a
+/// candidate tier (exactly or by suffix) or its tags match one of the tag
+/// rules in `rules`.
+/// Anything else would land on [`resolve_tier_by_label`]'s middle-tier guess.
+pub(crate) fn label_is_known(
+    label: &str,
+    tags: &HashMap<String, String>,
+    candidates: &[TierConfig],
+    rules: &[RuleConfig],
+) -> bool {
+    candidates.iter().any(|t| t.name == label || t.name.rsplit(':').next() == Some(label))
+        || rules.iter().any(|rule| !rule.is_request_rule() && rule_matches(rule, tags))
+}
+
 /// Resolve a classifier label string to a tier from the candidate slice.
 ///
 /// Matching is purely name-driven so the routing behaviour is defined entirely
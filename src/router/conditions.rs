@@ -0,0 +1,255 @@
+//! Request features that rule conditions ([`RequestConditions`]) test.
+//!
+//! Everything here is read from the request body and the client's headers
+//! and identity — no backend call — so request rules can be decided before
+//! the classifier runs, and in `dispatch` mode.
+
+use std::sync::OnceLock;
+
+use axum::http::HeaderMap;
+use chrono::{NaiveTime, Utc};
+use serde_json::Value;
+
+use crate::config::RequestConditions;
+
+use super::{explain::ConditionTrace, RequestMeta};
+
+/// One request's features, computed on first use.
+pub(crate) struct RequestFeatures<'a> {
+    body: &'a Value,
+    headers: &'a HeaderMap,
+    client: Option<&'a str>,
+    now: NaiveTime,
+    last_user_text: OnceLock<String>,
+    estimated_tokens: OnceLock<u64>,
+}
+
+impl<'a> RequestFeatures<'a> {
+    pub fn new(body: &'a Value, meta: &'a RequestMeta) -> Self {
+        Self {
+            body,
+            headers: &meta.headers,
+            client: meta.client.as_deref(),
+            now: Utc::now().time(),
+            last_user_text: OnceLock::new(),
+            estimated_tokens: OnceLock::new(),
+        }
+    }
+
+    /// Evaluate at `now` instead of the current UTC time.
+    #[allow(dead_code)] // used in tests
+    pub fn at(mut self, now: NaiveTime) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Whether every condition set in `conditions` holds for this request.
+    pub fn satisfies(&self, conditions: &RequestConditions) -> bool {
+        let RequestConditions {
+            message_matches,
+            message_contains,
+            has_tools,
+            has_images,
+            min_tokens,
+            max_tokens,
+            headers,
+            clients,
+            hours_utc,
+        } = conditions;
+        message_matches.as_ref().is_none_or(|pattern| pattern.is_match(self.last_user_text()))
+            && (message_contains.is_empty() || {
+                let text = self.last_user_text().to_lowercase();
+                message_contains.iter().any(|keyword| text.contains(&keyword.to_lowercase()))
+            })
+            && has_tools.is_none_or(|wanted| wanted == self.has_tools())
+            && has_images.is_none_or(|wanted| wanted == self.has_images())
+            && min_tokens.is_none_or(|min| self.estimated_tokens() >= min)
+            && max_tokens.is_none_or(|max| self.estimated_tokens() <= max)
+            && headers.iter().all(|(name, value)| {
+                self.headers.get(name.as_str()).and_then(|v| v.to_str().ok()) == Some(value.as_str())
+            })
+            && (clients.is_empty() || self.client.is_some_and(|client| clients.iter().any(|c| c == client)))
+            && hours_utc.is_none_or(|hours| hours.contains(self.now))
+    }
+
+    /// Each condition set in `conditions`, with this request's value and
+    /// whether it holds. Agrees with [`Self::satisfies`], for route traces.
+    pub fn explain(&self, conditions: &RequestConditions) -> Vec<ConditionTrace> {
+        let RequestConditions {
+            message_matches,
+            message_contains,
+            has_tools,
+            has_images,
+            min_tokens,
+            max_tokens,
+            headers,
+            clients,
+            hours_utc,
+        } = conditions;
+        let mut out = Vec::new();
+        let mut check = |key: &str, expected: String, actual: Option<String>, met: bool| {
+            out.push(ConditionTrace { key: key.to_owned(), expected, actual, met });
+        };
+        let text = self.last_user_text();
+        if let Some(pattern) = message_matches {
+            check("message_matches", pattern.as_str().to_owned(), Some(text.to_owned()), pattern.is_match(text));
+        }
+        if !message_contains.is_empty() {
+            let lower = text.to_lowercase();
+            let met = message_contains.iter().any(|keyword| lower.contains(&keyword.to_lowercase()));
+            check("message_contains", message_contains.join(", "), Some(text.to_owned()), met);
+        }
+        let flags = [("has_tools", has_tools, self.has_tools()), ("has_images", has_images, self.has_images())];
+        for (key, wanted, actual) in flags {
+            if let Some(wanted) = wanted {
+                check(key, wanted.to_string(), Some(actual.to_string()), *wanted == actual);
+            }
+        }
+        if let Some(min) = min_tokens {
+            let tokens = self.estimated_tokens();
+            check("min_tokens", min.to_string(), Some(tokens.to_string()), tokens >= *min);
+        }
+        if let Some(max) = max_tokens {
+            let tokens = self.estimated_tokens();
+            check("max_tokens", max.to_string(), Some(tokens.to_string()), tokens <= *max);
+        }
+        let mut headers: Vec<_> = headers.iter().collect();
+        headers.sort();
+        for (name, value) in headers {
+            let actual = self.headers.get(name.as_str()).and_then(|v| v.to_str().ok());
+            check(&format!("headers.{name}"), value.clone(), actual.map(str::to_owned), actual == Some(value.as_str()));
+        }
+        if !clients.is_empty() {
+            let met = self.client.is_some_and(|client| clients.iter().any(|c| c == client));
+            check("clients", clients.join(", "), self.client.map(str::to_owned), met);
+        }
+        if let Some(hours) = hours_utc {
+            check("hours_utc", hours.to_string(), Some(self.now.format("%H:%M").to_string()), hours.contains(self.now));
+        }
+        out
+    }
+
+    fn messages(&self) -> &'a [Value] {
+        self.body.get("messages").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Text of the last user message; empty when there is none.
+    pub fn last_user_text(&self) -> &str {
+        self.last_user_text.get_or_init(|| {
+            self.messages()
+                .iter()
+                .rev()
+                .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))
+                .and_then(super::extract_message_text)
+                .unwrap_or_default()
+        })
+    }
+
+    fn estimated_tokens(&self) -> u64 {
+        *self.estimated_tokens.get_or_init(|| super::estimate_request_tokens(self.body).into())
+    }
+
+    fn has_tools(&self) -> bool {
+        super::capabilities::has_tools(self.body)
+    }
+
+    fn has_images(&self) -> bool {
+        super::capabilities::has_images(self.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn conditions(toml: &str) -> RequestConditions {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn content_conditions() {
+        let meta = RequestMeta::default();
+        let body = json!({
+            "messages": [
+                { "role": "user", "content": "earlier question" },
+                { "role": "assistant", "content": "answer" },
+                { "role": "user", "content": [
+                    { "type": "text", "text": "What is in this Screenshot?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/png;base64,AAAA" } }
+                ] }
+            ],
+            "tools": [{ "type": "function", "function": { "name": "search" } }]
+        });
+        let features = RequestFeatures::new(&body, &meta);
+        assert!(features.satisfies(&conditions("has_images = true\nhas_tools = true")));
+        assert!(features.satisfies(&conditions(r#"message_contains = ["screenshot", "diagram"]"#)));
+        assert!(features.satisfies(&conditions(r#"message_matches = "^What is""#)));
+        assert!(!features.satisfies(&conditions(r#"message_matches = "earlier""#)), "only the last user turn");
+        assert!(!features.satisfies(&conditions("has_images = false")));
+        assert!(features.satisfies(&conditions("max_tokens = 1000")));
+        assert!(!features.satisfies(&conditions("min_tokens = 1000")));
+
+        let text_only = json!({ "messages": [{ "role": "user", "content": "hi", "images": [] }] });
+        assert!(RequestFeatures::new(&text_only, &meta).satisfies(&conditions("has_images = false\nhas_tools = false")));
+    }
+
+    #[test]
+    fn metadata_conditions() {
+        let mut meta = RequestMeta { client: Some("HA_KEY".into()), ..Default::default() };
+        meta.headers.insert("x-source", "voice".parse().unwrap());
+        let body = json!({ "messages": [] });
+        let features = RequestFeatures::new(&body, &meta).at(NaiveTime::from_hms_opt(23, 15, 0).unwrap());
+        assert!(features.satisfies(&conditions(
+            r#"
+            headers   = { X-Source = "voice" }
+            clients   = ["HA_KEY", "CLI_KEY"]
+            hours_utc = "22:00-06:00"
+            "#
+        )));
+        assert!(!features.satisfies(&conditions(r#"headers = { X-Source = "text" }"#)));
+        assert!(!features.satisfies(&conditions(r#"clients = ["CLI_KEY"]"#)));
+        assert!(!features.satisfies(&conditions(r#"hours_utc = "09:00-17:00""#)));
+
+        let anonymous = RequestMeta::default();
+        assert!(!RequestFeatures::new(&body, &anonymous).satisfies(&conditions(r#"clients = ["HA_KEY"]"#)));
+    }
+
+    #[test]
+    fn explain_reports_each_condition() {
+        let mut meta = RequestMeta { client: Some("HA_KEY".into()), ..Default::default() };
+        meta.headers.insert("x-source", "voice".parse().unwrap());
+        let body = json!({ "messages": [{ "role": "user", "content": "turn on the lights" }] });
+        let features = RequestFeatures::new(&body, &meta).at(NaiveTime::from_hms_opt(23, 15, 0).unwrap());
+        let all = conditions(
+            r#"
+            message_matches  = "lights?"
+            message_contains = ["heating"]
+            has_images       = false
+            max_tokens       = 1000
+            headers          = { X-Source = "voice" }
+            clients          = ["CLI_KEY"]
+            hours_utc        = "22:00-06:00"
+            "#,
+        );
+        let trace = features.explain(&all);
+        let met: Vec<_> = trace.iter().map(|c| (c.key.as_str(), c.met)).collect();
+        assert_eq!(
+            met,
+            [
+                ("message_matches", true),
+                ("message_contains", false),
+                ("has_images", true),
+                ("max_tokens", true),
+                ("headers.X-Source", true),
+                ("clients", false),
+                ("hours_utc", true),
+            ]
+        );
+        assert_eq!(trace[5].actual.as_deref(), Some("HA_KEY"));
+        assert_eq!(trace[6].actual.as_deref(), Some("23:15"));
+        assert_eq!(trace.iter().all(|c| c.met), features.satisfies(&all));
+        assert!(features.explain(&RequestConditions::default()).is_empty());
+    }
+}
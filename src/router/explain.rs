@@ -0,0 +1,308 @@
+//! Routing dry runs: how a request would be routed, without routing it.
+//!
+//! [`explain`] walks the same decision path as [`super::route`] — tier
+//! resolution, context-window gating, the classifier call and rule
+//! evaluation in [`super::modes::classify_and_resolve`], escalation
+//! pre-checks — and stops before the main backend call. The resulting
+//! [`RouteExplanation`] backs `POST /admin/route/explain` and the
+//! `X-LMG-Dry-Run: true` client header. Traffic splits assign the variant
+//! the request would get, without counting it.
+//!
+//! The classifier is a real backend call, so its metrics are recorded as
+//! usual; nothing is written to the traffic log.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Context as _;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    config::{Capability, Config, ProfileConfig, RoutingMode},
+    traffic::{ContextBump, RoutingDetail, RuleOutcome, SkippedTier, SplitAssignment},
+};
+
+use super::{
+    capabilities::{required_capabilities, TierNeeds},
+    conditions::RequestFeatures,
+    estimate_request_tokens, inject_system_prompt,
+    ladder::EscalationLadder,
+    modes::{classify_and_resolve, request_rule_tier, resolve_target_tier},
+    split, RequestMeta, RouterState,
+};
+
+/// One classifier consultation during classify-mode routing.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassifierStep {
+    pub profile: String,
+    pub classifier_tier: String,
+    /// Text sent to the classifier, as built by `build_classifier_input`.
+    /// `None` when classification was bypassed (no usable user turn, or
+    /// `classifier_context = 0`).
+    pub input: Option<String>,
+    /// Raw message content returned by the classifier.
+    pub output: Option<String>,
+    /// Call failure or timeout; routing then tried `classifier_fallback` or
+    /// used the profile's default label.
+    pub error: Option<String>,
+    /// Tier label parsed from the output (or the failure default).
+    pub label: Option<String>,
+    pub elapsed_ms: u64,
+    /// `output` came from the classifier result cache, not a backend call.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// kNN classifier: the winning label's share of the neighbours' vote.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+impl ClassifierStep {
+    pub(super) fn skipped(profile: &str, classifier_tier: &str) -> Self {
+        Self {
+            profile: profile.to_owned(),
+            classifier_tier: classifier_tier.to_owned(),
+            input: None,
+            output: None,
+            error: None,
+            label: None,
+            elapsed_ms: 0,
+            cached: false,
+            confidence: None,
+        }
+    }
+
+    pub(super) fn called(
+        profile: &str,
+        classifier_tier: &str,
+        input: String,
+        elapsed: std::time::Duration,
+    ) -> Self {
+        Self {
+            input: Some(input),
+            elapsed_ms: elapsed.as_millis() as u64,
+            ..Self::skipped(profile, classifier_tier)
+        }
+    }
+}
+
+/// Full routing trace for one request.
+#[derive(Debug, Serialize)]
+pub struct RouteExplanation {
+    pub profile: String,
+    pub mode: RoutingMode,
+    /// `model` from the request body.
+    pub requested_model: Option<String>,
+    /// Tier the model hint resolves to (or the fallback tier). Classify and
+    /// escalate modes pick their own tier; this is where dispatch starts.
+    pub requested_tier: Option<String>,
+    pub estimated_tokens: u32,
+    /// Tier capabilities the request needs (see [`TierNeeds`]).
+    pub required_capabilities: Vec<Capability>,
+    /// Classifier calls, one per cascade hop.
+    pub classification: Vec<ClassifiedProfile>,
+    /// Profiles traversed, starting with [`Self::profile`].
+    pub profile_chain: Vec<String>,
+    pub class_label: Option<String>,
+    pub think_override: Option<bool>,
+    pub context_bumps: Vec<ContextBump>,
+    /// Escalate mode: tiers ruled out before the first backend call.
+    pub skipped_tiers: Vec<SkippedTier>,
+    /// Traffic split variant the request would be assigned; `tier` is the
+    /// variant.
+    pub split: Option<SplitAssignment>,
+    /// Tier the request would be sent to — the first one tried in escalate
+    /// mode. `None` in reply mode, or when every escalation tier is skipped.
+    pub tier: Option<String>,
+    pub backend: Option<String>,
+    pub model: Option<String>,
+}
+
+/// A classifier step with the tags it produced and the rules checked against them.
+#[derive(Debug, Serialize)]
+pub struct ClassifiedProfile {
+    #[serde(flatten)]
+    pub step: ClassifierStep,
+    pub tags: BTreeMap<String, String>,
+    pub rules: Vec<RuleTrace>,
+}
+
+/// One rule as evaluated, with each condition: `when` tags against the
+/// classifier's, then request conditions against the request.
+#[derive(Debug, Serialize)]
+pub struct RuleTrace {
+    pub route_to: String,
+    pub priority: i32,
+    pub outcome: RuleOutcome,
+    pub conditions: Vec<ConditionTrace>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConditionTrace {
+    /// Tag name, or the request condition (`has_images`, `headers.<name>`, ...).
+    pub key: String,
+    pub expected: String,
+    /// Tag value the classifier produced for `key`, or the request's value
+    /// for a request condition; `None` when it has none.
+    pub actual: Option<String>,
+    pub met: bool,
+}
+
+/// Resolve `body` against `profile_name` without calling the target tier.
+pub async fn explain(
+    state: &RouterState,
+    mut body: Value,
+    profile_name: Option<&str>,
+    meta: &RequestMeta,
+    expert_gate: bool,
+) -> anyhow::Result<RouteExplanation> {
+    let profile_name = profile_name.unwrap_or("default");
+    let config = state.config();
+    let profile = config
+        .profile(profile_name)
+        .context("no matching profile and no default profile configured")?;
+
+    let mut out = RouteExplanation {
+        profile: profile_name.to_owned(),
+        mode: profile.mode.clone(),
+        requested_model: body.get("model").and_then(Value::as_str).map(str::to_owned),
+        requested_tier: None,
+        estimated_tokens: estimate_request_tokens(&body),
+        required_capabilities: required_capabilities(&body),
+        classification: Vec::new(),
+        profile_chain: vec![profile_name.to_owned()],
+        class_label: None,
+        think_override: None,
+        context_bumps: Vec::new(),
+        skipped_tiers: Vec::new(),
+        split: None,
+        tier: None,
+        backend: None,
+        model: None,
+    };
+    if profile.mode == RoutingMode::Reply {
+        return Ok(out);
+    }
+
+    let (requested, _) = resolve_target_tier(&config, profile, &body, expert_gate)?;
+    out.requested_tier = Some(requested.name.clone());
+
+    // Same order as `route`: dispatch gating sees the body before the profile
+    // system prompt is injected; classify and escalate see it after.
+    let tier_name = match profile.mode {
+        RoutingMode::Dispatch => {
+            // Request rules override the hint; their outcomes are traced under
+            // a skipped classifier step.
+            let mut detail = RoutingDetail::default();
+            let features = RequestFeatures::new(&body, meta);
+            let requested = request_rule_tier(&config, profile_name, profile, &features, &mut detail).unwrap_or(requested);
+            if !detail.rules.is_empty() {
+                let step = ClassifierStep::skipped(profile_name, &profile.classifier);
+                out.classification.push(trace_rules(&config, step, &detail, &features));
+            }
+            let tier_idx = config.tiers.iter().position(|t| t.name == requested.name).unwrap_or(0);
+            let tier = TierNeeds::of(&body).gate(&config.tiers, tier_idx, &mut detail);
+            out.context_bumps = detail.context_bumps;
+            Some(tier.name.clone())
+        }
+        RoutingMode::Escalate => {
+            inject_profile_prompt(&mut body, profile);
+            let ladder = EscalationLadder::new(state, &config, profile, &body);
+            let mut first = None;
+            for (idx, tier) in ladder.candidates.iter().enumerate() {
+                match ladder.precheck(idx) {
+                    Some(reason) => out
+                        .skipped_tiers
+                        .push(SkippedTier { tier: tier.name.clone(), reason: reason.to_owned() }),
+                    None => {
+                        first = Some(tier.name.clone());
+                        break;
+                    }
+                }
+            }
+            first
+        }
+        RoutingMode::Classify => {
+            inject_profile_prompt(&mut body, profile);
+            let features = RequestFeatures::new(&body, meta);
+            let resolution =
+                classify_and_resolve(state, &body, profile_name, vec![profile_name.to_owned()], &features).await?;
+            out.classification = resolution
+                .steps
+                .into_iter()
+                .map(|step| trace_rules(&config, step, &resolution.detail, &features))
+                .collect();
+            out.profile_chain = resolution.profile_chain;
+            out.class_label = Some(resolution.class_label).filter(|l| !l.is_empty());
+            out.think_override = resolution.think_override;
+            out.context_bumps = resolution.detail.context_bumps;
+            Some(resolution.tier_name)
+        }
+        RoutingMode::Reply => unreachable!("reply mode handled above"),
+    };
+
+    out.tier = tier_name;
+    if let Some(mut tier) = out.tier.as_deref().and_then(|name| config.resolve_tier(name)) {
+        if let Some(assignment) = split::assign(&config, tier, &body, meta) {
+            out.split = Some(SplitAssignment {
+                split: assignment.split.to_owned(),
+                variant: assignment.variant.name.clone(),
+            });
+            tier = assignment.variant;
+        }
+        out.tier = Some(tier.name.clone());
+        out.backend = Some(tier.backend.clone());
+        out.model = Some(tier.model.clone());
+    }
+    Ok(out)
+}
+
+fn inject_profile_prompt(body: &mut Value, profile: &ProfileConfig) {
+    if let Some(prompt) = profile.system_prompt.as_deref() {
+        inject_system_prompt(body, prompt);
+    }
+}
+
+/// Pair a step's recorded rule outcomes with the rules they came from.
+///
+/// `classify_and_resolve` evaluates a profile's request rules, then its tag
+/// rules, each in priority order, and records one outcome per rule until the
+/// first match, so the n-th recorded outcome for a profile belongs to its
+/// n-th rule in that order.
+fn trace_rules(
+    config: &Config,
+    step: ClassifierStep,
+    detail: &RoutingDetail,
+    features: &RequestFeatures<'_>,
+) -> ClassifiedProfile {
+    let tags = detail.tags.get(&step.profile).cloned().unwrap_or_default();
+    let outcomes = detail.rules.iter().filter(|r| r.profile == step.profile);
+    let rules = config.profiles.get(&step.profile).map(|p| p.rules.as_slice()).unwrap_or_default();
+    let (request_rules, tag_rules): (Vec<_>, Vec<_>) = rules.iter().partition(|rule| rule.is_request_rule());
+    let rules = request_rules
+        .into_iter()
+        .chain(tag_rules)
+        .zip(outcomes)
+        .map(|(rule, evaluated)| RuleTrace {
+            route_to: rule.route_to.clone(),
+            priority: rule.priority,
+            outcome: evaluated.outcome,
+            conditions: sorted(&rule.when)
+                .map(|(key, expected)| {
+                    let actual = tags.get(key).cloned();
+                    ConditionTrace {
+                        met: actual.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(expected)),
+                        key: key.clone(),
+                        expected: expected.clone(),
+                        actual,
+                    }
+                })
+                .chain(features.explain(&rule.request))
+                .collect(),
+        })
+        .collect();
+    ClassifiedProfile { step, tags, rules }
+}
+
+fn sorted(when: &HashMap<String, String>) -> impl Iterator<Item = (&String, &String)> {
+    when.iter().collect::<BTreeMap<_, _>>().into_iter()
+}
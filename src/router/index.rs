@@ -0,0 +1,128 @@
+//! Lookups derived from the live config.
+//!
+//! Client keys, per-profile rate limiters and tier priority gates come from
+//! the config but carry runtime state: resolved key values, token buckets,
+//! queued requests. [`ConfigIndex::build`] rebuilds them every time the
+//! config is swapped, carrying over the limiters and gates whose settings
+//! didn't change, so a reload neither resets a profile's quota nor strands
+//! the requests waiting at a gate.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{api::rate_limit::RateLimiter, config::Config};
+
+use super::priority::TierPriorityGate;
+
+/// The client an API key belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// Profile the client's requests route through.
+    pub profile: String,
+    /// The client's `key_env`, which identifies it in the traffic log.
+    pub name: String,
+}
+
+/// Per-config lookups, swapped together with the config.
+pub struct ConfigIndex {
+    /// Maps resolved client API key values → the client they identify.
+    ///
+    /// Built by reading each `[[clients]]` entry's `key_env`. An empty map
+    /// means no client key auth is configured — all requests use the
+    /// `default` profile (if present) or no profile.
+    pub clients: HashMap<String, ClientIdentity>,
+
+    /// Per-profile shared rate limiters, keyed by profile name.
+    ///
+    /// Built from profiles that specify a non-zero `rate_limit_rpm`. Each
+    /// limiter enforces a total-RPM quota shared across ALL clients that
+    /// resolve to the same profile.
+    pub profile_limiters: HashMap<String, Arc<RateLimiter>>,
+
+    /// Per-tier priority gates that enforce the "fire if top, queue if not"
+    /// policy, keyed by tier name.
+    pub gates: HashMap<String, TierPriorityGate>,
+}
+
+impl ConfigIndex {
+    /// Index `config`, reusing `previous`'s gates for tiers that remain and
+    /// its limiters for profiles whose `rate_limit_rpm` is unchanged.
+    pub fn build(config: &Config, previous: Option<&ConfigIndex>) -> Self {
+        let clients: HashMap<String, ClientIdentity> = config
+            .clients
+            .iter()
+            .filter_map(|c| {
+                let key = std::env::var(&c.key_env).ok().filter(|k| !k.is_empty())?;
+                Some((key, ClientIdentity { profile: c.profile.clone(), name: c.key_env.clone() }))
+            })
+            .collect();
+        if !clients.is_empty() {
+            tracing::info!(count = clients.len(), "loaded client key mappings");
+        }
+        let profile_limiters: HashMap<String, Arc<RateLimiter>> = config
+            .profiles
+            .iter()
+            .filter_map(|(name, profile)| {
+                let rpm = profile.rate_limit_rpm.filter(|&r| r > 0)?;
+                let kept = previous.and_then(|p| p.profile_limiters.get(name)).filter(|l| l.rpm == rpm);
+                Some((name.clone(), kept.map_or_else(|| Arc::new(RateLimiter::new(rpm)), Arc::clone)))
+            })
+            .collect();
+        if !profile_limiters.is_empty() {
+            tracing::info!(count = profile_limiters.len(), "loaded per-profile rate limiters");
+        }
+        let gates: HashMap<String, TierPriorityGate> = config
+            .tiers
+            .iter()
+            .map(|t| {
+                let kept = previous.and_then(|p| p.gates.get(&t.name)).cloned();
+                (t.name.clone(), kept.unwrap_or_else(TierPriorityGate::new))
+            })
+            .collect();
+        tracing::debug!(count = gates.len(), "priority gates initialised");
+        Self { clients, profile_limiters, gates }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rpm: u32, tiers: &[&str]) -> Config {
+        let tiers: String = tiers
+            .iter()
+            .map(|t| format!("[[tiers]]\nname = \"{t}\"\nbackend = \"ollama\"\nmodel = \"m\"\n"))
+            .collect();
+        toml::from_str(&format!(
+            r#"
+            [gateway]
+            client_port = 8080
+            admin_port  = 8081
+
+            [backends.ollama]
+            base_url = "http://localhost:11434"
+
+            {tiers}
+
+            [profiles.default]
+            classifier     = "local:fast"
+            max_auto_tier  = "local:fast"
+            rate_limit_rpm = {rpm}
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rebuild_keeps_unchanged_limiters_and_gates() {
+        let first = ConfigIndex::build(&config(60, &["local:fast"]), None);
+        let _permit = first.gates["local:fast"].acquire(0).await;
+        let same = ConfigIndex::build(&config(60, &["local:fast", "cloud:economy"]), Some(&first));
+        assert!(Arc::ptr_eq(&first.profile_limiters["default"], &same.profile_limiters["default"]));
+        assert_eq!(same.gates["local:fast"].depth().await, (1, 0), "in-flight request carried over");
+        assert!(same.gates.contains_key("cloud:economy"), "new tiers are gated");
+
+        let faster = ConfigIndex::build(&config(120, &["cloud:economy"]), Some(&same));
+        assert_eq!(faster.profile_limiters["default"].rpm, 120);
+        assert!(!faster.gates.contains_key("local:fast"));
+    }
+}
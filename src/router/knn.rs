@@ -0,0 +1,235 @@
+//! Embedding nearest-neighbour classifier (`classifier_kind = "knn"`).
+//!
+//! Each kNN profile's example utterances are embedded once per config — at
+//! startup and after a reload by [`RouterState::warm_knn_classifiers`], or by
+//! the first request that needs them — and kept as unit vectors. A request is
+//! labelled by a similarity-weighted vote of its `k` nearest examples; the
+//! winning label's share of the vote is its confidence.
+//!
+//! A failed build is remembered for [`BUILD_RETRY`]: until then requests skip
+//! the kNN vote and go straight to the LLM classifier, rather than each one
+//! re-embedding every example against a struggling embedding backend.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures_util::{StreamExt as _, TryStreamExt as _};
+use tracing::{debug, info, warn};
+
+use crate::{
+    cache::normalize,
+    config::{ClassifierKind, KnnConfig},
+};
+
+use super::{cache_lookup::embed_text, RouterState};
+
+/// Example embeddings requested at once while building an index.
+const EMBED_CONCURRENCY: usize = 8;
+
+/// How long a failed index build is remembered before the next attempt.
+const BUILD_RETRY: Duration = Duration::from_secs(30);
+
+/// Per-profile example indexes, rebuilt when a profile's kNN settings or
+/// embedding tier change.
+#[derive(Default)]
+pub struct KnnClassifiers {
+    slots: Mutex<HashMap<String, Arc<Slot>>>,
+}
+
+struct Slot {
+    source: Source,
+    index: tokio::sync::OnceCell<KnnIndex>,
+    /// Set by a failed build: no new attempt before this instant.
+    retry_at: Mutex<Option<Instant>>,
+}
+
+/// What an index was built from.
+#[derive(PartialEq)]
+struct Source {
+    knn: KnnConfig,
+    backend: String,
+    model: String,
+}
+
+/// Embedded examples of one profile.
+struct KnnIndex {
+    labels: Vec<String>,
+    examples: Vec<(usize, Vec<f32>)>,
+}
+
+/// Outcome of a vote: the winning classification and its share of the vote.
+#[derive(Debug, PartialEq)]
+pub(super) struct Vote {
+    pub label: String,
+    pub confidence: f32,
+}
+
+impl KnnClassifiers {
+    /// Vote on `text` with `profile_name`'s examples, embedding them first if
+    /// this config hasn't been seen yet. `None` when no example is similar.
+    pub(super) async fn classify(
+        &self,
+        state: &RouterState,
+        profile_name: &str,
+        knn: &KnnConfig,
+        text: &str,
+    ) -> anyhow::Result<Option<Vote>> {
+        let slot = self.slot(state, profile_name, knn);
+        let index = slot.index(state, profile_name).await?;
+        let query = embed_text(state, &knn.embedding_tier, text).await?;
+        Ok(index.vote(&query, knn.k))
+    }
+
+    /// The slot for `profile_name`, replaced when its source has changed.
+    fn slot(&self, state: &RouterState, profile_name: &str, knn: &KnnConfig) -> Arc<Slot> {
+        let config = state.config();
+        let tier = config.resolve_tier(&knn.embedding_tier);
+        let source = Source {
+            knn: knn.clone(),
+            backend: tier.map(|t| t.backend.clone()).unwrap_or_default(),
+            model: tier.map(|t| t.model.clone()).unwrap_or_default(),
+        };
+        let mut slots = self.slots.lock().expect("knn lock poisoned");
+        match slots.get(profile_name) {
+            Some(slot) if slot.source == source => Arc::clone(slot),
+            _ => {
+                let slot = Arc::new(Slot { source, index: Default::default(), retry_at: Mutex::new(None) });
+                slots.insert(profile_name.to_owned(), Arc::clone(&slot));
+                slot
+            }
+        }
+    }
+}
+
+impl Slot {
+    /// The built index. Concurrent callers share one build; after a failed
+    /// build, callers fail fast until [`BUILD_RETRY`] has passed.
+    async fn index(&self, state: &RouterState, profile_name: &str) -> anyhow::Result<&KnnIndex> {
+        self.index
+            .get_or_try_init(|| async {
+                // Checked inside the init so callers queued behind a failing
+                // build don't each start another one.
+                if let Some(retry_at) = *self.retry_at.lock().expect("knn lock poisoned") {
+                    let wait = retry_at.saturating_duration_since(Instant::now());
+                    anyhow::ensure!(
+                        wait.is_zero(),
+                        "embedding kNN examples failed recently; next attempt in {}s",
+                        wait.as_secs() + 1
+                    );
+                }
+                let built = build(state, profile_name, &self.source.knn).await;
+                if built.is_err() {
+                    *self.retry_at.lock().expect("knn lock poisoned") = Some(Instant::now() + BUILD_RETRY);
+                }
+                built
+            })
+            .await
+    }
+}
+
+impl KnnIndex {
+    fn new(embedded: impl IntoIterator<Item = (String, Vec<f32>)>) -> Self {
+        let mut labels: Vec<String> = Vec::new();
+        let mut examples = Vec::new();
+        for (label, vector) in embedded {
+            let Some(vector) = normalize(vector) else { continue };
+            let idx = labels.iter().position(|l| *l == label).unwrap_or_else(|| {
+                labels.push(label);
+                labels.len() - 1
+            });
+            examples.push((idx, vector));
+        }
+        Self { labels, examples }
+    }
+
+    /// Similarity-weighted vote of the `k` examples nearest to `query`.
+    /// `None` when nothing is similar at all.
+    fn vote(&self, query: &[f32], k: usize) -> Option<Vote> {
+        let query = normalize(query.to_vec())?;
+        let mut nearest: Vec<(usize, f32)> = self
+            .examples
+            .iter()
+            .filter(|(_, vector)| vector.len() == query.len())
+            .map(|(label, vector)| (*label, vector.iter().zip(&query).map(|(a, b)| a * b).sum::<f32>()))
+            .collect();
+        nearest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        nearest.truncate(k);
+
+        let mut weights = vec![0.0_f32; self.labels.len()];
+        for (label, similarity) in &nearest {
+            weights[*label] += similarity.max(0.0);
+        }
+        let total: f32 = weights.iter().sum();
+        let (winner, weight) = weights.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+        (total > 0.0).then(|| Vote { label: self.labels[winner].clone(), confidence: weight / total })
+    }
+}
+
+/// Embed every example of `knn` with its embedding tier.
+async fn build(state: &RouterState, profile_name: &str, knn: &KnnConfig) -> anyhow::Result<KnnIndex> {
+    let t0 = std::time::Instant::now();
+    let examples: Vec<(String, String)> =
+        knn.labelled_examples().map(|(label, text)| (label.to_owned(), text.to_owned())).collect();
+    let embedded: Vec<(String, Vec<f32>)> = futures_util::stream::iter(examples)
+        .map(|(label, text)| async move { anyhow::Ok((label, embed_text(state, &knn.embedding_tier, &text).await?)) })
+        .buffer_unordered(EMBED_CONCURRENCY)
+        .try_collect()
+        .await?;
+    let index = KnnIndex::new(embedded);
+    info!(
+        profile = %profile_name,
+        examples = index.examples.len(),
+        labels = index.labels.len(),
+        elapsed_ms = t0.elapsed().as_millis() as u64,
+        "kNN classifier examples embedded"
+    );
+    Ok(index)
+}
+
+impl RouterState {
+    /// Embed the examples of every kNN profile in the live config, so the
+    /// first requests don't wait for it. Failures are logged; those profiles
+    /// use their LLM classifier until a later request rebuilds the index.
+    pub async fn warm_knn_classifiers(&self) {
+        let config = self.config();
+        for (name, profile) in &config.profiles {
+            let Some(knn) = profile.knn.as_ref().filter(|_| profile.classifier_kind == ClassifierKind::Knn) else {
+                continue;
+            };
+            match self.knn.slot(self, name, knn).index(self, name).await {
+                Ok(_) => debug!(profile = %name, "kNN classifier ready"),
+                Err(e) => warn!(profile = %name, error = %e, "embedding kNN examples failed — using the LLM classifier for now"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn votes_are_weighted_by_similarity() {
+        let index = KnnIndex::new([
+            ("instant".to_owned(), vec![1.0, 0.0]),
+            ("instant".to_owned(), vec![0.9, 0.1]),
+            ("deep".to_owned(), vec![0.0, 1.0]),
+            ("deep".to_owned(), vec![0.1, 0.9]),
+            ("deep".to_owned(), vec![0.0, 0.0]), // unusable, dropped
+        ]);
+        assert_eq!(index.examples.len(), 4);
+
+        let vote = index.vote(&[1.0, 0.05], 3).unwrap();
+        assert_eq!(vote.label, "instant");
+        assert!(vote.confidence > 0.8, "far neighbour barely counts: {vote:?}");
+
+        let split = index.vote(&[1.0, 1.0], 4).unwrap();
+        assert!(split.confidence <= 0.5 + f32::EPSILON, "equidistant examples split the vote: {split:?}");
+
+        assert!(index.vote(&[-1.0, -1.0], 2).is_none(), "nothing similar");
+        assert!(index.vote(&[1.0, 0.0, 0.0], 2).is_none(), "dimension mismatch");
+    }
+}
@@ -0,0 +1,93 @@
+//! The escalation ladder: which tiers escalate mode may try for a request.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, ProfileConfig, TierConfig},
+    traffic::BackendHealthStats,
+};
+
+use super::{capabilities::TierNeeds, RouterState};
+
+/// Tiers escalation may try for a request, cheapest first, and the checks
+/// that rule some of them out before any backend call.
+///
+/// Shared by [`super::modes::escalate`] and the dry-run explainer so both skip the same tiers.
+pub(super) struct EscalationLadder<'a> {
+    /// Tiers up to `max_auto_tier`.
+    pub candidates: &'a [TierConfig],
+    needs: TierNeeds,
+    /// Lowest candidate that fits the request and has the capabilities it needs.
+    floor_idx: usize,
+    /// Some candidate has every capability the request needs.
+    capable_tier_exists: bool,
+    health_window: usize,
+    backend_health: HashMap<String, BackendHealthStats>,
+}
+
+impl<'a> EscalationLadder<'a> {
+    pub fn new(state: &RouterState, config: &'a Config, profile: &ProfileConfig, body: &Value) -> Self {
+        let max_idx = config
+            .tiers
+            .iter()
+            .position(|t| t.name == profile.max_auto_tier)
+            .unwrap_or(config.tiers.len() - 1);
+        let candidates = &config.tiers[..=max_idx];
+
+        // Context-window and capability pre-check: find the lowest tier that
+        // can serve the request.
+        let needs = TierNeeds::of(body);
+        let floor_idx = needs.floor(candidates, 0);
+        let capable_tier_exists = needs.capable_tier_exists(candidates);
+
+        // Pre-fetch backend health snapshot so degraded backends can be skipped.
+        let health_window = config.gateway.health_window.unwrap_or(10);
+        let health_threshold = config.gateway.health_error_threshold.unwrap_or(0.7);
+        let backend_health = if health_window > 0 {
+            state.traffic.backend_health(health_window, health_threshold)
+        } else {
+            HashMap::new()
+        };
+        Self { candidates, needs, floor_idx, capable_tier_exists, health_window, backend_health }
+    }
+
+    /// Why `candidates[tier_idx]` is skipped without being tried, if it is.
+    pub fn precheck(&self, tier_idx: usize) -> Option<&'static str> {
+        let tier = &self.candidates[tier_idx];
+        // Skip tiers that can't fit the request or lack a capability it needs
+        // (unless no tier has them all).
+        let missing = self.needs.missing(tier);
+        if self.capable_tier_exists && !missing.is_empty() {
+            debug!(tier = %tier.name, missing = ?missing, "skipping tier — lacks a required capability");
+            return Some("missing_capability");
+        }
+        if tier_idx < self.floor_idx {
+            debug!(
+                tier = %tier.name,
+                estimated_tokens = self.needs.estimated_tokens,
+                "skipping tier — request exceeds context window"
+            );
+            return Some("context_window");
+        }
+
+        // Skip tiers whose backends are currently degraded (too many recent errors).
+        if self.health_window > 0 {
+            if let Some(health) = self.backend_health.get(&tier.backend) {
+                if !health.healthy {
+                    warn!(
+                        tier = %tier.name,
+                        backend = %tier.backend,
+                        error_rate = health.error_rate,
+                        window = health.total,
+                        "skipping unhealthy backend — escalating"
+                    );
+                    return Some("unhealthy");
+                }
+            }
+        }
+        None
+    }
+}
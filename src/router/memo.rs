@@ -0,0 +1,225 @@
+//! Short-lived routing memory for classify mode.
+//!
+//! The first two stores are keyed by [`CacheKey`] hashes and bounded by
+//! [`MAX_ENTRIES`]:
+//!
+//! - **Classifier results** — the classifier's raw reply for an identical
+//!   classifier request (tier, prompt, input, think flag), reused for the
+//!   profile's `classifier_cache_secs`.
+//! - **Pinned decisions** — the resolved tier, class and profile chain of a
+//!   request, recalled for `routing_pin_secs` by the next hop of the same
+//!   agent tool loop (a request whose latest message is a `role: tool`
+//!   result) or by a later request carrying the same `X-LMG-Conversation-Id`.
+//!   Pinning keeps a multi-step task on one tier and skips the classifier on
+//!   every hop.
+//! - **Recent decisions** — the last [`RECENT_DECISIONS`] tiers per profile,
+//!   from which `speculate = "recent"` predicts the next one.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+use crate::{cache::CacheKey, traffic::PinSource};
+
+/// Entries kept per store; expired entries are pruned first, then the one
+/// closest to expiry.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Tiers remembered per profile for speculative tier prediction.
+const RECENT_DECISIONS: usize = 100;
+
+/// Classifier replies, pinned routing decisions and recent tiers.
+#[derive(Default)]
+pub struct RoutingMemory {
+    classifications: TtlMap<String>,
+    pins: TtlMap<Pin>,
+    recent: Mutex<HashMap<String, VecDeque<String>>>,
+}
+
+/// A routing decision recalled for a later request.
+#[derive(Debug, Clone)]
+pub(super) struct Pin {
+    pub tier_name: String,
+    pub think_override: Option<bool>,
+    pub class_label: String,
+    pub profile_chain: Vec<String>,
+}
+
+impl RoutingMemory {
+    /// Cached classifier reply for `request` sent to `classifier_tier`.
+    pub(super) fn classification(&self, classifier_tier: &str, request: &Value) -> Option<String> {
+        self.classifications.get(&CacheKey::new(classifier_tier, request))
+    }
+
+    pub(super) fn remember_classification(&self, classifier_tier: &str, request: &Value, reply: String, ttl: Duration) {
+        self.classifications.insert(CacheKey::new(classifier_tier, request), reply, ttl);
+    }
+
+    /// The decision pinned for `messages` routed through `profile`, with what
+    /// it was pinned by. A conversation ID takes precedence over the tool loop.
+    pub(super) fn pinned(&self, profile: &str, messages: &[Value], conversation: Option<&str>) -> Option<(Pin, PinSource)> {
+        if let Some(pin) = conversation.and_then(|id| self.pins.get(&conversation_key(profile, id))) {
+            return Some((pin, PinSource::Conversation));
+        }
+        let prefix = tool_loop_prefix(messages)?;
+        self.pins.get(&messages_key(profile, prefix)).map(|pin| (pin, PinSource::ToolLoop))
+    }
+
+    /// Pin `decision` for the next hop of this request's tool loop and, when
+    /// given, for its conversation.
+    pub(super) fn pin(&self, profile: &str, messages: &[Value], conversation: Option<&str>, decision: Pin, ttl: Duration) {
+        if let Some(id) = conversation {
+            self.pins.insert(conversation_key(profile, id), decision.clone(), ttl);
+        }
+        self.pins.insert(messages_key(profile, messages), decision, ttl);
+    }
+
+    /// Note the tier a classify-mode request on `profile` resolved to.
+    pub(super) fn record_decision(&self, profile: &str, tier: &str) {
+        let mut recent = self.recent.lock().expect("routing memory lock poisoned");
+        let tiers = recent.entry(profile.to_owned()).or_default();
+        if tiers.len() == RECENT_DECISIONS {
+            tiers.pop_front();
+        }
+        tiers.push_back(tier.to_owned());
+    }
+
+    /// The tier `profile` resolved to most often recently; ties go to the
+    /// one chosen last.
+    pub(super) fn likely_tier(&self, profile: &str) -> Option<String> {
+        let recent = self.recent.lock().expect("routing memory lock poisoned");
+        let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+        for (idx, tier) in recent.get(profile)?.iter().enumerate() {
+            let (count, last) = counts.entry(tier).or_default();
+            *count += 1;
+            *last = idx;
+        }
+        counts.into_iter().max_by_key(|(_, rank)| *rank).map(|(tier, _)| tier.to_owned())
+    }
+}
+
+/// The messages of the request that produced the trailing tool calls: the
+/// history before the last assistant turn, when the latest message is a
+/// `role: tool` result answering that turn's `tool_calls`.
+fn tool_loop_prefix(messages: &[Value]) -> Option<&[Value]> {
+    if messages.last()?.get("role").and_then(Value::as_str) != Some("tool") {
+        return None;
+    }
+    let call_idx = messages.iter().rposition(|m| m.get("role").and_then(Value::as_str) != Some("tool"))?;
+    let calling = &messages[call_idx];
+    let has_calls = calling.get("tool_calls").and_then(Value::as_array).is_some_and(|c| !c.is_empty());
+    (calling.get("role").and_then(Value::as_str) == Some("assistant") && has_calls).then(|| &messages[..call_idx])
+}
+
+fn messages_key(profile: &str, messages: &[Value]) -> CacheKey {
+    CacheKey::new(&format!("pin\0{profile}"), &json!({ "messages": messages }))
+}
+
+fn conversation_key(profile: &str, id: &str) -> CacheKey {
+    CacheKey::new(&format!("conversation\0{profile}\0{id}"), &Value::Null)
+}
+
+/// A map whose entries expire individually.
+struct TtlMap<V> {
+    entries: Mutex<HashMap<CacheKey, (Instant, V)>>,
+}
+
+impl<V> Default for TtlMap<V> {
+    fn default() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<V: Clone> TtlMap<V> {
+    fn get(&self, key: &CacheKey) -> Option<V> {
+        let mut entries = self.entries.lock().expect("routing memory lock poisoned");
+        match entries.get(key) {
+            Some((expires, value)) if *expires > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: CacheKey, value: V, ttl: Duration) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("routing memory lock poisoned");
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&key) {
+            entries.retain(|_, (expires, _)| *expires > now);
+            if entries.len() >= MAX_ENTRIES {
+                if let Some(soonest) = entries.iter().min_by_key(|(_, (expires, _))| *expires).map(|(k, _)| *k) {
+                    entries.remove(&soonest);
+                }
+            }
+        }
+        entries.insert(key, (now + ttl, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(tier: &str) -> Pin {
+        Pin { tier_name: tier.into(), think_override: None, class_label: "code".into(), profile_chain: vec!["p".into()] }
+    }
+
+    #[test]
+    fn tool_results_recall_the_decision_for_the_calling_request() {
+        let memory = RoutingMemory::default();
+        let ttl = Duration::from_secs(60);
+        let first = vec![json!({ "role": "user", "content": "list the files" })];
+        memory.pin("p", &first, None, decision("local:deep"), ttl);
+
+        let mut hop = first.clone();
+        hop.push(json!({ "role": "assistant", "content": null, "tool_calls": [{ "id": "1", "function": { "name": "ls" } }] }));
+        hop.push(json!({ "role": "tool", "tool_call_id": "1", "content": "a.rs b.rs" }));
+        let (pin, source) = memory.pinned("p", &hop, None).unwrap();
+        assert_eq!(pin.tier_name, "local:deep");
+        assert_eq!(source, PinSource::ToolLoop);
+
+        assert!(memory.pinned("other", &hop, None).is_none());
+        assert!(memory.pinned("p", &first, None).is_none(), "a user turn is classified afresh");
+    }
+
+    #[test]
+    fn conversation_ids_pin_across_user_turns() {
+        let memory = RoutingMemory::default();
+        let turn = |text: &str| vec![json!({ "role": "user", "content": text })];
+        memory.pin("p", &turn("hi"), Some("conv-1"), decision("cloud:fast"), Duration::from_secs(60));
+
+        let (pin, source) = memory.pinned("p", &turn("and then?"), Some("conv-1")).unwrap();
+        assert_eq!((pin.tier_name.as_str(), source), ("cloud:fast", PinSource::Conversation));
+        assert!(memory.pinned("p", &turn("and then?"), Some("conv-2")).is_none());
+    }
+
+    #[test]
+    fn likely_tier_is_the_most_frequent_recent_decision() {
+        let memory = RoutingMemory::default();
+        assert_eq!(memory.likely_tier("p"), None);
+        for tier in ["fast", "deep", "fast", "deep"] {
+            memory.record_decision("p", tier);
+        }
+        assert_eq!(memory.likely_tier("p").as_deref(), Some("deep"), "ties go to the latest");
+        for _ in 0..RECENT_DECISIONS {
+            memory.record_decision("p", "instant");
+        }
+        assert_eq!(memory.likely_tier("p").as_deref(), Some("instant"));
+    }
+
+    #[test]
+    fn entries_expire() {
+        let map = TtlMap::default();
+        let key = CacheKey::new("k", &Value::Null);
+        map.insert(key, 1, Duration::ZERO);
+        assert_eq!(map.get(&key), None);
+        map.insert(key, 2, Duration::from_secs(60));
+        assert_eq!(map.get(&key), Some(2));
+    }
+}
@@ -23,32 +23,57 @@
 //!   to a model.
 
 use std::{
-    collections::HashMap,
     path::PathBuf,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
 };
 
 use anyhow::Context;
+use arc_swap::ArcSwap;
 use bytes::Bytes;
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, Instrument as _};
 
 use futures_util::StreamExt as _;
 
 use crate::{
+    analytics::{self, GateDepth, GateHistory, GateSample},
     api::rate_limit::RateLimiter,
     backends::{BackendClient, SseStream},
-    config::{Config, RoutingMode},
-    traffic::{TrafficEntry, TrafficLog},
+    cache::{self, CacheDirective, CacheLookup, CacheScope, ResponseCache},
+    config::{BackendConfig, Config, ConfigHistory, ConfigSource, ProfileConfig, RoutingMode, TierConfig},
+    logging::LogControl,
+    metrics::Metrics,
+    telemetry::{self, Operation},
+    traffic::{RoutingDetail, SkippedTier, TrafficEntry, TrafficLog},
 };
 
-use self::modes::{classify_and_dispatch, classify_and_resolve, dispatch, escalate, resolve_target_tier};
+use self::cache_lookup::cache_lookup;
+use self::ladder::EscalationLadder;
+use self::modes::{classify_and_dispatch, dispatch, escalate, request_rule_tier, resolve_target_tier};
+use self::pinning::resolve_with_pins;
 
+mod cache_lookup;
+mod capabilities;
 mod classify;
+mod conditions;
+mod explain;
+pub mod index;
+mod knn;
+mod ladder;
+mod memo;
 mod modes;
+mod pinning;
 pub mod priority;
+mod speculation;
+mod split;
+mod stream;
 
-use priority::TierPriorityGate;
+pub use explain::explain;
+pub use index::ConfigIndex;
+use capabilities::TierNeeds;
+use conditions::RequestFeatures;
+use knn::KnnClassifiers;
+use memo::RoutingMemory;
 
 // ---------------------------------------------------------------------------
 // Text extraction
@@ -162,6 +187,13 @@ pub struct RouterState {
     config_lock: Arc<RwLock<Arc<Config>>>,
     /// Path to the config file on disk — used by the hot-reload background task.
     pub config_path: PathBuf,
+    /// Serialises admin API config mutations so concurrent edits of
+    /// `conf.d/admin.toml` never overwrite each other.
+    pub config_write: tokio::sync::Mutex<()>,
+    /// Recently applied configs, for `GET /admin/config/history` and rollback.
+    ///
+    /// Updated together with `config_lock` in [`RouterState::replace_config`].
+    pub history: Mutex<ConfigHistory>,
     /// In-memory ring-buffer of recent requests, exposed through the admin API.
     pub traffic: Arc<TrafficLog>,
     /// Gateway start time — used to compute uptime for the public status endpoint.
@@ -178,13 +210,9 @@ pub struct RouterState {
     /// Resolved at startup from `config.gateway.admin_token_env`; not
     /// updated on hot-reload.
     pub admin_token: Option<String>,
-    /// Maps resolved client API key values → profile names.
-    ///
-    /// Built at startup by reading each `[[clients]]` entry's `key_env`.
-    /// An empty map means no client key auth is configured — all requests
-    /// use the `default` profile (if present) or no profile.
-    /// Not updated on hot-reload; restart required to pick up new client keys.
-    pub client_map: HashMap<String, String>,
+    /// Client keys, profile rate limiters and priority gates for the live
+    /// config, rebuilt whenever it is swapped.
+    pub index: ArcSwap<ConfigIndex>,
 
     /// Fallback profile for unauthenticated requests when `[[clients]]` are configured.
     ///
@@ -193,19 +221,25 @@ pub struct RouterState {
     /// Not updated on hot-reload.
     pub public_profile: Option<String>,
 
-    /// Per-profile shared rate limiters, keyed by profile name.
-    ///
-    /// Built at startup from profiles that specify a non-zero `rate_limit_rpm`.
-    /// Each limiter enforces a total-RPM quota shared across ALL clients that
-    /// resolve to the same profile. Not updated on hot-reload.
-    pub profile_limiters: HashMap<String, Arc<RateLimiter>>,
+    /// Lifetime counters and histograms rendered by `GET /metrics`.
+    pub metrics: Arc<Metrics>,
 
-    /// Per-tier priority gates that enforce the "fire if top, queue if not" policy.
-    ///
-    /// Keyed by tier name. Built at startup from the configured tiers. Tiers added
-    /// via hot-reload will not have a gate and will fire immediately (safe fallback).
-    /// Not updated on hot-reload — restart required to gate newly added tiers.
-    pub gates: HashMap<String, TierPriorityGate>,
+    /// Recent gate occupancy samples for the dashboard, filled by
+    /// [`Self::sample_gates`].
+    pub gate_history: GateHistory,
+
+    /// Runtime control of the global log filter; `None` when the process
+    /// didn't install a reloadable subscriber (tests).
+    pub log_control: Option<LogControl>,
+
+    /// Exact-match response cache for profiles with `[profiles.<name>.cache]`.
+    pub response_cache: Arc<ResponseCache>,
+
+    /// Cached classifier replies and pinned classify-mode decisions.
+    pub routing_memory: RoutingMemory,
+
+    /// Embedded examples of `classifier_kind = "knn"` profiles.
+    pub knn: KnnClassifiers,
 
     /// When `true`, attach full request bodies to traffic log entries.
     /// Requires the `debug-traffic` Cargo feature.
@@ -230,38 +264,11 @@ impl RouterState {
             .as_deref()
             .and_then(|var| std::env::var(var).ok())
             .filter(|t| !t.is_empty());
-        let client_map: HashMap<String, String> = config
-            .clients
-            .iter()
-            .filter_map(|c| {
-                let key = std::env::var(&c.key_env).ok().filter(|k| !k.is_empty())?;
-                Some((key, c.profile.clone()))
-            })
-            .collect();
-        if !client_map.is_empty() {
-            tracing::info!(count = client_map.len(), "loaded client key mappings");
-        }
-        let profile_limiters: HashMap<String, Arc<RateLimiter>> = config
-            .profiles
-            .iter()
-            .filter_map(|(name, profile)| {
-                let rpm = profile.rate_limit_rpm.filter(|&r| r > 0)?;
-                Some((name.clone(), Arc::new(RateLimiter::new(rpm))))
-            })
-            .collect();
-        if !profile_limiters.is_empty() {
-            tracing::info!(count = profile_limiters.len(), "loaded per-profile rate limiters");
-        }
         let public_profile = config.gateway.public_profile.clone();
         if let Some(ref p) = public_profile {
             tracing::info!(profile = %p, "public (unauthenticated) profile configured");
         }
-        let gates: HashMap<String, TierPriorityGate> = config
-            .tiers
-            .iter()
-            .map(|t| (t.name.clone(), TierPriorityGate::new()))
-            .collect();
-        tracing::debug!(count = gates.len(), "priority gates initialised");
+        let index = ConfigIndex::build(&config, None);
         #[cfg(feature = "debug-traffic")]
         let debug_traffic = config.gateway.traffic_log_debug;
         #[cfg(feature = "debug-traffic")]
@@ -271,22 +278,47 @@ impl RouterState {
                  request bodies are accessible unauthenticated via /admin/traffic"
             );
         }
+        let history_dir = config.gateway.config_history_dir.as_deref().map(|dir| {
+            let dir = std::path::Path::new(dir);
+            if dir.is_absolute() {
+                dir.to_path_buf()
+            } else {
+                config_path.parent().unwrap_or(std::path::Path::new(".")).join(dir)
+            }
+        });
+        let history = ConfigHistory::new(
+            Arc::clone(&config),
+            config.gateway.config_history_size,
+            history_dir,
+        );
         Self {
             config_lock: Arc::new(RwLock::new(config)),
             config_path,
+            config_write: tokio::sync::Mutex::new(()),
+            history: Mutex::new(history),
             traffic,
             started_at: std::time::Instant::now(),
             rate_limiter,
             admin_token,
-            client_map,
+            index: ArcSwap::from_pointee(index),
             public_profile,
-            profile_limiters,
-            gates,
+            metrics: Arc::new(Metrics::default()),
+            gate_history: GateHistory::new(analytics::GATE_SAMPLE_CAPACITY),
+            log_control: None,
+            response_cache: Arc::new(ResponseCache::default()),
+            routing_memory: RoutingMemory::default(),
+            knn: KnnClassifiers::default(),
             #[cfg(feature = "debug-traffic")]
             debug_traffic,
         }
     }
 
+    /// Attach the log filter handle installed in `main`.
+    pub fn with_log_control(mut self, control: LogControl) -> Self {
+        self.log_control = Some(control);
+        self
+    }
+
     /// Returns a snapshot of the current live config.
     ///
     /// The `RwLock` is held only for the duration of `Arc::clone` (nanoseconds),
@@ -295,9 +327,110 @@ impl RouterState {
         self.config_lock.read().expect("config lock poisoned").clone()
     }
 
-    /// Atomically replaces the live config. Called only from the hot-reload task.
-    pub fn replace_config(&self, new: Arc<Config>) {
-        *self.config_lock.write().expect("config lock poisoned") = new;
+    /// Atomically replaces the live config and records it in the history.
+    ///
+    /// Called from the hot-reload task, `POST /admin/reload` and the admin
+    /// config mutation endpoints. Returns the new version number.
+    pub fn replace_config(&self, new: Arc<Config>, source: ConfigSource) -> u64 {
+        self.swap_config(new, source, None)
+    }
+
+    /// Record `new` in the history and make it live, then write the history
+    /// snapshot (if any) off the async runtime, outside both locks.
+    fn swap_config(&self, new: Arc<Config>, source: ConfigSource, rollback_of: Option<u64>) -> u64 {
+        let (version, snapshot, old) = {
+            let mut history = self.history.lock().expect("history lock poisoned");
+            let (version, snapshot) = history.record(Arc::clone(&new), source, rollback_of);
+            self.index.store(Arc::new(ConfigIndex::build(&new, Some(&self.index.load()))));
+            let old = std::mem::replace(&mut *self.config_lock.write().expect("config lock poisoned"), new);
+            (version, snapshot, old)
+        };
+        if let Some(snapshot) = snapshot {
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => drop(runtime.spawn_blocking(move || snapshot.write())),
+                Err(_) => snapshot.write(),
+            }
+        }
+        self.metrics.config_reloads.inc(&[&source.to_string(), "success"]);
+        self.apply_log_level(&old);
+        version
+    }
+
+    /// Follow a `log_level` change between `old` and the live config.
+    fn apply_log_level(&self, old: &Config) {
+        if let Some(control) = &self.log_control {
+            control.apply_config(&old.gateway, &self.config().gateway);
+        }
+    }
+
+    /// Record the current depth of every priority gate in [`Self::gate_history`].
+    pub async fn sample_gates(&self) {
+        let mut tiers = std::collections::BTreeMap::new();
+        let index = self.index.load_full();
+        for (tier, gate) in &index.gates {
+            let (in_flight, queued) = gate.depth().await;
+            tiers.insert(tier.clone(), GateDepth { in_flight, queued });
+        }
+        self.gate_history.record(GateSample { timestamp: chrono::Utc::now(), tiers });
+    }
+
+    /// Count a config reload that was rejected (parse or validation error).
+    pub fn record_reload_failure(&self, source: ConfigSource) {
+        self.metrics.config_reloads.inc(&[&source.to_string(), "failure"]);
+    }
+
+    /// Finish a backend call: record its span attributes and OTel metrics, and
+    /// count reported tokens.
+    pub(crate) fn record_backend_call(
+        &self,
+        span: &tracing::Span,
+        operation: telemetry::Operation,
+        tier: &TierConfig,
+        backend: &BackendConfig,
+        elapsed: std::time::Duration,
+        result: Result<Option<&Value>, &anyhow::Error>,
+    ) {
+        if let Ok(Some(response)) = result {
+            self.metrics.record_tokens(&tier.name, response);
+        }
+        let usage = result.map(|response| response.map(telemetry::CallUsage::of).unwrap_or_default());
+        telemetry::record_backend_result(span, operation, tier, backend, elapsed, usage);
+    }
+
+    /// Count a routed request and, when it has finished, its duration.
+    fn record_request(
+        &self,
+        profile: &str,
+        tier: &str,
+        backend: &str,
+        success: bool,
+        duration: Option<std::time::Duration>,
+    ) {
+        let outcome = if success { "success" } else { "error" };
+        self.metrics.requests.inc(&[profile, tier, backend, outcome]);
+        if let Some(duration) = duration {
+            self.metrics.request_duration.observe(&[profile, tier], duration);
+        }
+    }
+
+    /// Swap a previously applied config back in.
+    ///
+    /// The admin overlay it was loaded with is written back to
+    /// `conf.d/admin.toml` first, so later admin edits, reloads and restarts
+    /// build on the restored version instead of reverting it. Edits made to
+    /// `config.toml` or other `conf.d/` files since then are left on disk and
+    /// return on the next reload.
+    ///
+    /// The restored config is recorded as a new version with source
+    /// `rollback`. Returns `Ok(None)` when `version` is no longer retained.
+    pub async fn rollback(&self, version: u64) -> anyhow::Result<Option<u64>> {
+        let _guard = self.config_write.lock().await;
+        let config = match self.history.lock().expect("history lock poisoned").get(version) {
+            Some(restored) => Arc::clone(&restored.config),
+            None => return Ok(None),
+        };
+        config.admin_overlay.write(&self.config_path)?;
+        Ok(Some(self.swap_config(config, ConfigSource::Rollback, Some(version))))
     }
 }
 
@@ -305,6 +438,57 @@ impl RouterState {
 // Route entry points
 // ---------------------------------------------------------------------------
 
+/// Per-request context from the client API layer, copied onto the
+/// [`TrafficEntry`].
+#[derive(Debug, Clone, Default)]
+pub struct RequestMeta {
+    /// Inbound `X-Request-ID`; becomes the traffic entry ID.
+    pub id: Option<String>,
+    /// Authenticated client (its `key_env`) when `[[clients]]` auth is on.
+    pub client: Option<String>,
+    /// Scheduling priority from `X-LMG-Priority`.
+    pub priority: i32,
+    /// Entry ID this request replays; copied onto the new entry.
+    pub replay_of: Option<String>,
+    /// Leave the request out of the traffic log. Honoured by the
+    /// non-streaming entry points used for admin replays.
+    pub skip_traffic_log: bool,
+    /// Client `Cache-Control` preference for the response cache.
+    pub cache_control: CacheDirective,
+    /// `X-LMG-Conversation-Id`; classify mode pins the conversation's
+    /// routing decision under it.
+    pub conversation_id: Option<String>,
+    /// Inbound request headers, for rule `headers` conditions.
+    pub headers: axum::http::HeaderMap,
+}
+
+impl RequestMeta {
+    fn tag(&self, mut entry: TrafficEntry) -> TrafficEntry {
+        if let Some(id) = &self.id {
+            entry = entry.with_id(id);
+        }
+        if let Some(client) = &self.client {
+            entry = entry.with_client(client);
+        }
+        if let Some(original) = &self.replay_of {
+            entry = entry.with_replay_of(original);
+        }
+        entry.with_priority(self.priority)
+    }
+
+    /// The profile's response cache, when it has one.
+    fn cache_scope(&self, state: &RouterState, profile_name: &str, profile: &ProfileConfig) -> Option<CacheScope> {
+        let config = profile.cache.clone()?;
+        Some(CacheScope::new(Arc::clone(&state.response_cache), profile_name, config, self.cache_control))
+    }
+
+    fn record(&self, state: &RouterState, entry: &TrafficEntry) {
+        if !self.skip_traffic_log {
+            state.traffic.push(entry.clone());
+        }
+    }
+}
+
 /// Route a `/v1/chat/completions` request body to the appropriate backend tier.
 ///
 /// - Resolves the `model` field through aliases and tier names.
@@ -324,12 +508,17 @@ pub async fn route(
     state: &RouterState,
     mut request_body: Value,
     profile_name: Option<&str>,
-    request_id: Option<&str>,
-    priority: i32,
+    meta: &RequestMeta,
     stream: bool,
     expert_gate: bool,
 ) -> anyhow::Result<(Value, TrafficEntry)> {
     let profile_name = profile_name.unwrap_or("default");
+    let priority = meta.priority;
+    let started = std::time::Instant::now();
+    // Capture the body as the client sent it — before profile prompts and the
+    // backend model are applied — so a replay can route it afresh.
+    #[cfg(feature = "debug-traffic")]
+    let client_body = state.debug_traffic.then(|| request_body.clone());
     let config = state.config();
     let profile = config
         .profile(profile_name)
@@ -346,37 +535,32 @@ pub async fn route(
             .with_profile(profile_name)
             .with_requested_model(&model_hint)
             .with_routing_mode("reply");
-        if let Some(id) = request_id {
-            entry = entry.with_id(id);
-        }
-        entry = entry.with_priority(priority);
+        entry = meta.tag(entry);
         #[cfg(feature = "debug-traffic")]
-        if state.debug_traffic {
-            entry = entry.with_debug_request_body(request_body.clone());
+        if let Some(body) = client_body {
+            entry = entry.with_debug_request_body(body);
         }
-        state.traffic.push(entry.clone());
+        meta.record(state, &entry);
+        state.record_request(profile_name, "reply", "none", true, Some(started.elapsed()));
         return Ok((build_reply_response(msg), entry));
     }
 
     let (mut target_tier, model_hint) =
         resolve_target_tier(&config, profile, &request_body, expert_gate)?;
+    let mut detail = RoutingDetail::default();
 
-    // Context-window gating for dispatch mode only. Classify and escalate modes
-    // handle their own gating inside classify_and_resolve() / escalate().
+    // Request rules plus context-window and capability gating for dispatch
+    // mode only. Classify and escalate modes handle their own inside
+    // classify_and_resolve() / escalate().
     if profile.mode == RoutingMode::Dispatch {
-        let estimated_tokens = estimate_request_tokens(&request_body);
-        let tier_idx = config.tiers.iter().position(|t| t.name == target_tier.name).unwrap_or(0);
-        let min_idx = find_min_tier_for_tokens(&config.tiers, estimated_tokens, tier_idx);
-        if min_idx > tier_idx {
-            let bumped = &config.tiers[min_idx];
-            debug!(
-                estimated_tokens,
-                from = %target_tier.name,
-                to = %bumped.name,
-                "context-window floor — bumping tier"
-            );
-            target_tier = bumped;
+        let features = RequestFeatures::new(&request_body, meta);
+        if let Some(rule_tier) = request_rule_tier(&config, profile_name, profile, &features, &mut detail) {
+            debug!(from = %target_tier.name, to = %rule_tier.name, "request rule matched");
+            target_tier = rule_tier;
         }
+        let tier_idx = config.tiers.iter().position(|t| t.name == target_tier.name).unwrap_or(0);
+        target_tier = TierNeeds::of(&request_body).gate(&config.tiers, tier_idx, &mut detail);
+        target_tier = split::apply(state, &config, target_tier, &request_body, meta, &mut detail);
     }
 
     tracing::Span::current().record("tier", target_tier.name.as_str());
@@ -386,50 +570,91 @@ pub async fn route(
         inject_system_prompt(&mut request_body, prompt);
     }
 
-    let (response, entry) = match profile.mode {
+    let cache = meta.cache_scope(state, profile_name, profile);
+    let cache = cache.as_ref();
+    let result = match profile.mode {
         RoutingMode::Dispatch => {
-            dispatch(state, &mut request_body, target_tier, priority, stream).await?
+            dispatch(state, &mut request_body, target_tier, priority, stream, cache).await
         }
         RoutingMode::Escalate => {
-            escalate(state, &mut request_body, profile, priority, stream).await?
+            escalate(state, &mut request_body, profile, priority, stream, cache, meta).await
         }
         RoutingMode::Classify => {
-            classify_and_dispatch(state, &mut request_body, profile_name, priority, stream).await?
+            classify_and_dispatch(state, &mut request_body, profile_name, priority, stream, cache, meta).await
         }
         RoutingMode::Reply => unreachable!("reply mode handled above"),
     };
+    let (response, entry) = match result {
+        Ok(ok) => ok,
+        Err(e) => {
+            // The final tier is not known on failure; count it against the
+            // tier the request resolved to.
+            state.record_request(profile_name, &target_tier.name, &target_tier.backend, false, None);
+            return Err(e);
+        }
+    };
+    state.record_request(profile_name, &entry.tier, &entry.backend, true, Some(started.elapsed()));
+    if let Some(outcome) = entry.cache {
+        state.metrics.response_cache.inc(&[profile_name, outcome.as_str()]);
+    }
 
     // Enrich entry with request-level context only available at route() scope,
     // then record it in the traffic log.
+    let pinned = entry.routing.as_ref().is_some_and(|r| r.pinned.is_some());
     let mut entry = entry
+        .with_response_usage(&response)
+        .with_routing_detail(detail)
         .with_profile(profile_name)
         .with_requested_model(&model_hint)
         .with_routing_mode(match profile.mode {
             RoutingMode::Dispatch => "dispatch",
             RoutingMode::Escalate => "escalate",
+            RoutingMode::Classify if pinned => "pinned",
             RoutingMode::Classify => "classify",
             RoutingMode::Reply => "reply",
         });
-    if let Some(id) = request_id {
-        entry = entry.with_id(id);
-    }
-    entry = entry.with_priority(priority);
+    entry = meta.tag(entry);
     #[cfg(feature = "debug-traffic")]
-    if state.debug_traffic {
-        entry = entry.with_debug_request_body(request_body.clone());
+    if let Some(body) = client_body {
+        entry = entry.with_debug_request_body(body);
     }
 
-    state.traffic.push(entry.clone());
+    meta.record(state, &entry);
 
     Ok((response, entry))
 }
 
+/// Dispatch a request straight to `tier_name`, bypassing profile routing.
+///
+/// Used by admin replays to compare tiers on the same captured request.
+pub async fn route_to_tier(
+    state: &RouterState,
+    mut request_body: Value,
+    tier_name: &str,
+    meta: &RequestMeta,
+) -> anyhow::Result<(Value, TrafficEntry)> {
+    let config = state.config();
+    let tier = config
+        .resolve_tier(tier_name)
+        .with_context(|| format!("unknown tier `{tier_name}`"))?;
+    let (response, entry) = dispatch(state, &mut request_body, tier, meta.priority, false, None).await?;
+    let entry = meta.tag(
+        entry
+            .with_response_usage(&response)
+            .with_requested_model(tier_name)
+            .with_routing_mode("dispatch"),
+    );
+    meta.record(state, &entry);
+    Ok((response, entry))
+}
+
 /// Route a streaming `/v1/chat/completions` request.
 ///
 /// Streaming bypasses escalation — the first matching tier is dispatched to
 /// directly, and the backend's SSE output is returned as an [`SseStream`].
 /// In `classify` mode a non-streaming pre-flight call determines which tier to
-/// stream from; escalation mode falls back to dispatch behaviour.
+/// stream from; in `escalate` mode the stream goes to the hinted tier, or the
+/// first rung above it that escalation would not skip.
 /// All backends produce OpenAI-compatible SSE: OpenAI-compatible and Ollama
 /// backends proxy bytes verbatim; Anthropic translates on-the-fly.
 #[tracing::instrument(skip(state, request_body), fields(profile = profile_name.unwrap_or("default")))]
@@ -437,12 +662,15 @@ pub async fn route_stream(
     state: &RouterState,
     mut request_body: Value,
     profile_name: Option<&str>,
-    request_id: Option<&str>,
-    priority: i32,
+    meta: &RequestMeta,
     expert_gate: bool,
     use_native: bool,
 ) -> anyhow::Result<(SseStream, TrafficEntry, bool)> {
     let profile_name = profile_name.unwrap_or("default");
+    let started = std::time::Instant::now();
+    // As in `route`: the client's body, for replays.
+    #[cfg(feature = "debug-traffic")]
+    let client_body = state.debug_traffic.then(|| request_body.clone());
     let config = state.config();
     let profile = config
         .profile(profile_name)
@@ -460,36 +688,30 @@ pub async fn route_stream(
             .with_profile(profile_name)
             .with_requested_model(&model_hint)
             .with_routing_mode("reply");
-        if let Some(id) = request_id {
-            entry = entry.with_id(id);
-        }
-        entry = entry.with_priority(priority);
+        entry = meta.tag(entry);
         #[cfg(feature = "debug-traffic")]
-        if state.debug_traffic {
-            entry = entry.with_debug_request_body(request_body.clone());
+        if let Some(body) = client_body {
+            entry = entry.with_debug_request_body(body);
         }
         state.traffic.push(entry.clone());
+        state.record_request(profile_name, "reply", "none", true, Some(started.elapsed()));
         return Ok((stream, entry, false));
     }
 
     let (mut resolved_tier, model_hint) =
         resolve_target_tier(&config, profile, &request_body, expert_gate)?;
+    let mut detail = RoutingDetail::default();
 
-    // Context-window gating for dispatch mode only (classify/escalate handle it internally).
+    // Request rules plus context-window and capability gating for dispatch
+    // mode only (classify/escalate handle them internally).
     if profile.mode == RoutingMode::Dispatch {
-        let estimated_tokens = estimate_request_tokens(&request_body);
-        let tier_idx = config.tiers.iter().position(|t| t.name == resolved_tier.name).unwrap_or(0);
-        let min_idx = find_min_tier_for_tokens(&config.tiers, estimated_tokens, tier_idx);
-        if min_idx > tier_idx {
-            let bumped = &config.tiers[min_idx];
-            debug!(
-                estimated_tokens,
-                from = %resolved_tier.name,
-                to = %bumped.name,
-                "context-window floor — bumping tier (stream)"
-            );
-            resolved_tier = bumped;
+        let features = RequestFeatures::new(&request_body, meta);
+        if let Some(rule_tier) = request_rule_tier(&config, profile_name, profile, &features, &mut detail) {
+            debug!(from = %resolved_tier.name, to = %rule_tier.name, "request rule matched (stream)");
+            resolved_tier = rule_tier;
         }
+        let tier_idx = config.tiers.iter().position(|t| t.name == resolved_tier.name).unwrap_or(0);
+        resolved_tier = TierNeeds::of(&request_body).gate(&config.tiers, tier_idx, &mut detail);
     }
 
     // Inject the profile system prompt before dispatching to any backend.
@@ -497,13 +719,32 @@ pub async fn route_stream(
         inject_system_prompt(&mut request_body, prompt);
     }
 
+    // A stream can't escalate on a weak answer, but it skips the rungs that
+    // escalate mode would rule out before calling them: too small for the
+    // request, missing a capability it needs, or on an unhealthy backend.
+    if profile.mode == RoutingMode::Escalate {
+        let ladder = EscalationLadder::new(state, &config, profile, &request_body);
+        if let Some(start) = ladder.candidates.iter().position(|t| t.name == resolved_tier.name) {
+            let mut skipped = Vec::new();
+            let rung = (start..ladder.candidates.len()).find(|&idx| match ladder.precheck(idx) {
+                Some(reason) => {
+                    skipped.push(SkippedTier { tier: ladder.candidates[idx].name.clone(), reason: reason.to_owned() });
+                    false
+                }
+                None => true,
+            });
+            let rung = rung.with_context(|| format!("no tier up to `{}` can serve this request", profile.max_auto_tier))?;
+            resolved_tier = &ladder.candidates[rung];
+            detail.skipped_tiers = skipped;
+        }
+    }
+
     // In classify mode, run a non-streaming pre-flight call through classify_and_resolve,
     // which handles rule evaluation and profile cascade routing, then stream from the
     // resolved tier.  This path now shares all routing logic with the non-streaming path.
     let (target_tier_name, routing_trace): (String, Option<(String, Vec<String>)>) =
         if profile.mode == RoutingMode::Classify {
-            let visited = vec![profile_name.to_owned()];
-            let resolution = classify_and_resolve(state, &request_body, profile_name, visited).await?;
+            let resolution = resolve_with_pins(state, &request_body, profile_name, meta).await?;
             // Apply per-class system prompt from the final profile in the cascade chain.
             let final_profile_name = resolution.profile_chain.last().map(String::as_str).unwrap_or(profile_name);
             if let Some(final_profile) = config.profiles.get(final_profile_name) {
@@ -523,6 +764,7 @@ pub async fn route_stream(
                 chain = ?resolution.profile_chain,
                 "stream classify resolved"
             );
+            detail.merge(resolution.detail);
             let trace = (resolution.class_label, resolution.profile_chain);
             (resolution.tier_name, Some(trace))
         } else {
@@ -534,6 +776,7 @@ pub async fn route_stream(
         .iter()
         .find(|t| t.name == target_tier_name)
         .with_context(|| format!("resolved tier `{target_tier_name}` not found"))?;
+    let target_tier = split::apply(state, &config, target_tier, &request_body, meta, &mut detail);
 
     let backend_cfg = config
         .backends
@@ -550,11 +793,65 @@ pub async fn route_stream(
         }
     }
 
+    // The class decides whether the semantic cache layer applies.
+    let cache = meta
+        .cache_scope(state, profile_name, profile)
+        .map(|scope| match &routing_trace {
+            Some((class_label, _)) => scope.with_class(class_label),
+            None => scope,
+        });
+
+    let routing_mode = match profile.mode {
+        RoutingMode::Classify if detail.pinned.is_some() => "pinned+stream",
+        RoutingMode::Classify => "classify+stream",
+        _ => "stream",
+    };
+    let new_entry = |latency_ms: u64| {
+        let mut entry = TrafficEntry::new(
+            target_tier.name.clone(),
+            target_tier.backend.clone(),
+            latency_ms,
+            true,
+        )
+        .with_profile(profile_name)
+        .with_requested_model(&model_hint)
+        .with_routing_mode(routing_mode)
+        .with_routing_detail(detail);
+        if let Some((class_label, profile_chain)) = routing_trace {
+            entry = entry.with_routing_trace(class_label, profile_chain);
+        }
+        #[cfg(feature = "debug-traffic")]
+        if let Some(body) = &client_body {
+            entry = entry.with_debug_request_body(body.clone());
+        }
+        meta.tag(entry)
+    };
+
+    // A cached completion is replayed as synthetic SSE; the Ollama handler
+    // turns it into NDJSON like any other OpenAI-format stream.
+    let cache_fill = match &cache {
+        Some(scope) => match cache_lookup(state, scope, target_tier, &request_body).await {
+            CacheLookup::Hit(hit) => {
+                debug!(tier = %target_tier.name, similarity = ?hit.similarity, "response cache hit (stream)");
+                let entry = new_entry(0).with_cache_hit(&hit).with_response_usage(&hit.response);
+                state.traffic.push(entry.clone());
+                state.record_request(profile_name, &target_tier.name, &target_tier.backend, true, Some(started.elapsed()));
+                state.metrics.response_cache.inc(&[profile_name, hit.outcome().as_str()]);
+                return Ok((cache::completion_sse(&hit.response), entry, false));
+            }
+            CacheLookup::Miss(fill) => Some(fill),
+        },
+        None => None,
+    };
+
     debug!(tier = %target_tier.name, backend = %target_tier.backend, "streaming dispatch");
 
-    // Snapshot the request body before the client call consumes it.
-    #[cfg(feature = "debug-traffic")]
-    let debug_body: Option<Value> = if state.debug_traffic { Some(request_body.clone()) } else { None };
+    // Build the entry before the client call consumes the request body.
+    let mut entry = new_entry(0);
+    if let Some(fill) = &cache_fill {
+        entry = entry.with_cache(fill.outcome());
+        state.metrics.response_cache.inc(&[profile_name, fill.outcome().as_str()]);
+    }
 
     let client = BackendClient::new(backend_cfg)?;
     let t0 = std::time::Instant::now();
@@ -568,44 +865,47 @@ pub async fn route_stream(
         .map(|t| !t.is_empty())
         .unwrap_or(false);
 
-    let (stream_response, is_native_ndjson) = if use_native {
-        client.native_chat_stream(request_body).await?
-    } else if has_tools {
-        debug!("request has tools — routing via native /api/chat to fix tool_call translation");
-        client.tool_call_stream(request_body).await?
-    } else {
-        (client.chat_completions_stream(request_body).await?, false)
-    };
-    let latency_ms = t0.elapsed().as_millis() as u64;
-
-    let routing_mode = match profile.mode {
-        RoutingMode::Classify => "classify+stream",
-        _ => "stream",
-    };
-
-    // Latency here is time-to-first-byte (connection + headers), not full response.
-    let mut entry = TrafficEntry::new(
-        target_tier.name.clone(),
-        target_tier.backend.clone(),
-        latency_ms,
-        true,
-    )
-    .with_profile(profile_name)
-    .with_requested_model(&model_hint)
-    .with_routing_mode(routing_mode);
-    if let Some(id) = request_id {
-        entry = entry.with_id(id);
+    let span = telemetry::backend_span(Operation::Chat, target_tier, backend_cfg);
+    let opened = async {
+        if use_native {
+            client.native_chat_stream(request_body).await
+        } else if has_tools {
+            debug!("request has tools — routing via native /api/chat to fix tool_call translation");
+            client.tool_call_stream(request_body).await
+        } else {
+            Ok((client.chat_completions_stream(request_body).await?, false))
+        }
     }
-    if let Some((class_label, profile_chain)) = routing_trace {
-        entry = entry.with_routing_trace(class_label, profile_chain);
+    .instrument(span.clone())
+    .await;
+    // An opened stream records its call and counts its outcome when it ends
+    // (see `RecordedStream`), once its usage is known.
+    if let Err(e) = &opened {
+        state.record_backend_call(&span, Operation::Chat, target_tier, backend_cfg, t0.elapsed(), Err(e));
+        state.record_request(profile_name, &target_tier.name, &target_tier.backend, false, None);
     }
-    entry = entry.with_priority(priority);
-    #[cfg(feature = "debug-traffic")]
-    if let Some(body) = debug_body {
-        entry = entry.with_debug_request_body(body);
-    }
-
-    state.traffic.push(entry.clone());
+    let (stream_response, is_native_ndjson) = opened?;
+    // Latency here is time-to-first-byte (connection + headers); the recorded
+    // entry is finalised with the full duration when the stream ends.
+    entry.latency_ms = t0.elapsed().as_millis() as u64;
+
+    let stream_response: SseStream = Box::pin(stream::RecordedStream::new(
+        stream_response,
+        entry.clone(),
+        stream::StreamContext {
+            traffic: Arc::clone(&state.traffic),
+            metrics: Arc::clone(&state.metrics),
+            profile: profile_name.to_owned(),
+            route_started: started,
+            backend_started: t0,
+            backend_call: Some(stream::BackendCall {
+                span,
+                tier: target_tier.clone(),
+                backend: backend_cfg.clone(),
+            }),
+            cache_fill,
+        },
+    ));
 
     // Experimental: thinking message — inject a synthetic prefix chunk for perceived
     // responsiveness.  Works for streaming chat UIs; HA voice buffers the full response
@@ -723,7 +1023,7 @@ fn build_reply_sse_stream(msg: &str) -> SseStream {
 /// placed before its content (separated by `\n\n`), so client-provided context
 /// is preserved while the profile's instructions take precedence.
 /// If there is no existing system message, one is inserted at index 0.
-pub(super) fn inject_system_prompt(body: &mut Value, prompt: &str) {
+pub(crate) fn inject_system_prompt(body: &mut Value, prompt: &str) {
     let Some(messages) = body.pointer_mut("/messages").and_then(Value::as_array_mut) else {
         return;
     };
@@ -7,17 +7,33 @@
 use anyhow::Context;
 use futures_util::future::BoxFuture;
 use serde_json::Value;
-use tracing::{debug, warn};
+use tracing::{debug, field::Empty, warn, Instrument as _};
 
 use crate::{
     backends::BackendClient,
-    config::{Config, ProfileConfig, Provider, TierConfig, DEFAULT_CLASSIFIER_PROMPT},
-    traffic::TrafficEntry,
+    cache::{CacheLookup, CacheScope},
+    config::{
+        ClassifierKind, Config, KnnConfig, ProfileConfig, Provider, RuleConfig, TierConfig, DEFAULT_CLASSIFIER_PROMPT,
+    },
+    telemetry::{self, Operation},
+    traffic::{RoutingDetail, RuleEvaluation, RuleOutcome, SkippedTier, TrafficEntry},
 };
 
 use super::{
-    RouterState,
-    classify::{parse_classification, ParsedClassification, resolve_tier_by_label},
+    RequestMeta, RouterState,
+    classify::{
+        label_is_known, parse_classification, parse_classification_reply, ParsedClassification, resolve_tier_by_label,
+        rule_matches,
+    },
+    cache_lookup::{cache_lookup, cached_entry},
+    capabilities::TierNeeds,
+    conditions::RequestFeatures,
+    explain::ClassifierStep,
+    ladder::EscalationLadder,
+    pinning::resolve_with_pins,
+    priority::{PriorityPermit, TierPriorityGate},
+    speculation::SpeculativeDispatch,
+    split,
 };
 
 /// Build the classifier input string from a profile and message array.
@@ -101,14 +117,17 @@ pub(super) fn build_classifier_input(profile: &ProfileConfig, messages: &[Value]
 /// Outcome of a classify-mode routing pass.
 ///
 /// Carries the resolved tier name, optional `think` override to inject before
-/// dispatch, the top-level classification label (for logging/trace headers), and
+/// dispatch, the top-level classification label (for logging/trace headers),
 /// the chain of profile names traversed — e.g. `["auto", "ha-auto"]` for a
-/// two-hop cascade.
+/// two-hop cascade — and the decisions taken along the way.
 pub(super) struct RoutingResolution {
     pub tier_name: String,
     pub think_override: Option<bool>,
     pub class_label: String,
     pub profile_chain: Vec<String>,
+    pub detail: RoutingDetail,
+    /// One entry per classifier call (fallback retries included), in cascade order.
+    pub steps: Vec<ClassifierStep>,
 }
 
 /// Classify a request against the named profile and resolve it to a concrete tier.
@@ -127,6 +146,7 @@ pub(super) fn classify_and_resolve<'a>(
     body: &'a Value,
     profile_name: &'a str,
     visited: Vec<String>,
+    features: &'a RequestFeatures<'a>,
 ) -> BoxFuture<'a, anyhow::Result<RoutingResolution>> {
     Box::pin(async move {
         let config = state.config();
@@ -141,12 +161,6 @@ pub(super) fn classify_and_resolve<'a>(
             .find(|t| t.name == profile.classifier)
             .context("classifier tier not found in config")?;
 
-        let backend_cfg = config
-            .backends
-            .get(&classifier_tier.backend)
-            .with_context(|| format!("backend `{}` not in config", classifier_tier.backend))?
-            .clone();
-
         // Build classifier input from the conversation history.
         //
         // By default the classifier sees all user + assistant messages (system and
@@ -165,28 +179,51 @@ pub(super) fn classify_and_resolve<'a>(
         let candidates: &[TierConfig] = &config.tiers[..=max_idx];
 
         let classifier_input = messages.and_then(|arr| build_classifier_input(profile, arr));
+        let mut detail = RoutingDetail::default();
+
+        // Request rules are decided from the request alone, so a match skips
+        // the classifier entirely.
+        let request_rules = profile.rules.iter().filter(|rule| rule.is_request_rule());
+        let request_rule = first_matching_rule(profile_name, request_rules, &config, &visited, &mut detail, |rule| {
+            features.satisfies(&rule.request)
+        });
+        if let Some(rule) = request_rule {
+            debug!(profile = %profile_name, route_to = %rule.route_to, "request rule matched — skipping classification");
+            let steps = vec![ClassifierStep::skipped(profile_name, &classifier_tier.name)];
+            if config.profiles.contains_key(&rule.route_to) {
+                return cascade(state, body, features, &rule.route_to, visited, detail, steps).await;
+            }
+            let tier_name = gated_rule_tier(&config, rule, candidates, body, profile_name, &mut detail)?;
+            return Ok(RoutingResolution {
+                tier_name,
+                think_override: None,
+                class_label: String::new(), // no classification performed — skip class_prompts
+                profile_chain: visited,
+                detail,
+                steps,
+            });
+        }
 
         let Some(classifier_input) = classifier_input else {
-            // Apply context-window gating even on the bypass path so oversized
-            // requests don't land on a tier that can't fit them.
-            let estimated_tokens = super::estimate_request_tokens(body);
+            // Apply context-window and capability gating even on the bypass
+            // path so requests don't land on a tier that can't serve them.
             let classifier_idx = candidates
                 .iter()
                 .position(|t| t.name == classifier_tier.name)
                 .unwrap_or(0);
-            let min_idx = super::find_min_tier_for_tokens(candidates, estimated_tokens, classifier_idx);
-            let bypass_tier_name = candidates[min_idx].name.clone();
+            let bypass_tier_name = TierNeeds::of(body).gate(candidates, classifier_idx, &mut detail).name.clone();
             debug!(
                 profile = %profile_name,
-                estimated_tokens,
                 tier = %bypass_tier_name,
-                "classifier input unavailable — bypassing classification (context-window gating applied)"
+                "classifier input unavailable — bypassing classification (tier gating applied)"
             );
             return Ok(RoutingResolution {
                 tier_name: bypass_tier_name,
                 think_override: None,
                 class_label: String::new(), // no classification performed — skip class_prompts
                 profile_chain: visited,
+                detail,
+                steps: vec![ClassifierStep::skipped(profile_name, &classifier_tier.name)],
             });
         };
 
@@ -214,40 +251,65 @@ pub(super) fn classify_and_resolve<'a>(
             "options": { "num_predict": 10, "temperature": 0 }
         });
 
-        let client = BackendClient::new(&backend_cfg)?;
-        let classifier_timeout = std::time::Duration::from_millis(profile.classifier_timeout_ms);
-        let ParsedClassification { tier_label: label, think_override, tags } =
-            match tokio::time::timeout(classifier_timeout, client.classify(classifier_body)).await {
-                Ok(Ok(response)) => {
-                    let parsed = parse_classification(&response);
-                    debug!(
-                        profile = %profile_name,
-                        label = %parsed.tier_label,
-                        think_override = ?parsed.think_override,
-                        tags = ?parsed.tags,
-                        "classified request"
-                    );
-                    parsed
-                }
-                Ok(Err(e)) => {
-                    warn!(err = %e, profile = %profile_name, "classification call failed — defaulting to first tier");
-                    ParsedClassification { tier_label: "instant".into(), ..Default::default() }
-                }
-                Err(_) => {
-                    warn!(
-                        profile = %profile_name,
-                        timeout_ms = profile.classifier_timeout_ms,
-                        "classifier timed out — defaulting to first tier"
-                    );
-                    ParsedClassification { tier_label: "instant".into(), ..Default::default() }
+        let classify_span = tracing::info_span!("classify", lmg.profile = %profile_name, lmg.class = Empty);
+        let mut steps = Vec::new();
+        let known = |parsed: &ParsedClassification| {
+            label_is_known(&parsed.tier_label, &parsed.tags, candidates, &profile.rules)
+        };
+        let usable = |step: &ClassifierStep, parsed: &ParsedClassification| step.error.is_none() && known(parsed);
+
+        // A kNN profile asks its LLM classifier only when the vote is uncertain.
+        let knn = profile.knn.as_ref().filter(|_| profile.classifier_kind == ClassifierKind::Knn);
+        let knn_vote = match knn {
+            Some(knn) => Some(knn_classify(state, profile_name, knn, features.last_user_text(), known).await),
+            None => None,
+        };
+        let (mut step, mut parsed) = match knn_vote {
+            Some((step, Some(parsed))) => (step, parsed),
+            knn_vote => {
+                if let Some((step, None)) = knn_vote {
+                    steps.push(step);
                 }
-            };
+                classify_once(
+                    state,
+                    profile,
+                    profile_name,
+                    classifier_tier,
+                    classifier_body.clone(),
+                    classifier_input.clone(),
+                    &classify_span,
+                )
+                .await?
+            }
+        };
+
+        // A failed call or a label the profile can't map gets one more try on
+        // the fallback classifier, then the profile's default label.
+        let fallback_tier = profile.classifier_fallback.as_deref().and_then(|name| config.resolve_tier(name));
+        if let Some(fallback_tier) = fallback_tier.filter(|_| !usable(&step, &parsed)) {
+            warn!(
+                profile = %profile_name,
+                classifier = %classifier_tier.name,
+                fallback = %fallback_tier.name,
+                label = %parsed.tier_label,
+                failed = step.error.is_some(),
+                "classifier produced no usable label — retrying with fallback classifier"
+            );
+            step.label = Some(parsed.tier_label);
+            steps.push(step);
+            let mut fallback_body = classifier_body;
+            fallback_body["model"] = Value::String(fallback_tier.model.clone());
+            (step, parsed) =
+                classify_once(state, profile, profile_name, fallback_tier, fallback_body, classifier_input, &classify_span)
+                    .await?;
+        }
+        if let Some(default_label) = profile.classifier_default_label.as_deref().filter(|_| !usable(&step, &parsed)) {
+            debug!(profile = %profile_name, label = %parsed.tier_label, default_label, "using classifier default label");
+            parsed.tier_label = default_label.to_owned();
+            parsed.think_override = None;
+        }
+        let ParsedClassification { tier_label: label, think_override, tags } = parsed;
 
-        // Rule evaluation: rules are pre-sorted by priority DESC at config load time
-        // (Config::normalize), so we iterate directly without cloning or re-sorting.
-        // A rule matches when every `when` key=value pair is present in `tags`.
-        // Cyclic cascade rules are skipped and lower-priority rules are tried.
-        //
         // class_label: prefer the explicit `class=` tag. If absent, check whether any
         // other tag value matches a class_prompts key (defensive: handles prompts that
         // emit `intent=greeting` or similar). Fall back to the tier label as last resort.
@@ -260,81 +322,33 @@ pub(super) fn classify_and_resolve<'a>(
                     .cloned()
             })
             .unwrap_or_else(|| label.clone());
+        classify_span.record("lmg.class", class_label.as_str());
+        step.label = Some(label.clone());
+        steps.push(step);
+        state.metrics.classifier_labels.inc(&[profile_name, &class_label]);
+        if !tags.is_empty() {
+            detail.tags.insert(profile_name.to_owned(), tags.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        }
 
-        for rule in &profile.rules {
-            if !rule.when.iter().all(|(k, v)| {
-                tags.get(k.as_str())
-                    .map(|tv| tv.eq_ignore_ascii_case(v))
-                    .unwrap_or(false)
-            }) {
-                continue; // rule doesn't match tags
-            }
-            debug!(profile = %profile_name, route_to = %rule.route_to, "routing rule matched");
-
+        // Tag rules match when every `when` key=value pair is present in `tags`
+        // and any request conditions they carry also hold.
+        let tag_rules = profile.rules.iter().filter(|rule| !rule.is_request_rule());
+        let tag_rule = first_matching_rule(profile_name, tag_rules, &config, &visited, &mut detail, |rule| {
+            rule_matches(rule, &tags) && features.satisfies(&rule.request)
+        });
+        if let Some(rule) = tag_rule {
             if config.profiles.contains_key(&rule.route_to) {
-                // Cascade: route_to names a profile — recurse unless already visited.
-                if visited.contains(&rule.route_to) {
-                    warn!(
-                        profile = %profile_name,
-                        route_to = %rule.route_to,
-                        "cascade cycle at runtime — skipping cyclic rule, trying lower-priority rules"
-                    );
-                    continue; // skip this rule; lower-priority rules may still match safely
-                }
-                let mut next_visited = visited.clone();
-                next_visited.push(rule.route_to.clone());
-                let target_name = rule.route_to.clone();
-                debug!(cascade_to = %target_name, chain = ?next_visited, "cascading to profile");
-                let inner =
-                    classify_and_resolve(state, body, &target_name, next_visited).await?;
-                return Ok(inner);
-            } else {
-                // route_to is a tier name or alias — dispatch directly.
-                let rule_tier = config
-                    .resolve_tier(&rule.route_to)
-                    .with_context(|| format!("rule route_to `{}` not found in config", rule.route_to))?;
-
-                // Context-window gating: if the rule's target tier can't fit the
-                // request, bump up to the next tier that can.  Only applies when
-                // the rule's tier is within the auto-tier candidates — if it's
-                // outside (e.g. beyond max_auto_tier), respect it as-is.
-                let final_tier_name = if let Some(rule_idx) =
-                    candidates.iter().position(|t| t.name == rule_tier.name)
-                {
-                    let estimated_tokens = super::estimate_request_tokens(body);
-                    let rule_cap = candidates[rule_idx].max_context_tokens;
-                    debug!(
-                        profile = %profile_name,
-                        estimated_tokens,
-                        tier = %rule_tier.name,
-                        tier_max_ctx = ?rule_cap,
-                        "context-window gating check (rule path)"
-                    );
-                    let min_idx = super::find_min_tier_for_tokens(candidates, estimated_tokens, rule_idx);
-                    if min_idx > rule_idx {
-                        debug!(
-                            profile = %profile_name,
-                            estimated_tokens,
-                            from = %rule_tier.name,
-                            to = %candidates[min_idx].name,
-                            "context-window floor — bumping rule-matched tier"
-                        );
-                        candidates[min_idx].name.clone()
-                    } else {
-                        rule_tier.name.clone()
-                    }
-                } else {
-                    // Rule's target tier is outside auto-tier candidates; use as-is.
-                    rule_tier.name.clone()
-                };
-
-                return Ok(RoutingResolution {
-                    tier_name: final_tier_name,
-                    think_override,
-                    class_label: class_label.clone(),
-                    profile_chain: visited,
-                });
+                return cascade(state, body, features, &rule.route_to, visited, detail, steps).await;
             }
+            let tier_name = gated_rule_tier(&config, rule, candidates, body, profile_name, &mut detail)?;
+            return Ok(RoutingResolution {
+                tier_name,
+                think_override,
+                class_label,
+                profile_chain: visited,
+                detail,
+                steps,
+            });
         }
 
         // No rule matched (or all matching rules were cyclic) — resolve tier from the label.
@@ -352,29 +366,11 @@ pub(super) fn classify_and_resolve<'a>(
             target_tier = &candidates[1];
         }
 
-        // Context-window gating: if the resolved tier's model can't fit the
-        // estimated token count, bump up to the next tier that can.
-        let estimated_tokens = super::estimate_request_tokens(body);
+        // Context-window and capability gating: if the resolved tier's model
+        // can't fit the request or lacks a capability it needs, bump up to the
+        // next tier that can serve it.
         let target_idx = candidates.iter().position(|t| t.name == target_tier.name).unwrap_or(0);
-        let tier_cap = candidates[target_idx].max_context_tokens;
-        debug!(
-            profile = %profile_name,
-            estimated_tokens,
-            tier = %target_tier.name,
-            tier_max_ctx = ?tier_cap,
-            "context-window gating check"
-        );
-        let min_idx = super::find_min_tier_for_tokens(candidates, estimated_tokens, target_idx);
-        if min_idx > target_idx {
-            debug!(
-                profile = %profile_name,
-                estimated_tokens,
-                from = %target_tier.name,
-                to = %candidates[min_idx].name,
-                "context-window floor — bumping tier"
-            );
-            target_tier = &candidates[min_idx];
-        }
+        target_tier = TierNeeds::of(body).gate(candidates, target_idx, &mut detail);
 
         debug!(
             profile = %profile_name,
@@ -388,10 +384,268 @@ pub(super) fn classify_and_resolve<'a>(
             think_override,
             class_label,
             profile_chain: visited,
+            detail,
+            steps,
         })
     })
 }
 
+/// The first rule in `rules` that `applies`, recording each rule considered on
+/// `detail`. A rule cascading into a profile already in `visited` is recorded
+/// as a cycle and skipped.
+fn first_matching_rule<'r>(
+    profile_name: &str,
+    rules: impl Iterator<Item = &'r RuleConfig>,
+    config: &Config,
+    visited: &[String],
+    detail: &mut RoutingDetail,
+    applies: impl Fn(&RuleConfig) -> bool,
+) -> Option<&'r RuleConfig> {
+    // Rules are pre-sorted by priority DESC at config load (Config::normalize).
+    for rule in rules {
+        let evaluated = |outcome| RuleEvaluation {
+            profile: profile_name.to_owned(),
+            route_to: rule.route_to.clone(),
+            outcome,
+        };
+        if !applies(rule) {
+            detail.rules.push(evaluated(RuleOutcome::NoMatch));
+            continue;
+        }
+        if config.profiles.contains_key(&rule.route_to) && visited.contains(&rule.route_to) {
+            warn!(
+                profile = %profile_name,
+                route_to = %rule.route_to,
+                "cascade cycle at runtime — skipping cyclic rule, trying lower-priority rules"
+            );
+            detail.rules.push(evaluated(RuleOutcome::Cycle));
+            continue;
+        }
+        debug!(profile = %profile_name, route_to = %rule.route_to, "routing rule matched");
+        detail.rules.push(evaluated(RuleOutcome::Matched));
+        return Some(rule);
+    }
+    None
+}
+
+/// Dispatch mode: the tier named by the first request rule the request
+/// satisfies, overriding the client's model hint. Tag rules don't apply — there
+/// is no classification.
+pub(super) fn request_rule_tier<'c>(
+    config: &'c Config,
+    profile_name: &str,
+    profile: &ProfileConfig,
+    features: &RequestFeatures<'_>,
+    detail: &mut RoutingDetail,
+) -> Option<&'c TierConfig> {
+    let request_rules = profile.rules.iter().filter(|rule| rule.is_request_rule());
+    let rule = first_matching_rule(profile_name, request_rules, config, &[], detail, |rule| {
+        features.satisfies(&rule.request)
+    })?;
+    config.resolve_tier(&rule.route_to)
+}
+
+/// Follow a rule into the `target` profile: classify again there and carry
+/// this profile's decisions and classifier steps along.
+async fn cascade(
+    state: &RouterState,
+    body: &Value,
+    features: &RequestFeatures<'_>,
+    target: &str,
+    visited: Vec<String>,
+    mut detail: RoutingDetail,
+    mut steps: Vec<ClassifierStep>,
+) -> anyhow::Result<RoutingResolution> {
+    let mut next_visited = visited;
+    next_visited.push(target.to_owned());
+    debug!(cascade_to = %target, chain = ?next_visited, "cascading to profile");
+    let mut inner = classify_and_resolve(state, body, target, next_visited, features).await?;
+    detail.merge(inner.detail);
+    inner.detail = detail;
+    steps.append(&mut inner.steps);
+    inner.steps = steps;
+    Ok(inner)
+}
+
+/// The tier a matched rule routes to, bumped by context-window and capability
+/// gating when it is one of the auto-tier `candidates`. A tier beyond them (e.g. past
+/// `max_auto_tier`) is respected as-is.
+fn gated_rule_tier(
+    config: &Config,
+    rule: &RuleConfig,
+    candidates: &[TierConfig],
+    body: &Value,
+    profile_name: &str,
+    detail: &mut RoutingDetail,
+) -> anyhow::Result<String> {
+    let rule_tier = config
+        .resolve_tier(&rule.route_to)
+        .with_context(|| format!("rule route_to `{}` not found in config", rule.route_to))?;
+    let Some(rule_idx) = candidates.iter().position(|t| t.name == rule_tier.name) else {
+        return Ok(rule_tier.name.clone());
+    };
+    let tier = TierNeeds::of(body).gate(candidates, rule_idx, detail);
+    debug!(profile = %profile_name, rule_tier = %rule_tier.name, tier = %tier.name, "rule-matched tier gated");
+    Ok(tier.name.clone())
+}
+
+/// Vote on the request's last user message with the profile's kNN
+/// classifier. The step is returned either way; the classification only when
+/// the vote reaches `min_confidence` and the profile can map its label.
+async fn knn_classify(
+    state: &RouterState,
+    profile_name: &str,
+    knn: &KnnConfig,
+    text: &str,
+    known: impl Fn(&ParsedClassification) -> bool,
+) -> (ClassifierStep, Option<ParsedClassification>) {
+    let t0 = std::time::Instant::now();
+    let result = match text {
+        "" => Ok(None),
+        text => state.knn.classify(state, profile_name, knn, text).await,
+    };
+    let mut step = ClassifierStep::called(profile_name, &knn.embedding_tier, text.to_owned(), t0.elapsed());
+    let vote = match result {
+        Ok(vote) => vote,
+        Err(e) => {
+            warn!(profile = %profile_name, error = %e, "kNN classification failed — asking the LLM classifier");
+            state.metrics.knn_classifications.inc(&[profile_name, "error"]);
+            step.error = Some(format!("{e:#}"));
+            return (step, None);
+        }
+    };
+    let confident = vote.and_then(|vote| {
+        step.output = Some(vote.label.clone());
+        step.confidence = Some(vote.confidence);
+        let parsed = parse_classification_reply(&vote.label);
+        (vote.confidence >= knn.min_confidence && known(&parsed)).then_some(parsed)
+    });
+    let outcome = if confident.is_some() { "confident" } else { "uncertain" };
+    state.metrics.knn_classifications.inc(&[profile_name, outcome]);
+    debug!(
+        profile = %profile_name,
+        vote = ?step.output,
+        confidence = ?step.confidence,
+        outcome,
+        "kNN classifier voted"
+    );
+    (step, confident)
+}
+
+/// Classify with `classifier_tier`, reusing a cached reply when the profile
+/// sets `classifier_cache_secs`; successful replies are cached.
+async fn classify_once(
+    state: &RouterState,
+    profile: &ProfileConfig,
+    profile_name: &str,
+    classifier_tier: &TierConfig,
+    classifier_body: Value,
+    classifier_input: String,
+    classify_span: &tracing::Span,
+) -> anyhow::Result<(ClassifierStep, ParsedClassification)> {
+    let cache_ttl = profile.classifier_cache_secs.filter(|&secs| secs > 0).map(std::time::Duration::from_secs);
+    let cached_reply = cache_ttl.and_then(|_| state.routing_memory.classification(&classifier_tier.name, &classifier_body));
+    if let Some(reply) = cached_reply {
+        let mut step =
+            ClassifierStep::called(profile_name, &classifier_tier.name, classifier_input, std::time::Duration::ZERO);
+        step.cached = true;
+        let parsed = parse_classification_reply(&reply);
+        debug!(profile = %profile_name, label = %parsed.tier_label, "classifier result cache hit");
+        step.output = Some(reply);
+        return Ok((step, parsed));
+    }
+    let request = classifier_body.clone();
+    let (step, parsed) =
+        call_classifier(state, profile, profile_name, classifier_tier, classifier_body, classifier_input, classify_span)
+            .await?;
+    if let (Some(ttl), Some(reply), None) = (cache_ttl, &step.output, &step.error) {
+        state.routing_memory.remember_classification(&classifier_tier.name, &request, reply.clone(), ttl);
+    }
+    Ok((step, parsed))
+}
+
+/// Call the classifier tier, recording the call. A failure or timeout is not
+/// an error: it yields the profile's `classifier_default_label` (`instant`
+/// when unset) with the reason on the step.
+async fn call_classifier(
+    state: &RouterState,
+    profile: &ProfileConfig,
+    profile_name: &str,
+    classifier_tier: &TierConfig,
+    classifier_body: Value,
+    classifier_input: String,
+    classify_span: &tracing::Span,
+) -> anyhow::Result<(ClassifierStep, ParsedClassification)> {
+    let config = state.config();
+    let backend_cfg = config
+        .backends
+        .get(&classifier_tier.backend)
+        .with_context(|| format!("backend `{}` not in config", classifier_tier.backend))?;
+    let client = BackendClient::new(backend_cfg)?;
+    let classifier_timeout = std::time::Duration::from_millis(profile.classifier_timeout_ms);
+    let call_span = classify_span.in_scope(|| telemetry::backend_span(Operation::Chat, classifier_tier, backend_cfg));
+    let t0 = std::time::Instant::now();
+    let outcome = tokio::time::timeout(
+        classifier_timeout,
+        client.classify(classifier_body).instrument(call_span.clone()),
+    )
+    .instrument(classify_span.clone())
+    .await;
+    let timed_out = anyhow::anyhow!("classifier timed out");
+    let call_result = match &outcome {
+        Ok(Ok(response)) => Ok(Some(response)),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(&timed_out),
+    };
+    let classifier_elapsed = t0.elapsed();
+    let mut step = ClassifierStep::called(profile_name, &classifier_tier.name, classifier_input, classifier_elapsed);
+    state.record_backend_call(
+        &call_span,
+        Operation::Chat,
+        classifier_tier,
+        backend_cfg,
+        classifier_elapsed,
+        call_result,
+    );
+    state.metrics.classifier_duration.observe(&[profile_name], classifier_elapsed);
+    let parsed = match outcome {
+        Ok(Ok(response)) => {
+            step.output = response
+                .pointer("/choices/0/message/content")
+                .and_then(Value::as_str)
+                .map(str::to_owned);
+            let parsed = parse_classification(&response);
+            debug!(
+                profile = %profile_name,
+                label = %parsed.tier_label,
+                think_override = ?parsed.think_override,
+                tags = ?parsed.tags,
+                "classified request"
+            );
+            parsed
+        }
+        Ok(Err(e)) => {
+            warn!(err = %e, profile = %profile_name, "classification call failed — using default label");
+            step.error = Some(format!("{e:#}"));
+            ParsedClassification { tier_label: default_label(profile), ..Default::default() }
+        }
+        Err(_) => {
+            warn!(
+                profile = %profile_name,
+                timeout_ms = profile.classifier_timeout_ms,
+                "classifier timed out — using default label"
+            );
+            step.error = Some(timed_out.to_string());
+            ParsedClassification { tier_label: default_label(profile), ..Default::default() }
+        }
+    };
+    Ok((step, parsed))
+}
+
+fn default_label(profile: &ProfileConfig) -> String {
+    profile.classifier_default_label.clone().unwrap_or_else(|| "instant".to_owned())
+}
+
 /// Resolve the model hint in the request body to a concrete [`TierConfig`].
 ///
 /// Alias indirection is applied first (`hint:fast` → `local:fast`). When the
@@ -465,6 +719,7 @@ pub(super) async fn dispatch(
     tier: &TierConfig,
     priority: i32,
     stream: bool,
+    cache: Option<&CacheScope>,
 ) -> anyhow::Result<(Value, TrafficEntry)> {
     let config = state.config();
     let backend_cfg = config
@@ -484,16 +739,26 @@ pub(super) async fn dispatch(
         }
     }
 
+    // Serve repeats from the profile's response cache before queueing.
+    let cache = match cache {
+        Some(scope) => match cache_lookup(state, scope, tier, body).await {
+            CacheLookup::Hit(hit) => return Ok(cached_entry(hit, tier)),
+            CacheLookup::Miss(fill) => Some(fill),
+        },
+        None => None,
+    };
+
     // Acquire the priority gate for local providers.
     // Cloud-managed providers (Anthropic, OpenRouter) bypass the gate — the
     // cloud handles its own scheduling and adding a gateway queue would only
     // increase tail latency without benefit.
     let is_cloud = matches!(backend_cfg.provider, Provider::Anthropic | Provider::OpenRouter);
     let _gate_permit = if !is_cloud {
-        if let Some(gate) = state.gates.get(&tier.name) {
-            Some(gate.acquire(priority).await)
+        let gate = state.index.load().gates.get(&tier.name).cloned();
+        if let Some(gate) = gate {
+            Some(acquire_gate(state, &gate, tier, priority).await)
         } else {
-            None // Tier was added after startup (hot-reload) — fire immediately.
+            None // Tier removed by a reload since this request began — fire immediately.
         }
     } else {
         None
@@ -543,21 +808,31 @@ pub(super) async fn dispatch(
                 delay_ms = delay_ms.saturating_mul(2);
             }
 
+            let span = telemetry::backend_span(Operation::Chat, tier, backend_cfg);
             let t0 = std::time::Instant::now();
             let result = if has_tools {
-                client.tool_call(body.clone()).await
+                client.tool_call(body.clone()).instrument(span.clone()).await
             } else {
-                client.chat_completions(body.clone()).await
+                client.chat_completions(body.clone()).instrument(span.clone()).await
             };
+            let response = result.as_ref().map(Some);
+            state.record_backend_call(&span, Operation::Chat, tier, backend_cfg, t0.elapsed(), response);
             match result {
                 Ok(response) => {
                     let latency_ms = t0.elapsed().as_millis() as u64;
-                    let entry = TrafficEntry::new(
+                    let mut entry = TrafficEntry::new(
                         tier.name.clone(),
                         tier.backend.clone(),
                         latency_ms,
                         true,
                     );
+                    if attempt > 0 {
+                        entry.routing_mut().retries = attempt;
+                    }
+                    if let Some(fill) = &cache {
+                        fill.store(&response);
+                        entry = entry.with_cache(fill.outcome());
+                    }
                     return Ok((response, entry));
                 }
                 Err(e) => {
@@ -591,6 +866,24 @@ pub(super) async fn dispatch(
     }
 }
 
+/// Span covering the wait for `tier`'s priority gate.
+fn queue_wait_span(tier: &TierConfig, priority: i32) -> tracing::Span {
+    tracing::info_span!("queue_wait", lmg.tier = %tier.name, lmg.priority = priority)
+}
+
+/// Wait for `tier`'s priority gate, tracing and timing the wait.
+async fn acquire_gate(
+    state: &RouterState,
+    gate: &TierPriorityGate,
+    tier: &TierConfig,
+    priority: i32,
+) -> PriorityPermit {
+    let t0 = std::time::Instant::now();
+    let permit = gate.acquire(priority).instrument(queue_wait_span(tier, priority)).await;
+    state.metrics.queue_wait.observe(&[&tier.name], t0.elapsed());
+    permit
+}
+
 /// Mode B: try tiers cheapest-first and return the first sufficient response.
 ///
 /// Iteration stops at `profile.max_auto_tier`. Backend failures and insufficient
@@ -602,56 +895,24 @@ pub(super) async fn escalate(
     profile: &ProfileConfig,
     priority: i32,
     stream: bool,
+    cache: Option<&CacheScope>,
+    meta: &RequestMeta,
 ) -> anyhow::Result<(Value, TrafficEntry)> {
     let config = state.config();
-    // Collect candidate tiers up to max_auto_tier
-    let max_idx = config
-        .tiers
-        .iter()
-        .position(|t| t.name == profile.max_auto_tier)
-        .unwrap_or(config.tiers.len() - 1);
-
-    let candidates: Vec<&TierConfig> = config.tiers[..=max_idx].iter().collect();
-
-    // Context-window pre-check: find the lowest tier that can fit the request.
-    let estimated_tokens = super::estimate_request_tokens(body);
-    let token_floor_idx = super::find_min_tier_for_tokens(&config.tiers[..=max_idx], estimated_tokens, 0);
-
-    // Pre-fetch backend health snapshot so degraded backends can be skipped.
-    let health_window = config.gateway.health_window.unwrap_or(10);
-    let health_threshold = config.gateway.health_error_threshold.unwrap_or(0.7);
-    let backend_health = if health_window > 0 {
-        state.traffic.backend_health(health_window, health_threshold).await
-    } else {
-        std::collections::HashMap::new()
+    let ladder = EscalationLadder::new(state, &config, profile, body);
+    let mut skipped: Vec<SkippedTier> = Vec::new();
+    let mut skip = |tier: &TierConfig, reason: &str| {
+        skipped.push(SkippedTier { tier: tier.name.clone(), reason: reason.to_string() });
     };
 
-    for (tier_idx, tier) in candidates.iter().enumerate() {
-        // Skip tiers below the context-window floor.
-        if tier_idx < token_floor_idx {
-            debug!(
-                tier = %tier.name,
-                estimated_tokens,
-                "skipping tier — request exceeds context window"
-            );
+    for (tier_idx, tier) in ladder.candidates.iter().enumerate() {
+        if let Some(reason) = ladder.precheck(tier_idx) {
+            skip(tier, reason);
             continue;
         }
-
-        // Skip tiers whose backends are currently degraded (too many recent errors).
-        if health_window > 0 {
-            if let Some(health) = backend_health.get(&tier.backend) {
-                if !health.healthy {
-                    warn!(
-                        tier = %tier.name,
-                        backend = %tier.backend,
-                        error_rate = health.error_rate,
-                        window = health.total,
-                        "skipping unhealthy backend — escalating"
-                    );
-                    continue;
-                }
-            }
-        }
+        // A split on this rung sends the attempt to its variant.
+        let assignment = split::assign(&config, tier, body, meta);
+        let tier = assignment.as_ref().map_or(tier, |a| a.variant);
 
         let backend_cfg = match config.backends.get(&tier.backend) {
             Some(b) => b,
@@ -663,6 +924,25 @@ pub(super) async fn escalate(
             obj.insert("stream".into(), Value::Bool(stream));
         }
 
+        // Only sufficient responses are cached, so a hit needs no check.
+        let fill = match cache {
+            Some(scope) => match cache_lookup(state, scope, tier, body).await {
+                CacheLookup::Hit(hit) => {
+                    let (response, mut entry) = cached_entry(hit, tier);
+                    if tier_idx > 0 {
+                        entry = entry.mark_escalated();
+                    }
+                    entry.routing_mut().skipped_tiers = skipped;
+                    if let Some(assignment) = &assignment {
+                        assignment.record(state, entry.routing_mut());
+                    }
+                    return Ok((response, entry));
+                }
+                CacheLookup::Miss(fill) => Some(fill),
+            },
+            None => None,
+        };
+
         let client = match BackendClient::new(backend_cfg) {
             Ok(c) => c,
             Err(e) => {
@@ -674,8 +954,9 @@ pub(super) async fn escalate(
         // Acquire gate for local providers (same policy as dispatch).
         let is_cloud = matches!(backend_cfg.provider, Provider::Anthropic | Provider::OpenRouter);
         let _gate_permit = if !is_cloud {
-            if let Some(gate) = state.gates.get(&tier.name) {
-                Some(gate.acquire(priority).await)
+            let gate = state.index.load().gates.get(&tier.name).cloned();
+            if let Some(gate) = gate {
+                Some(acquire_gate(state, &gate, tier, priority).await)
             } else {
                 None
             }
@@ -683,8 +964,11 @@ pub(super) async fn escalate(
             None
         };
 
+        let span = telemetry::backend_span(Operation::Chat, tier, backend_cfg);
         let t0 = std::time::Instant::now();
-        match client.chat_completions(body.clone()).await {
+        let result = client.chat_completions(body.clone()).instrument(span.clone()).await;
+        state.record_backend_call(&span, Operation::Chat, tier, backend_cfg, t0.elapsed(), result.as_ref().map(Some));
+        match result {
             Ok(response) => {
                 let latency_ms = t0.elapsed().as_millis() as u64;
                 if is_sufficient(&response) {
@@ -693,12 +977,22 @@ pub(super) async fn escalate(
                     if tier_idx > 0 {
                         entry = entry.mark_escalated();
                     }
+                    entry.routing_mut().skipped_tiers = skipped;
+                    if let Some(assignment) = &assignment {
+                        assignment.record(state, entry.routing_mut());
+                    }
+                    if let Some(fill) = fill {
+                        fill.store(&response);
+                        entry = entry.with_cache(fill.outcome());
+                    }
                     return Ok((response, entry));
                 }
                 debug!(tier = %tier.name, "response insufficient — escalating");
+                skip(tier, "insufficient");
             }
             Err(e) => {
                 warn!(tier = %tier.name, error = %e, "tier request failed — escalating");
+                skip(tier, "error");
             }
         }
     }
@@ -714,16 +1008,26 @@ pub(super) async fn escalate(
 ///
 /// The `profile_name` parameter seeds the visited-set used for cascade cycle
 /// detection.  Pass the name of the profile being actively processed.
+///
+/// When the profile sets `speculate`, the request is dispatched to the
+/// predicted tier while classification runs (see [`SpeculativeDispatch`]).
 pub(super) async fn classify_and_dispatch(
     state: &RouterState,
     body: &mut Value,
     profile_name: &str,
     priority: i32,
     stream: bool,
+    cache: Option<&CacheScope>,
+    meta: &RequestMeta,
 ) -> anyhow::Result<(Value, TrafficEntry)> {
     let config = state.config();
-    let visited = vec![profile_name.to_owned()];
-    let resolution = classify_and_resolve(state, body, profile_name, visited).await?;
+    let mut speculative = SpeculativeDispatch::start(state, &config, body, profile_name, priority, stream, cache, meta);
+
+    let resolving = resolve_with_pins(state, body, profile_name, meta);
+    let resolution = match speculative.as_mut() {
+        Some(speculative) => speculative.overlap(resolving).await?,
+        None => resolving.await?,
+    };
 
     // Apply per-class system prompt from the final profile in the cascade chain.
     let final_profile_name = resolution.profile_chain.last().map(String::as_str).unwrap_or(profile_name);
@@ -746,8 +1050,23 @@ pub(super) async fn classify_and_dispatch(
         .find(|t| t.name == resolution.tier_name)
         .with_context(|| format!("resolved tier `{}` not found in config", resolution.tier_name))?;
 
-    let (response, entry) = dispatch(state, body, tier, priority, stream).await?;
-    let entry = entry.with_routing_trace(resolution.class_label, resolution.profile_chain);
+    let mut detail = resolution.detail;
+    let tier = split::apply(state, &config, tier, body, meta, &mut detail);
+    let kept = match speculative {
+        Some(speculative) => speculative.settle(state, profile_name, tier, body, &mut detail).await,
+        None => None,
+    };
+    let (response, entry) = match kept {
+        Some(kept) => kept,
+        None => {
+            // The class decides whether the semantic cache layer applies.
+            let cache = cache.map(|scope| scope.clone().with_class(&resolution.class_label));
+            dispatch(state, body, tier, priority, stream, cache.as_ref()).await?
+        }
+    };
+    let entry = entry
+        .with_routing_trace(resolution.class_label, resolution.profile_chain)
+        .with_routing_detail(detail);
     Ok((response, entry))
 }
 
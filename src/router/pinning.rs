@@ -0,0 +1,84 @@
+//! Routing pins: reusing a classify-mode decision for the next hop of a tool
+//! loop or conversation instead of classifying again.
+
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    config::Config,
+    traffic::{PinSource, RoutingDetail},
+};
+
+use super::{
+    capabilities::TierNeeds,
+    conditions::RequestFeatures,
+    memo::Pin,
+    modes::{classify_and_resolve, RoutingResolution},
+    RequestMeta, RouterState,
+};
+
+/// Resolve a classify-mode request, reusing a pinned decision when the
+/// request continues a recent tool loop or conversation (see [`super::memo`]).
+///
+/// Fresh and recalled decisions are pinned for the request's next hop. A pin
+/// whose tier no longer exists is ignored; a pinned tier that can't fit the
+/// grown request is bumped like any other. Every decision is also recorded for
+/// `speculate = "recent"`.
+pub(super) async fn resolve_with_pins(
+    state: &RouterState,
+    body: &Value,
+    profile_name: &str,
+    meta: &RequestMeta,
+) -> anyhow::Result<RoutingResolution> {
+    let config = state.config();
+    let features = RequestFeatures::new(body, meta);
+    let pin_secs = config.profiles.get(profile_name).map_or(0, |p| p.routing_pin_secs);
+    if pin_secs == 0 {
+        let resolution = classify_and_resolve(state, body, profile_name, vec![profile_name.to_owned()], &features).await?;
+        state.routing_memory.record_decision(profile_name, &resolution.tier_name);
+        return Ok(resolution);
+    }
+    let ttl = std::time::Duration::from_secs(pin_secs);
+    let conversation = meta.conversation_id.as_deref();
+    let messages = body.get("messages").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+
+    let recalled = state
+        .routing_memory
+        .pinned(profile_name, messages, conversation)
+        .and_then(|(pin, source)| pinned_resolution(&config, body, pin, source));
+    let resolution = match recalled {
+        Some(resolution) => {
+            debug!(
+                profile = %profile_name,
+                tier = %resolution.tier_name,
+                pinned = ?resolution.detail.pinned,
+                "reusing pinned routing decision"
+            );
+            resolution
+        }
+        None => classify_and_resolve(state, body, profile_name, vec![profile_name.to_owned()], &features).await?,
+    };
+    let decision = Pin {
+        tier_name: resolution.tier_name.clone(),
+        think_override: resolution.think_override,
+        class_label: resolution.class_label.clone(),
+        profile_chain: resolution.profile_chain.clone(),
+    };
+    state.routing_memory.pin(profile_name, messages, conversation, decision, ttl);
+    state.routing_memory.record_decision(profile_name, &resolution.tier_name);
+    Ok(resolution)
+}
+
+fn pinned_resolution(config: &Config, body: &Value, pin: Pin, source: PinSource) -> Option<RoutingResolution> {
+    let pinned_idx = config.tiers.iter().position(|t| t.name == pin.tier_name)?;
+    let mut detail = RoutingDetail { pinned: Some(source), ..Default::default() };
+    let tier = TierNeeds::of(body).gate(&config.tiers, pinned_idx, &mut detail);
+    Some(RoutingResolution {
+        tier_name: tier.name.clone(),
+        think_override: pin.think_override,
+        class_label: pin.class_label,
+        profile_chain: pin.profile_chain,
+        detail,
+        steps: Vec::new(),
+    })
+}
@@ -123,6 +123,12 @@ impl TierPriorityGate {
         }
     }
 
+    /// Current `(in_flight, queued)` request counts, for `/metrics`.
+    pub async fn depth(&self) -> (usize, usize) {
+        let state = self.state.lock().await;
+        (state.in_flight.len(), state.pending.len())
+    }
+
     /// Acquire an in-flight slot for a request with the given `priority`.
     ///
     /// Returns immediately if the request can fire, or suspends until a
@@ -0,0 +1,155 @@
+//! Speculative dispatch for classify mode.
+//!
+//! When a profile sets `speculate`, [`super::modes::classify_and_dispatch`]
+//! sends the request to a predicted tier while classification runs. The
+//! speculative result is used only if classification picks that tier and
+//! leaves the request unchanged; otherwise the speculative call is dropped —
+//! cancelling the backend request and releasing its gate permit — and the
+//! request dispatched as usual.
+
+use std::future::Future;
+
+use futures_util::future::{BoxFuture, FutureExt as _};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    cache::CacheScope,
+    config::{Config, TierConfig},
+    traffic::{RoutingDetail, Speculation, SpeculationOutcome, TrafficEntry},
+};
+
+use super::{capabilities::required_capabilities, modes::dispatch, split, RequestMeta, RouterState};
+
+/// A finished dispatch: the response and entry, and the request as sent.
+type Dispatched = anyhow::Result<((Value, TrafficEntry), Value)>;
+
+/// A dispatch to the predicted tier, running while the request is classified.
+pub(super) struct SpeculativeDispatch<'a> {
+    predicted: &'a TierConfig,
+    /// The request before the speculative dispatch rewrote it.
+    sent: Value,
+    call: BoxFuture<'a, Dispatched>,
+    /// The call's result, when it finished before classification did.
+    finished: Option<Dispatched>,
+}
+
+impl<'a> SpeculativeDispatch<'a> {
+    /// Dispatch `body` to the predicted tier — on the variant its split would
+    /// assign — if the profile speculates.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        state: &'a RouterState,
+        config: &'a Config,
+        body: &Value,
+        profile_name: &str,
+        priority: i32,
+        stream: bool,
+        cache: Option<&'a CacheScope>,
+        meta: &RequestMeta,
+    ) -> Option<Self> {
+        let predicted = predicted_tier(state, config, body, profile_name, meta.conversation_id.as_deref())?;
+        let predicted = split::assign(config, predicted, body, meta).map_or(predicted, |a| a.variant);
+        let mut spec_body = body.clone();
+        let call = async move {
+            let result = dispatch(state, &mut spec_body, predicted, priority, stream, cache).await;
+            result.map(|ok| (ok, spec_body))
+        }
+        .boxed();
+        Some(Self { predicted, sent: body.clone(), call, finished: None })
+    }
+
+    /// Await `classification`, driving the speculative call alongside it; a
+    /// call that completes first is held until [`Self::settle`].
+    pub async fn overlap<T>(&mut self, classification: impl Future<Output = T>) -> T {
+        tokio::pin!(classification);
+        loop {
+            tokio::select! {
+                output = &mut classification => return output,
+                result = &mut self.call, if self.finished.is_none() => self.finished = Some(result),
+            }
+        }
+    }
+
+    /// The speculative response, if classification chose the predicted tier
+    /// and left `body` as it was sent; otherwise the call is dropped. Records
+    /// the outcome in `detail` and the metrics either way.
+    pub async fn settle(
+        self,
+        state: &RouterState,
+        profile_name: &str,
+        tier: &TierConfig,
+        body: &mut Value,
+        detail: &mut RoutingDetail,
+    ) -> Option<(Value, TrafficEntry)> {
+        let Self { predicted, sent, call, finished } = self;
+        let outcome = if predicted.name == tier.name && sent == *body {
+            let result = match finished {
+                Some(result) => result,
+                None => call.await,
+            };
+            match result {
+                Ok((dispatched, spec_body)) => {
+                    *body = spec_body;
+                    let outcome = SpeculationOutcome::Kept;
+                    detail.speculation = Some(Speculation { tier: predicted.name.clone(), outcome });
+                    state.metrics.speculative_dispatch.inc(&[profile_name, outcome.as_str()]);
+                    return Some(dispatched);
+                }
+                Err(e) => {
+                    warn!(tier = %predicted.name, error = %e, "speculative dispatch failed — dispatching again");
+                    SpeculationOutcome::Discarded
+                }
+            }
+        } else if finished.is_some() {
+            SpeculationOutcome::Discarded
+        } else {
+            drop(call);
+            SpeculationOutcome::Cancelled
+        };
+        debug!(
+            profile = %profile_name,
+            predicted = %predicted.name,
+            tier = %tier.name,
+            outcome = outcome.as_str(),
+            "speculative dispatch not used"
+        );
+        detail.speculation = Some(Speculation { tier: predicted.name.clone(), outcome });
+        state.metrics.speculative_dispatch.inc(&[profile_name, outcome.as_str()]);
+        None
+    }
+}
+
+/// The tier to dispatch to speculatively, per the profile's `speculate`
+/// setting. `None` when speculation is off, the request will reuse a pinned
+/// decision (which needs no classifier to wait for), or the predicted tier
+/// lacks a capability the request needs.
+fn predicted_tier<'a>(
+    state: &RouterState,
+    config: &'a Config,
+    body: &Value,
+    profile_name: &str,
+    conversation: Option<&str>,
+) -> Option<&'a TierConfig> {
+    let profile = config.profiles.get(profile_name)?;
+    let predict = profile.speculate.as_deref()?;
+    if profile.routing_pin_secs > 0 {
+        let messages = body.get("messages").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        if state.routing_memory.pinned(profile_name, messages, conversation).is_some() {
+            return None;
+        }
+    }
+    let classifier_tier = || config.tiers.iter().find(|t| t.name == profile.classifier);
+    let predicted = match predict {
+        "recent" => state
+            .routing_memory
+            .likely_tier(profile_name)
+            .and_then(|name| config.tiers.iter().find(|t| t.name == name))
+            .or_else(classifier_tier),
+        "classifier" => classifier_tier(),
+        tier => config.resolve_tier(tier),
+    };
+    // Gating would move the request off a tier lacking a capability it needs.
+    let needed = required_capabilities(body);
+    predicted.filter(|tier| needed.iter().all(|&c| tier.capabilities.supports(c)))
+}
@@ -0,0 +1,212 @@
+//! Weighted traffic splits ([`SplitConfig`]).
+//!
+//! Once a routing mode has settled on a tier, a split on that tier hands the
+//! request to one of its variant tiers instead. The variant is picked by
+//! hashing the split key with the request's bucket key — its client or
+//! conversation ID, else the first user message — so a caller stays on one
+//! variant for as long as the weights don't move it. Variants that can't
+//! serve the request (see [`TierNeeds`]) are left out of the draw.
+
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+use tracing::debug;
+
+use crate::{
+    config::{BucketBy, Config, SplitConfig, TierConfig},
+    traffic::{RoutingDetail, SplitAssignment},
+};
+
+use super::{
+    capabilities::{required_capabilities, TierNeeds},
+    RequestMeta, RouterState,
+};
+
+/// A request's variant in the split on the tier it resolved to.
+pub(super) struct Assignment<'c> {
+    /// The split's key.
+    pub split: &'c str,
+    pub variant: &'c TierConfig,
+}
+
+impl Assignment<'_> {
+    /// Count the assignment and note it on `detail`.
+    pub fn record(&self, state: &RouterState, detail: &mut RoutingDetail) {
+        debug!(split = %self.split, variant = %self.variant.name, "traffic split assigned variant");
+        state.metrics.split_requests.inc(&[self.split, &self.variant.name]);
+        detail.split = Some(SplitAssignment { split: self.split.to_owned(), variant: self.variant.name.clone() });
+    }
+}
+
+/// The variant of the split on `tier` this request belongs to. `None` when
+/// no split applies to `tier`.
+pub(super) fn assign<'c>(config: &'c Config, tier: &TierConfig, body: &Value, meta: &RequestMeta) -> Option<Assignment<'c>> {
+    let (key, split) = config.split_for(&tier.name)?;
+    // Token estimation is costly; only a context window makes it matter.
+    let capped = std::iter::once(tier)
+        .chain(split.variants.iter().filter_map(|v| config.resolve_tier(&v.tier)))
+        .any(|t| t.max_context_tokens.is_some());
+    let needs = TierNeeds {
+        estimated_tokens: if capped { super::estimate_request_tokens(body) } else { 0 },
+        capabilities: required_capabilities(body),
+    };
+    // A variant must serve the request at least as well as `tier` does.
+    let tier_fits = needs.met_by(tier);
+    let point = bucket_point(key, &bucket_key(split, body, meta));
+    let variant = split.pick(point, |v| {
+        config.resolve_tier(&v.tier).is_some_and(|variant| !tier_fits || needs.met_by(variant))
+    })?;
+    Some(Assignment { split: key, variant: config.resolve_tier(&variant.tier)? })
+}
+
+/// [`assign`] and [record](Assignment::record) the request's variant;
+/// `tier` itself when no split applies.
+pub(super) fn apply<'c>(
+    state: &RouterState,
+    config: &'c Config,
+    tier: &'c TierConfig,
+    body: &Value,
+    meta: &RequestMeta,
+    detail: &mut RoutingDetail,
+) -> &'c TierConfig {
+    match assign(config, tier, body, meta) {
+        Some(assignment) => {
+            assignment.record(state, detail);
+            assignment.variant
+        }
+        None => tier,
+    }
+}
+
+/// The identity hashed into the split's buckets: the `bucket_by` one, else
+/// the other, else the text of the first user message.
+fn bucket_key(split: &SplitConfig, body: &Value, meta: &RequestMeta) -> String {
+    let (client, conversation) = (meta.client.as_deref(), meta.conversation_id.as_deref());
+    let key = match split.bucket_by {
+        BucketBy::Client => client.or(conversation),
+        BucketBy::Conversation => conversation.or(client),
+    };
+    key.map(str::to_owned).unwrap_or_else(|| {
+        let messages = body.get("messages").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        messages
+            .iter()
+            .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))
+            .and_then(super::extract_message_text)
+            .unwrap_or_default()
+    })
+}
+
+/// Where `bucket_key` falls in `[0, 1)` for the split `split_key` — uniform,
+/// and independent between splits.
+fn bucket_point(split_key: &str, bucket_key: &str) -> f64 {
+    let digest = Sha256::new().chain_update(split_key).chain_update([0]).chain_update(bucket_key).finalize();
+    let hash = u64::from_be_bytes(digest[..8].try_into().expect("sha256 digest is 32 bytes"));
+    // The top 53 bits fit an f64 exactly.
+    (hash >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn canary_config(bucket_by: &str, canary_weight: u32) -> Config {
+        toml::from_str(&format!(
+            r#"
+            [gateway]
+            client_port = 8080
+            admin_port  = 8081
+
+            [backends.ollama]
+            base_url = "http://localhost:11434"
+
+            [[tiers]]
+            name    = "local:fast"
+            backend = "ollama"
+            model   = "qwen2.5:1.5b"
+
+            [[tiers]]
+            name    = "cloud:economy"
+            backend = "ollama"
+            model   = "qwen2.5:7b"
+
+            [aliases]
+            "hint:fast" = "local:fast"
+
+            [profiles.default]
+            classifier    = "local:fast"
+            max_auto_tier = "cloud:economy"
+
+            [splits."hint:fast"]
+            bucket_by = "{bucket_by}"
+            variants  = [
+                {{ tier = "local:fast",    weight = 50 }},
+                {{ tier = "cloud:economy", weight = {canary_weight} }},
+            ]
+            "#
+        ))
+        .expect("split config should parse")
+    }
+
+    fn client(i: usize) -> RequestMeta {
+        RequestMeta { client: Some(format!("CLIENT_{i}")), ..Default::default() }
+    }
+
+    fn variant<'c>(config: &'c Config, body: &Value, meta: &RequestMeta) -> Option<&'c str> {
+        let tier = config.resolve_tier("local:fast").unwrap();
+        assign(config, tier, body, meta).map(|a| a.variant.name.as_str())
+    }
+
+    #[test]
+    fn bucket_points_are_stable_and_spread() {
+        assert_eq!(bucket_point("local:fast", "HA_KEY"), bucket_point("local:fast", "HA_KEY"));
+        assert_ne!(bucket_point("local:fast", "HA_KEY"), bucket_point("cloud:economy", "HA_KEY"));
+
+        let points: Vec<f64> = (0..1000).map(|i| bucket_point("local:fast", &format!("client-{i}"))).collect();
+        assert!(points.iter().all(|p| (0.0..1.0).contains(p)));
+        let low = points.iter().filter(|&&p| p < 0.1).count();
+        assert!((60..=140).contains(&low), "about 10% land below 0.1, got {low}");
+    }
+
+    #[test]
+    fn clients_stay_on_their_variant() {
+        let config = canary_config("client", 50);
+        let body = json!({ "messages": [{ "role": "user", "content": "turn on the lights" }] });
+        let assigned: Vec<_> = (0..32).map(|i| variant(&config, &body, &client(i)).unwrap()).collect();
+        for (i, tier) in assigned.iter().enumerate() {
+            assert_eq!(variant(&config, &body, &client(i)), Some(*tier), "a client stays on its variant");
+        }
+        assert!(assigned.contains(&"local:fast") && assigned.contains(&"cloud:economy"));
+        let split = assign(&config, config.resolve_tier("local:fast").unwrap(), &body, &client(0)).unwrap();
+        assert_eq!(split.split, "hint:fast");
+        assert!(assign(&config, config.resolve_tier("cloud:economy").unwrap(), &body, &client(0)).is_none());
+    }
+
+    #[test]
+    fn bucket_key_prefers_bucket_by_then_the_first_user_message() {
+        let body = json!({ "messages": [
+            { "role": "system", "content": "be brief" },
+            { "role": "user", "content": "turn on the lights" },
+            { "role": "user", "content": "and the fan" }
+        ] });
+        let both = RequestMeta { conversation_id: Some("conv-1".into()), ..client(1) };
+        let conversation = RequestMeta { conversation_id: Some("conv-1".into()), ..Default::default() };
+
+        let by_client = &canary_config("client", 50).splits["hint:fast"];
+        assert_eq!(bucket_key(by_client, &body, &both), "CLIENT_1");
+        assert_eq!(bucket_key(by_client, &body, &conversation), "conv-1");
+        assert_eq!(bucket_key(by_client, &body, &RequestMeta::default()), "turn on the lights");
+
+        let by_conversation = &canary_config("conversation", 50).splits["hint:fast"];
+        assert_eq!(bucket_key(by_conversation, &body, &both), "conv-1");
+        assert_eq!(bucket_key(by_conversation, &body, &client(1)), "CLIENT_1");
+        assert_eq!(bucket_key(by_conversation, &json!({ "messages": [] }), &RequestMeta::default()), "");
+    }
+
+    #[test]
+    fn zero_weight_variants_are_never_assigned() {
+        let config = canary_config("client", 0);
+        let body = json!({ "messages": [{ "role": "user", "content": "turn on the lights" }] });
+        assert!((0..32).all(|i| variant(&config, &body, &client(i)) == Some("local:fast")));
+    }
+}
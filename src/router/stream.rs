@@ -0,0 +1,464 @@
+//! Streaming response accounting.
+//!
+//! [`RecordedStream`] wraps the backend [`SseStream`] returned by
+//! [`super::route_stream`]. It passes every chunk through untouched while
+//! noting the time to the first chunk and scanning for token usage:
+//!
+//! - OpenAI-style SSE `data:` chunks carrying a `usage` object (sent by
+//!   OpenAI-compatible backends when the client asks for
+//!   `stream_options.include_usage`, and by the Anthropic translator on its
+//!   final chunk).
+//! - Ollama native NDJSON lines with `prompt_eval_count` / `eval_count`.
+//!
+//! The [`TrafficEntry`] is finalised and pushed to the traffic log when the
+//! wrapper is dropped — after the last chunk, on a mid-stream error, or when
+//! the client disconnects — so `latency_ms` covers the whole stream. The
+//! request's `lmg_requests_total` outcome is counted at the same point.
+//!
+//! When the profile caches responses, the streamed text is also assembled
+//! into a `chat.completion` and stored once the stream completes cleanly.
+//! Streams carrying tool calls are not cached.
+
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_util::Stream;
+use serde_json::{json, Value};
+
+use crate::{
+    backends::SseStream,
+    cache::CacheFill,
+    config::{BackendConfig, TierConfig},
+    metrics::Metrics,
+    telemetry::{self, Operation},
+    traffic::{TrafficEntry, TrafficLog},
+};
+
+/// Token counts found in the stream so far.
+#[derive(Debug, Default)]
+struct UsageScanner {
+    /// Bytes after the last newline, waiting for the rest of the line.
+    /// Kept raw so a character split across reads decodes whole.
+    partial: Vec<u8>,
+    prompt: Option<u64>,
+    completion: Option<u64>,
+    total: Option<u64>,
+    /// Model named by the chunk carrying usage.
+    model: Option<String>,
+    /// Streamed message, assembled when the response is to be cached.
+    capture: Option<Capture>,
+}
+
+/// Completion text gathered from OpenAI deltas or Ollama NDJSON messages.
+#[derive(Debug, Default)]
+struct Capture {
+    model: Option<Value>,
+    content: String,
+    finish_reason: Option<String>,
+    /// Tool calls or an unrecognised chunk were seen; don't cache.
+    unusable: bool,
+}
+
+impl Capture {
+    fn absorb(&mut self, value: &Value) {
+        if self.model.is_none() {
+            self.model = value.get("model").cloned();
+        }
+        let (message, finish) = match value.pointer("/choices/0") {
+            Some(choice) => (choice.get("delta"), choice.get("finish_reason")),
+            None => (value.get("message"), value.get("done_reason")),
+        };
+        if let Some(message) = message {
+            if message.get("tool_calls").is_some_and(|c| !c.is_null()) {
+                self.unusable = true;
+            }
+            if let Some(text) = message.get("content").and_then(Value::as_str) {
+                self.content.push_str(text);
+            }
+        }
+        if let Some(reason) = finish.and_then(Value::as_str) {
+            self.finish_reason = Some(reason.to_owned());
+        }
+    }
+
+    fn into_completion(self, usage: &UsageScanner) -> Option<Value> {
+        if self.unusable || self.content.is_empty() {
+            return None;
+        }
+        let mut completion = json!({
+            "id": "chatcmpl-cached",
+            "object": "chat.completion",
+            "created": chrono::Utc::now().timestamp(),
+            "model": self.model.unwrap_or(Value::Null),
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": self.content },
+                "finish_reason": self.finish_reason.as_deref().unwrap_or("stop"),
+            }],
+        });
+        if let (Some(prompt), Some(completion_tokens)) = (usage.prompt, usage.completion) {
+            completion["usage"] = json!({
+                "prompt_tokens": prompt,
+                "completion_tokens": completion_tokens,
+                "total_tokens": usage.total.unwrap_or(prompt + completion_tokens),
+            });
+        }
+        Some(completion)
+    }
+}
+
+impl UsageScanner {
+    fn feed(&mut self, chunk: &[u8]) {
+        self.partial.extend_from_slice(chunk);
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return;
+        };
+        let lines: Vec<u8> = self.partial.drain(..=end).collect();
+        for line in lines.split(|&b| b == b'\n') {
+            self.scan_line(String::from_utf8_lossy(line).trim());
+        }
+    }
+
+    fn scan_line(&mut self, line: &str) {
+        let payload = line.strip_prefix("data:").map(str::trim_start).unwrap_or(line);
+        // Without a capture, only parse the few lines that can carry usage.
+        let has_usage = payload.contains("usage") || payload.contains("eval_count");
+        if !payload.starts_with('{') || !(has_usage || self.capture.is_some()) {
+            return;
+        }
+        let Ok(value) = serde_json::from_str::<Value>(payload) else {
+            return;
+        };
+        if let Some(capture) = self.capture.as_mut() {
+            capture.absorb(&value);
+        }
+        if !has_usage {
+            return;
+        }
+        let (prompt, completion) = telemetry::usage_tokens(&value);
+        self.prompt = prompt.or(self.prompt);
+        self.completion = completion.or(self.completion);
+        self.total = value.pointer("/usage/total_tokens").and_then(Value::as_u64).or(self.total);
+        if let Some(model) = value.get("model").and_then(Value::as_str) {
+            self.model = Some(model.to_owned());
+        }
+    }
+}
+
+/// Shared handles needed to finalise a streamed request.
+pub(super) struct StreamContext {
+    pub traffic: Arc<TrafficLog>,
+    pub metrics: Arc<Metrics>,
+    pub profile: String,
+    /// When routing began (for `lmg_request_duration_seconds`).
+    pub route_started: Instant,
+    /// When the backend call began (for the entry's latency and TTFT).
+    pub backend_started: Instant,
+    /// The backend call to finish with the stream's usage.
+    pub backend_call: Option<BackendCall>,
+    /// Where to store the assembled completion, when the profile caches.
+    pub cache_fill: Option<CacheFill>,
+}
+
+/// The `backend_call` span of a stream and what its GenAI metrics are keyed by.
+pub(super) struct BackendCall {
+    pub span: tracing::Span,
+    pub tier: TierConfig,
+    pub backend: BackendConfig,
+}
+
+/// An [`SseStream`] that records its traffic entry when dropped.
+pub(super) struct RecordedStream {
+    inner: SseStream,
+    ctx: StreamContext,
+    entry: Option<TrafficEntry>,
+    first_chunk: Option<Instant>,
+    usage: UsageScanner,
+    finished: bool,
+}
+
+impl RecordedStream {
+    pub(super) fn new(inner: SseStream, entry: TrafficEntry, ctx: StreamContext) -> Self {
+        let usage = UsageScanner {
+            capture: ctx.cache_fill.as_ref().map(|_| Capture::default()),
+            ..UsageScanner::default()
+        };
+        Self {
+            inner,
+            ctx,
+            entry: Some(entry),
+            first_chunk: None,
+            usage,
+            finished: false,
+        }
+    }
+}
+
+impl Stream for RecordedStream {
+    type Item = anyhow::Result<bytes::Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => return Poll::Pending,
+        };
+        match &item {
+            Some(Ok(chunk)) => {
+                if this.first_chunk.is_none() {
+                    let now = Instant::now();
+                    this.first_chunk = Some(now);
+                    if let Some(entry) = &this.entry {
+                        this.ctx
+                            .metrics
+                            .time_to_first_token
+                            .observe(&[&entry.tier], now - this.ctx.route_started);
+                    }
+                }
+                this.usage.feed(chunk);
+            }
+            Some(Err(e)) => {
+                this.finished = true;
+                if let Some(entry) = this.entry.as_mut() {
+                    entry.success = false;
+                    entry.error = Some(format!("{e:#}"));
+                }
+            }
+            None => this.finished = true,
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for RecordedStream {
+    fn drop(&mut self) {
+        let Some(entry) = self.entry.take() else {
+            return;
+        };
+        // A final line without a trailing newline still counts.
+        let rest = std::mem::take(&mut self.usage.partial);
+        self.usage.scan_line(String::from_utf8_lossy(&rest).trim());
+
+        let elapsed = self.ctx.backend_started.elapsed();
+        // Rate generation after the first chunk, so queueing and prompt
+        // processing don't drag down tokens/sec.
+        let generation = self
+            .first_chunk
+            .map_or(Duration::ZERO, |first| first.elapsed());
+        let mut entry = entry.with_usage(
+            self.usage.prompt,
+            self.usage.completion,
+            self.usage.total,
+            generation,
+        );
+        entry.latency_ms = elapsed.as_millis() as u64;
+        entry.ttft_ms = self
+            .first_chunk
+            .map(|first| (first - self.ctx.backend_started).as_millis() as u64);
+        entry.client_disconnected = !self.finished;
+
+        if let (Some(fill), Some(capture)) = (self.ctx.cache_fill.take(), self.usage.capture.take()) {
+            if self.finished && entry.success {
+                if let Some(completion) = capture.into_completion(&self.usage) {
+                    fill.store(&completion);
+                }
+            }
+        }
+
+        if let Some(call) = self.ctx.backend_call.take() {
+            let failure = (!entry.success)
+                .then(|| anyhow::anyhow!("{}", entry.error.as_deref().unwrap_or("stream failed")));
+            let usage = telemetry::CallUsage {
+                model: self.usage.model.as_deref(),
+                input_tokens: self.usage.prompt,
+                output_tokens: self.usage.completion,
+            };
+            let result = failure.as_ref().map_or(Ok(usage), Err);
+            let (span, tier, backend) = (&call.span, &call.tier, &call.backend);
+            telemetry::record_backend_result(span, Operation::Chat, tier, backend, elapsed, result);
+        }
+
+        // The request counts once the stream ends; one the client cut short
+        // never delivered a full response.
+        let outcome = if self.finished && entry.success { "success" } else { "error" };
+        let metrics = &self.ctx.metrics;
+        metrics.requests.inc(&[&self.ctx.profile, &entry.tier, &entry.backend, outcome]);
+        metrics.add_tokens(&entry.tier, self.usage.prompt, self.usage.completion);
+        metrics
+            .request_duration
+            .observe(&[&self.ctx.profile, &entry.tier], self.ctx.route_started.elapsed());
+        self.ctx.traffic.push(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_util::StreamExt as _;
+
+    use super::*;
+
+    fn context(traffic: &Arc<TrafficLog>) -> StreamContext {
+        StreamContext {
+            traffic: Arc::clone(traffic),
+            metrics: Arc::new(Metrics::default()),
+            profile: "default".into(),
+            route_started: Instant::now(),
+            backend_started: Instant::now(),
+            backend_call: None,
+            cache_fill: None,
+        }
+    }
+
+    fn recorded(traffic: &Arc<TrafficLog>, chunks: Vec<&'static str>) -> RecordedStream {
+        recorded_bytes(chunks.into_iter().map(str::as_bytes).collect(), context(traffic))
+    }
+
+    fn recorded_bytes(chunks: Vec<&'static [u8]>, ctx: StreamContext) -> RecordedStream {
+        let inner: SseStream =
+            Box::pin(futures_util::stream::iter(chunks.into_iter().map(|c| Ok(Bytes::from_static(c)))));
+        let entry = TrafficEntry::new("local:fast".into(), "mock".into(), 3, true);
+        RecordedStream::new(inner, entry, ctx)
+    }
+
+    #[tokio::test]
+    async fn openai_usage_chunk_split_across_reads_is_recorded() {
+        let traffic = Arc::new(TrafficLog::new(10));
+        let mut stream = recorded(
+            &traffic,
+            vec![
+                "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tok",
+                "ens\":9,\"completion_tokens\":4,\"total_tokens\":13}}\n\ndata: [DONE]\n\n",
+            ],
+        );
+        while stream.next().await.is_some() {}
+        assert!(traffic.recent(1).is_empty(), "entry is recorded on drop");
+        let metrics = Arc::clone(&stream.ctx.metrics);
+        assert_eq!(metrics.requests.get(&["default", "local:fast", "mock", "success"]), 0);
+        drop(stream);
+        assert_eq!(metrics.requests.get(&["default", "local:fast", "mock", "success"]), 1);
+
+        let entry = &traffic.recent(1)[0];
+        assert_eq!(entry.prompt_tokens, Some(9));
+        assert_eq!(entry.completion_tokens, Some(4));
+        assert_eq!(entry.total_tokens, Some(13));
+        assert!(entry.ttft_ms.is_some());
+        assert!(!entry.client_disconnected);
+    }
+
+    #[tokio::test]
+    async fn ollama_native_final_line_is_recorded() {
+        let traffic = Arc::new(TrafficLog::new(10));
+        let mut stream = recorded(
+            &traffic,
+            vec![
+                "{\"message\":{\"content\":\"hi\"},\"done\":false}\n",
+                "{\"done\":true,\"prompt_eval_count\":20,\"eval_count\":6}",
+            ],
+        );
+        while stream.next().await.is_some() {}
+        drop(stream);
+
+        let entry = &traffic.recent(1)[0];
+        assert_eq!(entry.prompt_tokens, Some(20));
+        assert_eq!(entry.completion_tokens, Some(6));
+        assert_eq!(entry.total_tokens, Some(26));
+    }
+
+    #[tokio::test]
+    async fn client_disconnect_is_flagged() {
+        let traffic = Arc::new(TrafficLog::new(10));
+        let mut stream = recorded(&traffic, vec!["data: {}\n\n", "data: {}\n\n"]);
+        stream.next().await;
+        let metrics = Arc::clone(&stream.ctx.metrics);
+        drop(stream);
+
+        assert_eq!(metrics.requests.get(&["default", "local:fast", "mock", "error"]), 1);
+        let entry = &traffic.recent(1)[0];
+        assert!(entry.client_disconnected);
+        assert!(entry.success);
+        assert_eq!(entry.total_tokens, None);
+    }
+
+    #[tokio::test]
+    async fn character_split_across_reads_is_cached_whole() {
+        use crate::cache::{CacheDirective, CacheKey, CacheScope, ResponseCache};
+        use crate::config::CacheConfig;
+
+        let traffic = Arc::new(TrafficLog::new(10));
+        let config = CacheConfig { ttl_secs: 60, max_entries: 10, semantic: None };
+        let scope = CacheScope::new(Arc::new(ResponseCache::default()), "default", config, CacheDirective::Default);
+        let key = CacheKey::new("local:fast", &json!({ "messages": [] }));
+        let ctx = StreamContext { cache_fill: Some(CacheFill::new(scope.clone(), key)), ..context(&traffic) };
+        let line = "data: {\"choices\":[{\"delta\":{\"content\":\"lights on \u{1f4a1}\"}}]}\n\n".as_bytes();
+        let split = line.iter().position(|&b| b == 0xf0).unwrap() + 2;
+        let (head, tail) = line.split_at(split);
+        let mut stream = recorded_bytes(vec![head, tail, b"data: [DONE]\n\n"], ctx);
+        while stream.next().await.is_some() {}
+        drop(stream);
+
+        let cached = scope.lookup(&key).expect("completion cached");
+        assert_eq!(cached["choices"][0]["message"]["content"], "lights on \u{1f4a1}");
+    }
+
+    /// Fields recorded on spans after they were created, as `name=value`.
+    #[derive(Clone, Default)]
+    struct RecordedFields(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for RecordedFields {
+        fn on_record(
+            &self,
+            _: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Visit<'a>(&'a mut Vec<String>);
+            impl tracing::field::Visit for Visit<'_> {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                    self.0.push(format!("{}={value:?}", field.name()));
+                }
+            }
+            values.record(&mut Visit(&mut self.0.lock().unwrap()));
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_usage_is_recorded_on_the_backend_span() {
+        use tracing_subscriber::layer::SubscriberExt as _;
+
+        let fields = RecordedFields::default();
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry().with(fields.clone()));
+        let tier: TierConfig = toml::from_str("name = \"local:fast\"\nbackend = \"mock\"\nmodel = \"qwen\"").unwrap();
+        let backend: BackendConfig = toml::from_str("base_url = \"http://localhost:11434\"").unwrap();
+        let traffic = Arc::new(TrafficLog::new(10));
+        let ctx = StreamContext {
+            backend_call: Some(BackendCall {
+                span: telemetry::backend_span(Operation::Chat, &tier, &backend),
+                tier,
+                backend,
+            }),
+            ..context(&traffic)
+        };
+        let usage =
+            b"data: {\"model\":\"qwen\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":4}}\n\n";
+        let mut stream = recorded_bytes(vec![usage, b"data: [DONE]\n\n"], ctx);
+        while stream.next().await.is_some() {}
+        drop(stream);
+
+        let fields = fields.0.lock().unwrap();
+        let expected = [
+            "otel.status_code=\"OK\"",
+            "gen_ai.response.model=\"qwen\"",
+            "gen_ai.usage.input_tokens=9",
+            "gen_ai.usage.output_tokens=4",
+        ];
+        for expected in expected {
+            assert!(fields.iter().any(|f| f == expected), "{expected} missing from {fields:?}");
+        }
+    }
+}
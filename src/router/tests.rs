@@ -1,7 +1,8 @@
 use super::*;
 use serde_json::json;
+use std::collections::HashMap;
 
-use self::classify::{parse_classification, parse_classification_label, resolve_tier_by_label};
+use self::classify::{label_is_known, parse_classification, parse_classification_label, resolve_tier_by_label};
 use self::modes::is_sufficient;
 
 // -----------------------------------------------------------------------
@@ -79,17 +80,7 @@ async fn mock_state(server: &MockServer, mode: RoutingMode) -> RouterState {
             client_port: 8080,
             admin_port: 8081,
             traffic_log_capacity: 100,
-            log_level: None,
-            rate_limit_rpm: None,
-            admin_token_env: None,
-            max_retries: None,
-            retry_delay_ms: None,
-            health_window: None,
-            health_error_threshold: None,
-            public_profile: None,
-            request_timeout_ms: None,
-            profile_dir: None,
-            traffic_log_debug: false,
+            ..Default::default()
         },
         backends: {
             let mut m = std::collections::HashMap::new();
@@ -113,6 +104,7 @@ async fn mock_state(server: &MockServer, mode: RoutingMode) -> RouterState {
                 model: "fast-model".into(),
                 think: None,
                 max_context_tokens: None,
+                capabilities: Default::default(),
             },
             TierConfig {
                 name: "cloud:economy".into(),
@@ -120,6 +112,7 @@ async fn mock_state(server: &MockServer, mode: RoutingMode) -> RouterState {
                 model: "economy-model".into(),
                 think: None,
                 max_context_tokens: None,
+                capabilities: Default::default(),
             },
         ],
         aliases: {
@@ -147,6 +140,9 @@ async fn mock_state(server: &MockServer, mode: RoutingMode) -> RouterState {
             m
         },
         clients: vec![],
+        splits: Default::default(),
+        templates: Default::default(),
+        admin_overlay: Default::default(),
     };
     RouterState::new(Arc::new(config), std::path::PathBuf::default(), Arc::new(TrafficLog::new(100)))
 }
@@ -171,7 +167,7 @@ async fn dispatch_routes_to_resolved_tier_and_returns_response() {
     let state = mock_state(&server, RoutingMode::Dispatch).await;
     let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": "hi"}] });
 
-    let result = route(&state, body, None, None, 0, false, false).await;
+    let result = route(&state, body, None, &RequestMeta::default(), false, false).await;
     assert!(result.is_ok(), "dispatch failed: {:?}", result.err());
 
     let (resp, entry) = result.unwrap();
@@ -198,17 +194,7 @@ async fn dispatch_bumps_tier_when_context_window_exceeded() {
             client_port: 8080,
             admin_port: 8081,
             traffic_log_capacity: 100,
-            log_level: None,
-            rate_limit_rpm: None,
-            admin_token_env: None,
-            max_retries: None,
-            retry_delay_ms: None,
-            health_window: None,
-            health_error_threshold: None,
-            public_profile: None,
-            request_timeout_ms: None,
-            profile_dir: None,
-            traffic_log_debug: false,
+            ..Default::default()
         },
         backends: {
             let mut m = std::collections::HashMap::new();
@@ -232,6 +218,7 @@ async fn dispatch_bumps_tier_when_context_window_exceeded() {
                 model: "tiny-model".into(),
                 think: None,
                 max_context_tokens: Some(10), // Very small — will overflow
+                capabilities: Default::default(),
             },
             TierConfig {
                 name: "big".into(),
@@ -239,6 +226,7 @@ async fn dispatch_bumps_tier_when_context_window_exceeded() {
                 model: "big-model".into(),
                 think: None,
                 max_context_tokens: None, // No limit
+                capabilities: Default::default(),
             },
         ],
         aliases: {
@@ -266,6 +254,9 @@ async fn dispatch_bumps_tier_when_context_window_exceeded() {
             m
         },
         clients: vec![],
+        splits: Default::default(),
+        templates: Default::default(),
+        admin_overlay: Default::default(),
     };
     let state = RouterState::new(
         Arc::new(config),
@@ -279,9 +270,198 @@ async fn dispatch_bumps_tier_when_context_window_exceeded() {
         "messages": [{"role": "user", "content": "This message is long enough to exceed the tiny tier context window limit easily."}]
     });
 
-    let (_, entry) = route(&state, body, None, None, 0, false, false).await.unwrap();
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
     // Should have been bumped from "tiny" to "big"
     assert_eq!(entry.tier, "big", "expected context-window gating to bump from tiny to big");
+    let bumps = &entry.routing.expect("routing detail").context_bumps;
+    assert_eq!((bumps[0].from.as_str(), bumps[0].to.as_str()), ("tiny", "big"));
+}
+
+#[tokio::test]
+async fn classify_records_tags_and_rules_evaluated() {
+    use crate::config::RuleConfig;
+    use crate::traffic::RuleOutcome;
+
+    let server = MockServer::start().await;
+    // The classifier and the dispatched tier share the mock.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast domain=home")))
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let rule = |domain: &str, route_to: &str, priority| RuleConfig {
+        when: [("domain".to_string(), domain.to_string())].into(),
+        route_to: route_to.into(),
+        priority,
+        request: Default::default(),
+    };
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.rules = vec![rule("office", "local:fast", 10), rule("home", "cloud:economy", 0)];
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": "lights on"}] });
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+
+    assert_eq!(entry.tier, "cloud:economy");
+    let routing = entry.routing.expect("routing detail");
+    assert_eq!(routing.tags["default"]["domain"], "home");
+    let outcomes: Vec<_> = routing.rules.iter().map(|r| (r.route_to.as_str(), r.outcome)).collect();
+    assert_eq!(outcomes, [("local:fast", RuleOutcome::NoMatch), ("cloud:economy", RuleOutcome::Matched)]);
+}
+
+#[tokio::test]
+async fn explain_traces_classification_without_calling_the_tier() {
+    use crate::config::RuleConfig;
+    use crate::traffic::RuleOutcome;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast domain=home")))
+        .expect(1) // the classifier only
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.rules = vec![
+        RuleConfig { when: [("domain".into(), "office".into())].into(), route_to: "local:fast".into(), priority: 10, request: Default::default() },
+        RuleConfig { when: [("domain".into(), "home".into())].into(), route_to: "cloud:economy".into(), priority: 0, request: Default::default() },
+    ];
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": "lights on"}] });
+    let trace = explain(&state, body, None, &RequestMeta::default(), false).await.unwrap();
+
+    assert_eq!(trace.tier.as_deref(), Some("cloud:economy"));
+    assert_eq!(trace.requested_tier.as_deref(), Some("local:fast"));
+    let step = &trace.classification[0];
+    assert_eq!(step.step.input.as_deref(), Some("lights on"));
+    assert_eq!(step.step.output.as_deref(), Some("tier=fast domain=home"));
+    assert_eq!(step.tags["domain"], "home");
+    assert_eq!(step.rules.len(), 2);
+    assert_eq!(step.rules[0].outcome, RuleOutcome::NoMatch);
+    assert_eq!(step.rules[0].conditions[0].actual.as_deref(), Some("home"));
+    assert!(!step.rules[0].conditions[0].met);
+    assert_eq!(step.rules[1].outcome, RuleOutcome::Matched);
+    assert!(state.traffic.recent(10).is_empty());
+}
+
+#[tokio::test]
+async fn explain_reports_dispatch_context_bump() {
+    let server = MockServer::start().await;
+    let state = mock_state(&server, RoutingMode::Dispatch).await;
+    let mut config = (*state.config()).clone();
+    config.tiers[0].max_context_tokens = Some(10);
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let long = "word ".repeat(200);
+    let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": long}] });
+    let trace = explain(&state, body, None, &RequestMeta::default(), false).await.unwrap();
+
+    assert_eq!(trace.requested_tier.as_deref(), Some("local:fast"));
+    assert_eq!(trace.tier.as_deref(), Some("cloud:economy"));
+    assert_eq!(trace.context_bumps[0].from, "local:fast");
+    assert!(trace.classification.is_empty());
+}
+
+#[tokio::test]
+async fn dispatch_bumps_image_requests_off_text_only_tiers() {
+    use crate::config::Capability;
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({ "model": "economy-model" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("A cat on a windowsill.")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Dispatch).await;
+    let mut config = (*state.config()).clone();
+    config.tiers[0].capabilities.vision = Some(false);
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({
+        "model": "hint:fast",
+        "messages": [{ "role": "user", "content": [
+            { "type": "text", "text": "What is in this picture?" },
+            { "type": "image_url", "image_url": { "url": "data:image/png;base64,AAAA" } }
+        ] }]
+    });
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy");
+    let bumps = &entry.routing.expect("routing detail").context_bumps;
+    assert_eq!((bumps[0].from.as_str(), bumps[0].to.as_str()), ("local:fast", "cloud:economy"));
+    assert_eq!(bumps[0].missing_capabilities, [Capability::Vision]);
+}
+
+#[tokio::test]
+async fn escalate_stream_skips_rungs_the_ladder_rules_out() {
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({ "model": "economy-model", "stream": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("data: [DONE]\n\n", "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Escalate).await;
+    let mut config = (*state.config()).clone();
+    config.tiers[0].capabilities.tools = Some(false);
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({
+        "model": "hint:fast",
+        "stream": true,
+        "messages": [{ "role": "user", "content": "look up the weather" }],
+        "tools": [{ "type": "function", "function": { "name": "weather" } }]
+    });
+    let (_, entry, _) = route_stream(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy");
+    let skipped = &entry.routing.expect("routing detail").skipped_tiers;
+    assert_eq!((skipped[0].tier.as_str(), skipped[0].reason.as_str()), ("local:fast", "missing_capability"));
+}
+
+#[tokio::test]
+async fn capability_gating_applies_in_escalate_and_classify_modes() {
+    use crate::config::Capability;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("fast")))
+        .mount(&server)
+        .await;
+    let body = json!({
+        "messages": [{ "role": "user", "content": "look up the weather" }],
+        "tools": [{ "type": "function", "function": { "name": "weather" } }]
+    });
+
+    let state = mock_state(&server, RoutingMode::Escalate).await;
+    let mut config = (*state.config()).clone();
+    config.tiers[0].capabilities.tools = Some(false);
+    state.replace_config(Arc::new(config.clone()), crate::config::ConfigSource::AdminApi);
+    let trace = explain(&state, body.clone(), None, &RequestMeta::default(), false).await.unwrap();
+    assert_eq!(trace.required_capabilities, [Capability::Tools]);
+    assert_eq!(trace.skipped_tiers[0].reason, "missing_capability");
+    assert_eq!(trace.tier.as_deref(), Some("cloud:economy"));
+
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.mode = RoutingMode::Classify;
+    profile.classifier_timeout_ms = 5_000;
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+    let trace = explain(&state, body, None, &RequestMeta::default(), false).await.unwrap();
+    assert_eq!(trace.class_label.as_deref(), Some("fast"));
+    assert_eq!(trace.tier.as_deref(), Some("cloud:economy"));
+    assert_eq!(trace.context_bumps[0].missing_capabilities, [Capability::Tools]);
 }
 
 #[tokio::test]
@@ -298,7 +478,7 @@ async fn dispatch_resolves_direct_tier_name_without_alias() {
     let state = mock_state(&server, RoutingMode::Dispatch).await;
     let body = json!({ "model": "cloud:economy", "messages": [] });
 
-    let (_, entry) = route(&state, body, None, None, 0, false, false).await.unwrap();
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
     assert_eq!(entry.tier, "cloud:economy");
 }
 
@@ -317,7 +497,7 @@ async fn escalate_returns_first_sufficient_response() {
     let state = mock_state(&server, RoutingMode::Escalate).await;
     let body = json!({ "model": "hint:fast", "messages": [] });
 
-    let (_, entry) = route(&state, body, None, None, 0, false, false).await.unwrap();
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
     // Should have stopped at the first (cheapest) tier
     assert_eq!(entry.tier, "local:fast");
 }
@@ -336,14 +516,38 @@ async fn route_records_entry_in_traffic_log() {
     let state = mock_state(&server, RoutingMode::Dispatch).await;
     let body = json!({ "model": "local:fast", "messages": [] });
 
-    route(&state, body, None, None, 0, false, false).await.unwrap();
+    route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
 
-    let entries = state.traffic.recent(10).await;
+    let entries = state.traffic.recent(10);
     assert_eq!(entries.len(), 1);
     assert_eq!(entries[0].tier, "local:fast");
     assert!(entries[0].success);
 }
 
+#[tokio::test]
+async fn route_updates_lifetime_metrics() {
+    let server = MockServer::start().await;
+    let mut response = long_response("Lifetime counters should see every routed request and its tokens.");
+    response["usage"] = json!({ "prompt_tokens": 12, "completion_tokens": 30 });
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(response))
+        .mount(&server)
+        .await;
+
+    let state = mock_state(&server, RoutingMode::Dispatch).await;
+    for _ in 0..2 {
+        let body = json!({ "model": "local:fast", "messages": [] });
+        route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    }
+
+    let metrics = &state.metrics;
+    assert_eq!(metrics.requests.get(&["default", "local:fast", "mock", "success"]), 2);
+    assert_eq!(metrics.request_duration.count(&["default", "local:fast"]), 2);
+    assert_eq!(metrics.tokens.get(&["local:fast", "input"]), 24);
+    assert_eq!(metrics.tokens.get(&["local:fast", "output"]), 60);
+}
+
 #[tokio::test]
 async fn route_errors_when_no_profile_is_configured() {
     let state = RouterState::new(
@@ -352,29 +556,22 @@ async fn route_errors_when_no_profile_is_configured() {
                 client_port: 8080,
                 admin_port: 8081,
                 traffic_log_capacity: 10,
-                log_level: None,
-                rate_limit_rpm: None,
-                admin_token_env: None,
-                max_retries: None,
-                retry_delay_ms: None,
-                health_window: None,
-                health_error_threshold: None,
-                public_profile: None,
-                request_timeout_ms: None,
-                profile_dir: None,
-                traffic_log_debug: false,
+                ..Default::default()
             },
             backends: std::collections::HashMap::new(),
             tiers: vec![],
             aliases: std::collections::HashMap::new(),
             profiles: std::collections::HashMap::new(), // no default
             clients: vec![],
+            splits: Default::default(),
+            templates: Default::default(),
+            admin_overlay: Default::default(),
         }),
         std::path::PathBuf::default(),
         Arc::new(TrafficLog::new(10)),
     );
 
-    let result = route(&state, json!({}), None, None, 0, false, false).await;
+    let result = route(&state, json!({}), None, &RequestMeta::default(), false, false).await;
     assert!(result.is_err());
     assert!(result
         .unwrap_err()
@@ -397,7 +594,7 @@ async fn dispatch_falls_back_to_classifier_tier_on_unknown_model() {
     // "totally:unknown" exists in neither aliases nor tiers — should fall back to classifier
     let body = json!({ "model": "totally:unknown", "messages": [] });
 
-    let (_, entry) = route(&state, body, None, None, 0, false, false).await.unwrap();
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
     // classifier is "local:fast"
     assert_eq!(entry.tier, "local:fast");
 }
@@ -480,6 +677,7 @@ fn make_tiers(names: &[&str]) -> Vec<TierConfig> {
             model: "m".into(),
             think: None,
             max_context_tokens: None,
+            capabilities: Default::default(),
         })
         .collect()
 }
@@ -514,6 +712,25 @@ fn resolve_unknown_label_falls_back_to_middle_tier() {
     }
 }
 
+#[test]
+fn known_labels_name_a_tier_or_match_a_rule() {
+    use crate::config::RuleConfig;
+
+    let tiers = make_tiers(&["local:instant", "local:fast"]);
+    let rules = vec![RuleConfig {
+        when: [("intent".to_owned(), "command".to_owned())].into(),
+        route_to: "local:instant".into(),
+        priority: 0,
+        request: Default::default(),
+    }];
+    let no_tags = Default::default();
+    assert!(label_is_known("fast", &no_tags, &tiers, &rules));
+    assert!(label_is_known("local:instant", &no_tags, &tiers, &rules));
+    assert!(!label_is_known("banana", &no_tags, &tiers, &rules));
+    let tags = [("intent".to_owned(), "Command".to_owned())].into();
+    assert!(label_is_known("banana", &tags, &tiers, &rules), "rule tags make the label routable");
+}
+
 #[test]
 fn resolve_single_tier_always_returns_it() {
     let tiers = make_tiers(&["only"]);
@@ -702,9 +919,9 @@ fn estimate_tokens_empty_body() {
 fn find_min_tier_skips_small_context() {
     use crate::config::TierConfig;
     let tiers = vec![
-        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(4096) },
-        TierConfig { name: "medium".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(32768) },
-        TierConfig { name: "large".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None },
+        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(4096), capabilities: Default::default() },
+        TierConfig { name: "medium".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(32768), capabilities: Default::default() },
+        TierConfig { name: "large".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None, capabilities: Default::default() },
     ];
     // 5000 tokens exceeds small (4096) but fits medium (32768)
     assert_eq!(find_min_tier_for_tokens(&tiers, 5000, 0), 1);
@@ -714,8 +931,8 @@ fn find_min_tier_skips_small_context() {
 fn find_min_tier_fits_first() {
     use crate::config::TierConfig;
     let tiers = vec![
-        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(8192) },
-        TierConfig { name: "large".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None },
+        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(8192), capabilities: Default::default() },
+        TierConfig { name: "large".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None, capabilities: Default::default() },
     ];
     // 2000 tokens fits in small (8192)
     assert_eq!(find_min_tier_for_tokens(&tiers, 2000, 0), 0);
@@ -725,7 +942,7 @@ fn find_min_tier_fits_first() {
 fn find_min_tier_uncapped_always_fits() {
     use crate::config::TierConfig;
     let tiers = vec![
-        TierConfig { name: "uncapped".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None },
+        TierConfig { name: "uncapped".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None, capabilities: Default::default() },
     ];
     assert_eq!(find_min_tier_for_tokens(&tiers, 999999, 0), 0);
 }
@@ -734,8 +951,8 @@ fn find_min_tier_uncapped_always_fits() {
 fn find_min_tier_all_too_small_falls_back_to_last() {
     use crate::config::TierConfig;
     let tiers = vec![
-        TierConfig { name: "tiny".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(1024) },
-        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(2048) },
+        TierConfig { name: "tiny".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(1024), capabilities: Default::default() },
+        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(2048), capabilities: Default::default() },
     ];
     // 10000 tokens exceeds both — falls back to last
     assert_eq!(find_min_tier_for_tokens(&tiers, 10000, 0), 1);
@@ -745,9 +962,9 @@ fn find_min_tier_all_too_small_falls_back_to_last() {
 fn find_min_tier_respects_start_idx() {
     use crate::config::TierConfig;
     let tiers = vec![
-        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(8192) },
-        TierConfig { name: "medium".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(32768) },
-        TierConfig { name: "large".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None },
+        TierConfig { name: "small".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(8192), capabilities: Default::default() },
+        TierConfig { name: "medium".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: Some(32768), capabilities: Default::default() },
+        TierConfig { name: "large".into(), backend: "b".into(), model: "m".into(), think: None, max_context_tokens: None, capabilities: Default::default() },
     ];
     // start_idx=1 means we skip "small" entirely
     assert_eq!(find_min_tier_for_tokens(&tiers, 100, 1), 1);
@@ -774,17 +991,8 @@ async fn debug_traffic_captures_request_body_in_entry() {
             client_port: 8080,
             admin_port: 8081,
             traffic_log_capacity: 100,
-            log_level: None,
-            rate_limit_rpm: None,
-            admin_token_env: None,
-            max_retries: None,
-            retry_delay_ms: None,
-            health_window: None,
-            health_error_threshold: None,
-            public_profile: None,
-            request_timeout_ms: None,
-            profile_dir: None,
             traffic_log_debug: true,
+            ..Default::default()
         },
         backends: {
             let mut m = std::collections::HashMap::new();
@@ -807,6 +1015,7 @@ async fn debug_traffic_captures_request_body_in_entry() {
             model: "fast-model".into(),
             think: None,
             max_context_tokens: None,
+            capabilities: Default::default(),
         }],
         aliases: {
             let mut m = std::collections::HashMap::new();
@@ -833,6 +1042,9 @@ async fn debug_traffic_captures_request_body_in_entry() {
             m
         },
         clients: vec![],
+        splits: Default::default(),
+        templates: Default::default(),
+        admin_overlay: Default::default(),
     };
     let state = RouterState::new(
         Arc::new(config),
@@ -844,7 +1056,7 @@ async fn debug_traffic_captures_request_body_in_entry() {
         "model": "hint:fast",
         "messages": [{"role": "user", "content": "capture this body"}]
     });
-    let (_, entry) = route(&state, body.clone(), None, None, 0, false, false)
+    let (_, entry) = route(&state, body.clone(), None, &RequestMeta::default(), false, false)
         .await
         .unwrap();
 
@@ -852,13 +1064,10 @@ async fn debug_traffic_captures_request_body_in_entry() {
         entry.debug_request_body.is_some(),
         "debug_request_body must be populated when debug_traffic = true"
     );
-    // The original hint is preserved in requested_model; the captured body
-    // reflects what was actually sent to the backend (model rewritten by dispatch).
+    // The captured body is the client's, before dispatch rewrites the model,
+    // so a replay routes it afresh.
     assert_eq!(entry.requested_model.as_deref(), Some("hint:fast"));
-    assert_eq!(
-        entry.debug_request_body.as_ref().and_then(|b| b["model"].as_str()),
-        Some("fast-model")
-    );
+    assert_eq!(entry.debug_request_body.as_deref(), Some(&body));
 }
 
 #[cfg(feature = "debug-traffic")]
@@ -879,9 +1088,623 @@ async fn debug_traffic_omits_body_when_disabled() {
         "model": "hint:fast",
         "messages": [{"role": "user", "content": "do not capture"}]
     });
-    let (_, entry) = route(&state, body, None, None, 0, false, false).await.unwrap();
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
     assert!(
         entry.debug_request_body.is_none(),
         "debug_request_body must be None when debug_traffic = false"
     );
 }
+
+// -----------------------------------------------------------------------
+// Response cache
+// -----------------------------------------------------------------------
+
+async fn cached_state(server: &MockServer) -> RouterState {
+    let state = mock_state(server, RoutingMode::Dispatch).await;
+    let mut config = (*state.config()).clone();
+    config.profiles.get_mut("default").unwrap().cache =
+        Some(crate::config::CacheConfig { ttl_secs: 60, max_entries: 10, semantic: None });
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+    state
+}
+
+#[tokio::test]
+async fn cache_serves_repeats_and_honours_no_cache() {
+    use crate::cache::{CacheDirective, CacheOutcome};
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("It is 21 degrees in the living room.")))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let state = cached_state(&server).await;
+    let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": "what's the temperature"}] });
+    let meta = RequestMeta::default();
+
+    let (_, first) = route(&state, body.clone(), None, &meta, false, false).await.unwrap();
+    assert_eq!(first.cache, Some(CacheOutcome::Miss));
+    let (response, second) = route(&state, body.clone(), None, &meta, false, false).await.unwrap();
+    assert_eq!(second.cache, Some(CacheOutcome::Hit));
+    assert_eq!(second.tier, "local:fast");
+    assert_eq!(response["choices"][0]["message"]["content"], "It is 21 degrees in the living room.");
+
+    let no_cache = RequestMeta { cache_control: CacheDirective::NoCache, ..Default::default() };
+    let (_, third) = route(&state, body, None, &no_cache, false, false).await.unwrap();
+    assert_eq!(third.cache, Some(CacheOutcome::Bypass));
+}
+
+#[tokio::test]
+async fn cached_completion_replays_to_streaming_request() {
+    use futures_util::StreamExt as _;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("Cached answer for the stream.")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = cached_state(&server).await;
+    let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": "hello"}] });
+    route(&state, body.clone(), None, &RequestMeta::default(), false, false).await.unwrap();
+
+    let mut streamed = body;
+    streamed["stream"] = json!(true);
+    let (stream, entry, native) =
+        route_stream(&state, streamed, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.cache, Some(crate::cache::CacheOutcome::Hit));
+    assert!(!native);
+    let text: String = stream.map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap()).collect().await;
+    assert!(text.contains("Cached answer for the stream."), "{text}");
+    assert!(text.ends_with("data: [DONE]\n\n"));
+}
+
+#[tokio::test]
+async fn streamed_completion_fills_the_cache() {
+    use futures_util::StreamExt as _;
+
+    let server = MockServer::start().await;
+    let sse = concat!(
+        "data: {\"model\":\"fast-model\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Lights \"}}]}\n\n",
+        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"are on.\"},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: [DONE]\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = cached_state(&server).await;
+    let body = json!({ "model": "hint:fast", "stream": true, "messages": [{"role": "user", "content": "lights?"}] });
+
+    let (stream, entry, _) =
+        route_stream(&state, body.clone(), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.cache, Some(crate::cache::CacheOutcome::Miss));
+    stream.for_each(|_| async {}).await;
+
+    let (response, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.cache, Some(crate::cache::CacheOutcome::Hit));
+    assert_eq!(response["choices"][0]["message"]["content"], "Lights are on.");
+}
+
+#[tokio::test]
+async fn semantic_cache_serves_paraphrases_of_allowed_classes() {
+    use crate::cache::CacheOutcome;
+    use crate::config::{CacheConfig, SemanticCacheConfig};
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+    // The classifier and the dispatched tier share the mock: two calls for the
+    // first request, only the classifier for the paraphrase.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast class=inquiry")))
+        .expect(3)
+        .mount(&server)
+        .await;
+    for (input, vector) in [("is the kitchen light on", [0.6, 0.8, 0.0]), ("kitchen light on?", [0.62, 0.78, 0.05])] {
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .and(body_partial_json(json!({ "model": "embed-model", "input": input })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [{ "embedding": vector }] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    config.tiers.push(TierConfig {
+        name: "local:embed".into(),
+        backend: "mock".into(),
+        model: "embed-model".into(),
+        think: None,
+        max_context_tokens: None,
+        capabilities: Default::default(),
+    });
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.cache = Some(CacheConfig {
+        ttl_secs: 60,
+        max_entries: 10,
+        semantic: Some(SemanticCacheConfig {
+            embedding_tier: "local:embed".into(),
+            threshold: 0.95,
+            classes: vec!["inquiry".into()],
+        }),
+    });
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let ask = |text: &str| json!({ "model": "hint:fast", "messages": [{"role": "user", "content": text}] });
+    let (_, first) = route(&state, ask("is the kitchen light on"), None, &RequestMeta::default(), false, false)
+        .await
+        .unwrap();
+    assert_eq!(first.cache, Some(CacheOutcome::Miss));
+    assert_eq!(state.response_cache.semantic_len("default"), 1);
+
+    let (response, second) =
+        route(&state, ask("kitchen light on?"), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(second.cache, Some(CacheOutcome::Semantic));
+    assert!(second.cache_similarity.is_some_and(|s| s > 0.95), "{:?}", second.cache_similarity);
+    assert_eq!(second.class_label.as_deref(), Some("inquiry"));
+    assert_eq!(response["choices"][0]["message"]["content"], "tier=fast class=inquiry");
+}
+
+#[tokio::test]
+async fn knn_classifier_defers_uncertain_votes_to_the_llm() {
+    use crate::config::{ClassifierKind, KnnConfig};
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+    // Dispatch for both requests plus one LLM classification for the
+    // uncertain one.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast class=command")))
+        .expect(3)
+        .mount(&server)
+        .await;
+    for (input, vector) in [
+        ("compare these contracts", [1.0, 0.0]),
+        ("turn the lights off", [0.0, 1.0]),
+        ("compare two leases for me", [0.95, 0.05]),
+        ("hmm", [0.7, 0.7]),
+    ] {
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .and(body_partial_json(json!({ "model": "embed-model", "input": input })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [{ "embedding": vector }] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    config.tiers.push(TierConfig {
+        name: "local:embed".into(),
+        backend: "mock".into(),
+        model: "embed-model".into(),
+        think: None,
+        max_context_tokens: None,
+        capabilities: Default::default(),
+    });
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.classifier_kind = ClassifierKind::Knn;
+    profile.knn = Some(KnnConfig {
+        embedding_tier: "local:embed".into(),
+        k: 2,
+        min_confidence: 0.6,
+        examples: HashMap::from([
+            ("economy class=inquiry".into(), vec!["compare these contracts".into()]),
+            ("fast class=command".into(), vec!["turn the lights off".into()]),
+        ]),
+        examples_file: None,
+        file_examples: HashMap::new(),
+    });
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+    state.warm_knn_classifiers().await;
+
+    let ask = |text: &str| json!({ "messages": [{"role": "user", "content": text}] });
+    let (_, confident) =
+        route(&state, ask("compare two leases for me"), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(confident.tier, "cloud:economy");
+    assert_eq!(confident.class_label.as_deref(), Some("inquiry"));
+
+    let (_, uncertain) = route(&state, ask("hmm"), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(uncertain.tier, "local:fast");
+    assert_eq!(uncertain.class_label.as_deref(), Some("command"));
+
+    assert_eq!(state.metrics.knn_classifications.get(&["default", "confident"]), 1);
+    assert_eq!(state.metrics.knn_classifications.get(&["default", "uncertain"]), 1);
+}
+
+#[tokio::test]
+async fn failed_knn_index_build_is_not_retried_per_request() {
+    use crate::config::{ClassifierKind, KnnConfig};
+
+    let server = MockServer::start().await;
+    // Both requests are classified by the LLM, then dispatched.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("fast")))
+        .expect(4)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.classifier_kind = ClassifierKind::Knn;
+    profile.knn = Some(KnnConfig {
+        embedding_tier: "local:fast".into(),
+        k: 1,
+        min_confidence: 0.6,
+        examples: HashMap::from([("fast".into(), vec!["turn the lights off".into()])]),
+        examples_file: None,
+        file_examples: HashMap::new(),
+    });
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    for _ in 0..2 {
+        let body = json!({ "messages": [{"role": "user", "content": "lights please"}] });
+        let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+        assert_eq!(entry.tier, "local:fast");
+    }
+    assert_eq!(state.metrics.knn_classifications.get(&["default", "error"]), 2);
+}
+
+// -----------------------------------------------------------------------
+// Classifier cache and routing pins
+// -----------------------------------------------------------------------
+
+#[tokio::test]
+async fn classifier_cache_reuses_identical_classifications() {
+    let server = MockServer::start().await;
+    // Classifier once, tier twice.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast class=inquiry")))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.classifier_cache_secs = Some(60);
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "model": "hint:fast", "messages": [{"role": "user", "content": "is the door locked"}] });
+    let (_, first) = route(&state, body.clone(), None, &RequestMeta::default(), false, false).await.unwrap();
+    let (_, second) = route(&state, body.clone(), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(second.class_label, first.class_label);
+    assert_eq!(second.tier, first.tier);
+
+    let trace = explain(&state, body, None, &RequestMeta::default(), false).await.unwrap();
+    assert!(trace.classification[0].step.cached);
+}
+
+#[tokio::test]
+async fn tool_loop_hops_reuse_the_pinned_decision() {
+    use crate::traffic::PinSource;
+
+    let server = MockServer::start().await;
+    // Classifier and tier for the first request, only the tier for the hop.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=deep class=code")))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.routing_pin_secs = 600;
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let mut messages = vec![json!({ "role": "user", "content": "list the files in src" })];
+    let (_, first) = route(&state, json!({ "messages": messages }), None, &RequestMeta::default(), false, false)
+        .await
+        .unwrap();
+    assert_eq!(first.routing_mode.as_deref(), Some("classify"));
+
+    messages.push(json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{ "id": "call_1", "type": "function", "function": { "name": "ls", "arguments": "{}" } }],
+    }));
+    messages.push(json!({ "role": "tool", "tool_call_id": "call_1", "content": "main.rs lib.rs" }));
+    let (_, hop) = route(&state, json!({ "messages": messages }), None, &RequestMeta::default(), false, false)
+        .await
+        .unwrap();
+
+    assert_eq!(hop.routing_mode.as_deref(), Some("pinned"));
+    assert_eq!(hop.routing.as_ref().and_then(|r| r.pinned), Some(PinSource::ToolLoop));
+    assert_eq!(hop.tier, first.tier);
+    assert_eq!(hop.class_label.as_deref(), Some("code"));
+}
+
+#[tokio::test]
+async fn speculation_is_kept_when_classification_agrees() {
+    use crate::traffic::SpeculationOutcome;
+
+    let server = MockServer::start().await;
+    // Classifier and the speculative call — no second dispatch.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast class=inquiry")))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.speculate = Some("hint:fast".into());
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "messages": [{"role": "user", "content": "what time is it"}] });
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.tier, "local:fast");
+    let speculation = entry.routing.and_then(|r| r.speculation).expect("speculation recorded");
+    assert_eq!((speculation.tier.as_str(), speculation.outcome), ("local:fast", SpeculationOutcome::Kept));
+    assert_eq!(state.metrics.speculative_dispatch.get(&["default", "kept"]), 1);
+}
+
+#[tokio::test]
+async fn speculation_on_the_wrong_tier_is_cancelled() {
+    use crate::traffic::SpeculationOutcome;
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+    // The speculative call is still in flight when classification lands.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({ "model": "economy-model" })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(long_response("too slow"))
+                .set_delay(std::time::Duration::from_secs(10)),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=fast class=inquiry")))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.speculate = Some("cloud:economy".into());
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "messages": [{"role": "user", "content": "what time is it"}] });
+    let started = std::time::Instant::now();
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(5), "did not wait for the speculative call");
+    assert_eq!(entry.tier, "local:fast");
+    let speculation = entry.routing.and_then(|r| r.speculation).expect("speculation recorded");
+    assert_eq!((speculation.tier.as_str(), speculation.outcome), ("cloud:economy", SpeculationOutcome::Cancelled));
+    assert_eq!(state.metrics.speculative_dispatch.get(&["default", "cancelled"]), 1);
+    assert_eq!(state.metrics.speculative_dispatch.get(&["default", "kept"]), 0);
+}
+
+#[tokio::test]
+async fn unknown_labels_retry_on_the_fallback_classifier() {
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+    // The primary classifier rambles; the fallback and the economy tier agree.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_partial_json(json!({ "model": "economy-model" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("tier=economy class=inquiry")))
+        .with_priority(1)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("Sure! I think this is moderately hard")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.classifier_fallback = Some("cloud:economy".into());
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "messages": [{"role": "user", "content": "compare these two contracts"}] });
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy");
+    assert_eq!(entry.class_label.as_deref(), Some("inquiry"));
+}
+
+#[tokio::test]
+async fn failed_classification_routes_by_the_default_label() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(wiremock::matchers::body_partial_json(json!({ "options": { "num_predict": 10 } })))
+        .respond_with(ResponseTemplate::new(503))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("Here is the answer.")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.classifier_default_label = Some("economy".into());
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "messages": [{"role": "user", "content": "what's the weather"}] });
+    let (_, entry) = route(&state, body, None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy", "not the hard-coded instant/first tier");
+
+    let trace = explain(&state, json!({ "messages": [{"role": "user", "content": "hi"}] }), None, &RequestMeta::default(), false).await.unwrap();
+    assert!(trace.classification[0].step.error.is_some());
+    assert_eq!(trace.classification[0].step.label.as_deref(), Some("economy"));
+}
+
+#[tokio::test]
+async fn request_rules_skip_the_classifier() {
+    use crate::config::RuleConfig;
+    use crate::traffic::RuleOutcome;
+
+    let server = MockServer::start().await;
+    // Only the tier — no classifier call.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("It's a cat on a keyboard.")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Classify).await;
+    let mut config = (*state.config()).clone();
+    let profile = config.profiles.get_mut("default").unwrap();
+    profile.classifier_timeout_ms = 5_000;
+    profile.rules = vec![
+        RuleConfig {
+            when: Default::default(),
+            route_to: "cloud:economy".into(),
+            priority: 10,
+            request: toml::from_str("has_images = true").unwrap(),
+        },
+        RuleConfig {
+            when: [("class".into(), "code".into())].into(),
+            route_to: "local:fast".into(),
+            priority: 0,
+            request: Default::default(),
+        },
+    ];
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "messages": [{ "role": "user", "content": [
+        { "type": "text", "text": "what is this" },
+        { "type": "image_url", "image_url": { "url": "data:image/png;base64,AAAA" } }
+    ] }] });
+    let (_, entry) = route(&state, body.clone(), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy");
+    let rules = entry.routing.expect("routing detail").rules;
+    assert_eq!(rules.len(), 1, "tag rules are never reached");
+    assert_eq!(rules[0].outcome, RuleOutcome::Matched);
+
+    let trace = explain(&state, body, None, &RequestMeta::default(), false).await.unwrap();
+    assert_eq!(trace.tier.as_deref(), Some("cloud:economy"));
+    assert!(trace.classification[0].step.input.is_none(), "classifier skipped");
+}
+
+#[tokio::test]
+async fn dispatch_mode_request_rules_override_the_model_hint() {
+    use crate::config::RuleConfig;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response(
+            "Here is a comprehensive answer that passes the sufficiency heuristic.",
+        )))
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Dispatch).await;
+    let mut config = (*state.config()).clone();
+    config.profiles.get_mut("default").unwrap().rules = vec![RuleConfig {
+        when: Default::default(),
+        route_to: "cloud:economy".into(),
+        priority: 0,
+        request: toml::from_str(r#"headers = { X-Source = "batch" }"#).unwrap(),
+    }];
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "model": "hint:fast", "messages": [{ "role": "user", "content": "summarise" }] });
+    let (_, interactive) = route(&state, body.clone(), None, &RequestMeta::default(), false, false).await.unwrap();
+    assert_eq!(interactive.tier, "local:fast");
+
+    let mut meta = RequestMeta::default();
+    meta.headers.insert("x-source", "batch".parse().unwrap());
+    let (_, batch) = route(&state, body.clone(), None, &meta, false, false).await.unwrap();
+    assert_eq!(batch.tier, "cloud:economy");
+
+    let trace = explain(&state, body, None, &RequestMeta::default(), false).await.unwrap();
+    let condition = &trace.classification[0].rules[0].conditions[0];
+    assert_eq!((condition.key.as_str(), condition.expected.as_str()), ("headers.X-Source", "batch"));
+    assert_eq!((condition.actual.as_deref(), condition.met), (None, false));
+}
+
+#[tokio::test]
+async fn splits_apply_in_every_mode() {
+    use crate::config::{BucketBy, SplitConfig, SplitVariant};
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(long_response("fast — and long enough to pass the sufficiency check")))
+        .mount(&server)
+        .await;
+    let state = mock_state(&server, RoutingMode::Dispatch).await;
+    let mut config = (*state.config()).clone();
+    let variants = vec![
+        SplitVariant { tier: "local:fast".into(), weight: 50 },
+        SplitVariant { tier: "cloud:economy".into(), weight: 50 },
+    ];
+    config.splits.insert("hint:fast".into(), SplitConfig { bucket_by: BucketBy::Client, variants });
+    state.replace_config(Arc::new(config.clone()), crate::config::ConfigSource::AdminApi);
+
+    let body = json!({ "model": "hint:fast", "messages": [{ "role": "user", "content": "turn on the lights" }] });
+    // Bucketing itself is covered in `split`; find a client it puts on the canary.
+    let fast = config.resolve_tier("local:fast").unwrap();
+    let canary_client = (0..)
+        .map(|i| RequestMeta { client: Some(format!("CLIENT_{i}")), ..Default::default() })
+        .find(|meta| super::split::assign(&config, fast, &body, meta).unwrap().variant.name == "cloud:economy")
+        .unwrap();
+
+    let (_, entry) = route(&state, body.clone(), None, &canary_client, false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy");
+    let split = entry.routing.and_then(|r| r.split).expect("entry tagged with the split");
+    assert_eq!((split.split.as_str(), split.variant.as_str()), ("hint:fast", "cloud:economy"));
+    assert_eq!(state.metrics.split_requests.get(&["hint:fast", "cloud:economy"]), 1);
+
+    // Streaming, escalate and classify modes land the client on the same variant.
+    let (_, entry, _) = route_stream(&state, body.clone(), None, &canary_client, false, false).await.unwrap();
+    assert_eq!(entry.tier, "cloud:economy");
+    for mode in [RoutingMode::Escalate, RoutingMode::Classify] {
+        let profile = config.profiles.get_mut("default").unwrap();
+        profile.mode = mode;
+        profile.classifier_timeout_ms = 5_000;
+        state.replace_config(Arc::new(config.clone()), crate::config::ConfigSource::AdminApi);
+        let (_, entry) = route(&state, body.clone(), None, &canary_client, false, false).await.unwrap();
+        assert_eq!(entry.tier, "cloud:economy", "{:?}", config.profiles["default"].mode);
+    }
+    let trace = explain(&state, body.clone(), None, &canary_client, false).await.unwrap();
+    assert_eq!(trace.tier.as_deref(), Some("cloud:economy"));
+    assert_eq!(trace.split.map(|s| s.split).as_deref(), Some("hint:fast"));
+
+    // Weight 0 takes a variant out of rotation.
+    config.splits.get_mut("hint:fast").unwrap().variants[1].weight = 0;
+    state.replace_config(Arc::new(config), crate::config::ConfigSource::AdminApi);
+    let (_, entry) = route(&state, body, None, &canary_client, false, false).await.unwrap();
+    assert_eq!(entry.tier, "local:fast");
+}
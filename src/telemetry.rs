@@ -0,0 +1,429 @@
+//! OpenTelemetry export — traces and metrics over OTLP (HTTP/protobuf).
+//!
+//! Opt-in via `gateway.otlp_endpoint`. When set, [`init`] installs a
+//! `tracing-opentelemetry` layer so the gateway's existing `tracing` spans are
+//! exported, plus a meter provider for the GenAI client metrics below. When
+//! unset, nothing is exported and the helpers in this module are cheap no-ops.
+//!
+//! Spans:
+//!
+//! | Span | Where |
+//! |---|---|
+//! | `request` | Every inbound HTTP request; parent taken from a W3C `traceparent` header |
+//! | `route` / `route_stream` | Routing decision for one chat request |
+//! | `classify` | Pre-flight classifier call in `classify` mode |
+//! | `queue_wait` | Time spent waiting on a tier's priority gate |
+//! | `chat <model>` / `embeddings <model>` | One backend call (GenAI semantic conventions) |
+//!
+//! Backend calls carry `gen_ai.operation.name`, `gen_ai.provider.name`,
+//! `gen_ai.request.model`, `gen_ai.response.model` and
+//! `gen_ai.usage.{input,output}_tokens`, plus `lmg.tier` and `lmg.backend`.
+//! The current trace context is injected into every backend request as
+//! `traceparent`, so backends that participate in tracing join the trace.
+//!
+//! Metrics: `gen_ai.client.operation.duration` (s) and
+//! `gen_ai.client.token.usage` (tokens, by `gen_ai.token.type`).
+
+use std::{sync::OnceLock, time::Duration};
+
+use anyhow::Context as _;
+use opentelemetry::{
+    global,
+    metrics::Histogram,
+    propagation::TextMapPropagator,
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_otlp::{MetricExporter, Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::SdkMeterProvider,
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+    Resource,
+};
+use serde_json::Value;
+use tracing::{field::Empty, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::config::{BackendConfig, GatewayConfig, Provider, TierConfig};
+
+/// Instrumentation scope name for spans and metrics.
+const SCOPE: &str = "lm-gateway";
+
+/// Exporter handles, flushed and shut down by [`Telemetry::shutdown`].
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+/// Build the OTLP pipeline described by `[gateway]`.
+///
+/// Returns the `tracing` layer to install alongside the fmt layer, and the
+/// exporter handles to shut down on exit. Returns `None` when
+/// `otlp_endpoint` is unset.
+pub fn init<S>(
+    gateway: &GatewayConfig,
+) -> anyhow::Result<Option<(tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>, Telemetry)>>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    let Some(endpoint) = gateway.otlp_endpoint.as_deref() else {
+        return Ok(None);
+    };
+    let endpoint = endpoint.trim_end_matches('/');
+    let resource = Resource::builder()
+        .with_service_name(gateway.otel_service_name.clone())
+        .build();
+
+    let span_exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpBinary)
+        .with_endpoint(format!("{endpoint}/v1/traces"))
+        .with_timeout(Duration::from_secs(10))
+        .build()
+        .context("building OTLP span exporter")?;
+    let ratio = gateway.otel_sample_ratio.clamp(0.0, 1.0);
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(span_exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio))))
+        .with_resource(resource.clone())
+        .build();
+
+    let metric_exporter = MetricExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpBinary)
+        .with_endpoint(format!("{endpoint}/v1/metrics"))
+        .with_timeout(Duration::from_secs(10))
+        .build()
+        .context("building OTLP metric exporter")?;
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(metric_exporter)
+        .with_resource(resource)
+        .build();
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_meter_provider(meter_provider.clone());
+
+    let layer = tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(SCOPE));
+    Ok(Some((layer, Telemetry { tracer_provider, meter_provider })))
+}
+
+impl Telemetry {
+    /// Flush pending spans and metrics. Call once, before the process exits.
+    pub fn shutdown(self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            tracing::warn!(error = %e, "OTLP trace exporter shutdown failed");
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            tracing::warn!(error = %e, "OTLP metric exporter shutdown failed");
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Context propagation
+// ---------------------------------------------------------------------------
+
+/// `make_span_with` for `tower_http::trace::TraceLayer`: a `request` span whose
+/// parent is the W3C trace context in the inbound headers, if any.
+pub fn make_request_span<B>(request: &axum::http::Request<B>) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        otel.kind = "server",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+    );
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(request.headers())));
+    // Fails only when no OpenTelemetry layer is installed — nothing to link then.
+    let _ = span.set_parent(parent);
+    span
+}
+
+/// Headers carrying the current span's trace context (`traceparent`,
+/// `tracestate`), for outgoing backend requests. Empty when tracing export is
+/// disabled.
+pub fn trace_headers() -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let cx = Span::current().context();
+    TraceContextPropagator::new().inject_context(&cx, &mut HeaderInjector(&mut headers));
+    headers
+}
+
+// ---------------------------------------------------------------------------
+// GenAI spans and metrics
+// ---------------------------------------------------------------------------
+
+/// `gen_ai.provider.name` for a backend provider.
+fn provider_name(provider: &Provider) -> &'static str {
+    match provider {
+        Provider::OpenAI => "openai",
+        Provider::OpenRouter => "openrouter",
+        Provider::Anthropic => "anthropic",
+        Provider::Ollama => "ollama",
+    }
+}
+
+/// `gen_ai.operation.name` of a backend call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Chat,
+    Embeddings,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Embeddings => "embeddings",
+        }
+    }
+}
+
+/// Span for one `operation` call to `tier`. Enter it (or `.instrument()` the
+/// call) and finish with [`record_backend_result`].
+pub fn backend_span(operation: Operation, tier: &TierConfig, backend: &BackendConfig) -> Span {
+    let server = reqwest::Url::parse(&backend.base_url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_owned))
+        .unwrap_or_default();
+    tracing::info_span!(
+        "backend_call",
+        otel.name = %format!("{} {}", operation.as_str(), tier.model),
+        otel.kind = "client",
+        otel.status_code = Empty,
+        otel.status_description = Empty,
+        gen_ai.operation.name = operation.as_str(),
+        gen_ai.provider.name = provider_name(&backend.provider),
+        gen_ai.request.model = %tier.model,
+        gen_ai.response.model = Empty,
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        server.address = %server,
+        lmg.tier = %tier.name,
+        lmg.backend = %tier.backend,
+    )
+}
+
+/// Model and token counts a successful backend call reported.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CallUsage<'a> {
+    pub model: Option<&'a str>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+impl<'a> CallUsage<'a> {
+    /// Usage reported in an OpenAI-format or Ollama native response body.
+    pub fn of(response: &'a Value) -> Self {
+        let (input_tokens, output_tokens) = usage_tokens(response);
+        Self { model: response.get("model").and_then(Value::as_str), input_tokens, output_tokens }
+    }
+}
+
+/// Record the outcome of a backend call on `span` and in the GenAI metrics.
+///
+/// Streams are recorded when they end, with the usage their chunks carried.
+pub fn record_backend_result(
+    span: &Span,
+    operation: Operation,
+    tier: &TierConfig,
+    backend: &BackendConfig,
+    elapsed: Duration,
+    result: Result<CallUsage<'_>, &anyhow::Error>,
+) {
+    let mut attrs = vec![
+        KeyValue::new("gen_ai.operation.name", operation.as_str()),
+        KeyValue::new("gen_ai.provider.name", provider_name(&backend.provider)),
+        KeyValue::new("gen_ai.request.model", tier.model.clone()),
+        KeyValue::new("lmg.tier", tier.name.clone()),
+    ];
+    match result {
+        Ok(usage) => {
+            span.record("otel.status_code", "OK");
+            if let Some(model) = usage.model {
+                span.record("gen_ai.response.model", model);
+            }
+            if let Some(n) = usage.input_tokens {
+                span.record("gen_ai.usage.input_tokens", n);
+                instruments().tokens.record(n, &with(&attrs, "gen_ai.token.type", "input"));
+            }
+            if let Some(n) = usage.output_tokens {
+                span.record("gen_ai.usage.output_tokens", n);
+                instruments().tokens.record(n, &with(&attrs, "gen_ai.token.type", "output"));
+            }
+        }
+        Err(e) => {
+            span.record("otel.status_code", "ERROR");
+            span.record("otel.status_description", format!("{e:#}").as_str());
+            attrs.push(KeyValue::new("error.type", "backend_error"));
+        }
+    }
+    instruments().duration.record(elapsed.as_secs_f64(), &attrs);
+}
+
+/// Input and output token counts from an OpenAI-format `usage` object, or
+/// Ollama's native `prompt_eval_count` / `eval_count`.
+pub fn usage_tokens(response: &Value) -> (Option<u64>, Option<u64>) {
+    let usage = response.get("usage");
+    let field = |openai: &str, ollama: &str| {
+        usage
+            .and_then(|u| u.get(openai))
+            .or_else(|| response.get(ollama))
+            .and_then(Value::as_u64)
+    };
+    (
+        field("prompt_tokens", "prompt_eval_count"),
+        field("completion_tokens", "eval_count"),
+    )
+}
+
+fn with(attrs: &[KeyValue], key: &'static str, value: &'static str) -> Vec<KeyValue> {
+    let mut attrs = attrs.to_vec();
+    attrs.push(KeyValue::new(key, value));
+    attrs
+}
+
+struct Instruments {
+    duration: Histogram<f64>,
+    tokens: Histogram<u64>,
+}
+
+/// GenAI client instruments, created on first use from the global meter
+/// provider ([`init`] runs before any request is served).
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+    INSTRUMENTS.get_or_init(|| {
+        let meter = global::meter(SCOPE);
+        Instruments {
+            duration: meter
+                .f64_histogram("gen_ai.client.operation.duration")
+                .with_unit("s")
+                .with_description("GenAI operation duration")
+                .build(),
+            tokens: meter
+                .u64_histogram("gen_ai.client.token.usage")
+                .with_unit("{token}")
+                .with_description("Measures number of input and output tokens used")
+                .build(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn usage_tokens_reads_openai_and_ollama_shapes() {
+        let openai = serde_json::json!({ "usage": { "prompt_tokens": 12, "completion_tokens": 3 } });
+        assert_eq!(usage_tokens(&openai), (Some(12), Some(3)));
+        let ollama = serde_json::json!({ "prompt_eval_count": 7, "eval_count": 2 });
+        assert_eq!(usage_tokens(&ollama), (Some(7), Some(2)));
+        assert_eq!(usage_tokens(&serde_json::json!({})), (None, None));
+    }
+
+    #[test]
+    fn inbound_traceparent_is_continued_on_backend_requests() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = axum::http::Request::builder()
+                .uri("/v1/chat/completions")
+                .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .body(())
+                .unwrap();
+            let span = make_request_span(&request);
+            let _guard = span.enter();
+
+            let headers = trace_headers();
+            let traceparent = headers["traceparent"].to_str().unwrap();
+            assert!(
+                traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"),
+                "trace id must be propagated: {traceparent}"
+            );
+            assert!(!traceparent.contains("00f067aa0ba902b7"), "span id must be the gateway's own");
+        });
+    }
+
+    #[test]
+    fn backend_span_names_the_operation() {
+        #[derive(Clone, Default)]
+        struct Fields(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Fields {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _: &tracing::span::Id,
+                _: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                struct Visit<'a>(&'a mut Vec<String>);
+                impl tracing::field::Visit for Visit<'_> {
+                    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                        self.0.push(format!("{}={value:?}", field.name()));
+                    }
+                }
+                attrs.record(&mut Visit(&mut self.0.lock().unwrap()));
+            }
+        }
+
+        let fields = Fields::default();
+        let tier: TierConfig = toml::from_str("name = \"local:embed\"\nbackend = \"b\"\nmodel = \"nomic\"").unwrap();
+        let backend: BackendConfig = toml::from_str("base_url = \"http://localhost:11434\"").unwrap();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(fields.clone()), || {
+            backend_span(Operation::Embeddings, &tier, &backend);
+        });
+        let fields = fields.0.lock().unwrap();
+        assert!(fields.contains(&"otel.name=embeddings nomic".to_owned()), "{fields:?}");
+        assert!(fields.contains(&"gen_ai.operation.name=\"embeddings\"".to_owned()), "{fields:?}");
+    }
+
+    #[test]
+    fn trace_headers_are_empty_without_an_active_trace() {
+        assert!(trace_headers().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn spans_are_exported_to_otlp_receiver() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let receiver = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .and(header("content-type", "application/x-protobuf"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&receiver)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/metrics"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&receiver)
+            .await;
+
+        let config: crate::config::Config =
+            toml::from_str(&format!("[gateway]\notlp_endpoint = \"{}\"", receiver.uri())).unwrap();
+        let (layer, telemetry) = init(&config.gateway).unwrap().expect("endpoint is set");
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("route", gen_ai.request.model = "qwen3").entered();
+        });
+        // Shutdown flushes synchronously over a blocking client.
+        tokio::task::spawn_blocking(move || telemetry.shutdown()).await.unwrap();
+
+        let traces = receiver
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.url.path() == "/v1/traces")
+            .count();
+        assert_eq!(traces, 1);
+    }
+}
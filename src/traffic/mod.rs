@@ -0,0 +1,866 @@
+//! In-memory traffic log exposed through the admin API.
+//!
+//! [`TrafficLog`] is a fixed-capacity ring-buffer: once full, the oldest entry
+//! is evicted to make room for the newest. This gives a bounded, O(1) memory
+//! footprint regardless of request volume.
+//!
+//! The slots themselves are lock-free: each push claims the next sequence
+//! number with an atomic increment and publishes the entry into slot
+//! `seq % capacity` with an atomic pointer swap, and readers load the slots
+//! without blocking writers, cloning only what they return. If pushes race a
+//! full lap of the ring, the older of two entries for one slot is discarded
+//! and counted in [`TrafficLog::dropped`].
+//!
+//! Window aggregates ([`TrafficStats`], `/metrics` gauges) are kept
+//! incrementally — added on push, subtracted on eviction — instead of
+//! rescanning the buffer. Pushes update them under a `Mutex`, taken once per
+//! push, and then publish an immutable copy; [`TrafficLog::stats`],
+//! [`TrafficLog::public_stats`] and [`TrafficLog::window_breakdown`] read that
+//! copy and never lock, so a `/metrics` scrape cannot hold up a push.
+//!
+//! Sequence numbers double as a stable pagination cursor for
+//! [`TrafficLog::query`] while new entries keep arriving.
+//! [`TrafficLog::subscribe`] delivers entries live as they are pushed.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use arc_swap::{ArcSwap, ArcSwapOption};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+mod query;
+mod window;
+
+pub use query::TrafficFilter;
+pub use window::TrafficStats;
+use window::WindowAggregates;
+
+use crate::{
+    cache::{CacheHit, CacheOutcome},
+    config::Capability,
+    traffic_archive::TrafficArchive,
+    traffic_webhook::TrafficWebhook,
+};
+
+/// Fixed-capacity ring-buffer of recent [`TrafficEntry`] records.
+///
+/// Safe to share across threads via `Arc<TrafficLog>`. [`push`][Self::push]
+/// never waits on readers; it shares only the window-aggregate lock with
+/// other pushes.
+pub struct TrafficLog {
+    capacity: usize,
+    slots: Box<[ArcSwapOption<Slot>]>,
+    /// Sequence number the next push will claim.
+    next_seq: AtomicU64,
+    window: Mutex<WindowAggregates>,
+    /// Copy of `window` as of the last completed push, for lock-free reads.
+    published: ArcSwap<WindowAggregates>,
+    /// Entries overwritten by a newer one before they were published — only
+    /// possible when pushes race a full lap of the ring.
+    dropped: AtomicU64,
+    live: broadcast::Sender<TrafficEntry>,
+    archive: Option<TrafficArchive>,
+    webhook: Option<TrafficWebhook>,
+}
+
+/// Live-tail subscribers that fall this far behind skip ahead.
+const LIVE_CAPACITY: usize = 256;
+
+/// One published entry and its sequence number.
+#[derive(Debug)]
+struct Slot {
+    seq: u64,
+    entry: TrafficEntry,
+}
+
+impl TrafficLog {
+    /// Create a new log with the given capacity.
+    ///
+    /// `capacity` is the maximum number of entries retained. Older entries are
+    /// silently dropped once the buffer is full.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            slots: (0..capacity).map(|_| ArcSwapOption::empty()).collect(),
+            next_seq: AtomicU64::new(0),
+            window: Mutex::new(WindowAggregates::default()),
+            published: ArcSwap::default(),
+            dropped: AtomicU64::new(0),
+            live: broadcast::channel(LIVE_CAPACITY).0,
+            archive: None,
+            webhook: None,
+        }
+    }
+
+    /// Also append every pushed entry to a persistent JSONL archive.
+    pub fn with_archive(mut self, archive: TrafficArchive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Also export every pushed entry to an HTTP collector.
+    pub fn with_webhook(mut self, webhook: TrafficWebhook) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// The attached archive writer, if any (for its drop counter).
+    pub fn archive(&self) -> Option<&TrafficArchive> {
+        self.archive.as_ref()
+    }
+
+    /// The attached webhook exporter, if any (for its delivery counters).
+    pub fn webhook(&self) -> Option<&TrafficWebhook> {
+        self.webhook.as_ref()
+    }
+
+    /// Seed the buffer with entries recovered from the archive, oldest first.
+    ///
+    /// Only the newest `capacity` entries are kept. Seeded entries are not
+    /// re-archived or sent to the live tail.
+    pub fn rehydrate(&self, recovered: Vec<TrafficEntry>) {
+        for entry in recovered {
+            self.store(entry);
+        }
+    }
+
+    /// Record a completed request.
+    ///
+    /// Attached sinks receive the entry through their own non-blocking queues
+    /// (see [`TrafficArchive::append`] and [`TrafficWebhook::send`]).
+    pub fn push(&self, entry: TrafficEntry) {
+        if let Some(archive) = &self.archive {
+            archive.append(entry.clone());
+        }
+        if let Some(webhook) = &self.webhook {
+            webhook.send(entry.clone());
+        }
+        if self.live.receiver_count() > 0 {
+            let _ = self.live.send(entry.clone());
+        }
+        self.store(entry);
+    }
+
+    /// Publish `entry` into its slot and update the window aggregates.
+    fn store(&self, entry: TrafficEntry) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        // Held across the slot swap: the entry is counted before it becomes
+        // visible, so a later eviction can never subtract it first.
+        let mut window = self.window.lock().expect("traffic window lock poisoned");
+        window.add(&entry);
+        let new = Arc::new(Slot { seq, entry });
+        let slot = &self.slots[(seq % self.capacity as u64) as usize];
+        // A racing push from a later lap may already own the slot; never
+        // replace a newer entry with an older one.
+        let previous = slot.rcu(|current| match current {
+            Some(current) if current.seq > seq => Some(Arc::clone(current)),
+            _ => Some(Arc::clone(&new)),
+        });
+        let evicted = match previous {
+            Some(previous) if previous.seq > seq => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Some(new)
+            }
+            previous => previous,
+        };
+        if let Some(evicted) = evicted {
+            window.remove(&evicted.entry);
+        }
+        self.published.store(Arc::new(window.clone()));
+    }
+
+    /// Entries that could not be stored (see the field docs); `0` in practice.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Published entries, newest first. Clones `Arc`s, not entries.
+    fn snapshot(&self) -> Vec<Arc<Slot>> {
+        let end = self.next_seq.load(Ordering::Acquire);
+        let start = end.saturating_sub(self.capacity as u64);
+        let mut slots: Vec<Arc<Slot>> = self
+            .slots
+            .iter()
+            .filter_map(|slot| slot.load_full())
+            .filter(|slot| slot.seq >= start)
+            .collect();
+        slots.sort_unstable_by(|a, b| b.seq.cmp(&a.seq));
+        slots
+    }
+
+    /// Receive every entry pushed from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<TrafficEntry> {
+        self.live.subscribe()
+    }
+
+    /// Return up to `limit` recent entries, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<TrafficEntry> {
+        self.snapshot()
+            .iter()
+            .take(limit)
+            .map(|slot| slot.entry.clone())
+            .collect()
+    }
+
+    /// Compute per-backend health from the most recent `window` entries for each backend.
+    ///
+    /// Returns a map from backend name to [`BackendHealthStats`].  Backends with no
+    /// traffic in the window are not included — callers should treat a missing entry
+    /// as healthy (no evidence of failure yet).
+    ///
+    /// A minimum of 3 samples is required before a backend can be classified as
+    /// unhealthy, to avoid false positives for rarely-used or newly-added backends.
+    pub fn backend_health(
+        &self,
+        window: usize,
+        threshold: f64,
+    ) -> HashMap<String, BackendHealthStats> {
+        // Iterate newest-first, collecting up to `window` outcomes per backend.
+        let mut by_backend: HashMap<String, Vec<bool>> = HashMap::new();
+        for slot in self.snapshot() {
+            let bucket = by_backend.entry(slot.entry.backend.clone()).or_default();
+            if bucket.len() < window {
+                bucket.push(slot.entry.success);
+            }
+        }
+        by_backend
+            .into_iter()
+            .map(|(backend, outcomes)| {
+                let total = outcomes.len();
+                let errors = outcomes.iter().filter(|&&ok| !ok).count();
+                let error_rate = if total == 0 {
+                    0.0
+                } else {
+                    errors as f64 / total as f64
+                };
+                // Require at least 3 samples before marking a backend unhealthy.
+                let healthy = total < 3 || error_rate < threshold;
+                (backend, BackendHealthStats { total, errors, error_rate, healthy })
+            })
+            .collect()
+    }
+}
+
+/// A single request record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficEntry {
+    /// Unique request ID.
+    pub id: String,
+    /// Timestamp of the request.
+    pub timestamp: DateTime<Utc>,
+    /// Active profile at the time of the request.
+    pub profile: Option<String>,
+    /// Authenticated client (its `key_env`), when `[[clients]]` auth is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Original model alias or tier name from the request body.
+    pub requested_model: Option<String>,
+    /// Tier that ultimately handled this request.
+    pub tier: String,
+    /// Backend that handled this request.
+    pub backend: String,
+    /// Routing mode applied (`"dispatch"` or `"escalate"`).
+    pub routing_mode: Option<String>,
+    /// Whether the request was escalated to a higher tier during routing.
+    pub escalated: bool,
+    /// End-to-end latency in milliseconds. For streams, measured until the
+    /// last chunk (or the client disconnecting).
+    pub latency_ms: u64,
+    /// Whether the backend returned a success response.
+    pub success: bool,
+    /// Error description when `success` is `false`.
+    pub error: Option<String>,
+    /// Classification class label (e.g. `"greeting"`, `"command"`).
+    /// Populated only when the profile uses `mode = "classify"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_label: Option<String>,
+    /// Ordered list of profiles traversed during cascade routing.
+    /// A single-hop request has exactly one entry (the initial profile).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_chain: Option<Vec<String>>,
+    /// Scheduling priority from the `X-LMG-Priority` request header.
+    /// `0` = normal (default), `+N` = higher, `-N` = background.
+    #[serde(default)]
+    pub priority: i32,
+    /// Prompt tokens reported by the backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    /// Completion tokens reported by the backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+    /// Total tokens, as reported or summed from the two fields above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<u64>,
+    /// Completion tokens per second of generation time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_sec: Option<f64>,
+    /// Time to the first streamed chunk, in milliseconds. Streams only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttft_ms: Option<u64>,
+    /// The client went away before the stream finished.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub client_disconnected: bool,
+    /// Decisions made while routing: classifier tags, rules evaluated,
+    /// context-window bumps, skipped tiers and retries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingDetail>,
+    /// ID of the entry this request replays (`POST /admin/traffic/{id}/replay`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay_of: Option<String>,
+    /// Response cache outcome; absent when the profile has no cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheOutcome>,
+    /// Cosine similarity to the cached request, for semantic cache hits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_similarity: Option<f32>,
+    /// Full request body captured for debugging.
+    ///
+    /// Only populated when the `debug-traffic` Cargo feature is compiled in
+    /// **and** `traffic_log_debug = true` in the `[gateway]` config.
+    ///
+    /// This is the inbound body (messages, tools, client system prompt, etc.)
+    /// as the client sent it — before the gateway adds profile or class
+    /// prompts or swaps in the tier's model — so a replay routes it exactly
+    /// like the original request.
+    ///
+    /// Stored behind `Arc` so that cloning a [`TrafficEntry`] (e.g. when pushing
+    /// to the ring buffer) shares the allocation rather than duplicating the body.
+    #[cfg(feature = "debug-traffic")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug_request_body: Option<Arc<Value>>,
+}
+
+impl TrafficEntry {
+    pub fn new(tier: String, backend: String, latency_ms: u64, success: bool) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            profile: None,
+            client: None,
+            requested_model: None,
+            tier,
+            backend,
+            routing_mode: None,
+            escalated: false,
+            latency_ms,
+            success,
+            error: None,
+            class_label: None,
+            profile_chain: None,
+            priority: 0,
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            tokens_per_sec: None,
+            ttft_ms: None,
+            client_disconnected: false,
+            routing: None,
+            replay_of: None,
+            cache: None,
+            cache_similarity: None,
+            #[cfg(feature = "debug-traffic")]
+            debug_request_body: None,
+        }
+    }
+
+    /// Attach the active profile name.
+    pub fn with_profile(mut self, profile: &str) -> Self {
+        self.profile = Some(profile.to_string());
+        self
+    }
+
+    /// Attach the authenticated client name.
+    pub fn with_client(mut self, client: &str) -> Self {
+        self.client = Some(client.to_string());
+        self
+    }
+
+    /// Attach the original model hint from the request.
+    pub fn with_requested_model(mut self, model: &str) -> Self {
+        self.requested_model = Some(model.to_string());
+        self
+    }
+
+    /// Attach the routing mode string (`"dispatch"` or `"escalate"`).
+    pub fn with_routing_mode(mut self, mode: &str) -> Self {
+        self.routing_mode = Some(mode.to_string());
+        self
+    }
+
+    /// Mark this entry as having been escalated to a higher tier.
+    pub fn mark_escalated(mut self) -> Self {
+        self.escalated = true;
+        self
+    }
+
+    /// Attach an error description for failed requests.
+    pub fn with_error(mut self, err: &str) -> Self {
+        self.error = Some(err.to_string());
+        self
+    }
+
+    /// Override the auto-generated UUID with a specific ID.
+    ///
+    /// Used to unify the `TrafficEntry` ID with the inbound `X-Request-ID`,
+    /// so the admin traffic view, log output, and client response headers all
+    /// reference the same identifier.
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.to_string();
+        self
+    }
+
+    /// Record how the response cache served the request.
+    pub fn with_cache(mut self, outcome: CacheOutcome) -> Self {
+        self.cache = Some(outcome);
+        self
+    }
+
+    /// Record a response served from the cache.
+    pub fn with_cache_hit(mut self, hit: &CacheHit) -> Self {
+        self.cache = Some(hit.outcome());
+        self.cache_similarity = hit.similarity;
+        self
+    }
+
+    /// Mark the entry as a replay of entry `id`.
+    pub fn with_replay_of(mut self, id: &str) -> Self {
+        self.replay_of = Some(id.to_string());
+        self
+    }
+
+    /// Attach the scheduling priority from `X-LMG-Priority`.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Attach token usage.
+    ///
+    /// `generation` is the time spent producing the completion, used for
+    /// `tokens_per_sec`. `total` falls back to `prompt + completion`.
+    pub fn with_usage(
+        mut self,
+        prompt: Option<u64>,
+        completion: Option<u64>,
+        total: Option<u64>,
+        generation: std::time::Duration,
+    ) -> Self {
+        self.prompt_tokens = prompt;
+        self.completion_tokens = completion;
+        self.total_tokens = total.or_else(|| Some(prompt? + completion?));
+        let secs = generation.as_secs_f64();
+        self.tokens_per_sec = completion.filter(|_| secs > 0.0).map(|n| n as f64 / secs);
+        self
+    }
+
+    /// Attach token usage from a non-streaming OpenAI- or Ollama-format
+    /// response, rating throughput over the whole request latency.
+    pub fn with_response_usage(self, response: &Value) -> Self {
+        let (prompt, completion) = crate::telemetry::usage_tokens(response);
+        let total = response.pointer("/usage/total_tokens").and_then(Value::as_u64);
+        let latency = std::time::Duration::from_millis(self.latency_ms);
+        self.with_usage(prompt, completion, total, latency)
+    }
+
+    /// Attach the full request body for debugging.
+    ///
+    /// Only available when compiled with `--features debug-traffic`.
+    /// The body is stored behind [`Arc`] so that cloning the entry doesn't
+    /// duplicate the allocation.
+    #[cfg(feature = "debug-traffic")]
+    pub fn with_debug_request_body(mut self, body: Value) -> Self {
+        self.debug_request_body = Some(Arc::new(body));
+        self
+    }
+
+    /// The captured request body, when `debug-traffic` capture was on.
+    pub fn captured_request_body(&self) -> Option<Value> {
+        #[cfg(feature = "debug-traffic")]
+        return self.debug_request_body.as_deref().cloned();
+        #[cfg(not(feature = "debug-traffic"))]
+        None
+    }
+
+    /// Attach routing trace from a classify-mode resolution.
+    ///
+    /// Records the class label (e.g. `"greeting"`) and the ordered chain of
+    /// profiles traversed during cascade routing. Used to populate
+    /// `X-LMG-Class` and `X-LMG-Profile` response headers.
+    pub fn with_routing_trace(mut self, class_label: String, profile_chain: Vec<String>) -> Self {
+        if !class_label.is_empty() {
+            self.class_label = Some(class_label);
+        }
+        if !profile_chain.is_empty() {
+            self.profile_chain = Some(profile_chain);
+        }
+        self
+    }
+
+    /// Merge routing decisions into the entry's [`RoutingDetail`].
+    pub fn with_routing_detail(mut self, detail: RoutingDetail) -> Self {
+        if !detail.is_empty() {
+            self.routing_mut().merge(detail);
+        }
+        self
+    }
+
+    /// The entry's routing detail, created empty on first use.
+    pub fn routing_mut(&mut self) -> &mut RoutingDetail {
+        self.routing.get_or_insert_with(RoutingDetail::default)
+    }
+}
+
+/// How a request was routed, for the dashboard's request detail view.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RoutingDetail {
+    /// Tags parsed from each classifier reply, keyed by profile.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, BTreeMap<String, String>>,
+    /// Profile rules in evaluation order, up to and including the match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleEvaluation>,
+    /// Tier changes forced by the estimated prompt size or a missing capability.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_bumps: Vec<ContextBump>,
+    /// Escalate-mode tiers passed over, cheapest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_tiers: Vec<SkippedTier>,
+    /// Backend attempts after the first one failed.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    /// Classify mode reused an earlier decision instead of classifying.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<PinSource>,
+    /// Request started on a predicted tier while the classifier ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speculation: Option<Speculation>,
+    /// Variant a traffic split assigned the request to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<SplitAssignment>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+impl RoutingDetail {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Append `other`'s decisions after this one's.
+    pub fn merge(&mut self, other: RoutingDetail) {
+        self.tags.extend(other.tags);
+        self.rules.extend(other.rules);
+        self.context_bumps.extend(other.context_bumps);
+        self.skipped_tiers.extend(other.skipped_tiers);
+        self.retries += other.retries;
+        self.pinned = self.pinned.or(other.pinned);
+        self.speculation = self.speculation.take().or(other.speculation);
+        self.split = self.split.take().or(other.split);
+    }
+
+    /// Record a bump from tier `from` to tier `to`; `missing_capabilities`
+    /// is empty when only the context window forced it.
+    pub fn bump(&mut self, from: &str, to: &str, estimated_tokens: u32, missing_capabilities: Vec<Capability>) {
+        self.context_bumps.push(ContextBump {
+            from: from.to_string(),
+            to: to.to_string(),
+            estimated_tokens,
+            missing_capabilities,
+        });
+    }
+}
+
+/// One `[[profiles.*.rules]]` entry checked against the classifier tags.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RuleEvaluation {
+    pub profile: String,
+    pub route_to: String,
+    pub outcome: RuleOutcome,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOutcome {
+    /// The rule's `when` tags matched and it decided the route.
+    Matched,
+    /// At least one `when` tag was missing or different.
+    NoMatch,
+    /// The rule matched but would cascade into a profile already visited.
+    Cycle,
+}
+
+/// A speculative request and what became of it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Speculation {
+    /// Tier the request was started on before classification finished.
+    pub tier: String,
+    pub outcome: SpeculationOutcome,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeculationOutcome {
+    /// Classification agreed; the speculative response was returned.
+    Kept,
+    /// Classification disagreed (or the speculative call failed) after the
+    /// speculative response had already arrived.
+    Discarded,
+    /// Classification disagreed while the speculative call was in flight; it
+    /// was dropped, closing the backend connection.
+    Cancelled,
+}
+
+impl SpeculationOutcome {
+    /// Metric label value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Kept => "kept",
+            Self::Discarded => "discarded",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A request's place in a traffic split.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SplitAssignment {
+    /// The split's key — the tier (or alias) whose traffic is shared out.
+    pub split: String,
+    /// Variant tier serving the request.
+    pub variant: String,
+}
+
+/// What a pinned routing decision was recalled by.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PinSource {
+    /// The request continues a tool loop whose calling request was routed.
+    ToolLoop,
+    /// The request's `X-LMG-Conversation-Id` matched a recent decision.
+    Conversation,
+}
+
+/// A tier replaced because the request would not fit its context window or
+/// needs a capability the tier lacks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextBump {
+    pub from: String,
+    pub to: String,
+    pub estimated_tokens: u32,
+    /// Capabilities the request needs that `from` is declared to lack.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_capabilities: Vec<Capability>,
+}
+
+/// A tier escalate mode moved past, and why.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SkippedTier {
+    pub tier: String,
+    /// `context_window`, `missing_capability`, `unhealthy`, `error` or
+    /// `insufficient`.
+    pub reason: String,
+}
+
+/// Per-backend health summary derived from recent traffic entries.
+///
+/// Returned by [`TrafficLog::backend_health`]. A backend needs at least 3
+/// samples in the window before it can be classified as unhealthy.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendHealthStats {
+    /// Number of recent entries for this backend within the window.
+    pub total: usize,
+    /// Number of those entries that were errors.
+    pub errors: usize,
+    /// Error fraction in `[0.0, 1.0]`.
+    pub error_rate: f64,
+    /// `true` if the backend passes the health threshold (or has < 3 samples).
+    pub healthy: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_entry(tier: &str, latency_ms: u64) -> TrafficEntry {
+        TrafficEntry::new(tier.into(), "test-backend".into(), latency_ms, true)
+    }
+
+    // -----------------------------------------------------------------------
+    // Basic push / read
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn push_and_retrieve_single_entry() {
+        let log = TrafficLog::new(10);
+        log.push(make_entry("local:fast", 42));
+
+        let recent = log.recent(10);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].tier, "local:fast");
+        assert_eq!(recent[0].latency_ms, 42);
+    }
+
+    #[tokio::test]
+    async fn recent_returns_entries_newest_first() {
+        let log = TrafficLog::new(10);
+        log.push(make_entry("local:fast", 1));
+        log.push(make_entry("cloud:economy", 2));
+        log.push(make_entry("cloud:standard", 3));
+
+        let recent = log.recent(10);
+        // Newest first
+        assert_eq!(recent[0].tier, "cloud:standard");
+        assert_eq!(recent[1].tier, "cloud:economy");
+        assert_eq!(recent[2].tier, "local:fast");
+    }
+
+    #[tokio::test]
+    async fn recent_limits_result_count() {
+        let log = TrafficLog::new(20);
+        for i in 0..10u64 {
+            log.push(make_entry("local:fast", i));
+        }
+        let recent = log.recent(3);
+        assert_eq!(recent.len(), 3);
+    }
+
+    // -----------------------------------------------------------------------
+    // Ring-buffer overflow
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn oldest_entry_evicted_when_capacity_exceeded() {
+        let log = TrafficLog::new(3);
+        log.push(make_entry("oldest", 1));
+        log.push(make_entry("middle", 2));
+        log.push(make_entry("newest", 3));
+        // This push should evict "oldest"
+        log.push(make_entry("extra", 4));
+
+        let all = log.recent(100);
+        assert_eq!(all.len(), 3);
+        // "oldest" must be gone
+        assert!(!all.iter().any(|e| e.tier == "oldest"));
+        // "extra" must be present
+        assert!(all.iter().any(|e| e.tier == "extra"));
+    }
+
+    #[test]
+    fn concurrent_pushes_are_never_dropped() {
+        let log = Arc::new(TrafficLog::new(1_000));
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let log = Arc::clone(&log);
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        log.push(make_entry("local:fast", i));
+                    }
+                })
+            })
+            .collect();
+        // Readers run alongside the writers without blocking them.
+        for _ in 0..50 {
+            let _ = log.recent(10);
+            let _ = log.stats();
+        }
+        for w in writers {
+            w.join().unwrap();
+        }
+        assert_eq!(log.recent(usize::MAX).len(), 800);
+        assert_eq!(log.stats().total_requests, 800);
+        assert_eq!(log.dropped(), 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // Query / live tail
+    // -----------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn subscribers_receive_pushed_entries() {
+        let log = TrafficLog::new(10);
+        let mut rx = log.subscribe();
+        log.push(make_entry("local:fast", 7));
+        assert_eq!(rx.recv().await.unwrap().latency_ms, 7);
+    }
+
+    // -----------------------------------------------------------------------
+    // TrafficEntry fields
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn entry_has_unique_ids() {
+        let a = make_entry("local:fast", 1);
+        let b = make_entry("local:fast", 1);
+        assert_ne!(a.id, b.id, "every entry must have a unique UUID");
+    }
+
+    #[test]
+    fn response_usage_fills_tokens_and_throughput() {
+        let response = serde_json::json!({ "usage": { "prompt_tokens": 40, "completion_tokens": 100 } });
+        let entry = make_entry("local:fast", 2_000).with_response_usage(&response);
+        assert_eq!(entry.prompt_tokens, Some(40));
+        assert_eq!(entry.completion_tokens, Some(100));
+        assert_eq!(entry.total_tokens, Some(140));
+        assert_eq!(entry.tokens_per_sec, Some(50.0));
+
+        let bare = make_entry("local:fast", 0).with_response_usage(&serde_json::json!({}));
+        assert_eq!(bare.total_tokens, None);
+        assert_eq!(bare.tokens_per_sec, None);
+    }
+
+    #[test]
+    fn entry_records_success_flag() {
+        let ok = TrafficEntry::new("t".into(), "b".into(), 0, true);
+        let err = TrafficEntry::new("t".into(), "b".into(), 0, false);
+        assert!(ok.success);
+        assert!(!err.success);
+    }
+
+    // -----------------------------------------------------------------------
+    // debug-traffic builder
+    // -----------------------------------------------------------------------
+
+    #[cfg(feature = "debug-traffic")]
+    #[test]
+    fn with_debug_request_body_populates_field() {
+        use serde_json::json;
+        let body = json!({
+            "model": "hint:fast",
+            "messages": [{"role": "user", "content": "hello"}]
+        });
+        let entry = TrafficEntry::new("local:fast".into(), "mock".into(), 10, true)
+            .with_debug_request_body(body.clone());
+        assert_eq!(
+            entry.debug_request_body.as_deref(),
+            Some(&body),
+            "debug_request_body must equal the body passed to with_debug_request_body"
+        );
+    }
+
+    #[cfg(feature = "debug-traffic")]
+    #[test]
+    fn with_debug_request_body_clone_is_independent() {
+        use serde_json::json;
+        let mut body = json!({"model": "hint:fast", "messages": []});
+        let entry = TrafficEntry::new("local:fast".into(), "mock".into(), 10, true)
+            .with_debug_request_body(body.clone());
+        // Mutate the original — the captured copy must be unaffected.
+        body["model"] = json!("mutated");
+        assert_eq!(
+            entry.debug_request_body.as_ref().and_then(|b| b["model"].as_str()),
+            Some("hint:fast")
+        );
+    }
+}
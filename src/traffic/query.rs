@@ -0,0 +1,153 @@
+//! Filtered, paginated reads of a [`TrafficLog`].
+//!
+//! Pages are addressed by entry sequence number, so a cursor stays valid
+//! while new entries keep arriving.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{window::WindowAggregates, TrafficEntry, TrafficLog, TrafficStats};
+
+impl TrafficLog {
+    /// Entries matching `filter`, newest first, `limit` at a time.
+    ///
+    /// Pass the previous page's `next_cursor` as `before` to continue. The
+    /// `stats` cover every buffered match, not just the returned page.
+    pub fn query(&self, filter: &TrafficFilter, before: Option<u64>, limit: usize) -> TrafficPage {
+        let mut matched = WindowAggregates::default();
+        let mut entries = Vec::new();
+        let mut last_seq = None;
+        let mut has_more = false;
+        for slot in self.snapshot().iter().filter(|s| filter.matches(&s.entry)) {
+            matched.add(&slot.entry);
+            if before.is_some_and(|before| slot.seq >= before) {
+                continue;
+            }
+            if entries.len() < limit {
+                entries.push(slot.entry.clone());
+                last_seq = Some(slot.seq);
+            } else {
+                has_more = true;
+            }
+        }
+        let next_cursor = last_seq.filter(|_| has_more);
+        TrafficPage { stats: matched.stats(), entries, next_cursor }
+    }
+
+    /// Every buffered entry matching `filter`, newest first.
+    pub fn matching(&self, filter: &TrafficFilter) -> Vec<TrafficEntry> {
+        self.snapshot()
+            .iter()
+            .filter(|slot| filter.matches(&slot.entry))
+            .map(|slot| slot.entry.clone())
+            .collect()
+    }
+}
+
+/// Criteria for [`TrafficLog::query`] and the live tail. Unset fields match
+/// everything; string fields match exactly.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TrafficFilter {
+    pub profile: Option<String>,
+    pub tier: Option<String>,
+    pub backend: Option<String>,
+    pub class_label: Option<String>,
+    pub success: Option<bool>,
+    /// Inclusive lower bound on `priority`.
+    pub min_priority: Option<i32>,
+    /// Inclusive upper bound on `priority`.
+    pub max_priority: Option<i32>,
+    /// Entries at or after this time (RFC 3339).
+    pub since: Option<DateTime<Utc>>,
+    /// Entries before this time (RFC 3339).
+    pub until: Option<DateTime<Utc>>,
+    /// Request ID (`X-Request-ID`).
+    pub id: Option<String>,
+    /// Client `key_env`.
+    pub client: Option<String>,
+    /// Traffic split key; its variants are told apart by `tier`.
+    pub split: Option<String>,
+}
+
+impl TrafficFilter {
+    pub fn matches(&self, e: &TrafficEntry) -> bool {
+        fn eq(want: &Option<String>, have: Option<&str>) -> bool {
+            want.as_deref().is_none_or(|w| have == Some(w))
+        }
+        eq(&self.profile, e.profile.as_deref())
+            && eq(&self.tier, Some(&e.tier))
+            && eq(&self.backend, Some(&e.backend))
+            && eq(&self.class_label, e.class_label.as_deref())
+            && eq(&self.id, Some(&e.id))
+            && eq(&self.client, e.client.as_deref())
+            && eq(&self.split, e.routing.as_ref().and_then(|r| r.split.as_ref()).map(|s| s.split.as_str()))
+            && self.success.is_none_or(|s| e.success == s)
+            && self.min_priority.is_none_or(|p| e.priority >= p)
+            && self.max_priority.is_none_or(|p| e.priority <= p)
+            && self.since.is_none_or(|t| e.timestamp >= t)
+            && self.until.is_none_or(|t| e.timestamp < t)
+    }
+}
+
+/// One page of [`TrafficLog::query`] results.
+#[derive(Debug, Serialize)]
+pub struct TrafficPage {
+    /// Aggregates over every buffered entry matching the filter.
+    pub stats: TrafficStats,
+    pub entries: Vec<TrafficEntry>,
+    /// Pass as `before` to fetch the next (older) page; `None` on the last page.
+    pub next_cursor: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_entry(tier: &str, latency_ms: u64) -> TrafficEntry {
+        TrafficEntry::new(tier.into(), "test-backend".into(), latency_ms, true)
+    }
+
+    #[tokio::test]
+    async fn query_filters_and_paginates_with_stable_cursor() {
+        let log = TrafficLog::new(10);
+        for i in 0..6u64 {
+            let mut entry = make_entry(if i % 2 == 0 { "local:fast" } else { "cloud:economy" }, i)
+                .with_profile("ha-auto");
+            entry.success = i != 4;
+            log.push(entry);
+        }
+        let filter = TrafficFilter { tier: Some("local:fast".into()), ..Default::default() };
+
+        let first = log.query(&filter, None, 2);
+        assert_eq!(first.entries.iter().map(|e| e.latency_ms).collect::<Vec<_>>(), vec![4, 2]);
+        assert_eq!(first.stats.total_requests, 3);
+        assert_eq!(first.stats.error_count, 1);
+        assert_eq!(first.stats.profile_counts["ha-auto"], 3);
+
+        // New traffic must not shift the next page.
+        log.push(make_entry("local:fast", 99));
+        let second = log.query(&filter, first.next_cursor, 2);
+        assert_eq!(second.entries.iter().map(|e| e.latency_ms).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(second.next_cursor, None);
+
+        let failed = TrafficFilter { success: Some(false), ..Default::default() };
+        assert_eq!(log.query(&failed, None, 10).entries[0].latency_ms, 4);
+    }
+
+    #[tokio::test]
+    async fn filter_matches_priority_range_time_and_client() {
+        let entry = make_entry("local:fast", 1).with_priority(5).with_client("HA_KEY");
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        let in_range = TrafficFilter {
+            min_priority: Some(0),
+            max_priority: Some(5),
+            since: Some(hour_ago),
+            client: Some("HA_KEY".into()),
+            ..Default::default()
+        };
+        assert!(in_range.matches(&entry));
+        assert!(!TrafficFilter { max_priority: Some(4), ..Default::default() }.matches(&entry));
+        assert!(!TrafficFilter { until: Some(hour_ago), ..Default::default() }.matches(&entry));
+        assert!(!TrafficFilter { class_label: Some("greeting".into()), ..Default::default() }.matches(&entry));
+    }
+}
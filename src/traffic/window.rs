@@ -0,0 +1,249 @@
+//! Aggregates over the entries currently in a [`TrafficLog`].
+//!
+//! [`WindowAggregates`] is updated incrementally as entries are pushed and
+//! evicted; the log republishes a copy after every push, which the readers
+//! here load without locking.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::{TrafficEntry, TrafficLog};
+
+impl TrafficLog {
+    /// Compute public-safe aggregate statistics (no tier/backend names).
+    pub fn public_stats(&self) -> PublicStats {
+        let window = self.published.load();
+        PublicStats {
+            total_requests: window.total,
+            error_count: window.errors,
+            escalation_count: window.escalations,
+            avg_latency_ms: window.avg_latency_ms(),
+        }
+    }
+
+    /// Compute aggregate statistics over all buffered entries.
+    pub fn stats(&self) -> TrafficStats {
+        self.published.load().stats()
+    }
+
+    /// Per-`(tier, backend)` request counts and latency sums for `/metrics`.
+    pub fn window_breakdown(&self) -> WindowBreakdown {
+        let window = self.published.load();
+        WindowBreakdown {
+            total: window.total,
+            errors: window.errors,
+            escalations: window.escalations,
+            requests: window.requests.clone(),
+            latency_ms: window.latency_ms.clone(),
+        }
+    }
+}
+
+/// Running totals over the entries currently in the window.
+#[derive(Debug, Clone, Default)]
+pub(super) struct WindowAggregates {
+    total: usize,
+    errors: usize,
+    escalations: usize,
+    latency_sum_ms: u64,
+    total_tokens: u64,
+    tier_counts: HashMap<String, usize>,
+    backend_counts: HashMap<String, usize>,
+    profile_counts: HashMap<String, usize>,
+    class_counts: HashMap<String, usize>,
+    requests: HashMap<(String, String, bool), u64>,
+    latency_ms: HashMap<(String, String), (u64, u64)>,
+}
+
+impl WindowAggregates {
+    pub(super) fn add(&mut self, e: &TrafficEntry) {
+        self.apply(e, true);
+    }
+
+    pub(super) fn remove(&mut self, e: &TrafficEntry) {
+        self.apply(e, false);
+    }
+
+    fn apply(&mut self, e: &TrafficEntry, add: bool) {
+        fn step(n: &mut usize, add: bool) {
+            if add { *n += 1 } else { *n -= 1 }
+        }
+        fn count(map: &mut HashMap<String, usize>, key: &str, add: bool) {
+            if add {
+                *map.entry(key.to_owned()).or_default() += 1;
+            } else if let Some(n) = map.get_mut(key) {
+                *n -= 1;
+                if *n == 0 {
+                    map.remove(key);
+                }
+            }
+        }
+        step(&mut self.total, add);
+        if !e.success {
+            step(&mut self.errors, add);
+        }
+        if e.escalated {
+            step(&mut self.escalations, add);
+        }
+        let tokens = e.total_tokens.unwrap_or(0);
+        if add {
+            self.latency_sum_ms += e.latency_ms;
+            self.total_tokens += tokens;
+        } else {
+            self.latency_sum_ms -= e.latency_ms;
+            self.total_tokens -= tokens;
+        }
+        count(&mut self.tier_counts, &e.tier, add);
+        count(&mut self.backend_counts, &e.backend, add);
+        count(&mut self.profile_counts, e.profile.as_deref().unwrap_or("default"), add);
+        if let Some(label) = &e.class_label {
+            count(&mut self.class_counts, label, add);
+        }
+
+        let key = (e.tier.clone(), e.backend.clone(), e.success);
+        if add {
+            *self.requests.entry(key).or_default() += 1;
+        } else if let Some(n) = self.requests.get_mut(&key) {
+            *n -= 1;
+            if *n == 0 {
+                self.requests.remove(&key);
+            }
+        }
+        let key = (e.tier.clone(), e.backend.clone());
+        if add {
+            let lat = self.latency_ms.entry(key).or_default();
+            lat.0 += e.latency_ms;
+            lat.1 += 1;
+        } else if let Some(lat) = self.latency_ms.get_mut(&key) {
+            lat.0 -= e.latency_ms;
+            lat.1 -= 1;
+            if lat.1 == 0 {
+                self.latency_ms.remove(&key);
+            }
+        }
+    }
+
+    fn avg_latency_ms(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.latency_sum_ms as f64 / self.total as f64
+        }
+    }
+
+    pub(super) fn stats(&self) -> TrafficStats {
+        TrafficStats {
+            total_requests: self.total,
+            error_count: self.errors,
+            escalation_count: self.escalations,
+            avg_latency_ms: self.avg_latency_ms(),
+            tier_counts: self.tier_counts.clone(),
+            backend_counts: self.backend_counts.clone(),
+            profile_counts: self.profile_counts.clone(),
+            class_counts: self.class_counts.clone(),
+            total_tokens: self.total_tokens,
+        }
+    }
+}
+
+/// Window gauges for `/metrics`, read from the incremental aggregates.
+#[derive(Debug)]
+pub struct WindowBreakdown {
+    pub total: usize,
+    pub errors: usize,
+    pub escalations: usize,
+    /// `(tier, backend, success)` → request count.
+    pub requests: HashMap<(String, String, bool), u64>,
+    /// `(tier, backend)` → `(latency sum in ms, count)`.
+    pub latency_ms: HashMap<(String, String), (u64, u64)>,
+}
+
+/// Aggregate statistics derived from all buffered [`TrafficEntry`] records.
+#[derive(Debug, Serialize)]
+pub struct TrafficStats {
+    pub total_requests: usize,
+    /// Number of requests that returned an error.
+    pub error_count: usize,
+    /// Number of requests that were escalated to a higher tier.
+    pub escalation_count: usize,
+    pub avg_latency_ms: f64,
+    pub tier_counts: HashMap<String, usize>,
+    pub backend_counts: HashMap<String, usize>,
+    /// Requests per profile (`"default"` when none was recorded).
+    pub profile_counts: HashMap<String, usize>,
+    /// Requests per classifier class label, for classified requests.
+    pub class_counts: HashMap<String, usize>,
+    /// Sum of reported prompt and completion tokens.
+    pub total_tokens: u64,
+}
+
+/// Public-safe aggregate statistics — no backend or tier names included.
+///
+/// Safe to return from an unauthenticated endpoint: contains only counts and
+/// latency data, never any configuration detail that could reveal infrastructure.
+#[derive(Debug, Serialize)]
+pub struct PublicStats {
+    pub total_requests: usize,
+    pub error_count: usize,
+    pub escalation_count: usize,
+    pub avg_latency_ms: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_entry(tier: &str, latency_ms: u64) -> TrafficEntry {
+        TrafficEntry::new(tier.into(), "test-backend".into(), latency_ms, true)
+    }
+
+    #[test]
+    fn aggregates_follow_eviction() {
+        let log = TrafficLog::new(2);
+        log.push(make_entry("oldest", 100).with_profile("ha"));
+        log.push(make_entry("middle", 200));
+        log.push(make_entry("newest", 300));
+
+        let stats = log.stats();
+        assert_eq!(stats.total_requests, 2);
+        assert!((stats.avg_latency_ms - 250.0).abs() < f64::EPSILON);
+        assert!(!stats.tier_counts.contains_key("oldest"));
+        assert!(!stats.profile_counts.contains_key("ha"));
+        assert_eq!(log.public_stats().total_requests, 2);
+    }
+
+    #[tokio::test]
+    async fn stats_on_empty_log() {
+        let log = TrafficLog::new(10);
+        let stats = log.stats();
+        assert_eq!(stats.total_requests, 0);
+        assert_eq!(stats.avg_latency_ms, 0.0);
+        assert!(stats.tier_counts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stats_averages_latency_correctly() {
+        let log = TrafficLog::new(10);
+        log.push(make_entry("local:fast", 100));
+        log.push(make_entry("local:fast", 200));
+        log.push(make_entry("cloud:economy", 300));
+
+        let stats = log.stats();
+        assert_eq!(stats.total_requests, 3);
+        // Average: (100 + 200 + 300) / 3 = 200.0
+        assert!((stats.avg_latency_ms - 200.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn stats_counts_requests_per_tier() {
+        let log = TrafficLog::new(10);
+        log.push(make_entry("local:fast", 10));
+        log.push(make_entry("local:fast", 20));
+        log.push(make_entry("cloud:economy", 30));
+
+        let stats = log.stats();
+        assert_eq!(stats.tier_counts["local:fast"], 2);
+        assert_eq!(stats.tier_counts["cloud:economy"], 1);
+    }
+}
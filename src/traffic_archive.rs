@@ -0,0 +1,442 @@
+//! Persistent JSONL archive of [`TrafficEntry`] records.
+//!
+//! Opt-in via `gateway.traffic_log_path`. Every entry pushed to the
+//! [`TrafficLog`][crate::TrafficLog] is also handed to a [`TrafficArchive`],
+//! which forwards it over a bounded channel to a dedicated writer thread. The
+//! request path never blocks on disk I/O: when the channel is full the entry is
+//! dropped from the archive (it still reaches the in-memory ring buffer) and
+//! the writer logs how many were lost.
+//!
+//! The writer appends one JSON object per line to the active file and rotates
+//! it when it exceeds `traffic_log_rotate_mb` or is older than
+//! `traffic_log_rotate_hours`. Rotated segments are gzipped next to the active
+//! file and pruned by count (`traffic_log_retain_files`) and age
+//! (`traffic_log_retain_days`):
+//!
+//! ```text
+//! /var/lib/lm-gateway/
+//!   traffic.jsonl                          ← active segment
+//!   traffic-20261017T000000.000Z.jsonl.gz  ← rotated, gzipped
+//!   traffic-20261016T000000.000Z.jsonl.gz
+//! ```
+//!
+//! On startup, [`rehydrate`] reads the newest segments back so the admin
+//! traffic view and backend health windows survive restarts.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use tokio::sync::mpsc;
+
+use crate::{config::GatewayConfig, traffic::TrafficEntry};
+
+/// Entries buffered between the request path and the writer thread.
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Rotation and retention limits, taken from `[gateway]`.
+#[derive(Debug, Clone)]
+pub struct ArchiveSettings {
+    /// Active segment path, e.g. `/var/lib/lm-gateway/traffic.jsonl`.
+    pub path: PathBuf,
+    /// Rotate once the active segment reaches this many bytes (0 = never).
+    pub rotate_bytes: u64,
+    /// Rotate once the active segment is this old (zero = never).
+    pub rotate_age: Duration,
+    /// Rotated segments kept; older ones are deleted (0 = unlimited).
+    pub retain_files: usize,
+    /// Rotated segments older than this are deleted (zero = unlimited).
+    pub retain_age: Duration,
+}
+
+impl ArchiveSettings {
+    /// Settings from `[gateway]`, or `None` when `traffic_log_path` is unset.
+    ///
+    /// A relative `traffic_log_path` is resolved against `base_dir` (the
+    /// directory containing `config.toml`).
+    pub fn from_gateway(gateway: &GatewayConfig, base_dir: &Path) -> Option<Self> {
+        let path = gateway.traffic_log_path.as_deref()?;
+        Some(Self {
+            path: base_dir.join(path),
+            rotate_bytes: gateway.traffic_log_rotate_mb * 1024 * 1024,
+            rotate_age: Duration::from_secs(gateway.traffic_log_rotate_hours * 3600),
+            retain_files: gateway.traffic_log_retain_files,
+            retain_age: Duration::from_secs(gateway.traffic_log_retain_days * 86_400),
+        })
+    }
+}
+
+/// Non-blocking handle to the archive writer thread.
+#[derive(Debug, Clone)]
+pub struct TrafficArchive {
+    tx: mpsc::Sender<TrafficEntry>,
+    /// Lifetime count of entries not archived.
+    dropped: Arc<AtomicU64>,
+}
+
+impl TrafficArchive {
+    /// Open (or create) the active segment and start the writer thread.
+    pub fn start(settings: ArchiveSettings) -> anyhow::Result<Self> {
+        let writer = SegmentWriter::open(settings)?;
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&dropped);
+        std::thread::Builder::new()
+            .name("traffic-archive".into())
+            .spawn(move || writer.run(rx, &counter))
+            .context("spawning traffic archive writer")?;
+        Ok(Self { tx, dropped })
+    }
+
+    /// Queue `entry` for writing. Never blocks; drops the entry when the
+    /// writer is behind.
+    pub fn append(&self, entry: TrafficEntry) {
+        if self.tx.try_send(entry).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Entries dropped because the writer fell behind, since startup.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Owns the active segment file. Runs on the writer thread.
+struct SegmentWriter {
+    settings: ArchiveSettings,
+    file: BufWriter<File>,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl SegmentWriter {
+    fn open(settings: ArchiveSettings) -> anyhow::Result<Self> {
+        if let Some(dir) = settings.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&settings.path)
+            .with_context(|| format!("opening {}", settings.path.display()))?;
+        let meta = file.metadata()?;
+        // An existing segment keeps aging from when it was first written.
+        let opened_at = meta.created().or_else(|_| meta.modified()).unwrap_or_else(|_| SystemTime::now());
+        Ok(Self {
+            settings,
+            file: BufWriter::new(file),
+            size: meta.len(),
+            opened_at,
+        })
+    }
+
+    fn run(mut self, mut rx: mpsc::Receiver<TrafficEntry>, dropped: &AtomicU64) {
+        let mut reported = 0;
+        while let Some(entry) = rx.blocking_recv() {
+            self.write(&entry);
+            // Drain whatever else is queued before paying for a flush.
+            while let Ok(entry) = rx.try_recv() {
+                self.write(&entry);
+            }
+            if let Err(e) = self.file.flush() {
+                tracing::warn!(error = %e, "traffic archive flush failed");
+            }
+            let total = dropped.load(Ordering::Relaxed);
+            let lost = total - reported;
+            reported = total;
+            if lost > 0 {
+                tracing::warn!(dropped = lost, "traffic archive writer fell behind — entries not archived");
+            }
+        }
+    }
+
+    fn write(&mut self, entry: &TrafficEntry) {
+        if self.should_rotate() {
+            if let Err(e) = self.rotate() {
+                tracing::warn!(error = %format!("{e:#}"), "traffic archive rotation failed");
+            }
+        }
+        let mut line = match serde_json::to_vec(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to serialize traffic entry");
+                return;
+            }
+        };
+        line.push(b'\n');
+        match self.file.write_all(&line) {
+            Ok(()) => self.size += line.len() as u64,
+            Err(e) => tracing::warn!(error = %e, "traffic archive write failed"),
+        }
+    }
+
+    fn should_rotate(&self) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self.settings.rotate_bytes > 0 && self.size >= self.settings.rotate_bytes;
+        let too_old = !self.settings.rotate_age.is_zero()
+            && self.opened_at.elapsed().is_ok_and(|age| age >= self.settings.rotate_age);
+        too_big || too_old
+    }
+
+    /// Close the active segment, gzip it alongside, start a fresh one and
+    /// apply retention.
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        self.file.flush()?;
+        let path = &self.settings.path;
+        let rotated = segment_path(path, &Utc::now().format("%Y%m%dT%H%M%S%.3fZ").to_string());
+        compress(path, &rotated)?;
+
+        // A new file rather than a truncated one, so its creation time (used
+        // for age-based rotation after a restart) starts over.
+        fs::remove_file(path).with_context(|| format!("removing {}", path.display()))?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("reopening {}", path.display()))?;
+        self.file = BufWriter::new(file);
+        self.size = 0;
+        self.opened_at = SystemTime::now();
+        tracing::info!(segment = %rotated.display(), "traffic archive rotated");
+
+        prune(&self.settings);
+        Ok(())
+    }
+}
+
+/// `traffic.jsonl` + `stamp` → `traffic-<stamp>.jsonl.gz` in the same directory.
+fn segment_path(active: &Path, stamp: &str) -> PathBuf {
+    let (stem, _) = segment_affixes(active);
+    active.with_file_name(format!("{stem}{stamp}.jsonl.gz"))
+}
+
+/// File name prefix and suffix shared by every rotated segment of `active`.
+fn segment_affixes(active: &Path) -> (String, &'static str) {
+    let stem = active
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "traffic".into());
+    (format!("{stem}-"), ".jsonl.gz")
+}
+
+/// Rotated segments of `active`, oldest first.
+fn rotated_segments(active: &Path) -> Vec<PathBuf> {
+    let (prefix, suffix) = segment_affixes(active);
+    let dir = active.parent().unwrap_or(Path::new("."));
+    let Ok(read) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut segments: Vec<PathBuf> = read
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(suffix))
+        })
+        .collect();
+    // Timestamps sort lexicographically.
+    segments.sort();
+    segments
+}
+
+fn compress(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    let mut input = File::open(src).with_context(|| format!("opening {}", src.display()))?;
+    let tmp = dst.with_extension("gz.tmp");
+    let mut encoder = GzEncoder::new(
+        File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?,
+        Compression::default(),
+    );
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&tmp, dst).with_context(|| format!("renaming {}", tmp.display()))?;
+    Ok(())
+}
+
+/// Delete rotated segments beyond the count and age limits.
+fn prune(settings: &ArchiveSettings) {
+    let segments = rotated_segments(&settings.path);
+    let excess = match settings.retain_files {
+        0 => 0,
+        keep => segments.len().saturating_sub(keep),
+    };
+    for (i, segment) in segments.iter().enumerate() {
+        let expired = !settings.retain_age.is_zero()
+            && fs::metadata(segment)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age > settings.retain_age);
+        if i < excess || expired {
+            match fs::remove_file(segment) {
+                Ok(()) => tracing::debug!(segment = %segment.display(), "traffic archive segment pruned"),
+                Err(e) => tracing::warn!(segment = %segment.display(), error = %e, "failed to prune traffic archive segment"),
+            }
+        }
+    }
+}
+
+/// Read up to `limit` of the most recent archived entries, oldest first.
+///
+/// Starts with the active segment and walks back through rotated segments
+/// until `limit` entries are found. Unparseable lines (e.g. a line truncated
+/// by a crash) are skipped.
+pub fn rehydrate(active: &Path, limit: usize) -> Vec<TrafficEntry> {
+    let mut segments = rotated_segments(active);
+    segments.push(active.to_path_buf());
+
+    // Newest segment first; each segment's entries stay in file order.
+    let mut chunks: Vec<Vec<TrafficEntry>> = Vec::new();
+    let mut total = 0;
+    for segment in segments.iter().rev() {
+        if total >= limit {
+            break;
+        }
+        let entries = match read_segment(segment) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!(segment = %segment.display(), error = %format!("{e:#}"), "skipping unreadable traffic archive segment");
+                continue;
+            }
+        };
+        total += entries.len();
+        chunks.push(entries);
+    }
+
+    let mut entries: Vec<TrafficEntry> = chunks.into_iter().rev().flatten().collect();
+    let skip = entries.len().saturating_sub(limit);
+    entries.drain(..skip);
+    entries
+}
+
+fn read_segment(path: &Path) -> anyhow::Result<Vec<TrafficEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(BufReader::new(reader)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lmg-archive-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn settings(dir: &Path) -> ArchiveSettings {
+        ArchiveSettings {
+            path: dir.join("traffic.jsonl"),
+            rotate_bytes: 0,
+            rotate_age: Duration::ZERO,
+            retain_files: 0,
+            retain_age: Duration::ZERO,
+        }
+    }
+
+    fn entry(tier: &str) -> TrafficEntry {
+        TrafficEntry::new(tier.into(), "ollama".into(), 5, true)
+    }
+
+    #[test]
+    fn rotation_gzips_segments_and_rehydrate_reads_across_them() {
+        let dir = temp_dir();
+        let mut settings = settings(&dir);
+        // Every entry is larger than this, so each write rotates the previous one.
+        settings.rotate_bytes = 1;
+        let mut writer = SegmentWriter::open(settings.clone()).unwrap();
+        for tier in ["a", "b", "c"] {
+            writer.write(&entry(tier));
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        writer.file.flush().unwrap();
+
+        assert_eq!(rotated_segments(&settings.path).len(), 2);
+        let tiers: Vec<String> = rehydrate(&settings.path, 10).into_iter().map(|e| e.tier).collect();
+        assert_eq!(tiers, vec!["a", "b", "c"]);
+
+        let newest: Vec<String> = rehydrate(&settings.path, 2).into_iter().map(|e| e.tier).collect();
+        assert_eq!(newest, vec!["b", "c"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn retention_keeps_newest_segments() {
+        let dir = temp_dir();
+        let mut settings = settings(&dir);
+        settings.rotate_bytes = 1;
+        settings.retain_files = 2;
+        let mut writer = SegmentWriter::open(settings.clone()).unwrap();
+        for tier in ["a", "b", "c", "d", "e"] {
+            writer.write(&entry(tier));
+            // Distinct millisecond timestamps in segment names.
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        writer.file.flush().unwrap();
+
+        assert_eq!(rotated_segments(&settings.path).len(), 2);
+        let tiers: Vec<String> = rehydrate(&settings.path, 10).into_iter().map(|e| e.tier).collect();
+        assert_eq!(tiers, vec!["c", "d", "e"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn rehydrate_skips_corrupt_lines() {
+        let dir = temp_dir();
+        let path = dir.join("traffic.jsonl");
+        let good = serde_json::to_string(&entry("ok")).unwrap();
+        fs::write(&path, format!("{good}\n{{\"truncated\n")).unwrap();
+
+        let entries = rehydrate(&path, 10);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].tier, "ok");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn archive_appends_off_the_request_path() {
+        let dir = temp_dir();
+        let settings = settings(&dir);
+        let archive = TrafficArchive::start(settings.clone()).unwrap();
+        archive.append(entry("async"));
+
+        // The writer thread flushes after draining the channel.
+        for _ in 0..100 {
+            if !rehydrate(&settings.path, 10).is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(rehydrate(&settings.path, 10)[0].tier, "async");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,331 @@
+//! Push export of [`TrafficEntry`] records to an HTTP collector.
+//!
+//! Opt-in via `gateway.traffic_webhook_url`. Like the
+//! [archive][crate::traffic_archive], every entry pushed to the
+//! [`TrafficLog`][crate::TrafficLog] is handed to a [`TrafficWebhook`] over a
+//! bounded channel, so the request path never waits on the collector. A
+//! background task groups entries into batches of up to
+//! `traffic_webhook_batch_size`, flushed at least every
+//! `traffic_webhook_flush_ms`, and POSTs each batch as:
+//!
+//! ```json
+//! { "entries": [ { "id": "...", "tier": "local:fast", ... } ] }
+//! ```
+//!
+//! Failed deliveries (network errors, `429` and `5xx`) are retried with
+//! exponential backoff. Entries are dropped — and counted in `/metrics` — when
+//! the buffer is full or a batch exhausts its retries.
+//!
+//! When `traffic_webhook_secret_env` is set, each request carries
+//! `X-LMG-Signature: sha256=<hex>`, the HMAC-SHA256 of the raw body keyed with
+//! that secret, so receivers can verify the batch came from this gateway.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::{config::GatewayConfig, traffic::TrafficEntry};
+
+/// Entries buffered while a batch is being delivered or retried.
+const BUFFER_CAPACITY: usize = 10_000;
+
+/// Delivery attempts per batch before it is dropped.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Longest wait between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Signature header name.
+pub const SIGNATURE_HEADER: &str = "X-LMG-Signature";
+
+/// Delivery settings, taken from `[gateway]`.
+#[derive(Debug, Clone)]
+pub struct WebhookSettings {
+    pub url: String,
+    /// Extra request headers (e.g. an `Authorization` for the collector).
+    pub headers: Vec<(String, String)>,
+    /// HMAC-SHA256 signing key; `None` sends unsigned batches.
+    pub secret: Option<String>,
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    /// Delay before the first retry; doubles on each further attempt.
+    pub retry_base: Duration,
+}
+
+impl WebhookSettings {
+    /// Settings from `[gateway]`, or `None` when `traffic_webhook_url` is unset.
+    ///
+    /// The signing secret is read from `traffic_webhook_secret_env` once, here.
+    pub fn from_gateway(gateway: &GatewayConfig) -> Option<Self> {
+        let url = gateway.traffic_webhook_url.clone()?;
+        let secret = gateway.traffic_webhook_secret_env.as_deref().and_then(|var| {
+            let secret = std::env::var(var).ok().filter(|s| !s.is_empty());
+            if secret.is_none() {
+                tracing::warn!(var, "traffic_webhook_secret_env is not set — webhook batches will be unsigned");
+            }
+            secret
+        });
+        let mut headers: Vec<(String, String)> = gateway
+            .traffic_webhook_headers
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        headers.sort();
+        Some(Self {
+            url,
+            headers,
+            secret,
+            batch_size: gateway.traffic_webhook_batch_size.max(1),
+            flush_interval: Duration::from_millis(gateway.traffic_webhook_flush_ms.max(1)),
+            retry_base: Duration::from_millis(500),
+        })
+    }
+}
+
+/// Lifetime delivery counters, rendered by `/metrics`.
+#[derive(Debug, Default)]
+pub struct WebhookStats {
+    /// Entries acknowledged by the collector.
+    pub sent: AtomicU64,
+    /// Entries dropped because the buffer was full.
+    pub dropped_buffer_full: AtomicU64,
+    /// Entries dropped after a batch failed permanently.
+    pub dropped_delivery_failed: AtomicU64,
+    /// Failed delivery attempts that were retried.
+    pub retries: AtomicU64,
+}
+
+/// Non-blocking handle to the webhook delivery task.
+#[derive(Debug, Clone)]
+pub struct TrafficWebhook {
+    tx: mpsc::Sender<TrafficEntry>,
+    stats: Arc<WebhookStats>,
+}
+
+impl TrafficWebhook {
+    /// Spawn the delivery task on the current Tokio runtime.
+    pub fn start(settings: WebhookSettings) -> Self {
+        let (tx, rx) = mpsc::channel(BUFFER_CAPACITY);
+        let stats = Arc::new(WebhookStats::default());
+        tokio::spawn(deliver_loop(settings, rx, Arc::clone(&stats)));
+        Self { tx, stats }
+    }
+
+    /// Queue `entry` for export. Never blocks; counts a drop when the buffer
+    /// is full.
+    pub fn send(&self, entry: TrafficEntry) {
+        if self.tx.try_send(entry).is_err() {
+            self.stats.dropped_buffer_full.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> &WebhookStats {
+        &self.stats
+    }
+}
+
+async fn deliver_loop(
+    settings: WebhookSettings,
+    mut rx: mpsc::Receiver<TrafficEntry>,
+    stats: Arc<WebhookStats>,
+) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default();
+
+    while let Some(first) = rx.recv().await {
+        // Fill the batch until it is full or the flush interval has passed.
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + settings.flush_interval;
+        while batch.len() < settings.batch_size {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(entry)) => batch.push(entry),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        deliver(&client, &settings, &batch, &stats).await;
+    }
+}
+
+/// POST one batch, retrying transient failures with exponential backoff.
+async fn deliver(
+    client: &reqwest::Client,
+    settings: &WebhookSettings,
+    batch: &[TrafficEntry],
+    stats: &WebhookStats,
+) {
+    let count = batch.len() as u64;
+    let body = match serde_json::to_vec(&serde_json::json!({ "entries": batch })) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to serialize webhook batch");
+            stats.dropped_delivery_failed.fetch_add(count, Ordering::Relaxed);
+            return;
+        }
+    };
+    let signature = settings.secret.as_deref().map(|secret| sign(secret, &body));
+
+    let mut backoff = settings.retry_base;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut request = client
+            .post(&settings.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        for (name, value) in &settings.headers {
+            request = request.header(name, value);
+        }
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let retryable = match request.send().await {
+            Ok(resp) if resp.status().is_success() => {
+                stats.sent.fetch_add(count, Ordering::Relaxed);
+                return;
+            }
+            Ok(resp) => {
+                let status = resp.status();
+                tracing::warn!(%status, attempt, "traffic webhook rejected batch");
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, attempt, "traffic webhook delivery failed");
+                true
+            }
+        };
+        if !retryable || attempt == MAX_ATTEMPTS {
+            break;
+        }
+        stats.retries.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+
+    tracing::warn!(entries = count, "dropping traffic webhook batch");
+    stats.dropped_delivery_failed.fetch_add(count, Ordering::Relaxed);
+}
+
+/// `sha256=<hex>` HMAC of `body` keyed with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn settings(url: String) -> WebhookSettings {
+        WebhookSettings {
+            url,
+            headers: vec![("Authorization".into(), "Bearer collector".into())],
+            secret: Some("shh".into()),
+            batch_size: 10,
+            flush_interval: Duration::from_millis(20),
+            retry_base: Duration::from_millis(5),
+        }
+    }
+
+    fn entry(tier: &str) -> TrafficEntry {
+        TrafficEntry::new(tier.into(), "ollama".into(), 5, true)
+    }
+
+    /// Poll `check` until it holds or a second has passed.
+    async fn eventually(check: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if check() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[test]
+    fn signature_matches_known_vector() {
+        // RFC 4231 test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn batches_are_signed_and_carry_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/ingest"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let webhook = TrafficWebhook::start(settings(format!("{}/ingest", server.uri())));
+        webhook.send(entry("a"));
+        webhook.send(entry("b"));
+        eventually(|| webhook.stats().sent.load(Ordering::Relaxed) == 2).await;
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1, "both entries fit in one batch");
+        let req = &requests[0];
+        assert_eq!(req.headers["authorization"], "Bearer collector");
+        assert_eq!(req.headers[SIGNATURE_HEADER].to_str().unwrap(), sign("shh", &req.body));
+        let body: serde_json::Value = serde_json::from_slice(&req.body).unwrap();
+        assert_eq!(body["entries"][1]["tier"], "b");
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let webhook = TrafficWebhook::start(settings(server.uri()));
+        webhook.send(entry("a"));
+        eventually(|| webhook.stats().sent.load(Ordering::Relaxed) == 1).await;
+        assert_eq!(webhook.stats().retries.load(Ordering::Relaxed), 2);
+        assert_eq!(webhook.stats().dropped_delivery_failed.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn client_errors_drop_the_batch_without_retry() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+
+        let webhook = TrafficWebhook::start(settings(server.uri()));
+        webhook.send(entry("a"));
+        eventually(|| webhook.stats().dropped_delivery_failed.load(Ordering::Relaxed) == 1).await;
+        assert_eq!(webhook.stats().retries.load(Ordering::Relaxed), 0);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}